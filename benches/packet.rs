@@ -0,0 +1,73 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use ds::internals::{SendState, encode_tick};
+use ds::*;
+use std::hint::black_box;
+
+/// A response packet containing one of every tag the roboRIO is known to send
+const RESPONSE: &[u8] = &[
+    0x00, 0x2A, 0x01, 0x04, 0x30, 0x0C, 0x80, 0x00, // Header
+    0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Joystick output
+    0x05, 0x04, 0x00, 0x00, 0x10, 0x00, // Disk info
+    0x15, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // CPU info
+    0x09, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, // RAM info
+    0x1A, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // PDP log
+    0x0A, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Unknown
+    0x0F, 0x0e, 0x3E, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, // CAN metrics
+];
+
+fn full_joysticks() -> Vec<Vec<JoystickValue>> {
+    (0..6)
+        .map(|_| {
+            let axes = (0..6).map(|id| JoystickValue::Axis { id, value: 0.5 });
+            let buttons = (1..=10).map(|id| JoystickValue::Button {
+                id,
+                pressed: id % 2 == 0,
+            });
+            let povs = std::iter::once(JoystickValue::POV { id: 0, angle: 90 });
+            axes.chain(buttons).chain(povs).collect()
+        })
+        .collect()
+}
+
+fn encode_control(c: &mut Criterion) {
+    let mut state = SendState::new(Alliance::new_red(1));
+    state.queue_joysticks(full_joysticks());
+    let packet = state.control();
+    c.bench_function("encode control packet (6 joysticks)", |b| {
+        b.iter(|| black_box(&packet).encode())
+    });
+}
+
+fn decode_response(c: &mut Criterion) {
+    c.bench_function("decode response packet (all tags)", |b| {
+        b.iter(|| {
+            let mut buf = black_box(RESPONSE);
+            UdpResponsePacket::decode(&mut buf).unwrap()
+        })
+    });
+}
+
+fn send_loop_iteration(c: &mut Criterion) {
+    let mut state = SendState::new(Alliance::new_red(1));
+    state.set_joystick_supplier(full_joysticks);
+    c.bench_function("send loop iteration (6 joysticks)", |b| {
+        b.iter(|| {
+            // Polled as the send loop does, into the buffer the previous packet is done with
+            let polled = state
+                .joystick_sources()
+                .poll_with(state.take_joystick_buffer());
+            encode_tick(&mut state, Some(polled))
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    encode_control,
+    decode_response,
+    send_loop_iteration
+);
+criterion_main!(benches);
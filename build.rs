@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto/ds.proto");
+
+    // The gRPC service is generated with a pure Rust protobuf compiler, so building it doesn't need protoc
+    #[cfg(feature = "grpc")]
+    {
+        let fds =
+            protox::compile(["proto/ds.proto"], ["proto"]).expect("Invalid protobuf definition");
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_fds(fds)
+            .expect("Failed to generate gRPC service");
+    }
+}
@@ -0,0 +1,31 @@
+extern crate ds;
+
+use ds::*;
+
+use std::time::Duration;
+
+/// Drives a fake robot on 127.0.0.1, no roboRIO needed
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let (mut ds, robot) = ds::loopback::connect().await?;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    ds.set_mode(Mode::Autonomous).await;
+    ds.enable().await;
+
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let snapshot = ds.status_snapshot().await;
+        println!(
+            "Connected: {}, enabled: {}, code: {}, battery: {:.2}V",
+            snapshot.connected,
+            snapshot.robot_enabled,
+            snapshot.trace.is_code_started(),
+            snapshot.battery_voltage
+        );
+        robot.set_battery_voltage(robot.battery_voltage() - 0.1);
+    }
+
+    ds.disable().await;
+    Ok(())
+}
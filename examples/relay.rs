@@ -0,0 +1,12 @@
+extern crate ds;
+
+use std::env;
+
+/// Relays WebSocket connections on port 5810 to the roboRIO at the given IP
+#[tokio::main]
+async fn main() {
+    let robot_ip = env::args().nth(1).expect("Usage: relay <roboRIO ip>");
+
+    println!("Relaying ws://0.0.0.0:5810 to {}", robot_ip);
+    ds::relay::serve("0.0.0.0:5810", &robot_ip).await.unwrap();
+}
@@ -0,0 +1,417 @@
+//! Recording of raw driver station traffic, and analysis of the recordings
+//!
+//! [`record`] writes every packet seen by the packet tap to a capture file. A [`SessionReader`] iterates the packets of a
+//! capture with their timestamps, decodes them, and derives the battery voltage, latency, and packet loss over the
+//! session, so post-match analysis tools don't need to know the wire format.
+//!
+//! A capture starts with the magic `DSCAP`, a version byte, and the start time as little endian microseconds since the
+//! Unix epoch. Each packet is then a direction byte, its offset from the start in microseconds as a little endian
+//! `u64`, its length as a little endian `u32`, and its bytes.
+//!
+//! [`dump_on_failure`] keeps only the last few seconds of traffic, and writes them to a capture when the robot
+//! disconnects or its packets stop decoding.
+
+use crate::proto::Direction;
+use crate::{Control, DsTcpCodec, TcpPacket, UdpResponsePacket};
+
+use bytes::BytesMut;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio_util::codec::Decoder;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod dump;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::dump::{DumpConfig, dump_on_failure};
+
+const MAGIC: &[u8; 5] = b"DSCAP";
+const VERSION: u8 = 1;
+
+/// The width of the windows packet loss is measured over
+const LOSS_WINDOW: Duration = Duration::from_secs(1);
+
+const fn direction_id(direction: Direction) -> u8 {
+    match direction {
+        Direction::UdpOutbound => 0,
+        Direction::UdpInbound => 1,
+        Direction::TcpOutbound => 2,
+        Direction::TcpInbound => 3,
+    }
+}
+
+const fn direction_from_id(id: u8) -> Option<Direction> {
+    match id {
+        0 => Some(Direction::UdpOutbound),
+        1 => Some(Direction::UdpInbound),
+        2 => Some(Direction::TcpOutbound),
+        3 => Some(Direction::TcpInbound),
+        _ => None,
+    }
+}
+
+/// Writes packets to a capture
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Writes the header of a capture that started at `started`
+    pub fn new(mut writer: W, started: DateTime<Utc>) -> io::Result<CaptureWriter<W>> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&started.timestamp_micros().to_le_bytes())?;
+        Ok(CaptureWriter { writer })
+    }
+
+    /// Writes a packet seen `offset` after the capture started
+    pub fn write(
+        &mut self,
+        direction: Direction,
+        offset: Duration,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        self.writer.write_all(&[direction_id(direction)])?;
+        self.writer
+            .write_all(&(offset.as_micros() as u64).to_le_bytes())?;
+        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.writer.write_all(bytes)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Starts capturing the traffic of `ds` to a new file at `path`
+///
+/// This replaces the packet tap of `ds`, and the capture ends when the tap is cleared or replaced. The tap only hands
+/// packets to a thread that writes the file, so the network tasks never wait on the disk. Errors writing packets stop
+/// the capture, and are logged.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn record(ds: &mut crate::DriverStation, path: impl AsRef<Path>) -> io::Result<()> {
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    let path = path.as_ref().to_path_buf();
+    let file = tokio::task::spawn_blocking(move || File::create(path)).await??;
+    let capture = CaptureWriter::new(BufWriter::new(file), Utc::now())?;
+    let (packets, received) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("ds-capture".to_string())
+        .spawn(move || {
+            if let Err(e) = write_packets(capture, received) {
+                warn!("Stopping capture: {}", e);
+            }
+        })?;
+
+    let started = Instant::now();
+    ds.set_packet_tap(move |direction, bytes, at| {
+        // Fails once the writer has stopped
+        let _ = packets.send((
+            direction,
+            at.saturating_duration_since(started),
+            bytes.to_vec(),
+        ));
+    });
+    Ok(())
+}
+
+/// Writes the packets received until the tap sending them is dropped, or writing one fails
+#[cfg(not(target_arch = "wasm32"))]
+fn write_packets<W: Write>(
+    mut capture: CaptureWriter<W>,
+    packets: std::sync::mpsc::Receiver<(Direction, Duration, Vec<u8>)>,
+) -> io::Result<CaptureWriter<W>> {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Instant;
+
+    // Flushing once a second keeps the file usable if the process stops, without a write per packet
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+    let mut last_flush = Instant::now();
+    loop {
+        match packets.recv_timeout(FLUSH_INTERVAL) {
+            Ok((direction, offset, bytes)) => capture.write(direction, offset, &bytes)?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                capture.flush()?;
+                return Ok(capture);
+            }
+        }
+        if last_flush.elapsed() >= FLUSH_INTERVAL {
+            capture.flush()?;
+            last_flush = Instant::now();
+        }
+    }
+}
+
+/// A packet read from a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub direction: Direction,
+    /// The time since the capture started
+    pub offset: Duration,
+    pub timestamp: DateTime<Utc>,
+    pub bytes: Vec<u8>,
+}
+
+/// The contents of a captured packet
+#[derive(Debug)]
+pub enum Decoded {
+    /// A control packet sent to the robot
+    Control { seqnum: u16, control: Control },
+    /// A status packet received from the robot
+    Status(UdpResponsePacket),
+    /// A TCP frame received from the robot
+    Tcp(TcpPacket),
+    /// A TCP frame sent to the robot. These aren't decoded
+    TcpOutbound,
+    /// A packet that couldn't be decoded
+    Invalid,
+}
+
+impl CapturedPacket {
+    pub fn decode(&self) -> Decoded {
+        match self.direction {
+            Direction::UdpOutbound => match self.bytes[..] {
+                [hi, lo, _, control, ..] => Decoded::Control {
+                    seqnum: u16::from_be_bytes([hi, lo]),
+                    control: Control::from_bits_truncate(control),
+                },
+                _ => Decoded::Invalid,
+            },
+            Direction::UdpInbound => match UdpResponsePacket::decode(&mut &self.bytes[..]) {
+                Ok(packet) => Decoded::Status(packet),
+                Err(_) => Decoded::Invalid,
+            },
+            Direction::TcpInbound => {
+                let mut buf = BytesMut::from(&self.bytes[..]);
+                match DsTcpCodec::new(None).decode(&mut buf) {
+                    Ok(Some(packet)) => Decoded::Tcp(packet),
+                    _ => Decoded::Invalid,
+                }
+            }
+            Direction::TcpOutbound => Decoded::TcpOutbound,
+        }
+    }
+}
+
+/// Series derived from a whole capture, each sample paired with its offset from the start of the capture
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionSeries {
+    /// The battery voltage of every status packet
+    pub voltage: Vec<(Duration, f32)>,
+    /// The round trip time of every answered control packet, at the time it was answered
+    pub latency: Vec<(Duration, Duration)>,
+    /// The fraction of control packets sent in each second that were never answered, at the start of the second
+    pub packet_loss: Vec<(Duration, f32)>,
+}
+
+/// Reads the packets of a capture
+pub struct SessionReader<R: Read> {
+    reader: R,
+    started: DateTime<Utc>,
+}
+
+impl SessionReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<SessionReader<BufReader<File>>> {
+        SessionReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> SessionReader<R> {
+    /// Reads the header of the capture in `reader`
+    pub fn new(mut reader: R) -> io::Result<SessionReader<R>> {
+        let mut header = [0u8; 14];
+        reader.read_exact(&mut header)?;
+        if &header[..5] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a capture"));
+        }
+        if header[5] != VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported capture version {}", header[5]),
+            ));
+        }
+        let micros = i64::from_le_bytes(header[6..14].try_into().unwrap());
+        let started = DateTime::from_timestamp_micros(micros)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid start time"))?;
+        Ok(SessionReader { reader, started })
+    }
+
+    /// The time the capture started
+    #[inline(always)]
+    pub const fn started(&self) -> DateTime<Utc> {
+        self.started
+    }
+
+    /// Reads the rest of the capture, deriving the voltage, latency, and packet loss
+    pub fn analyze(self) -> io::Result<SessionSeries> {
+        let mut series = SessionSeries::default();
+        // Every control packet sent, with whether it was answered
+        let mut sent = Vec::<(Duration, bool)>::new();
+        // The index in `sent` of the latest control packet with each sequence number
+        let mut in_flight = HashMap::<u16, usize>::new();
+
+        for packet in self {
+            let packet = packet?;
+            match packet.decode() {
+                Decoded::Control { seqnum, .. } => {
+                    in_flight.insert(seqnum, sent.len());
+                    sent.push((packet.offset, false));
+                }
+                Decoded::Status(status) => {
+                    series.voltage.push((packet.offset, status.battery));
+                    if let Some(i) = in_flight.remove(&status.seqnum) {
+                        sent[i].1 = true;
+                        let rtt = packet.offset.saturating_sub(sent[i].0);
+                        series.latency.push((packet.offset, rtt));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut window = 0;
+        while let Some(&(start, _)) = sent.get(window) {
+            let bucket = start.as_micros() / LOSS_WINDOW.as_micros();
+            let end = sent[window..]
+                .iter()
+                .position(|(at, _)| at.as_micros() / LOSS_WINDOW.as_micros() != bucket)
+                .map_or(sent.len(), |n| window + n);
+            let lost = sent[window..end]
+                .iter()
+                .filter(|(_, answered)| !answered)
+                .count();
+            series.packet_loss.push((
+                LOSS_WINDOW * bucket as u32,
+                lost as f32 / (end - window) as f32,
+            ));
+            window = end;
+        }
+        Ok(series)
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        let mut header = [0u8; 13];
+        match self.reader.read_exact(&mut header[..1]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        self.reader.read_exact(&mut header[1..])?;
+
+        let direction = direction_from_id(header[0])
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid direction"))?;
+        let offset = Duration::from_micros(u64::from_le_bytes(header[1..9].try_into().unwrap()));
+        let len = u32::from_le_bytes(header[9..13].try_into().unwrap());
+        let mut bytes = vec![0u8; len as usize];
+        self.reader.read_exact(&mut bytes)?;
+
+        let timestamp = TimeDelta::from_std(offset)
+            .ok()
+            .and_then(|offset| self.started.checked_add_signed(offset))
+            .unwrap_or(self.started);
+        Ok(Some(CapturedPacket {
+            direction,
+            offset,
+            timestamp,
+            bytes,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = io::Result<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_packet().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn verify_tapped_packets_are_written_until_the_tap_is_dropped() {
+        let started = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let (tap, packets) = std::sync::mpsc::channel();
+        let writer = std::thread::spawn(move || {
+            write_packets(CaptureWriter::new(Vec::new(), started)?, packets)
+        });
+        for seqnum in 0..3u8 {
+            let offset = Duration::from_millis(20 * seqnum as u64);
+            tap.send((
+                Direction::UdpOutbound,
+                offset,
+                vec![0x00, seqnum, 0x01, 0x04, 0x00, 0x00],
+            ))
+            .unwrap();
+        }
+        drop(tap);
+
+        let bytes = writer.join().unwrap().unwrap().into_inner();
+        let packets = SessionReader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[2].offset, Duration::from_millis(40));
+        assert_eq!(packets[2].bytes[1], 2);
+    }
+
+    #[test]
+    fn verify_capture_round_trip() {
+        let started = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut writer = CaptureWriter::new(Vec::new(), started).unwrap();
+        let at = Duration::from_millis;
+        // Two control packets, only the first of which is answered
+        writer
+            .write(
+                Direction::UdpOutbound,
+                at(0),
+                &[0x00, 0x01, 0x01, 0x04, 0x00, 0x00],
+            )
+            .unwrap();
+        writer
+            .write(
+                Direction::UdpOutbound,
+                at(20),
+                &[0x00, 0x02, 0x01, 0x04, 0x00, 0x00],
+            )
+            .unwrap();
+        writer
+            .write(
+                Direction::UdpInbound,
+                at(25),
+                &[0x00, 0x01, 0x01, 0x04, 0x30, 0x0C, 0x80, 0x00],
+            )
+            .unwrap();
+        let bytes = writer.into_inner();
+
+        let reader = SessionReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.started(), started);
+        let packets = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[2].timestamp, started + TimeDelta::milliseconds(25));
+        assert!(matches!(
+            packets[0].decode(),
+            Decoded::Control { seqnum: 1, .. }
+        ));
+
+        let series = SessionReader::new(&bytes[..]).unwrap().analyze().unwrap();
+        assert_eq!(series.voltage, vec![(at(25), 12.5)]);
+        assert_eq!(series.latency, vec![(at(25), at(25))]);
+        assert_eq!(series.packet_loss, vec![(at(0), 0.5)]);
+    }
+}
@@ -0,0 +1,153 @@
+use super::CaptureWriter;
+
+use crate::proto::Direction;
+use crate::{DriverStation, StateChange};
+
+use chrono::{Local, TimeDelta, Utc};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+
+/// When and where [`dump_on_failure`] writes the recent traffic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpConfig {
+    /// The directory dumps are written to
+    pub dir: PathBuf,
+    /// How much traffic before the failure is written. Failures within this long of the last dump don't start another
+    pub window: Duration,
+    /// The number of status packets that failed to decode within `window` that counts as a failure
+    pub decode_errors: u64,
+}
+
+impl DumpConfig {
+    /// Dumps the last 10 seconds to `dir` on a disconnect, or 10 decode errors within them
+    pub fn new(dir: impl Into<PathBuf>) -> DumpConfig {
+        DumpConfig {
+            dir: dir.into(),
+            window: Duration::from_secs(10),
+            decode_errors: 10,
+        }
+    }
+}
+
+/// A packet kept in case of a failure
+type Recent = VecDeque<(Direction, Instant, Vec<u8>)>;
+
+/// Keeps the recent traffic of `ds`, and writes it to a capture whenever the robot disconnects or decode errors spike
+///
+/// Each dump is a capture named after the time it was written, such as `dump-20240312-184501.dscap`, readable with a
+/// [`SessionReader`](super::SessionReader), next to a `.events` file with the events in the same window. Like
+/// [`record`](super::record), this replaces the packet tap of `ds`. This future returns once the driver station is
+/// dropped, or if writing fails. Drop it to stop watching.
+pub async fn dump_on_failure(ds: Arc<Mutex<DriverStation>>, config: DumpConfig) -> io::Result<()> {
+    let recent = Arc::new(std::sync::Mutex::new(Recent::new()));
+    let mut changes = {
+        let mut ds = ds.lock().await;
+        let recent = recent.clone();
+        let window = config.window;
+        ds.set_packet_tap(move |direction, bytes, at| {
+            let Ok(mut recent) = recent.lock() else {
+                return;
+            };
+            while recent
+                .front()
+                .is_some_and(|(_, seen, _)| at.saturating_duration_since(*seen) > window)
+            {
+                recent.pop_front();
+            }
+            recent.push_back((direction, at, bytes.to_vec()));
+        });
+        ds.subscribe()
+    };
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    // The decode error count once a second, over the last window
+    let mut errors = VecDeque::<(Instant, u64)>::new();
+    let mut last_dump: Option<Instant> = None;
+    loop {
+        let reason = tokio::select! {
+            change = changes.recv() => match change {
+                Ok(StateChange::ConnectionChanged(false)) => Some("the robot disconnected"),
+                Ok(_) | Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = ticker.tick() => {
+                let count = ds.lock().await.stats().await.decode_errors;
+                let now = Instant::now();
+                while errors.front().is_some_and(|(at, _)| now.duration_since(*at) > config.window) {
+                    errors.pop_front();
+                }
+                errors.push_back((now, count));
+                let oldest = errors.front().map_or(count, |(_, count)| *count);
+                (count - oldest >= config.decode_errors).then_some("status packets failed to decode")
+            },
+        };
+        let Some(reason) = reason else {
+            continue;
+        };
+        if last_dump.is_some_and(|at| at.elapsed() < config.window) {
+            continue;
+        }
+        last_dump = Some(Instant::now());
+        let packets = recent
+            .lock()
+            .map(|recent| recent.clone())
+            .unwrap_or_default();
+        let path = write_dump(&ds, &config, packets, reason).await?;
+        info!(
+            "Dumped recent traffic to {} after {}",
+            path.display(),
+            reason
+        );
+    }
+}
+
+/// Writes `packets` and the recent events, returning the path of the capture
+///
+/// Both files are built in memory and written on the blocking pool, so a slow disk doesn't stall the runtime.
+async fn write_dump(
+    ds: &Mutex<DriverStation>,
+    config: &DumpConfig,
+    packets: Recent,
+    reason: &str,
+) -> io::Result<PathBuf> {
+    let name = format!("dump-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let path = config.dir.join(format!("{}.dscap", name));
+
+    // The capture starts with its oldest packet
+    let now = Instant::now();
+    let first = packets.front().map_or(now, |(_, at, _)| *at);
+    let started = Utc::now() - TimeDelta::from_std(now.duration_since(first)).unwrap_or_default();
+    let mut capture = CaptureWriter::new(Vec::new(), started)?;
+    for (direction, at, bytes) in &packets {
+        capture.write(*direction, at.duration_since(first), bytes)?;
+    }
+
+    let since = Utc::now() - TimeDelta::from_std(config.window).unwrap_or_default();
+    let events = ds.lock().await.events_since(since).await;
+    let mut listing = format!("# Dumped after {}\n", reason);
+    for event in events {
+        let _ = writeln!(
+            listing,
+            "{} {:?}",
+            event
+                .timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            event.event
+        );
+    }
+
+    let capture_path = path.clone();
+    let events_path = config.dir.join(format!("{}.events", name));
+    tokio::task::spawn_blocking(move || {
+        std::fs::write(capture_path, capture.into_inner())?;
+        std::fs::write(events_path, listing)
+    })
+    .await??;
+    Ok(path)
+}
@@ -0,0 +1,225 @@
+//! Logging of the robot console to rotating files
+//!
+//! [`log_console`] appends every [`ConsoleLine`] to a file named after the configured path with the time it was
+//! started, such as `console-20240312-184501.log`. Each file starts with a header giving the team number, the robot's
+//! versions, and the match info, and the header is repeated whenever the robot reports new versions. A new file is
+//! started when the current one grows too large.
+
+use crate::{ConsoleLevel, ConsoleLine, DriverStation, MatchInfo, RobotIdentity, StateChange};
+
+use chrono::{DateTime, Local, Utc};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Where the console is logged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleLogConfig {
+    /// The path files are named after. The extension is replaced with `log`
+    pub path: PathBuf,
+    /// The size a file can grow to, in bytes
+    pub max_bytes: Option<u64>,
+}
+
+impl ConsoleLogConfig {
+    /// Logs to files named after `path`, without rotating
+    pub fn new(path: impl Into<PathBuf>) -> ConsoleLogConfig {
+        ConsoleLogConfig {
+            path: path.into(),
+            max_bytes: None,
+        }
+    }
+}
+
+/// What the session header describes
+struct Session {
+    identity: RobotIdentity,
+    match_info: Option<MatchInfo>,
+}
+
+impl Session {
+    async fn read(ds: &Mutex<DriverStation>) -> Session {
+        let ds = ds.lock().await;
+        Session {
+            identity: ds.identity().await,
+            match_info: ds.match_info().await,
+        }
+    }
+}
+
+/// The file lines are currently appended to
+struct Output {
+    writer: BufWriter<File>,
+    bytes: u64,
+}
+
+impl Output {
+    async fn open(config: &ConsoleLogConfig, session: &Session) -> io::Result<Output> {
+        let path = file_path(&config.path, Local::now());
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let mut output = Output {
+            writer: BufWriter::new(file),
+            bytes: 0,
+        };
+        output.header(session).await?;
+        Ok(output)
+    }
+
+    async fn header(&mut self, session: &Session) -> io::Result<()> {
+        for line in format_header(session, Utc::now()) {
+            self.write(&line).await?;
+        }
+        self.writer.flush().await
+    }
+
+    async fn write(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.write_all(b"\n").await?;
+        self.bytes += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn expired(&self, config: &ConsoleLogConfig) -> bool {
+        config.max_bytes.is_some_and(|max| self.bytes >= max)
+    }
+}
+
+/// Appends the console output, errors, and warnings of `ds` to the files described by `config`
+///
+/// Lines are flushed as they are written, so the file can be followed with `tail -f`. This future returns once the
+/// driver station is dropped, or if writing fails. Drop it to stop logging.
+pub async fn log_console(
+    ds: Arc<Mutex<DriverStation>>,
+    config: ConsoleLogConfig,
+) -> io::Result<()> {
+    let (mut lines, mut changes) = {
+        let ds = ds.lock().await;
+        (ds.watch_console().await, ds.subscribe())
+    };
+    let mut output = Output::open(&config, &Session::read(&ds).await).await?;
+
+    loop {
+        tokio::select! {
+            line = lines.recv() => {
+                let line = match line {
+                    Ok(line) => format_line(&line),
+                    Err(RecvError::Lagged(skipped)) => format!("# {} lines skipped", skipped),
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+                if output.expired(&config) {
+                    output = Output::open(&config, &Session::read(&ds).await).await?;
+                }
+                output.write(&line).await?;
+                output.writer.flush().await?;
+            }
+            change = changes.recv() => match change {
+                // Versions arrive after the TCP connection opens, usually after the first file was started
+                Ok(StateChange::IdentityChanged) => output.header(&Session::read(&ds).await).await?,
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Inserts the time the file was started before the extension of `path`
+fn file_path(path: &Path, started: DateTime<Local>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "console".to_string());
+    path.with_file_name(format!("{}-{}.log", stem, started.format("%Y%m%d-%H%M%S")))
+}
+
+fn format_header(session: &Session, now: DateTime<Utc>) -> Vec<String> {
+    let identity = &session.identity;
+    let unknown =
+        |version: &Option<String>| version.clone().unwrap_or_else(|| "unknown".to_string());
+    let mut header = vec![
+        format!(
+            "# {}",
+            now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        ),
+        format!(
+            "# team {}, robot at {}",
+            identity.team_number, identity.target
+        ),
+        format!(
+            "# image {}, WPILib {}, serial number {}",
+            unknown(&identity.image_version),
+            unknown(&identity.wpilib_version),
+            unknown(&identity.serial_number)
+        ),
+    ];
+    if let Some(ref info) = session.match_info {
+        header.push(format!(
+            "# {} {:?} match",
+            info.competition(),
+            info.match_type()
+        ));
+    }
+    header
+}
+
+fn format_line(line: &ConsoleLine) -> String {
+    let level = match line.level {
+        ConsoleLevel::Output => "",
+        ConsoleLevel::Warning => "WARNING ",
+        ConsoleLevel::Error => "ERROR ",
+    };
+    format!(
+        "{} {:>9.3} {}{}",
+        line.received
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        line.timestamp,
+        level,
+        line.text.trim_end()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MatchType;
+
+    #[test]
+    fn verify_lines_are_formatted() {
+        let received = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let line = ConsoleLine {
+            received,
+            timestamp: 12.5,
+            level: ConsoleLevel::Warning,
+            text: "Loop time of 0.02s overrun\n".to_string(),
+        };
+        assert_eq!(
+            format_line(&line),
+            "2023-11-14T22:13:20.000Z    12.500 WARNING Loop time of 0.02s overrun"
+        );
+
+        let session = Session {
+            identity: RobotIdentity {
+                team_number: 4533,
+                target: "10.45.33.2".to_string(),
+                wpilib_version: Some("2024.3.2".to_string()),
+                ..RobotIdentity::default()
+            },
+            match_info: Some(MatchInfo::new("Week 0", MatchType::Practice)),
+        };
+        assert_eq!(
+            format_header(&session, received)[1..],
+            [
+                "# team 4533, robot at 10.45.33.2",
+                "# image unknown, WPILib 2024.3.2, serial number unknown",
+                "# Week 0 Practice match",
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Mirroring of driver station state to displays outside the application, such as a screen in the pits
+//!
+//! [`forward`] calls a [`DashboardBridge`] with each [`StateChange`] and with a [`DsSnapshot`] of the robot's state at a
+//! fixed interval. It runs as a future of its own that follows the driver station's broadcasts, so a slow bridge only
+//! delays itself, never packets to the robot. [`UdpJsonBridge`] sends each one as a JSON datagram.
+
+use crate::{DriverStation, DsSnapshot, StateChange};
+
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Receives the state of a driver station to show it elsewhere
+///
+/// Implementations are called from the future returned by [`forward`], and should return quickly.
+pub trait DashboardBridge: Send {
+    /// Called with each change selected by [`DashboardBridge::wants`]
+    fn change(&mut self, change: &StateChange);
+
+    /// Called with the state of the robot every interval given to [`forward`]
+    fn telemetry(&mut self, snapshot: &DsSnapshot);
+
+    /// Whether `change` should be forwarded. All changes are by default
+    fn wants(&self, change: &StateChange) -> bool {
+        let _ = change;
+        true
+    }
+}
+
+/// Forwards the state of `ds` to `bridge`, with telemetry every `interval`
+///
+/// Changes missed by falling behind are skipped, the next telemetry catches the bridge up. This future returns once the
+/// driver station is dropped, drop it to stop forwarding.
+pub async fn forward(
+    ds: Arc<Mutex<DriverStation>>,
+    interval: Duration,
+    mut bridge: impl DashboardBridge,
+) {
+    let mut changes = ds.lock().await.subscribe();
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let snapshot = ds.lock().await.status_snapshot().await;
+                bridge.telemetry(&snapshot);
+            }
+            change = changes.recv() => match change {
+                Ok(change) if bridge.wants(&change) => bridge.change(&change),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+/// A [`DashboardBridge`] that sends a JSON object in a UDP datagram for each change and each telemetry update
+///
+/// Changes look like `{"type":"change","field":"connected","value":true}`, and telemetry like
+/// `{"type":"telemetry","battery_voltage":12.5,"mode":"Teleoperated",...}` with the fields of [`DsSnapshot`].
+/// Datagrams nobody is listening for are dropped.
+#[derive(Debug)]
+pub struct UdpJsonBridge {
+    socket: UdpSocket,
+}
+
+impl UdpJsonBridge {
+    /// Creates a bridge sending to `target`, such as `10.45.33.50:5800`
+    pub fn new(target: impl ToSocketAddrs) -> io::Result<UdpJsonBridge> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        socket.connect(target)?;
+        Ok(UdpJsonBridge { socket })
+    }
+
+    fn send(&self, json: String) {
+        // A display that isn't running yet refuses datagrams, which isn't worth reporting
+        let _ = self.socket.send(json.as_bytes());
+    }
+}
+
+impl DashboardBridge for UdpJsonBridge {
+    fn change(&mut self, change: &StateChange) {
+        self.send(change_json(change));
+    }
+
+    fn telemetry(&mut self, snapshot: &DsSnapshot) {
+        self.send(telemetry_json(snapshot));
+    }
+}
+
+fn change_json(change: &StateChange) -> String {
+    let (field, value) = match *change {
+        StateChange::ConnectionChanged(connected) => ("connected", connected.to_string()),
+        StateChange::TcpConnectionChanged(connected) => ("tcp_connected", connected.to_string()),
+        StateChange::VoltageChanged(voltage) => ("battery_voltage", number(f64::from(voltage))),
+        StateChange::ModeConfirmed(mode) => ("robot_mode", format!("\"{:?}\"", mode)),
+        StateChange::EnabledConfirmed(enabled) => ("robot_enabled", enabled.to_string()),
+        StateChange::TraceChanged(trace) => ("trace", trace.bits().to_string()),
+        StateChange::BrownoutChanged(brownout) => ("brownout", brownout.to_string()),
+        StateChange::IdentityChanged => ("identity", "null".to_string()),
+    };
+    format!(
+        "{{\"type\":\"change\",\"field\":\"{}\",\"value\":{}}}",
+        field, value
+    )
+}
+
+fn telemetry_json(snapshot: &DsSnapshot) -> String {
+    let optional = |value: Option<f64>| value.map_or_else(|| "null".to_string(), number);
+    format!(
+        "{{\"type\":\"telemetry\",\"battery_voltage\":{},\"mode\":\"{:?}\",\"enabled\":{},\"robot_enabled\":{},\
+         \"estopped\":{},\"brownout\":{},\"trace\":{},\"connected\":{},\"tcp_connected\":{},\"latency_ms\":{},\
+         \"match_time_remaining\":{}}}",
+        number(f64::from(snapshot.battery_voltage)),
+        snapshot.mode,
+        snapshot.enabled,
+        snapshot.robot_enabled,
+        snapshot.estopped,
+        snapshot.brownout,
+        snapshot.trace.bits(),
+        snapshot.connected,
+        snapshot.tcp_connected,
+        optional(
+            snapshot
+                .latency
+                .map(|latency| latency.as_secs_f64() * 1000.0)
+        ),
+        optional(
+            snapshot
+                .match_time_remaining
+                .map(|remaining| remaining.as_secs_f64())
+        ),
+    )
+}
+
+/// Formats `value` as a JSON number, which can't be NaN or infinite
+fn number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Mode, Trace};
+
+    #[test]
+    fn verify_json_is_formatted() {
+        assert_eq!(
+            change_json(&StateChange::ModeConfirmed(Mode::Autonomous)),
+            r#"{"type":"change","field":"robot_mode","value":"Autonomous"}"#
+        );
+        assert_eq!(
+            change_json(&StateChange::VoltageChanged(f32::NAN)),
+            r#"{"type":"change","field":"battery_voltage","value":null}"#
+        );
+
+        let snapshot = DsSnapshot {
+            battery_voltage: 12.5,
+            mode: Mode::Teleoperated,
+            enabled: true,
+            robot_enabled: false,
+            estopped: false,
+            brownout: false,
+            trace: Trace::ROBOT_CODE,
+            connected: true,
+            tcp_connected: true,
+            udp_only: false,
+            latency: Some(Duration::from_millis(4)),
+            match_time_remaining: None,
+        };
+        assert_eq!(
+            telemetry_json(&snapshot),
+            r#"{"type":"telemetry","battery_voltage":12.5,"mode":"Teleoperated","enabled":true,"robot_enabled":false,"estopped":false,"brownout":false,"trace":32,"connected":true,"tcp_connected":true,"latency_ms":4,"match_time_remaining":null}"#
+        );
+    }
+}
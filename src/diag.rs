@@ -0,0 +1,39 @@
+//! The driver station's own messages, such as why it refused to enable, logged like the robot console
+//!
+//! With the `log` or `tracing` feature these go to the facade under the [`TARGET`] target, and without either they're
+//! dropped, rather than printed to the application's stdout.
+
+/// The target the driver station's own messages are logged under
+pub(crate) const TARGET: &str = "ds";
+
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        ::log::$level!(target: $crate::diag::TARGET, $($arg)+);
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!(target: $crate::diag::TARGET, $($arg)+);
+        // Keeps the arguments used, so they don't need their own cfgs
+        #[cfg(not(any(feature = "log", feature = "tracing")))]
+        let _ = format_args!($($arg)+);
+    }};
+}
+
+/// Logs a change the application may want to know about, such as the robot disconnecting
+macro_rules! info {
+    ($($arg:tt)+) => { log_at!(info, $($arg)+) };
+}
+
+/// Logs something that went wrong without stopping the driver station, such as a refused enable
+macro_rules! warn {
+    ($($arg:tt)+) => { log_at!(warn, $($arg)+) };
+}
+
+/// Logs a failure that stops part of the driver station
+macro_rules! error {
+    ($($arg:tt)+) => { log_at!(error, $($arg)+) };
+}
+
+/// Logs detail only useful when debugging the driver station itself
+macro_rules! debug {
+    ($($arg:tt)+) => { log_at!(debug, $($arg)+) };
+}
@@ -1,197 +1,1547 @@
-use anyhow::bail;
-
-mod conn;
+pub(crate) mod aggregate;
+pub(crate) mod alert;
+pub(crate) mod alliance;
+pub(crate) mod battery;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod builder;
+pub(crate) mod can;
+pub(crate) mod changes;
+pub(crate) mod clock;
+pub(crate) mod command;
+pub(crate) mod composite;
+pub(crate) mod conditioner;
+pub(crate) mod config;
+pub(crate) mod conflict;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod conn;
+pub(crate) mod console;
+pub(crate) mod controllers;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod discovery;
+pub(crate) mod events;
+#[cfg(feature = "extensions")]
+pub(crate) mod extensions;
+pub(crate) mod history;
+pub(crate) mod identity;
+pub(crate) mod inputs;
+pub(crate) mod manual;
+pub(crate) mod nt;
+pub(crate) mod outage;
+pub(crate) mod outputs;
+pub(crate) mod report;
+pub(crate) mod resources;
+pub(crate) mod rumble;
+pub(crate) mod safety;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod selftest;
+pub(crate) mod session;
+pub(crate) mod shape;
+pub(crate) mod simclock;
+pub(crate) mod snapshot;
+pub(crate) mod sources;
 pub(crate) mod state;
+pub(crate) mod summary;
+pub(crate) mod tasks;
+#[cfg(not(target_arch = "wasm32"))]
+mod timer;
+pub(crate) mod timesync;
+pub(crate) mod validation;
+pub(crate) mod watchdog;
 
+#[cfg(not(target_arch = "wasm32"))]
 use self::conn::*;
+use self::state::send::SendState;
 use self::state::*;
+#[cfg(not(target_arch = "wasm32"))]
+use self::tasks::spawn_named;
+#[cfg(not(target_arch = "wasm32"))]
+use self::timer::MatchTimer;
+
+pub use self::aggregate::{TelemetryWindow, VoltageRange};
+pub use self::alert::{Alert, AlertSink, Severity};
+pub use self::alliance::AllianceSource;
+pub use self::battery::VoltageSag;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::builder::DriverStationBuilder;
+pub use self::changes::{PacketSent, StateChange};
+pub use self::clock::{Clock, MockClock, SystemClock};
+pub use self::command::{Command, Commander};
+pub use self::composite::{CompositeJoystick, InputMap};
+pub use self::conditioner::LinkConditions;
+pub use self::config::{
+    CanUtilizationAlert, ConsoleLimits, DropAction, DsConfig, JoystickMapping, JoystickSuppression,
+    MappingProfile, Pacing, Ports, PracticeTiming, ReconnectPolicy, ResourceLimits, SocketOptions,
+    SourceFilter,
+};
+pub use self::conflict::ControlConflict;
+pub use self::console::{ConsoleLevel, ConsoleLine};
+pub use self::controllers::{Controller, GamepadAxis, GamepadButton, StickAxis};
+pub use self::events::{Event, TimedEvent};
+pub use self::history::StatusTransition;
+pub use self::identity::RobotIdentity;
+pub use self::inputs::InputChange;
+pub use self::manual::ControlSink;
+pub use self::nt::{NT4_PORT, NetworkTables};
+pub use self::report::{ShutdownReason, ShutdownReport};
+pub use self::rumble::{EndgameRumble, MatchPhase, Rumble, RumblePattern};
+pub use self::safety::{JoystickFault, JoystickSafety, MAX_JOYSTICKS};
+pub use self::session::Session;
+pub use self::shape::{JoystickShape, ShapeMismatch};
+pub use self::simclock::SimClock;
+pub use self::snapshot::{ConnectionState, DsSnapshot};
+pub use self::sources::{JoystickSource, SUPPLIER_SOURCE};
+pub use self::state::stats::{Stats, TagStats};
+pub use self::summary::{EnabledTime, MatchSummary};
+pub use self::tasks::TaskActivity;
+pub use self::timesync::ClockSync;
+pub use self::validation::JoystickValidation;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "config")]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+#[cfg(feature = "config")]
+use tokio::sync::watch;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::task::JoinHandle;
+
+use crate::proto::Direction;
+use crate::proto::tcp::outbound::{GameData, JoystickDescriptor, MatchInfo, TcpTag};
+use crate::proto::udp::inbound::types::{
+    CANMetrics, InboundTagKind, RawStatus, RobotRequests, Trace,
+};
+use crate::proto::udp::outbound::types::*;
+use crate::proto::year::ProtocolYear;
+use crate::{Result, TcpPacket};
+
+/// Represents a connection to the roboRIO acting as a driver station
+///
+/// This struct will contain relevant functions to update the state of the robot,
+/// and also manages the threads that manage network connections and joysticks
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DriverStation {
+    thread_tx: UnboundedSender<Signal>,
+    conn_tasks: ConnTasks,
+    /// The settings the connection tasks were started from, see [`DriverStation::reconfigure`]
+    conn_settings: ConnSettings,
+    /// The settings and signals for connection tasks that haven't been started, see [`DriverStation::connect`]
+    pending_conn: Option<(DsConfig, UnboundedReceiver<Signal>)>,
+    backend: Backend,
+    /// What's sent to the robot when this is shut down or dropped
+    final_burst: FinalBurst,
+    team_number: u16,
+    /// The season of the roboRIO image, used to find the robot from the team number
+    protocol: ProtocolYear,
+    state: Arc<DsState>,
+    endgame_rumble: Option<EndgameRumble>,
+    match_timer: Option<MatchTimer>,
+    /// The sets of joystick mappings the operator can switch between
+    profiles: Vec<MappingProfile>,
+    /// The operator settings, mirrored here so they can be saved without locking the states
+    session: Session,
+    /// Hands the session to the task saving it when it changes
+    #[cfg(feature = "config")]
+    session_saver: Option<watch::Sender<Session>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DriverStation {
+    /// Creates a new driver station with the given team number and alliance
+    ///
+    /// This driver station will attempt to connect to a roboRIO at 10.TE.AM.2,
+    /// if the roboRIO is at a different ip, use [new] and specify the ip directly.
+    pub async fn new_team(team_number: u16, alliance: Alliance) -> DriverStation {
+        Self::from_config(&DsConfig {
+            team_number,
+            alliance,
+            ..DsConfig::default()
+        })
+        .await
+    }
+
+    /// Creates a new driver station for the given alliance station and team number
+    /// Connects to the roborio at `ip`. To infer the ip from team_number, use `new_team` instead.
+    pub async fn new(ip: &str, alliance: Alliance, team_number: u16) -> DriverStation {
+        Self::from_config(&DsConfig {
+            team_number,
+            ip: Some(ip.to_string()),
+            alliance,
+            ..DsConfig::default()
+        })
+        .await
+    }
+
+    /// Starts building a driver station, see [`DriverStationBuilder`]
+    pub fn builder() -> DriverStationBuilder {
+        DriverStationBuilder::default()
+    }
+
+    /// Creates a new driver station from the given settings
+    ///
+    /// With the `config` feature these can be loaded from a file with [`DsConfig::load`].
+    pub async fn from_config(config: &DsConfig) -> DriverStation {
+        Self::start(config, Backend::RoboRio).await
+    }
+
+    /// Creates a new driver station for a robot controlled by a cRIO, as used from 2009 to 2014
+    ///
+    /// The cRIO is expected at 10.TE.AM.2, with the ports and joystick settings from `config`. Only the control flags,
+    /// alliance, team number, battery voltage, and the first 4 joysticks are exchanged with a cRIO. Tags, the TCP
+    /// connection, game data, simulators, and [`ProtocolYear`] have no effect.
+    #[cfg(feature = "crio")]
+    pub async fn new_crio(config: &DsConfig) -> DriverStation {
+        Self::start(config, Backend::Crio).await
+    }
+
+    async fn start(config: &DsConfig, backend: Backend) -> DriverStation {
+        let ip = target_of(config);
+
+        // Global state of the driver station
+        let state = Arc::new(DsState::new(config.alliance, ip.clone()));
+        apply_config(&state, config).await;
+        // Channels to communicate to the threads that make up the application, used to break out of infinite loops when the struct is dropped
+        let (tx, rx) = unbounded_channel::<Signal>();
+        state.set_alliance_source(
+            AllianceSource::Config,
+            config.lock_alliance.then_some(config.alliance),
+        );
+        let (conn_tasks, pending_conn) = if config.connect_on_build {
+            (spawn_conn(&state, config, ip, &tx, rx, backend), None)
+        } else {
+            (ConnTasks::default(), Some((config.clone(), rx)))
+        };
+
+        DriverStation {
+            thread_tx: tx,
+            conn_tasks,
+            conn_settings: ConnSettings::of(config),
+            pending_conn,
+            backend,
+            final_burst: FinalBurst::new(config),
+            state,
+            team_number: config.team_number,
+            protocol: config.protocol,
+            endgame_rumble: None,
+            match_timer: None,
+            profiles: config.profiles.clone(),
+            session: Session {
+                alliance: config.alliance,
+                practice: config.practice,
+                ..Session::default()
+            },
+            #[cfg(feature = "config")]
+            session_saver: None,
+        }
+    }
+
+    /// Starts talking to the robot, for a driver station built with [`DsConfig::connect_on_build`] off
+    ///
+    /// Changes made before connecting, such as to the team number, take effect as it starts. Does nothing if the driver
+    /// station is already connecting.
+    pub async fn connect(&mut self) {
+        if let Some((config, rx)) = self.pending_conn.take() {
+            // Commands queued before connecting take effect as it starts, rather than with its first packet
+            command::apply_queued(&self.state).await;
+            let ip = target_of(&config);
+            self.conn_tasks =
+                spawn_conn(&self.state, &config, ip, &self.thread_tx, rx, self.backend);
+        }
+    }
+
+    /// Stops talking to the robot, waiting for the connection tasks to finish, and reports why they stopped
+    ///
+    /// The reason is [`ShutdownReason::Requested`] unless the connection had already died, in which case it's why.
+    /// The robot is then sent a few packets doing what [`DsConfig::on_drop`] says, for up to
+    /// [`DsConfig::drop_timeout`].
+    pub async fn shutdown(mut self) -> ShutdownReport {
+        let mut reason = self.stopped_reason();
+        self.conn_tasks.abort_listeners();
+        let _ = self.thread_tx.send(Signal::Disconnect);
+        for task in self.conn_tasks.take() {
+            if let Err(e) = task.await
+                && e.is_panic()
+                && reason.is_none()
+            {
+                reason = Some(ShutdownReason::Panicked(panic_message(e.into_panic())));
+            }
+        }
+        if self.connect_started() {
+            self.final_burst
+                .send(&self.state, |send| self.final_packet(send))
+                .await;
+            // The robot has been told, dropping mustn't tell it again
+            self.final_burst.action = DropAction::Nothing;
+        }
+        self.report(reason.unwrap_or(ShutdownReason::Requested))
+            .await
+    }
+
+    /// Returns a report of why the connection tasks stopped, if they died without being asked to
+    ///
+    /// This lets an application watching the driver station tell that it has to be restarted, see
+    /// [`DriverStation::shutdown`].
+    pub async fn shutdown_report(&self) -> Option<ShutdownReport> {
+        let reason = self.stopped_reason()?;
+        Some(self.report(reason).await)
+    }
+
+    /// Returns why the connection tasks stopped on their own, if they did
+    fn stopped_reason(&self) -> Option<ShutdownReason> {
+        if let Some(reason) = self.state.shutdown_reason() {
+            return Some(reason);
+        }
+        // The receiving task only returns without an error once it's told to disconnect, so it panicked
+        self.conn_tasks
+            .root
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+            .then(|| {
+                ShutdownReason::Panicked("The connection task stopped unexpectedly".to_string())
+            })
+    }
+
+    async fn report(&self, reason: ShutdownReason) -> ShutdownReport {
+        ShutdownReport {
+            reason,
+            stopped_at: self.state.now(),
+            stats: self.stats().await,
+            summary: self.match_summary().await,
+            last_events: self.recent_events().await,
+        }
+    }
+
+    /// Encodes the next control packet of `send` for the backend in use, as the send loop would
+    fn final_packet(&self, send: &mut SendState) -> Bytes {
+        let packet = match self.backend {
+            Backend::RoboRio => send.control().encode(),
+            #[cfg(feature = "crio")]
+            Backend::Crio => crate::proto::crio::CrioControlPacket::from_control(
+                &send.control(),
+                self.team_number,
+            )
+            .encode(),
+        };
+        send.increment_seqnum();
+        packet
+    }
+
+    /// Whether the driver station has started talking to the robot, see [`DriverStation::connect`]
+    #[inline(always)]
+    pub const fn connect_started(&self) -> bool {
+        self.pending_conn.is_none()
+    }
+
+    /// Changes what the robot is told when this driver station is shut down or dropped, see [`DsConfig::on_drop`]
+    pub fn set_drop_action(&mut self, action: DropAction) {
+        self.final_burst.action = action;
+    }
+
+    #[inline(always)]
+    pub const fn drop_action(&self) -> DropAction {
+        self.final_burst.action
+    }
+
+    /// Applies `config` to this driver station in place, such as to change its ports, target, or protocol year
+    ///
+    /// When only settings like the pacing, safety, or console limits change, they're applied to the running connection,
+    /// and the robot stays enabled. Changing how the robot is reached, its target, ports, reconnect policy, socket
+    /// options, source filter, simulator detection, or UDP-only mode, stops the connection tasks and waits for them,
+    /// then starts them again with sockets bound from `config`, and the robot is disabled as it's reached anew.
+    ///
+    /// Either way the mode, alliance, game data, joysticks, queued tags, and event log are kept, so a frontend doesn't
+    /// have to restore them. The practice timing in `config` is ignored, it's part of the [`Session`], and so is the
+    /// alliance unless [`DsConfig::lock_alliance`] is set. A driver station that isn't connected yet stays that way,
+    /// whatever [`DsConfig::connect_on_build`] is set to.
+    pub async fn reconfigure(&mut self, config: &DsConfig) {
+        let settings = ConnSettings::of(config);
+        let restart = self.connect_started()
+            && (settings != self.conn_settings || target_of(config) != self.state.target());
+        if restart {
+            self.restart_link().await;
+        }
+
+        let ip = target_of(config);
+        if ip != self.state.target() {
+            self.state.set_target(&ip);
+            self.state.emit(Event::TargetChanged(ip.clone())).await;
+        }
+        apply_config(&self.state, config).await;
+        self.profiles = config.profiles.clone();
+        command::select_alliance(
+            &self.state,
+            AllianceSource::Config,
+            config.lock_alliance.then_some(config.alliance),
+        )
+        .await;
+        if let Some((_, rx)) = self.pending_conn.take() {
+            self.pending_conn = Some((config.clone(), rx));
+        } else if restart {
+            let (tx, rx) = unbounded_channel::<Signal>();
+            self.conn_tasks = spawn_conn(&self.state, config, ip, &tx, rx, self.backend);
+            self.thread_tx = tx;
+        } else {
+            // Control packets for the cRIO contain the team number, which the running task is told of
+            #[cfg(feature = "crio")]
+            if config.team_number != self.team_number {
+                let _ = self
+                    .thread_tx
+                    .send(Signal::NewTeamNumber(config.team_number));
+            }
+        }
+        self.conn_settings = settings;
+        self.final_burst = FinalBurst::new(config);
+        self.team_number = config.team_number;
+        self.protocol = config.protocol;
+    }
+
+    /// Stops the connection tasks and forgets the robot, disabling it, so they can be started again to reach it anew
+    async fn restart_link(&mut self) {
+        // The simulator listener goes first, so it can't report a mode to a connection task that has stopped
+        self.conn_tasks.abort_listeners();
+        let _ = self.thread_tx.send(Signal::Disconnect);
+        for task in self.conn_tasks.take() {
+            let _ = task.await;
+        }
+
+        command::disable(&self.state).await;
+        self.state.send().write().await.reset_seqnum();
+        self.state.reset_recv().await;
+        self.state.telemetry().write().await.clear_identity();
+        let was_connected = {
+            let mut link = self.state.link().write().await;
+            let connected = link.connected();
+            link.set_connected(false);
+            connected
+        };
+        if was_connected {
+            self.state.notify(StateChange::ConnectionChanged(false));
+            self.state.emit(Event::Disconnected).await;
+        }
+        self.state.stats().write().await.reset_link();
+        // The new simulator listener finds a running simulator again
+        if self.state.send().read().await.ds_mode() == DsMode::Simulation {
+            self.state.send().write().await.set_ds_mode(DsMode::Normal);
+            self.state.set_simulating(false);
+            self.state.emit(Event::DsModeChanged(DsMode::Normal)).await;
+        }
+    }
+
+    /// Changes the modes in which neutral joystick values are sent in place of the supplier's, see [`JoystickSuppression`]
+    pub async fn set_joystick_suppression(&mut self, suppression: JoystickSuppression) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_joystick_suppression(suppression);
+    }
+
+    pub async fn joystick_suppression(&self) -> JoystickSuppression {
+        self.state.send().read().await.joystick_suppression()
+    }
+
+    /// Sets whether joysticks after the last one the supplier gave values for are left out of control packets
+    ///
+    /// This keeps packets small when the supplier always returns every slot, and only a few have a controller in them.
+    /// The robot sees the joysticks left out as unplugged, same as the slots the official driver station leaves empty.
+    pub async fn set_compact_joysticks(&mut self, compact: bool) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_compact_joysticks(compact);
+    }
+
+    pub async fn compact_joysticks(&self) -> bool {
+        self.state.send().read().await.compact_joysticks()
+    }
+
+    /// Returns a receiver of the robot's console output, errors, and warnings, after the [`ConsoleLimits`]
+    ///
+    /// This works alongside the TCP consumer. A receiver that falls more than 1024 lines behind skips the oldest.
+    pub async fn watch_console(&self) -> broadcast::Receiver<ConsoleLine> {
+        self.state.tcp().read().await.watch_console()
+    }
+
+    /// Changes whether the console is logged through the `log` and `tracing` facades, under the `robot` target
+    ///
+    /// Lines are logged after the [`ConsoleLimits`], at the info level for output and at the warn and error levels for
+    /// the robot's warnings and errors. This is on by default with the `log` or `tracing` features, and does nothing
+    /// without them.
+    pub async fn set_log_console(&mut self, log: bool) {
+        self.state.tcp().write().await.set_log_console(log);
+    }
+
+    pub async fn log_console(&self) -> bool {
+        self.state.tcp().read().await.log_console()
+    }
+
+    /// Changes how console messages are limited before they reach the TCP consumer
+    pub async fn set_console_limits(&mut self, limits: ConsoleLimits) {
+        self.state
+            .tcp()
+            .write()
+            .await
+            .console_mut()
+            .set_limits(limits);
+    }
+
+    pub async fn console_limits(&self) -> ConsoleLimits {
+        self.state.tcp().read().await.console().limits()
+    }
+
+    /// Provides a closure that will be called when constructing outbound packets to append joystick values
+    ///
+    /// The closure is called without any driver station state locked, so it can't stall calls like [`DriverStation::mode`].
+    /// Several devices can be merged into one slot with a [`CompositeJoystick`].
+    ///
+    /// This adds the closure as the [`SUPPLIER_SOURCE`] at priority 0, see [`DriverStation::add_joystick_source`]. The
+    /// closure returns new vectors for every packet, a [`JoystickSource`] implementing [`JoystickSource::poll_into`]
+    /// can refill the ones it was given instead.
+    pub async fn set_joystick_supplier(
+        &mut self,
+        supplier: impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static,
+    ) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_joystick_supplier(supplier);
+    }
+
+    /// Adds a source of joystick values, replacing any source with the same name
+    ///
+    /// Each control packet uses the available source with the highest `priority`, such as a physical gamepad over
+    /// keyboard bindings, falling back to the next when it returns `None`. While no source is available the joysticks
+    /// are sent neutral. Switching sources raises [`Event::JoystickSourceChanged`].
+    pub async fn add_joystick_source(
+        &mut self,
+        name: &str,
+        priority: i32,
+        source: impl JoystickSource + 'static,
+    ) {
+        self.state.send().write().await.joystick_sources_mut().add(
+            name,
+            priority,
+            Arc::new(source),
+        );
+    }
+
+    /// Removes the named joystick source, returning whether there was one
+    pub async fn remove_joystick_source(&mut self, name: &str) -> bool {
+        self.state
+            .send()
+            .write()
+            .await
+            .joystick_sources_mut()
+            .remove(name)
+    }
+
+    /// Returns the name and priority of each joystick source, highest priority first
+    pub async fn joystick_sources(&self) -> Vec<(String, i32)> {
+        self.state.send().read().await.joystick_sources().names()
+    }
+
+    /// Changes how accurately control packets are spaced, see [`Pacing`]
+    pub async fn set_pacing(&mut self, pacing: Pacing) {
+        self.state.send().write().await.set_pacing(pacing);
+    }
+
+    pub async fn pacing(&self) -> Pacing {
+        self.state.send().read().await.pacing()
+    }
+
+    /// Changes which joystick supplier faults automatically disable the robot
+    ///
+    /// By default the robot is disabled if the supplier panics, returns malformed values, or returns values rejected by
+    /// [`JoystickValidation::Error`].
+    pub async fn set_joystick_safety(&mut self, safety: JoystickSafety) {
+        self.state.send().write().await.set_joystick_safety(safety);
+    }
+
+    /// Changes what is done with joystick values outside the limits of the protocol. The default is to clamp them
+    pub async fn set_joystick_validation(&mut self, validation: JoystickValidation) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_joystick_validation(validation);
+    }
+
+    /// Provides a closure that will be called when TCP packets are received from the roboRIO
+    ///
+    /// Example usage: Logging all stdout messages from robot code.
+    pub async fn set_tcp_consumer(
+        &mut self,
+        consumer: impl FnMut(TcpPacket) + Send + Sync + 'static,
+    ) {
+        self.state.tcp().write().await.set_tcp_consumer(consumer);
+    }
+
+    /// Provides a closure that will be called with the raw bytes of every UDP datagram and TCP frame exchanged with the roboRIO
+    ///
+    /// This is intended for custom logging and protocol debugging. The closure is called from the network tasks, and should return quickly.
+    pub fn set_packet_tap(
+        &mut self,
+        tap: impl Fn(Direction, &[u8], Instant) + Send + Sync + 'static,
+    ) {
+        self.state.set_packet_tap(Some(Box::new(tap)));
+    }
+
+    /// Removes the packet tap set with [`DriverStation::set_packet_tap`]
+    pub fn clear_packet_tap(&mut self) {
+        self.state.set_packet_tap(None);
+    }
+
+    /// Registers a NetworkTables client that should follow the robot this driver station is connected to
+    ///
+    /// The client is immediately told to connect to the current target.
+    pub fn set_network_tables(&mut self, nt: impl NetworkTables + 'static) {
+        self.state.set_network_tables(Some(Arc::new(nt)));
+    }
+
+    /// Removes the client set with [`DriverStation::set_network_tables`]
+    pub fn clear_network_tables(&mut self) {
+        self.state.set_network_tables(None);
+    }
+
+    /// Registers a receiver for alerts about connection loss, brownouts, estops, match phases, and joystick faults
+    pub fn set_alert_sink(&mut self, sink: impl AlertSink + 'static) {
+        self.state.set_alert_sink(Some(Box::new(sink)));
+    }
+
+    /// Removes the receiver set with [`DriverStation::set_alert_sink`]
+    pub fn clear_alert_sink(&mut self) {
+        self.state.set_alert_sink(None);
+    }
+
+    /// Sets which joysticks rumble, and how, as the match timer nears the end of a match
+    ///
+    /// The rumble is mixed into what robot code sets on the joysticks, and given to the joystick source through
+    /// [`JoystickSource::output`]. Takes effect the next time the timer is started. Pass `None` to stop rumbling.
+    pub fn set_endgame_rumble(&mut self, rumble: Option<EndgameRumble>) {
+        self.endgame_rumble = rumble;
+    }
+
+    /// Starts a practice match timer for a match `length` long, replacing any running timer
+    ///
+    /// The timer emits [`Event::MatchPhase`] events, and rumbles joysticks according to [`DriverStation::set_endgame_rumble`].
+    /// It doesn't enable or disable the robot.
+    pub fn start_match_timer(&mut self, length: Duration) {
+        self.stop_match_timer();
+        self.match_timer = Some(MatchTimer::start(
+            self.state.clone(),
+            length,
+            self.endgame_rumble.clone(),
+        ));
+    }
+
+    /// Starts the match timer for a whole practice match, as long as the configured [`PracticeTiming`]
+    pub fn start_practice_timer(&mut self) {
+        self.start_match_timer(self.session.practice.match_length());
+    }
+
+    #[inline(always)]
+    pub const fn practice_timing(&self) -> PracticeTiming {
+        self.session.practice
+    }
+
+    pub fn set_practice_timing(&mut self, timing: PracticeTiming) {
+        self.session.practice = timing;
+        self.session_changed();
+    }
+
+    /// Follows the match time reported by the field, for applications that learn it from an FMS
+    ///
+    /// This crate doesn't talk to an FMS itself. Each report moves the match timer to the time left, starting one if
+    /// none is following the field, so [`Event::MatchPhase`] and the endgame rumble keep to the field's clock. More
+    /// time left than the timer has, as when teleop starts after autonomous, starts a new timer. `None` stops it.
+    pub fn set_fms_match_time(&mut self, remaining: Option<Duration>) {
+        let Some(remaining) = remaining else {
+            self.stop_match_timer();
+            return;
+        };
+        if self
+            .match_timer
+            .as_ref()
+            .is_some_and(|timer| timer.report_fms(remaining))
+        {
+            return;
+        }
+        self.stop_match_timer();
+        self.match_timer = Some(MatchTimer::follow_fms(
+            self.state.clone(),
+            remaining,
+            self.endgame_rumble.clone(),
+        ));
+    }
+
+    pub fn stop_match_timer(&mut self) {
+        if let Some(timer) = self.match_timer.take() {
+            timer.stop();
+        }
+    }
+
+    /// Drives the match timer, connection watchdog, and timestamps from `clock` while in simulation mode, see
+    /// [`SimClock`]
+    ///
+    /// A running match timer keeps the clock it was started with.
+    pub fn set_sim_clock(&mut self, clock: SimClock) {
+        self.state.set_sim_clock(Some(clock));
+    }
+
+    /// Goes back to real time in simulation mode
+    pub fn clear_sim_clock(&mut self) {
+        self.state.set_sim_clock(None);
+    }
+
+    /// Reads timestamps, packet timings, and the date sent to the roboRIO, from `clock`, see [`Clock`]
+    ///
+    /// Match timers started afterwards follow the clock if it has a timebase, unless a [`SimClock`] is in use.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.state.set_clock(Arc::new(clock));
+    }
+
+    /// Sets the FMS connected bit of control packets, so robot code behaves as if it were at an event
+    ///
+    /// `DriverStation.isFMSAttached()` returns true in robot code while this is set, and the event name and match type
+    /// it reports can be set with [`DriverStation::set_match_info`]. No real FMS is involved.
+    pub async fn set_fms_attached(&mut self, attached: bool) {
+        self.state.send().write().await.set_fms_attached(attached);
+    }
+
+    pub async fn fms_attached(&self) -> bool {
+        self.state.send().read().await.fms_attached()
+    }
+
+    /// Sets the event name and match type reported to robot code
+    ///
+    /// Like the game specific message, this is sent again whenever the TCP connection is re-established.
+    pub async fn set_match_info(&mut self, info: MatchInfo) {
+        self.state.tcp().write().await.set_match_info(info);
+    }
+
+    pub async fn match_info(&self) -> Option<MatchInfo> {
+        self.state.tcp().read().await.match_info()
+    }
+
+    /// Waits until the current match info has been written to the TCP connection, see
+    /// [`DriverStation::game_data_delivered`]
+    pub async fn match_info_delivered(&self) {
+        let (mut delivered, version) = {
+            let tcp = self.state.tcp().read().await;
+            (tcp.watch_delivered(), tcp.match_info_version())
+        };
+        let _ = delivered
+            .wait_for(|delivered| delivered.match_info >= version)
+            .await;
+    }
+
+    /// Describes the joysticks in each slot to robot code, such as with [`Controller::descriptor`]
+    ///
+    /// Like the game specific message, the descriptors are sent again whenever the TCP connection is re-established.
+    /// Until descriptors are set, a WPILib simulator is sent descriptors matching the joysticks sent to it, with the
+    /// other slots empty.
+    ///
+    /// The joysticks sent are then checked against the descriptors, raising [`Event::JoystickShapeMismatch`] when one
+    /// sets an input its descriptor doesn't declare.
+    pub async fn set_joystick_descriptors(&mut self, descriptors: Vec<JoystickDescriptor>) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_declared_shapes(shape::declared_shapes(&descriptors));
+        self.state
+            .tcp()
+            .write()
+            .await
+            .set_joystick_descriptors(descriptors);
+    }
+
+    /// Returns the time left in the practice match, if the timer is running
+    pub fn match_time_remaining(&self) -> Option<Duration> {
+        self.match_timer.as_ref().map(MatchTimer::remaining)
+    }
+
+    /// Changes the alliance station chosen by the user, from the next packet
+    ///
+    /// The station isn't sent while the config locks one or the field assigned one, see [`AllianceSource`]. It's kept,
+    /// and sent again once neither does.
+    pub async fn set_alliance(&mut self, alliance: Alliance) {
+        command::queue(&self.state, Command::SetAlliance(alliance)).await;
+        self.session.alliance = alliance;
+        self.session_changed();
+    }
+
+    /// Moves the user's station to the next one, from red 1 through blue 3 and back, returning the new station
+    ///
+    /// Like [`DriverStation::set_alliance`], the next control packet carries the new station unless another source
+    /// overrides it. An invalid station moves to red 1.
+    pub async fn cycle_station(&mut self) -> Alliance {
+        let alliance = self.state.alliances().user().next_station();
+        self.set_alliance(alliance).await;
+        alliance
+    }
+
+    /// Sets the station assigned by the field, which takes precedence over the others, or clears it with `None`
+    ///
+    /// This crate doesn't talk to an FMS itself, this is for applications that learn the assignment from one.
+    pub async fn set_fms_alliance(&mut self, alliance: Option<Alliance>) {
+        command::select_alliance(&self.state, AllianceSource::Fms, alliance).await;
+    }
+
+    /// Returns the alliance station sent to the robot
+    pub fn alliance(&self) -> Alliance {
+        self.state.alliances().effective().0
+    }
+
+    /// Returns where the alliance station sent to the robot came from
+    pub fn alliance_source(&self) -> AllianceSource {
+        self.state.alliances().effective().1
+    }
+
+    /// Records which devices the operator locked to joystick slots, so they can be restored with the session
+    ///
+    /// The driver station doesn't read devices itself, this is for the application's joystick supplier.
+    pub fn set_joystick_locks(&mut self, locks: Vec<JoystickMapping>) {
+        self.session.joystick_locks = locks;
+        self.session.profile = None;
+        self.session_changed();
+    }
+
+    /// Locks the devices of the mapping profile `name` to their slots, failing if there's no such profile
+    ///
+    /// The profile's required joysticks replace those of [`JoystickSafety::required`]. The choice is saved with the
+    /// session, and lasts until the locks are changed with [`DriverStation::set_joystick_locks`].
+    pub async fn use_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profiles.iter().find(|profile| profile.name == name) else {
+            anyhow::bail!("There's no mapping profile named {:?}", name);
+        };
+        let required = required_slots(&profile.joysticks);
+        self.session.joystick_locks = profile.joysticks.clone();
+        self.session.profile = Some(profile.name.clone());
+        self.session_changed();
+        {
+            let mut send = self.state.send().write().await;
+            let mut safety = send.joystick_safety().clone();
+            safety.required = required;
+            send.set_joystick_safety(safety);
+        }
+        info!("Using mapping profile {:?}", name);
+        self.state
+            .emit(Event::MappingProfileChanged(name.to_string()))
+            .await;
+        Ok(())
+    }
+
+    /// Saves the current joystick locks as the mapping profile `name`, replacing any profile with that name
+    ///
+    /// Profiles are kept in [`DsConfig::profiles`], so an application saves them by writing [`DriverStation::profiles`]
+    /// there and saving the config.
+    pub fn save_profile(&mut self, name: &str) {
+        let profile = MappingProfile {
+            name: name.to_string(),
+            joysticks: self.session.joystick_locks.clone(),
+        };
+        match self
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name == name)
+        {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.session.profile = Some(name.to_string());
+        self.session_changed();
+    }
+
+    #[inline(always)]
+    pub fn profiles(&self) -> &[MappingProfile] {
+        &self.profiles
+    }
+
+    /// Returns the name of the mapping profile in use, if the locks came from one
+    pub fn profile(&self) -> Option<&str> {
+        self.session.profile.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn joystick_locks(&self) -> &[JoystickMapping] {
+        &self.session.joystick_locks
+    }
+
+    /// Returns the current operator settings
+    #[inline(always)]
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Applies previously saved operator settings
+    pub async fn restore_session(&mut self, session: Session) -> Result<()> {
+        command::select_alliance(&self.state, AllianceSource::User, Some(session.alliance)).await;
+        if let Some(ref message) = session.game_data {
+            self.set_game_specific_message(message).await?;
+        }
+        self.session = session;
+        self.session_changed();
+        Ok(())
+    }
+
+    /// Saves the session to `path` whenever it changes, first restoring the session already saved there if there is one
+    #[cfg(feature = "config")]
+    pub async fn persist_session(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        if let Some(saved) = Session::load_saved(path.clone()).await? {
+            self.restore_session(saved).await?;
+        }
+        // Replacing the sender ends the task saving to an earlier path
+        let (saver, sessions) = watch::channel(self.session.clone());
+        spawn_named("ds-session", session::save_changes(path, sessions));
+        self.session_saver = Some(saver);
+        Ok(())
+    }
+
+    /// Saves the session in the background, if it's being persisted
+    fn session_changed(&mut self) {
+        #[cfg(feature = "config")]
+        if let Some(ref saver) = self.session_saver {
+            saver.send_replace(self.session.clone());
+        }
+    }
+
+    /// Changes the given `mode` the robot will be in, from the next packet
+    ///
+    /// An enabled robot is disabled first, emitting [`Event::Disabled`] before [`Event::ModeChanged`], unless that's
+    /// turned off with [`set_disable_on_mode_change`](Self::set_disable_on_mode_change).
+    ///
+    /// Like the other control setters, this queues a [`Command`] for the send loop rather than changing the state
+    /// itself, so it's applied in order with those sent through a [`Commander`].
+    pub async fn set_mode(&mut self, mode: Mode) {
+        command::queue(&self.state, Command::SetMode(mode)).await;
+    }
+
+    /// Changes whether switching modes while enabled disables the robot first. This is on by default, as it is in the
+    /// official driver station
+    pub async fn set_disable_on_mode_change(&mut self, disable: bool) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_disable_on_mode_change(disable);
+    }
+
+    pub async fn disable_on_mode_change(&self) -> bool {
+        self.state.send().read().await.disable_on_mode_change()
+    }
+
+    /// Sets the artificial delay, jitter, reordering, and loss applied to packets sent to a simulator
+    ///
+    /// These conditions have no effect unless the driver station is in [`DsMode::Simulation`].
+    pub async fn set_link_conditions(&mut self, conditions: LinkConditions) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_link_conditions(conditions);
+    }
+
+    /// ORs `bits` into the control byte of every packet sent to a roboRIO, for robot controllers that extend the protocol
+    ///
+    /// Bits that overlap the mode, enable, or estop flags force them on, so this should be left at 0 for a stock roboRIO.
+    pub async fn set_extra_control_bits(&mut self, bits: u8) {
+        self.state.send().write().await.set_extra_control(bits);
+    }
+
+    /// Returns the bits ORed into the control byte of every packet
+    pub async fn extra_control_bits(&self) -> u8 {
+        self.state.send().read().await.extra_control()
+    }
+
+    /// Returns the link conditions currently applied in simulation mode
+    pub async fn link_conditions(&self) -> LinkConditions {
+        self.state.send().read().await.link_conditions()
+    }
+
+    pub async fn ds_mode(&self) -> DsMode {
+        self.state.send().read().await.ds_mode()
+    }
+
+    /// Changes the team number of this driver station, as well as the ip the driver station will attempt to connect to.
+    /// The ip of the new roboRIO target is 10.TE.AM.2, or the mDNS name of the roboRIO for seasons before 2020
+    pub fn set_team_number(&mut self, team_number: u16) {
+        self.team_number = team_number;
+        // Control packets for the cRIO contain the team number
+        #[cfg(feature = "crio")]
+        self.thread_tx
+            .send(Signal::NewTeamNumber(team_number))
+            .unwrap();
+        self.thread_tx
+            .send(Signal::NewTarget(self.protocol.robot_address(team_number)))
+            .unwrap();
+    }
+
+    /// Changes the season of the roboRIO image, see [`ProtocolYear`]
+    ///
+    /// This doesn't change the target, call [`DriverStation::set_team_number`] afterwards if the
+    /// robot should be found at the new season's address.
+    pub fn set_protocol_year(&mut self, protocol: ProtocolYear) {
+        self.protocol = protocol;
+    }
+
+    #[inline(always)]
+    pub const fn protocol_year(&self) -> ProtocolYear {
+        self.protocol
+    }
+
+    pub fn set_use_usb(&mut self, use_usb: bool) {
+        if use_usb {
+            self.thread_tx
+                .send(Signal::NewTarget("172.22.11.2".to_string()))
+                .unwrap();
+        } else {
+            self.thread_tx
+                .send(Signal::NewTarget(
+                    self.protocol.robot_address(self.team_number),
+                ))
+                .unwrap();
+        }
+    }
+
+    #[inline(always)]
+    pub const fn team_number(&self) -> u16 {
+        self.team_number
+    }
+
+    /// Sets the game specific message sent to the robot, and used during the autonomous period
+    ///
+    /// The message is sent again whenever the TCP connection to the robot is re-established. Fails if it isn't a valid
+    /// [`GameData`].
+    pub async fn set_game_specific_message(&mut self, message: &str) -> Result<()> {
+        self.set_game_data(GameData::new(message)?).await;
+        Ok(())
+    }
+
+    /// Sets the game specific message, such as one built for a season with [`GameData::power_up`]
+    pub async fn set_game_data(&mut self, data: GameData) {
+        if let Some(season) = data.season()
+            && season != self.protocol
+        {
+            warn!(
+                "Sending game data for {}, the robot runs the {} image",
+                season.year(),
+                self.protocol.year()
+            );
+        }
+        self.session.game_data = Some(data.message().to_string());
+        self.state.tcp().write().await.set_game_data(data);
+        self.session_changed();
+    }
+
+    /// Waits until the current game specific message has been written to the TCP connection
+    ///
+    /// The TCP connection opens some time after the robot starts answering control packets, and the message is only
+    /// sent once it does, so this can wait a while. It waits again after the connection closes, until the message is
+    /// sent on the next one. Returns straight away if no message is set, and never returns when running
+    /// [UDP-only](DsConfig::udp_only).
+    pub async fn game_data_delivered(&self) {
+        let (mut delivered, version) = {
+            let tcp = self.state.tcp().read().await;
+            (tcp.watch_delivered(), tcp.game_data_version())
+        };
+        let _ = delivered
+            .wait_for(|delivered| delivered.game_data >= version)
+            .await;
+    }
+
+    /// Returns the current mode of the robot
+    pub async fn mode(&self) -> Mode {
+        self.state.send().read().await.mode()
+    }
+
+    /// Returns the joystick values sent with the most recent control packet
+    pub async fn joysticks(&self) -> Vec<Vec<JoystickValue>> {
+        self.state.send().read().await.last_joysticks().to_vec()
+    }
+
+    /// Enables outputs on the robot, from the next packet
+    ///
+    /// If [`DriverStation::set_refuse_enable_on_conflict`] is set, the robot isn't enabled while another driver station seems to be controlling it.
+    /// If [`DriverStation::set_brownout_cooldown`] is set, it isn't enabled for a while after a brownout.
+    /// If [`DriverStation::set_arm_timeout`] is set, it isn't enabled unless [`DriverStation::arm`] was called within it.
+    /// Nothing is enabled after [`DriverStation::release_control`].
+    pub async fn enable(&mut self) {
+        command::queue(&self.state, Command::Enable).await;
+    }
+
+    /// Arms the robot, allowing one [`DriverStation::enable`] within the arm timeout
+    ///
+    /// This is the first step of a two-step enable, such as a frontend's "hold to enable" button arming on press and
+    /// enabling once held long enough. Does nothing unless [`DriverStation::set_arm_timeout`] is set.
+    pub async fn arm(&mut self) {
+        command::queue(&self.state, Command::Arm).await;
+    }
+
+    /// Cancels an arming that hasn't been used to enable yet, such as when the button is released early
+    pub async fn disarm(&mut self) {
+        command::queue(&self.state, Command::Disarm).await;
+    }
+
+    /// Returns whether the robot is armed and can be enabled
+    pub async fn armed(&self) -> bool {
+        self.state.send().read().await.armed(Instant::now())
+    }
+
+    /// Requires the robot to be [armed](DriverStation::arm) before enabling, with arming lasting for `timeout`, or
+    /// stops requiring it with `None`
+    ///
+    /// A refused enable emits [`Event::EnableRefusedNotArmed`].
+    pub async fn set_arm_timeout(&mut self, timeout: Option<Duration>) {
+        self.state.send().write().await.set_arm_timeout(timeout);
+    }
+
+    pub async fn arm_timeout(&self) -> Option<Duration> {
+        self.state.send().read().await.arm_timeout()
+    }
+
+    /// Shows that the application is still responsive, keeping the robot enabled while the watchdog is on
+    ///
+    /// This doesn't await or take any async locks, so it can be called from a UI's event loop every frame. See also
+    /// [`Commander::feed_watchdog`].
+    pub fn feed_watchdog(&self) {
+        self.state.watchdog().feed(Instant::now());
+    }
+
+    /// Disables the robot whenever [`DriverStation::feed_watchdog`] isn't called for `timeout`, or turns the watchdog off
+    /// with `None`
+    ///
+    /// The robot is disabled by the send loop, before the next control packet, and [`Event::WatchdogExpired`] is
+    /// emitted. Enabling feeds the watchdog.
+    pub fn set_watchdog(&mut self, timeout: Option<Duration>) {
+        self.state.watchdog().set_timeout(timeout);
+    }
+
+    pub fn watchdog(&self) -> Option<Duration> {
+        self.state.watchdog().timeout()
+    }
+
+    /// Returns when each running driver station task last made progress, to find the one that is stuck
+    ///
+    /// Tasks are listed by the names they're spawned with, such as `ds-send` and `ds-tcp`, which tokio-console also
+    /// shows with the `console` feature.
+    pub fn task_activity(&self) -> Vec<TaskActivity> {
+        self.state.task_activity()
+    }
+
+    /// Switches the robot to `mode` without awaiting, like [`DriverStation::set_mode`]
+    ///
+    /// Fails if the send loop has fallen behind, see [`Commander::try_send`].
+    pub fn set_mode_sync(&self, mode: Mode) -> Result<()> {
+        command::try_queue(self.state.commands_tx(), Command::SetMode(mode))
+    }
+
+    /// Enables or disables the robot without awaiting, like [`DriverStation::enable`] and [`DriverStation::disable`]
+    ///
+    /// Fails if the send loop has fallen behind, see [`Commander::try_send`].
+    pub fn set_enabled_sync(&self, enabled: bool) -> Result<()> {
+        let command = if enabled {
+            Command::Enable
+        } else {
+            Command::Disable
+        };
+        command::try_queue(self.state.commands_tx(), command)
+    }
+
+    /// Changes the alliance station chosen by the user without awaiting, like [`DriverStation::set_alliance`]
+    ///
+    /// Fails if the send loop has fallen behind, see [`Commander::try_send`]. Unlike [`DriverStation::set_alliance`],
+    /// the station isn't saved with the session.
+    pub fn set_alliance_sync(&self, alliance: Alliance) -> Result<()> {
+        command::try_queue(self.state.commands_tx(), Command::SetAlliance(alliance))
+    }
+
+    /// Returns a handle that controls the robot without awaiting, see [`Commander`]
+    ///
+    /// Commands sent through it are applied by the send loop, so they don't wait on other callers of the driver station.
+    pub fn commander(&self) -> Commander {
+        self.state.commander()
+    }
+
+    /// Takes over sending control packets, for experimenting with the protocol
+    ///
+    /// While the sink is alive the send loop stops building packets, so joysticks and the watchdog have no effect and
+    /// commands only change the state, and sends each packet pushed through the sink instead. Status packets are still decoded into telemetry.
+    /// Dropping the sink, or creating another, hands sending back. The sink stops accepting packets if the connection
+    /// tasks restart, such as on [`DriverStation::reconfigure`], and isn't used with the cRIO protocol.
+    pub fn control_sink(&self) -> ControlSink {
+        let (tx, rx) = tokio::sync::mpsc::channel(manual::MANUAL_CAPACITY);
+        self.state.set_manual_rx(rx);
+        ControlSink::new(tx)
+    }
+
+    /// Hands the robot over to another driver station
+    ///
+    /// The robot is disabled, and after a few disabled control packets nothing more is sent and the TCP connection is
+    /// closed, so the robot doesn't alternate between two driver stations. Call [`DriverStation::take_control`] to resume.
+    pub async fn release_control(&mut self) {
+        let mut send = self.state.send().write().await;
+        if send.released() {
+            return;
+        }
+        let was_enabled = send.enabled();
+        send.disable();
+        send.set_released(true);
+        drop(send);
+
+        let _ = self.thread_tx.send(Signal::ReleaseControl);
+        if was_enabled {
+            self.state.emit(Event::Disabled).await;
+        }
+        self.state.emit(Event::ControlReleased).await;
+    }
+
+    /// Resumes controlling the robot after [`DriverStation::release_control`]
+    ///
+    /// The robot stays disabled until it's enabled again.
+    pub async fn take_control(&mut self) {
+        let mut send = self.state.send().write().await;
+        if !send.released() {
+            return;
+        }
+        send.disable();
+        send.set_released(false);
+        drop(send);
+        self.state.emit(Event::ControlTaken).await;
+    }
+
+    /// Returns false if control was released with [`DriverStation::release_control`]
+    pub async fn in_control(&self) -> bool {
+        !self.state.send().read().await.released()
+    }
+
+    /// Returns the symptom of another driver station controlling the robot, if one has been seen
+    ///
+    /// Only detected with the roboRIO protocol.
+    pub async fn control_conflict(&self) -> Option<ControlConflict> {
+        self.state.link().read().await.conflict()
+    }
+
+    /// Changes whether [`DriverStation::enable`] does nothing while [`DriverStation::control_conflict`] is `Some`
+    pub fn set_refuse_enable_on_conflict(&mut self, refuse: bool) {
+        self.state.set_refuse_enable_on_conflict(refuse);
+    }
+
+    /// Returns whether the TCP connection is left closed, see [`DsConfig::udp_only`]
+    pub fn udp_only(&self) -> bool {
+        self.state.udp_only()
+    }
+
+    /// Changes how far the battery voltage has to drop after enabling to emit [`Event::VoltageSag`]. The default is 1.5V
+    pub async fn set_sag_threshold(&mut self, threshold: f32) {
+        self.state
+            .stats()
+            .write()
+            .await
+            .set_sag_threshold(threshold);
+    }
+
+    pub async fn sag_threshold(&self) -> f32 {
+        self.state.stats().read().await.sag_threshold()
+    }
+
+    /// Refuses to enable for `cooldown` after the roboRIO last reported a brownout, or never with `None`
+    ///
+    /// A refused enable emits [`Event::EnableRefusedBrownout`] rather than leaving the robot silently disabled.
+    pub async fn set_brownout_cooldown(&mut self, cooldown: Option<Duration>) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_brownout_cooldown(cooldown);
+    }
 
-use std::sync::Arc;
+    pub async fn brownout_cooldown(&self) -> Option<Duration> {
+        self.state.send().read().await.brownout_cooldown()
+    }
 
-use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+    /// Sends `request` with the next control packet, along with any requests not yet sent
+    pub async fn request(&mut self, request: RequestFlags) {
+        command::queue(&self.state, Command::Request(request)).await;
+    }
 
-use crate::proto::tcp::outbound::{GameData, TcpTag};
-use crate::proto::udp::inbound::types::Trace;
-use crate::proto::udp::outbound::types::*;
-use crate::util::ip_from_team_number;
-use crate::{Result, TcpPacket};
+    /// Returns the requests that will be sent with the next control packet
+    pub async fn pending_requests(&self) -> RequestFlags {
+        self.state.send().read().await.pending_requests()
+    }
 
-/// Represents a connection to the roboRIO acting as a driver station
-///
-/// This struct will contain relevant functions to update the state of the robot,
-/// and also manages the threads that manage network connections and joysticks
-pub struct DriverStation {
-    thread_tx: UnboundedSender<Signal>,
-    team_number: u16,
-    state: Arc<DsState>,
-}
+    /// Returns the sequence number of the next control packet, which wraps from 65535 to 0
+    ///
+    /// The roboRIO echoes it in the status packet answering each control packet.
+    pub async fn seqnum(&self) -> u16 {
+        self.state.send().read().await.seqnum()
+    }
 
-impl DriverStation {
-    /// Creates a new driver station with the given team number and alliance
+    /// Starts the sequence numbers of control packets at `seqnum`, now and whenever the target changes
     ///
-    /// This driver station will attempt to connect to a roboRIO at 10.TE.AM.2,
-    /// if the roboRIO is at a different ip, use [new] and specify the ip directly.
-    pub async fn new_team(team_number: u16, alliance: Alliance) -> DriverStation {
-        Self::new(
-            &ip_from_team_number(team_number),
-            alliance,
-            team_number,
-        )
-        .await
+    /// This is meant for tests and replays that have to line up with recorded sequence numbers, such as by starting
+    /// just before the rollover.
+    pub async fn set_initial_seqnum(&mut self, seqnum: u16) {
+        self.state.send().write().await.set_initial_seqnum(seqnum);
+        // Answers to the restarted sequence would otherwise look older than the ones before
+        self.state.link().write().await.forget_seqnum();
     }
 
-    /// Creates a new driver station for the given alliance station and team number
-    /// Connects to the roborio at `ip`. To infer the ip from team_number, use `new_team` instead.
-    pub async fn new(ip: &str, alliance: Alliance, team_number: u16) -> DriverStation {
-        // Channels to communicate to the threads that make up the application, used to break out of infinite loops when the struct is dropped
-        let (tx, rx) = unbounded_channel::<Signal>();
+    /// Instructs the roboRIO to restart robot code
+    pub async fn restart_code(&mut self) {
+        self.request(RequestFlags::RESTART_CODE).await;
+    }
 
-        // Global state of the driver station
-        let state = Arc::new(DsState::new(alliance));
+    /// Instructs the roboRIO to reboot
+    pub async fn restart_roborio(&mut self) {
+        self.request(RequestFlags::REBOOT_ROBORIO).await;
+    }
 
-        // Thread containing UDP sockets communicating with the roboRIO
-        let udp_state = state.clone();
-        let udp_ip = ip.to_owned();
+    /// Returns whether the robot is currently enabled
+    pub async fn enabled(&self) -> bool {
+        self.state.send().read().await.enabled()
+    }
 
-        let sim_tx = tx.clone();
-        tokio::spawn(async {
-            sim_conn(sim_tx).await.unwrap();
-        });
-        udp_conn(udp_state, udp_ip, rx)
-            .await
-            .expect("Error with udp connection");
+    /// Returns the last received Trace from the robot
+    pub async fn trace(&self) -> Trace {
+        self.state.telemetry().read().await.trace()
+    }
 
-        DriverStation {
-            thread_tx: tx,
-            state,
-            team_number,
-        }
+    /// Returns the last 256 changes of the status and trace bytes reported by the robot, oldest first
+    ///
+    /// Unlike [`DriverStation::trace`], this catches changes that didn't last, such as robot code that crashed and
+    /// restarted. It's kept across disconnects.
+    pub async fn trace_history(&self) -> Vec<StatusTransition> {
+        self.state.telemetry().read().await.history().transitions()
     }
 
-    /// Provides a closure that will be called when constructing outbound packets to append joystick values
-    pub async fn set_joystick_supplier(
-        &mut self,
-        supplier: impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static,
-    ) {
-        self.state
-            .send()
-            .write()
-            .await
-            .set_joystick_supplier(supplier);
+    /// Returns the last received battery voltage from the robot
+    pub async fn battery_voltage(&self) -> f32 {
+        self.state.telemetry().read().await.battery_voltage()
     }
 
-    /// Provides a closure that will be called when TCP packets are received from the roboRIO
+    /// Returns when a tag of the given kind was last received from the robot
+    pub async fn tag_last_received(&self, tag: InboundTagKind) -> Option<Instant> {
+        self.state.telemetry().read().await.tag_received(tag)
+    }
+
+    /// Returns whether the robot stopped reporting tags of the given kind, or never reported one, within `max_age`
     ///
-    /// Example usage: Logging all stdout messages from robot code.
-    pub async fn set_tcp_consumer(
-        &mut self,
-        consumer: impl FnMut(TcpPacket) + Send + Sync + 'static,
-    ) {
-        self.state.tcp().write().await.set_tcp_consumer(consumer);
+    /// UIs can use this to grey out data instead of showing values that stopped updating.
+    pub async fn tag_is_stale(&self, tag: InboundTagKind, max_age: Duration) -> bool {
+        self.tag_last_received(tag)
+            .await
+            .is_none_or(|at| self.state.instant().saturating_duration_since(at) > max_age)
     }
 
-    /// Changes the alliance for the given `DriverStation`
-    pub async fn set_alliance(&mut self, alliance: Alliance) {
-        self.state.send().write().await.set_alliance(alliance);
+    /// Returns what is known about the robot, from the configuration and what it has reported
+    pub async fn identity(&self) -> RobotIdentity {
+        let mut identity = self.state.telemetry().read().await.identity().clone();
+        identity.team_number = self.team_number;
+        identity.target = self.state.target();
+        identity
     }
 
-    /// Changes the given `mode` the robot will be in
-    pub async fn set_mode(&mut self, mode: Mode) {
-        self.state.send().write().await.set_mode(mode);
+    /// Returns the status and trace bytes of the last status packet, including bits this crate doesn't understand
+    ///
+    /// This is `None` until a status packet is received from a roboRIO or simulator.
+    pub async fn raw_status(&self) -> Option<RawStatus> {
+        self.state.telemetry().read().await.raw_status()
     }
 
-    pub async fn ds_mode(&self) -> DsMode {
-        self.state.send().read().await.ds_mode()
+    /// Returns what the robot asked for in the last status packet, such as [`RobotRequests::DATE`]
+    pub async fn robot_requests(&self) -> RobotRequests {
+        self.state.telemetry().read().await.requests()
     }
 
-    /// Changes the team number of this driver station, as well as the ip the driver station will attempt to connect to.
-    /// The ip of the new roboRIO target is 10.TE.AM.2
-    pub fn set_team_number(&mut self, team_number: u16) {
-        self.team_number = team_number;
-        self.thread_tx
-            .send(Signal::NewTarget(
-                ip_from_team_number(team_number),
-            ))
-            .unwrap();
+    /// Returns the number of status packets in which the robot asked for the date and time
+    ///
+    /// Each is answered with the next control packet, so this and [`dates_sent`](Self::dates_sent) should stay close.
+    /// A roboRIO that keeps asking after the date was sent isn't accepting it.
+    pub async fn date_requests(&self) -> u64 {
+        self.state.telemetry().read().await.date_requests()
     }
 
-    pub fn set_use_usb(&mut self, use_usb: bool) {
-        if use_usb {
-            self.thread_tx
-                .send(Signal::NewTarget("172.22.11.2".to_string()))
-                .unwrap();
-        } else {
-            self.thread_tx
-                .send(Signal::NewTarget(
-                    ip_from_team_number(self.team_number),
-                ))
-                .unwrap();
-        }
+    /// Returns the number of control packets that carried the date and time
+    pub async fn dates_sent(&self) -> u64 {
+        self.state.send().read().await.dates_sent()
     }
 
-    #[inline(always)]
-    pub const fn team_number(&self) -> u16 {
-        self.team_number
+    /// Returns whether the roboRIO is reporting a brownout
+    pub async fn brownout(&self) -> bool {
+        self.state.telemetry().read().await.brownout()
     }
 
-    /// Sets the game specific message sent to the robot, and used during the autonomous period
-    pub async fn set_game_specific_message(&mut self, message: &str) -> Result<()> {
-        if message.len() != 3 {
-            bail!("Message should be 3 characters long");
-        }
+    /// Returns the free disk space on the roboRIO in bytes, if it has reported it since connecting
+    pub async fn free_disk(&self) -> Option<u32> {
+        self.state.telemetry().read().await.resources().free_disk()
+    }
 
-        let _ = self
-            .state
-            .tcp()
+    /// Returns the free memory on the roboRIO in bytes, if it has reported it since connecting
+    pub async fn free_ram(&self) -> Option<u32> {
+        self.state.telemetry().read().await.resources().free_ram()
+    }
+
+    /// Changes how little free disk space or memory the roboRIO can report before an event is raised, see
+    /// [`ResourceLimits`]
+    pub async fn set_resource_limits(&mut self, limits: ResourceLimits) {
+        self.state
+            .telemetry()
             .write()
             .await
-            .queue_tcp(TcpTag::GameData(GameData {
-                gsm: message.to_string(),
-            }));
-        Ok(())
+            .resources_mut()
+            .set_limits(limits);
     }
 
-    /// Returns the current mode of the robot
-    pub async fn mode(&self) -> Mode {
-        self.state.send().read().await.mode()
+    pub async fn resource_limits(&self) -> ResourceLimits {
+        self.state.telemetry().read().await.resources().limits()
     }
 
-    /// Enables outputs on the robot
-    pub async fn enable(&mut self) {
-        self.state.send().write().await.enable();
+    /// Returns the CAN bus metrics the roboRIO last reported, if it has since connecting
+    pub async fn can_metrics(&self) -> Option<CANMetrics> {
+        self.state.telemetry().read().await.can().latest()
     }
 
-    /// Instructs the roboRIO to restart robot code
-    pub async fn restart_code(&mut self) {
+    /// Changes when the CAN bus is considered saturated, see [`CanUtilizationAlert`]
+    pub async fn set_can_utilization_alert(&mut self, alert: CanUtilizationAlert) {
         self.state
-            .send()
+            .telemetry()
             .write()
             .await
-            .request(Request::RESTART_CODE);
+            .can_mut()
+            .set_alert(alert);
     }
 
-    /// Instructs the roboRIO to reboot
-    pub async fn restart_roborio(&mut self) {
+    pub async fn can_utilization_alert(&self) -> CanUtilizationAlert {
+        self.state.telemetry().read().await.can().alert()
+    }
+
+    /// Returns the state of the robot and the connection to it, with every lock read at once
+    ///
+    /// This is meant for UIs that redraw everything each frame, and would otherwise take each lock several times.
+    pub async fn status_snapshot(&self) -> DsSnapshot {
+        let send = self.state.send().read().await;
+        let telemetry = self.state.telemetry().read().await;
+        let link = self.state.link().read().await;
+        let tcp = self.state.tcp().read().await;
+        let stats = self.state.stats().read().await;
+        DsSnapshot {
+            battery_voltage: telemetry.battery_voltage(),
+            mode: send.mode(),
+            enabled: send.enabled(),
+            robot_enabled: telemetry.reported_enabled(),
+            estopped: send.estopped(),
+            brownout: telemetry.brownout(),
+            trace: telemetry.trace(),
+            connected: link.connected(),
+            tcp_connected: tcp.connected(),
+            udp_only: self.state.udp_only(),
+            latency: stats.snapshot(self.state.instant()).latency,
+            match_time_remaining: self.match_time_remaining(),
+        }
+    }
+
+    /// Returns a receiver of changes to the state reported by the robot and to the connection to it
+    ///
+    /// Each receiver gets every change made after subscribing. One that falls more than 256 changes behind skips the oldest,
+    /// and gets a [`RecvError::Lagged`](tokio::sync::broadcast::error::RecvError::Lagged).
+    pub fn subscribe(&self) -> broadcast::Receiver<StateChange> {
+        self.state.subscribe()
+    }
+
+    /// Returns a receiver of summaries of the telemetry, one every [`DsConfig::telemetry_window`]
+    ///
+    /// This is for UIs and forwarders that don't need every status packet. A receiver that falls more than 16 windows
+    /// behind skips the oldest.
+    pub fn subscribe_windows(&self) -> broadcast::Receiver<TelemetryWindow> {
+        self.state.subscribe_windows()
+    }
+
+    /// Changes how much telemetry each window summarises, or stops publishing them with zero
+    pub async fn set_telemetry_window(&mut self, length: Duration) {
+        self.state.stats().write().await.set_window_length(length);
+    }
+
+    pub async fn telemetry_window(&self) -> Duration {
+        self.state.stats().read().await.window_length()
+    }
+
+    /// Returns a receiver of every control packet sent, or one in every [`set_packet_sample`](Self::set_packet_sample)
+    ///
+    /// At the full rate this is 50 a second. A receiver that falls more than 256 packets behind skips the oldest.
+    pub fn subscribe_packets(&self) -> broadcast::Receiver<PacketSent> {
+        self.state.subscribe_packets()
+    }
+
+    /// Only sends every `every`th control packet to [`subscribe_packets`](Self::subscribe_packets) receivers, by
+    /// sequence number. The default of 1 sends all of them
+    pub fn set_packet_sample(&mut self, every: u32) {
+        self.state.set_packet_sample(every);
+    }
+
+    /// Returns a receiver of the button edges and significant axis moves in the joystick values sent to the robot
+    ///
+    /// Like [`subscribe`](Self::subscribe), a receiver that falls more than 256 changes behind skips the oldest.
+    pub fn subscribe_inputs(&self) -> broadcast::Receiver<InputChange> {
+        self.state.subscribe_inputs()
+    }
+
+    /// Changes how far an axis has to move before an [`InputChange::AxisMoved`] is sent. The default is 0.05
+    pub async fn set_axis_change_threshold(&mut self, threshold: f32) {
         self.state
             .send()
             .write()
             .await
-            .request(Request::REBOOT_ROBORIO);
+            .set_axis_change_threshold(threshold);
     }
 
-    /// Returns whether the robot is currently enabled
-    pub async fn enabled(&self) -> bool {
-        self.state.send().read().await.enabled()
+    pub async fn axis_change_threshold(&self) -> f32 {
+        self.state.send().read().await.axis_change_threshold()
     }
 
-    /// Returns the last received Trace from the robot
-    pub async fn trace(&self) -> Trace {
-        self.state.recv().read().await.trace()
+    /// Returns the current estimate of the robot's clock relative to this driver station's
+    ///
+    /// The estimate is made from the timestamps of console messages, so it's only available once the robot has printed something.
+    pub async fn clock_sync(&self) -> ClockSync {
+        self.state.link().read().await.clock().estimate()
     }
 
-    /// Returns the last received battery voltage from the robot
-    pub async fn battery_voltage(&self) -> f32 {
-        self.state.recv().read().await.battery_voltage()
+    /// Returns statistics about the health of the connection to the robot
+    ///
+    /// With the `metrics` feature enabled these are also published through the [`metrics`](https://docs.rs/metrics) facade.
+    pub async fn stats(&self) -> Stats {
+        self.state
+            .stats()
+            .read()
+            .await
+            .snapshot(self.state.instant())
+    }
+
+    /// Returns the summary of the practice run so far, which can be saved with [`MatchSummary::write_json`]
+    ///
+    /// [`DriverStation::shutdown`] also returns the summary, as part of its report.
+    pub async fn match_summary(&self) -> MatchSummary {
+        self.state
+            .stats()
+            .read()
+            .await
+            .match_summary(self.state.instant())
+    }
+
+    /// Returns the summary of the practice run so far, and starts summarising the next one
+    pub async fn take_match_summary(&self) -> MatchSummary {
+        self.state
+            .stats()
+            .write()
+            .await
+            .take_match_summary(self.state.instant())
     }
 
     /// Queues a UDP tag to be transmitted with the next outbound packet to the roboRIO
@@ -205,13 +1555,50 @@ impl DriverStation {
     }
 
     /// Queues a TCP tag to be transmitted to the roboRIO
+    ///
+    /// While the TCP connection is closed the tag is kept, along with up to 63 others, and sent once it opens. A tag the
+    /// connection closed before writing is sent on the next one.
     pub async fn queue_tcp(&mut self, tcp_tag: TcpTag) {
-        let _ = self.state.tcp().write().await.queue_tcp(tcp_tag);
+        self.state.tcp().write().await.queue_tcp(tcp_tag);
+    }
+
+    /// Sends the command `name` to a cooperating robot program, and waits up to `timeout` for its response
+    ///
+    /// Fails if the TCP connection is closed or closes before the robot responds, or the robot doesn't respond in time.
+    /// The response's status is up to the robot program, check [`CommandResponse::succeeded`](crate::CommandResponse::succeeded).
+    #[cfg(feature = "extensions")]
+    pub async fn send_command(
+        &self,
+        name: &str,
+        payload: impl Into<bytes::Bytes>,
+        timeout: Duration,
+    ) -> Result<crate::CommandResponse> {
+        let (id, rx) = self
+            .state
+            .tcp()
+            .write()
+            .await
+            .send_command(name, payload.into())?;
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => anyhow::bail!(
+                "The TCP connection closed before the robot responded to {:?}",
+                name
+            ),
+            Err(_) => {
+                self.state.tcp().write().await.cancel_command(id);
+                anyhow::bail!(
+                    "The robot didn't respond to {:?} within {:?}",
+                    name,
+                    timeout
+                )
+            }
+        }
     }
 
-    /// Disables outputs on the robot and disallows enabling it until the code is restarted.
+    /// Disables outputs on the robot and disallows enabling it until the code is restarted, from the next packet
     pub async fn estop(&mut self) {
-        self.state.send().write().await.estop();
+        command::queue(&self.state, Command::Estop).await;
     }
 
     /// Returns whether the robot is currently E-stopped
@@ -219,9 +1606,26 @@ impl DriverStation {
         self.state.send().read().await.estopped()
     }
 
-    /// Disables outputs on the robot
+    /// Disables outputs on the robot, from the next packet
     pub async fn disable(&mut self) {
-        self.state.send().write().await.disable();
+        command::queue(&self.state, Command::Disable).await;
+    }
+
+    /// Returns the most recent events, oldest first
+    ///
+    /// Only a bounded number of events are kept, see [`DriverStation::set_event_log_capacity`].
+    pub async fn recent_events(&self) -> Vec<TimedEvent> {
+        self.state.events().read().await.recent()
+    }
+
+    /// Returns the kept events that happened at or after `since`, oldest first
+    pub async fn events_since(&self, since: DateTime<Utc>) -> Vec<TimedEvent> {
+        self.state.events().read().await.since(since)
+    }
+
+    /// Changes how many of the most recent events are kept. The default is 256
+    pub async fn set_event_log_capacity(&mut self, capacity: usize) {
+        self.state.events().write().await.set_capacity(capacity);
     }
 }
 
@@ -264,16 +1668,451 @@ impl JoystickValue {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Drop for DriverStation {
     fn drop(&mut self) {
+        if self.connect_started() {
+            self.final_burst
+                .send_now(&self.state, |send| self.final_packet(send));
+        }
         // When this struct is dropped the threads that we spawned should be stopped otherwise we're leaking
         let _ = self.thread_tx.send(Signal::Disconnect);
+        self.conn_tasks.abort_listeners();
+        self.stop_match_timer();
+    }
+}
+
+/// Returns the message a task panicked with
+#[cfg(not(target_arch = "wasm32"))]
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "The connection task panicked".to_string(),
+        },
+    }
+}
+
+/// Returns the slots of the required joysticks in `mappings`
+#[cfg(not(target_arch = "wasm32"))]
+fn required_slots(mappings: &[JoystickMapping]) -> Vec<usize> {
+    mappings
+        .iter()
+        .filter(|mapping| mapping.required)
+        .map(|mapping| mapping.slot)
+        .collect()
+}
+
+/// Applies the settings in `config` that live in the state shared with the connection tasks
+#[cfg(not(target_arch = "wasm32"))]
+async fn apply_config(state: &DsState, config: &DsConfig) {
+    {
+        let mut send = state.send().write().await;
+        send.set_pacing(config.pacing);
+        send.set_brownout_cooldown(config.brownout_cooldown);
+        send.set_arm_timeout(config.arm_timeout);
+        send.set_joystick_suppression(config.joystick_suppression);
+        send.set_compact_joysticks(config.compact_joysticks);
+    }
+    state
+        .stats()
+        .write()
+        .await
+        .set_window_length(config.telemetry_window);
+    state.set_refuse_enable_on_conflict(config.refuse_enable_on_conflict);
+    state.set_udp_only(config.udp_only);
+    state.watchdog().set_timeout(config.watchdog);
+    if config.udp_only {
+        info!("Running UDP-only, the TCP connection will stay closed");
+    }
+    state
+        .tcp()
+        .write()
+        .await
+        .console_mut()
+        .set_limits(config.console);
+    {
+        let mut telemetry = state.telemetry().write().await;
+        telemetry.resources_mut().set_limits(config.resource_limits);
+        telemetry.can_mut().set_alert(config.can_utilization);
+    }
+    let required = required_slots(&config.joysticks);
+    state
+        .send()
+        .write()
+        .await
+        .set_joystick_safety(JoystickSafety {
+            required,
+            ..JoystickSafety::default()
+        });
+}
+
+/// Spawns the tasks talking to the robot at `ip`, signalled through `rx`, returning them
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_conn(
+    state: &Arc<DsState>,
+    config: &DsConfig,
+    ip: String,
+    tx: &UnboundedSender<Signal>,
+    rx: UnboundedReceiver<Signal>,
+    backend: Backend,
+) -> ConnTasks {
+    // Thread containing UDP sockets communicating with the roboRIO
+    let udp_state = state.clone();
+    let ports = config.ports;
+    let reconnect = config.reconnect;
+    let socket = config.socket.clone();
+    let filter = config.source_filter.clone();
+
+    // Failing to bind, usually because another driver station holds the port, is reported instead of panicking the task
+    let error_state = state.clone();
+    state.set_shutdown_reason(None);
+    match backend {
+        Backend::RoboRio => {
+            let root = spawn_named("ds-recv", async move {
+                if let Err(e) = udp_conn(udp_state, ip, rx, ports, reconnect, socket, filter).await
+                {
+                    error!("Error with udp connection: {}", e);
+                    error_state.set_shutdown_reason(Some(ShutdownReason::Failed(format!(
+                        "UDP connection failed: {}",
+                        e
+                    ))));
+                    error_state
+                        .emit(Event::Error(format!("UDP connection failed: {}", e)))
+                        .await;
+                }
+            });
+            let sim_listener = config.simulator.then(|| {
+                let sim_tx = tx.clone();
+                spawn_named("ds-sim-listener", async move {
+                    if let Err(e) = sim_conn(sim_tx, ports.simulator).await {
+                        warn!("Simulator detection stopped: {}", e);
+                    }
+                })
+            });
+            ConnTasks {
+                root: Some(root),
+                sim_listener,
+            }
+        }
+        #[cfg(feature = "crio")]
+        Backend::Crio => {
+            let team_number = config.team_number;
+            let root = spawn_named("ds-crio", async move {
+                if let Err(e) = crio_conn(
+                    udp_state,
+                    ip,
+                    team_number,
+                    rx,
+                    ports,
+                    reconnect,
+                    socket,
+                    filter,
+                )
+                .await
+                {
+                    error!("Error with cRIO connection: {}", e);
+                    error_state.set_shutdown_reason(Some(ShutdownReason::Failed(format!(
+                        "cRIO connection failed: {}",
+                        e
+                    ))));
+                    error_state
+                        .emit(Event::Error(format!("cRIO connection failed: {}", e)))
+                        .await;
+                }
+            });
+            ConnTasks {
+                root: Some(root),
+                sim_listener: None,
+            }
+        }
+    }
+}
+
+/// The tasks talking to the robot, which haven't been started before [`DriverStation::connect`]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct ConnTasks {
+    /// The task owning the sockets, which stops the others it spawned once it's told to disconnect
+    root: Option<JoinHandle<()>>,
+    /// Finds a running simulator, if [`DsConfig::simulator`] is set
+    sim_listener: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConnTasks {
+    /// Stops the tasks that don't stop when the root is told to disconnect
+    fn abort_listeners(&self) {
+        if let Some(ref task) = self.sim_listener {
+            task.abort();
+        }
+    }
+
+    /// Takes the tasks to wait for, the root first
+    fn take(&mut self) -> impl Iterator<Item = JoinHandle<()>> + use<> {
+        self.root.take().into_iter().chain(self.sim_listener.take())
+    }
+}
+
+/// The parts of a [`DsConfig`] the connection tasks are started from, and have to be started again to change
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq)]
+struct ConnSettings {
+    ports: Ports,
+    reconnect: ReconnectPolicy,
+    socket: SocketOptions,
+    source_filter: SourceFilter,
+    simulator: bool,
+    udp_only: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConnSettings {
+    fn of(config: &DsConfig) -> ConnSettings {
+        ConnSettings {
+            ports: config.ports,
+            reconnect: config.reconnect,
+            socket: config.socket.clone(),
+            source_filter: config.source_filter.clone(),
+            simulator: config.simulator,
+            udp_only: config.udp_only,
+        }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn target_of(config: &DsConfig) -> String {
+    config
+        .ip
+        .clone()
+        .unwrap_or_else(|| config.protocol.robot_address(config.team_number))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub(crate) enum Signal {
     Disconnect,
     NewTarget(String),
     NewMode(DsMode),
+    ReleaseControl,
+    #[cfg(feature = "crio")]
+    NewTeamNumber(u16),
+}
+
+/// The protocol spoken to the robot controller
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Backend {
+    RoboRio,
+    #[cfg(feature = "crio")]
+    Crio,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::UdpSocket as TokioUdpSocket;
+    use tokio::time::timeout;
+
+    fn config(ports: Ports) -> DsConfig {
+        DsConfig {
+            ip: Some("127.0.0.1".to_string()),
+            simulator: false,
+            udp_only: true,
+            ports,
+            ..DsConfig::default()
+        }
+    }
+
+    /// Returns the sequence number and control bits of the next packet the robot receives, if one arrives soon
+    async fn next_packet(robot: &TokioUdpSocket) -> Option<(u16, Control)> {
+        let mut buf = [0u8; 1500];
+        let (len, _) = timeout(Duration::from_millis(200), robot.recv_from(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        (len >= 6).then(|| {
+            (
+                u16::from_be_bytes([buf[0], buf[1]]),
+                Control::from_bits_truncate(buf[3]),
+            )
+        })
+    }
+
+    /// Waits for the robot to be sent an enabled packet, returning its sequence number
+    async fn wait_enabled(robot: &TokioUdpSocket) -> u16 {
+        while let Some((seqnum, control)) = next_packet(robot).await {
+            if control.contains(Control::ENABLED) {
+                return seqnum;
+            }
+        }
+        panic!("The robot was never enabled");
+    }
+
+    #[tokio::test]
+    async fn verify_reconfigure_keeps_the_link_for_other_settings() {
+        let ports = Ports {
+            robot_udp: 41412,
+            ds_udp: 41452,
+            tcp: 41642,
+            simulator: 41637,
+        };
+        let robot = TokioUdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut config = config(ports);
+        let mut ds = DriverStation::from_config(&config).await;
+        ds.enable().await;
+        let before = wait_enabled(&robot).await;
+
+        config.pacing = Pacing::Precise;
+        config.watchdog = Some(Duration::from_secs(60));
+        ds.reconfigure(&config).await;
+        assert!(ds.enabled().await);
+        assert_eq!(ds.watchdog(), Some(Duration::from_secs(60)));
+        // The same send loop carries on, rather than one counting from zero again
+        let after = wait_enabled(&robot).await;
+        assert!(after > before);
+        assert!(
+            ds.conn_tasks
+                .root
+                .as_ref()
+                .is_some_and(|task| !task.is_finished())
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_reconfigure_restarts_the_link_for_new_ports() {
+        let ports = Ports {
+            robot_udp: 41413,
+            ds_udp: 41453,
+            tcp: 41643,
+            simulator: 41638,
+        };
+        let moved = Ports {
+            robot_udp: 41414,
+            ds_udp: 41454,
+            ..ports
+        };
+        let robot = TokioUdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let moved_robot = TokioUdpSocket::bind(("127.0.0.1", moved.robot_udp))
+            .await
+            .unwrap();
+        let mut config = config(ports);
+        config.simulator = true;
+        let mut ds = DriverStation::from_config(&config).await;
+        ds.enable().await;
+        wait_enabled(&robot).await;
+        let old_listener = ds.conn_tasks.sim_listener.as_ref().unwrap().abort_handle();
+
+        config.ports = moved;
+        ds.reconfigure(&config).await;
+        assert!(!ds.enabled().await);
+        assert!(old_listener.is_finished());
+        assert!(ds.conn_tasks.sim_listener.is_some());
+        // Packets already queued on the old port are drained before checking nothing more arrives there
+        while next_packet(&robot).await.is_some() {}
+        let (_, control) = next_packet(&moved_robot).await.unwrap();
+        assert!(!control.contains(Control::ENABLED));
+        assert!(next_packet(&robot).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn verify_shutdown_reports_why_it_stopped() {
+        let ports = Ports {
+            robot_udp: 41415,
+            ds_udp: 41455,
+            tcp: 41645,
+            simulator: 41639,
+        };
+        let robot = TokioUdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut ds = DriverStation::from_config(&config(ports)).await;
+        ds.enable().await;
+        wait_enabled(&robot).await;
+        assert_eq!(ds.shutdown_report().await, None);
+
+        let report = ds.shutdown().await;
+        assert_eq!(report.reason, ShutdownReason::Requested);
+        assert!(report.stats.packets_sent > 0);
+        assert!(
+            report
+                .last_events
+                .iter()
+                .any(|timed| timed.event == Event::Enabled)
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_shutdown_reports_a_failed_bind() {
+        let ports = Ports {
+            robot_udp: 41416,
+            ds_udp: 41456,
+            tcp: 41646,
+            simulator: 41640,
+        };
+        // Another driver station already holds the port
+        let _taken = std::net::UdpSocket::bind(("0.0.0.0", ports.ds_udp)).unwrap();
+        let ds = DriverStation::from_config(&config(ports)).await;
+        let report = async {
+            loop {
+                if let Some(report) = ds.shutdown_report().await {
+                    return report;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let report = timeout(Duration::from_secs(1), report)
+            .await
+            .expect("The failure was never reported");
+        assert!(matches!(report.reason, ShutdownReason::Failed(_)));
+        assert!(!report.reason.requested());
+        assert_eq!(ds.shutdown().await.reason, report.reason);
+    }
+
+    #[cfg(feature = "config")]
+    #[tokio::test]
+    async fn verify_sessions_are_saved_in_the_background() {
+        let path = std::env::temp_dir().join(format!("ds-session-{}.toml", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut ds = DriverStation::from_config(&config(Ports {
+            robot_udp: 41417,
+            ds_udp: 41457,
+            tcp: 41647,
+            simulator: 41644,
+        }))
+        .await;
+        ds.persist_session(&path).await.unwrap();
+        ds.set_alliance(Alliance::new_blue(2)).await;
+
+        let saved = async {
+            loop {
+                if let Ok(Some(saved)) = Session::load_saved(path.clone()).await
+                    && saved.alliance == Alliance::new_blue(2)
+                {
+                    return saved;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let saved = timeout(Duration::from_secs(2), saved).await.unwrap();
+        assert_eq!(&saved, ds.session());
+
+        // A driver station persisting to the same file starts from what was saved
+        drop(ds);
+        let mut restored = DriverStation::from_config(&config(Ports {
+            robot_udp: 41418,
+            ds_udp: 41458,
+            tcp: 41648,
+            simulator: 41649,
+        }))
+        .await;
+        restored.persist_session(&path).await.unwrap();
+        assert_eq!(restored.session().alliance, Alliance::new_blue(2));
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// The number of windows kept for subscribers that fall behind
+pub(crate) const WINDOW_CAPACITY: usize = 16;
+
+/// The lowest, mean, and highest battery voltage over a window
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoltageRange {
+    pub min: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+/// Telemetry summarised over a window of time, for UIs and forwarders that don't need every status packet
+///
+/// Published every [`DsConfig::telemetry_window`](crate::DsConfig::telemetry_window), see
+/// [`DriverStation::subscribe_windows`](crate::DriverStation::subscribe_windows).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TelemetryWindow {
+    /// When the window started, on the driver station's clock
+    pub started: DateTime<Utc>,
+    pub length: Duration,
+    /// The battery voltage over the window, or `None` if no status packets arrived in it
+    pub voltage: Option<VoltageRange>,
+    /// The number of control packets sent in the window
+    pub packets_sent: u32,
+    /// The number of status packets received in the window
+    pub packets_received: u32,
+    /// The fraction, from `0.0..=1.0`, of the control packets given up on in the window that were never answered
+    pub packet_loss: f32,
+    /// The longest round trip of a control packet answered in the window
+    pub max_latency: Option<Duration>,
+}
+
+/// Accumulates telemetry until its window closes
+pub(crate) struct WindowAggregator {
+    length: Duration,
+    started: Option<(Instant, DateTime<Utc>)>,
+    voltage: Option<VoltageRange>,
+    voltage_sum: f32,
+    voltage_samples: u32,
+    packets_sent: u32,
+    packets_received: u32,
+    answered: u32,
+    lost: u32,
+    max_latency: Option<Duration>,
+}
+
+impl WindowAggregator {
+    pub(crate) const fn new(length: Duration) -> WindowAggregator {
+        WindowAggregator {
+            length,
+            started: None,
+            voltage: None,
+            voltage_sum: 0.0,
+            voltage_samples: 0,
+            packets_sent: 0,
+            packets_received: 0,
+            answered: 0,
+            lost: 0,
+            max_latency: None,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn length(&self) -> Duration {
+        self.length
+    }
+
+    /// Changes the length of the windows, starting a new one. A length of zero stops publishing them
+    pub(crate) fn set_length(&mut self, length: Duration) {
+        *self = WindowAggregator::new(length);
+    }
+
+    pub(crate) fn packet_sent(&mut self) {
+        self.packets_sent += 1;
+    }
+
+    pub(crate) fn packet_received(&mut self, latency: Option<Duration>) {
+        self.packets_received += 1;
+        if let Some(latency) = latency {
+            self.max_latency = self.max_latency.max(Some(latency));
+        }
+    }
+
+    /// Records a control packet leaving the loss measurement, answered or not
+    pub(crate) fn retired(&mut self, lost: bool) {
+        if lost {
+            self.lost += 1;
+        } else {
+            self.answered += 1;
+        }
+    }
+
+    pub(crate) fn voltage(&mut self, voltage: f32) {
+        self.voltage_sum += voltage;
+        self.voltage_samples += 1;
+        self.voltage = Some(match self.voltage {
+            Some(range) => VoltageRange {
+                min: range.min.min(voltage),
+                mean: self.voltage_sum / self.voltage_samples as f32,
+                max: range.max.max(voltage),
+            },
+            None => VoltageRange {
+                min: voltage,
+                mean: voltage,
+                max: voltage,
+            },
+        });
+    }
+
+    /// Closes the window if it's been open for its length, returning its summary and starting the next
+    pub(crate) fn close_due(
+        &mut self,
+        now: Instant,
+        wall: DateTime<Utc>,
+    ) -> Option<TelemetryWindow> {
+        if self.length.is_zero() {
+            return None;
+        }
+        let (started, started_wall) = *self.started.get_or_insert((now, wall));
+        let length = now.saturating_duration_since(started);
+        if length < self.length {
+            return None;
+        }
+        let retired = self.answered + self.lost;
+        let window = TelemetryWindow {
+            started: started_wall,
+            length,
+            voltage: self.voltage,
+            packets_sent: self.packets_sent,
+            packets_received: self.packets_received,
+            packet_loss: if retired == 0 {
+                0.0
+            } else {
+                self.lost as f32 / retired as f32
+            },
+            max_latency: self.max_latency,
+        };
+        *self = WindowAggregator::new(self.length);
+        self.started = Some((now, wall));
+        Some(window)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_windows_summarise_their_samples() {
+        let mut aggregator = WindowAggregator::new(Duration::from_secs(1));
+        let start = Instant::now();
+        let wall = Utc::now();
+        assert_eq!(aggregator.close_due(start, wall), None);
+
+        for voltage in [12.0, 11.0, 13.0] {
+            aggregator.packet_sent();
+            aggregator.voltage(voltage);
+        }
+        aggregator.packet_received(Some(Duration::from_millis(3)));
+        aggregator.packet_received(Some(Duration::from_millis(9)));
+        aggregator.retired(false);
+        aggregator.retired(true);
+        assert_eq!(
+            aggregator.close_due(start + Duration::from_millis(500), wall),
+            None
+        );
+
+        let window = aggregator
+            .close_due(start + Duration::from_secs(1), wall)
+            .unwrap();
+        assert_eq!(window.started, wall);
+        assert_eq!(
+            window.voltage,
+            Some(VoltageRange {
+                min: 11.0,
+                mean: 12.0,
+                max: 13.0
+            })
+        );
+        assert_eq!((window.packets_sent, window.packets_received), (3, 2));
+        assert_eq!(window.packet_loss, 0.5);
+        assert_eq!(window.max_latency, Some(Duration::from_millis(9)));
+
+        // The next window starts empty
+        let window = aggregator
+            .close_due(start + Duration::from_secs(2), wall)
+            .unwrap();
+        assert_eq!(window.voltage, None);
+        assert_eq!(window.packets_sent, 0);
+    }
+}
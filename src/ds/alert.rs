@@ -0,0 +1,79 @@
+use super::events::Event;
+use super::rumble::MatchPhase;
+
+/// How urgently an [`Alert`] should be brought to the driver's attention
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// A notable change that a driver station frontend should announce, for example with a beep or text to speech
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Alert {
+    /// The roboRIO started responding after not responding
+    ConnectionRestored,
+    /// The roboRIO stopped responding
+    ConnectionLost,
+    /// The roboRIO reported that the battery voltage dropped low enough to disable outputs
+    Brownout,
+    /// The robot was emergency stopped
+    Estop,
+    /// The practice match timer reached a new phase
+    MatchPhase(MatchPhase),
+    /// The joystick supplier started misbehaving
+    JoystickFault,
+    /// Another driver station seems to be controlling the robot
+    ControlConflict,
+    /// The roboRIO is running out of disk space or memory, and the robot code may crash soon
+    LowResources,
+    /// The CAN bus stayed saturated, so messages to motor controllers and sensors may be delayed or dropped
+    CanSaturated,
+}
+
+impl Alert {
+    /// Returns the alert that should be raised for `event`, if any
+    pub fn from_event(event: &Event) -> Option<Alert> {
+        match event {
+            Event::Connected => Some(Alert::ConnectionRestored),
+            Event::Disconnected => Some(Alert::ConnectionLost),
+            Event::Brownout => Some(Alert::Brownout),
+            Event::Estopped => Some(Alert::Estop),
+            Event::MatchPhase(phase) => Some(Alert::MatchPhase(*phase)),
+            Event::JoystickFault(_) => Some(Alert::JoystickFault),
+            Event::ControlConflict(_) => Some(Alert::ControlConflict),
+            Event::LowDisk(_) | Event::LowRam(_) => Some(Alert::LowResources),
+            Event::CanSaturated(_) => Some(Alert::CanSaturated),
+            _ => None,
+        }
+    }
+
+    pub const fn severity(self) -> Severity {
+        match self {
+            Alert::ConnectionRestored | Alert::MatchPhase(_) => Severity::Info,
+            Alert::Brownout | Alert::LowResources | Alert::CanSaturated => Severity::Warning,
+            Alert::ConnectionLost
+            | Alert::Estop
+            | Alert::JoystickFault
+            | Alert::ControlConflict => Severity::Critical,
+        }
+    }
+}
+
+/// Receives alerts as they happen, registered with `DriverStation::set_alert_sink`
+///
+/// Alerts are delivered from the network tasks, so implementations should return quickly.
+/// Closures taking an `&Alert` implement this trait.
+pub trait AlertSink: Send + Sync {
+    fn alert(&self, alert: &Alert);
+}
+
+impl<F> AlertSink for F
+where
+    F: Fn(&Alert) + Send + Sync,
+{
+    fn alert(&self, alert: &Alert) {
+        self(alert)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Alliance;
+
+/// Where the alliance station in use came from, from lowest to highest precedence
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AllianceSource {
+    /// Chosen with [`DriverStation::set_alliance`](crate::DriverStation::set_alliance), or restored with the session
+    User,
+    /// Locked by the config, see [`DsConfig::lock_alliance`](crate::DsConfig::lock_alliance)
+    Config,
+    /// Assigned by the field, see [`DriverStation::set_fms_alliance`](crate::DriverStation::set_fms_alliance)
+    Fms,
+}
+
+/// The station each source asked for, of which the one with the highest precedence is used
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AllianceSelection {
+    user: Alliance,
+    config: Option<Alliance>,
+    fms: Option<Alliance>,
+}
+
+impl AllianceSelection {
+    pub(crate) const fn new(user: Alliance) -> AllianceSelection {
+        AllianceSelection {
+            user,
+            config: None,
+            fms: None,
+        }
+    }
+
+    /// Returns the station in use, and where it came from
+    pub(crate) const fn effective(&self) -> (Alliance, AllianceSource) {
+        if let Some(alliance) = self.fms {
+            (alliance, AllianceSource::Fms)
+        } else if let Some(alliance) = self.config {
+            (alliance, AllianceSource::Config)
+        } else {
+            (self.user, AllianceSource::User)
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn user(&self) -> Alliance {
+        self.user
+    }
+
+    /// Sets the station asked for by `source`, or clears it, returning the station in use if that changed it
+    ///
+    /// The user's station can't be cleared, it's used when no other source asks for one.
+    pub(crate) fn set(
+        &mut self,
+        source: AllianceSource,
+        alliance: Option<Alliance>,
+    ) -> Option<(Alliance, AllianceSource)> {
+        let before = self.effective();
+        match source {
+            AllianceSource::User => self.user = alliance.unwrap_or(self.user),
+            AllianceSource::Config => self.config = alliance,
+            AllianceSource::Fms => self.fms = alliance,
+        }
+        let after = self.effective();
+        (after != before).then_some(after)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_higher_sources_override() {
+        let mut selection = AllianceSelection::new(Alliance::new_red(1));
+        assert_eq!(
+            selection.set(AllianceSource::Config, Some(Alliance::new_red(2))),
+            Some((Alliance::new_red(2), AllianceSource::Config))
+        );
+        assert_eq!(
+            selection.set(AllianceSource::User, Some(Alliance::new_blue(1))),
+            None
+        );
+
+        // The field assigning the station already in use still changes where it came from
+        assert_eq!(
+            selection.set(AllianceSource::Fms, Some(Alliance::new_red(2))),
+            Some((Alliance::new_red(2), AllianceSource::Fms))
+        );
+        selection.set(AllianceSource::Config, None);
+        assert_eq!(
+            selection.set(AllianceSource::Fms, None),
+            Some((Alliance::new_blue(1), AllianceSource::User))
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+/// The sag in voltage on enable that is reported by default
+pub(crate) const DEFAULT_SAG_THRESHOLD: f32 = 1.5;
+
+/// How long after enabling the voltage is watched for a sag
+const SAG_WINDOW: Duration = Duration::from_secs(2);
+
+/// How far the smoothed voltage moves towards each new sample, which at 50 packets a second takes about half a second
+/// to follow a step
+const SMOOTHING: f32 = 0.1;
+
+/// A drop in battery voltage after the robot was enabled, usually a sign of a worn out or undercharged battery
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoltageSag {
+    /// The smoothed voltage just before the robot was enabled
+    pub baseline: f32,
+    /// The lowest voltage in the 2 seconds after it was enabled
+    pub minimum: f32,
+}
+
+impl VoltageSag {
+    #[inline(always)]
+    pub const fn drop(&self) -> f32 {
+        self.baseline - self.minimum
+    }
+}
+
+/// Smooths the battery voltage, and watches it for sags on enable
+pub(crate) struct BatteryMonitor {
+    smoothed: Option<f32>,
+    enabled: bool,
+    /// The baseline and lowest voltage of the enable being watched, and when it started
+    watching: Option<(Instant, VoltageSag)>,
+    last_sag: Option<VoltageSag>,
+    threshold: f32,
+}
+
+impl BatteryMonitor {
+    pub(crate) const fn new() -> BatteryMonitor {
+        BatteryMonitor {
+            smoothed: None,
+            enabled: false,
+            watching: None,
+            last_sag: None,
+            threshold: DEFAULT_SAG_THRESHOLD,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn smoothed(&self) -> Option<f32> {
+        self.smoothed
+    }
+
+    #[inline(always)]
+    pub(crate) const fn last_sag(&self) -> Option<VoltageSag> {
+        self.last_sag
+    }
+
+    #[inline(always)]
+    pub(crate) const fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub(crate) fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Records the voltage of a status packet, returning a sag once the window after an enable has passed
+    pub(crate) fn sample(
+        &mut self,
+        voltage: f32,
+        enabled: bool,
+        now: Instant,
+    ) -> Option<VoltageSag> {
+        if enabled
+            && !self.enabled
+            && let Some(baseline) = self.smoothed
+        {
+            self.watching = Some((
+                now,
+                VoltageSag {
+                    baseline,
+                    minimum: voltage,
+                },
+            ));
+        }
+        self.enabled = enabled;
+        self.smoothed = Some(match self.smoothed {
+            Some(smoothed) => smoothed + (voltage - smoothed) * SMOOTHING,
+            None => voltage,
+        });
+
+        let (started, mut sag) = self.watching?;
+        sag.minimum = sag.minimum.min(voltage);
+        if now.duration_since(started) < SAG_WINDOW {
+            self.watching = Some((started, sag));
+            return None;
+        }
+        self.watching = None;
+        if sag.drop() < self.threshold {
+            return None;
+        }
+        self.last_sag = Some(sag);
+        Some(sag)
+    }
+
+    /// Forgets the voltage of a robot that disconnected, so the next one isn't compared against it
+    pub(crate) fn reset(&mut self) {
+        self.smoothed = None;
+        self.enabled = false;
+        self.watching = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_sag_on_enable_is_reported() {
+        let mut monitor = BatteryMonitor::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(monitor.sample(12.5, false, at(0)), None);
+        assert_eq!(monitor.sample(12.5, true, at(20)), None);
+        assert_eq!(monitor.sample(10.5, true, at(40)), None);
+        assert_eq!(monitor.sample(11.8, true, at(1000)), None);
+        assert_eq!(
+            monitor.sample(12.0, true, at(2100)),
+            Some(VoltageSag {
+                baseline: 12.5,
+                minimum: 10.5
+            })
+        );
+        assert_eq!(monitor.sample(9.0, true, at(2200)), None);
+    }
+}
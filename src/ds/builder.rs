@@ -0,0 +1,251 @@
+use super::DriverStation;
+use super::config::{
+    CanUtilizationAlert, ConsoleLimits, DropAction, DsConfig, JoystickMapping, JoystickSuppression,
+    MappingProfile, Pacing, Ports, PracticeTiming, ReconnectPolicy, ResourceLimits, SocketOptions,
+};
+
+use crate::Alliance;
+use crate::proto::year::ProtocolYear;
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+/// Builds a [`DriverStation`] one setting at a time, as an alternative to filling in a [`DsConfig`]
+///
+/// Created with [`DriverStation::builder`]. Settings that aren't given keep the defaults of [`DsConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct DriverStationBuilder {
+    config: DsConfig,
+}
+
+impl DriverStationBuilder {
+    pub fn team_number(mut self, team_number: u16) -> DriverStationBuilder {
+        self.config.team_number = team_number;
+        self
+    }
+
+    /// Connects to the roboRIO at `ip`, instead of the address inferred from the team number
+    pub fn ip(mut self, ip: &str) -> DriverStationBuilder {
+        self.config.ip = Some(ip.to_string());
+        self
+    }
+
+    pub fn alliance(mut self, alliance: Alliance) -> DriverStationBuilder {
+        self.config.alliance = alliance;
+        self
+    }
+
+    /// Uses the alliance given to the builder even when the user chooses another, see [`DsConfig::lock_alliance`]
+    pub fn lock_alliance(mut self, lock: bool) -> DriverStationBuilder {
+        self.config.lock_alliance = lock;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: ProtocolYear) -> DriverStationBuilder {
+        self.config.protocol = protocol;
+        self
+    }
+
+    pub fn ports(mut self, ports: Ports) -> DriverStationBuilder {
+        self.config.ports = ports;
+        self
+    }
+
+    pub fn practice(mut self, practice: PracticeTiming) -> DriverStationBuilder {
+        self.config.practice = practice;
+        self
+    }
+
+    pub fn reconnect(mut self, reconnect: ReconnectPolicy) -> DriverStationBuilder {
+        self.config.reconnect = reconnect;
+        self
+    }
+
+    /// Adds a joystick mapping, in addition to those already given
+    pub fn joystick(mut self, mapping: JoystickMapping) -> DriverStationBuilder {
+        self.config.joysticks.push(mapping);
+        self
+    }
+
+    /// Adds a mapping profile, which can be switched to with
+    /// [`DriverStation::use_profile`](crate::DriverStation::use_profile)
+    pub fn profile(mut self, profile: MappingProfile) -> DriverStationBuilder {
+        self.config.profiles.push(profile);
+        self
+    }
+
+    /// Replaces every socket option at once
+    pub fn socket_options(mut self, options: SocketOptions) -> DriverStationBuilder {
+        self.config.socket = options;
+        self
+    }
+
+    /// Marks outbound packets with the DSCP code point `dscp`, see [`SocketOptions::dscp`]
+    pub fn dscp(mut self, dscp: u8) -> DriverStationBuilder {
+        self.config.socket.dscp = Some(dscp);
+        self
+    }
+
+    pub fn ttl(mut self, ttl: u32) -> DriverStationBuilder {
+        self.config.socket.ttl = Some(ttl);
+        self
+    }
+
+    pub fn recv_buffer_size(mut self, size: usize) -> DriverStationBuilder {
+        self.config.socket.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub fn send_buffer_size(mut self, size: usize) -> DriverStationBuilder {
+        self.config.socket.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Restricts traffic to the network interface named `interface`, see [`SocketOptions::interface`]
+    pub fn interface(mut self, interface: &str) -> DriverStationBuilder {
+        self.config.socket.interface = Some(interface.to_string());
+        self
+    }
+
+    /// Binds every socket to `address`, see [`SocketOptions::local_address`]
+    pub fn local_address(mut self, address: Ipv4Addr) -> DriverStationBuilder {
+        self.config.socket.local_address = Some(address);
+        self
+    }
+
+    /// Allows sharing ports with other driver stations on the host, see [`SocketOptions::reuse_address`]
+    pub fn reuse_address(mut self, reuse: bool) -> DriverStationBuilder {
+        self.config.socket.reuse_address = reuse;
+        self
+    }
+
+    /// Changes whether simulators are detected, see [`DsConfig::simulator`]
+    pub fn simulator(mut self, simulator: bool) -> DriverStationBuilder {
+        self.config.simulator = simulator;
+        self
+    }
+
+    /// Also accepts status packets from `address`, see [`SourceFilter::allow`](crate::SourceFilter::allow)
+    pub fn accept_from(mut self, address: IpAddr) -> DriverStationBuilder {
+        self.config.source_filter.allow.push(address);
+        self
+    }
+
+    /// Changes whether status packets from other addresses than the robot are dropped, see [`SourceFilter`](crate::SourceFilter)
+    pub fn validate_source(mut self, enabled: bool) -> DriverStationBuilder {
+        self.config.source_filter.enabled = enabled;
+        self
+    }
+
+    /// Changes whether enabling is refused while another driver station seems to be controlling the robot
+    pub fn refuse_enable_on_conflict(mut self, refuse: bool) -> DriverStationBuilder {
+        self.config.refuse_enable_on_conflict = refuse;
+        self
+    }
+
+    /// Leaves the TCP connection closed, see [`DsConfig::udp_only`]
+    pub fn udp_only(mut self, udp_only: bool) -> DriverStationBuilder {
+        self.config.udp_only = udp_only;
+        self
+    }
+
+    /// Refuses to enable for `cooldown` after the roboRIO last reported a brownout
+    pub fn brownout_cooldown(mut self, cooldown: Duration) -> DriverStationBuilder {
+        self.config.brownout_cooldown = Some(cooldown);
+        self
+    }
+
+    /// Requires the robot to be armed before enabling, staying armed for `timeout`, see [`DsConfig::arm_timeout`]
+    pub fn arm_timeout(mut self, timeout: Duration) -> DriverStationBuilder {
+        self.config.arm_timeout = Some(timeout);
+        self
+    }
+
+    /// Disables the robot when the watchdog isn't fed for `timeout`, see [`DsConfig::watchdog`]
+    pub fn watchdog(mut self, timeout: Duration) -> DriverStationBuilder {
+        self.config.watchdog = Some(timeout);
+        self
+    }
+
+    /// Changes how accurately control packets are spaced, see [`Pacing`]
+    pub fn pacing(mut self, pacing: Pacing) -> DriverStationBuilder {
+        self.config.pacing = pacing;
+        self
+    }
+
+    /// Sends neutral joystick values in the modes given by `suppression`, see [`JoystickSuppression`]
+    pub fn joystick_suppression(
+        mut self,
+        suppression: JoystickSuppression,
+    ) -> DriverStationBuilder {
+        self.config.joystick_suppression = suppression;
+        self
+    }
+
+    /// Leaves trailing joysticks with no values out of control packets, see
+    /// [`DriverStation::set_compact_joysticks`](crate::DriverStation::set_compact_joysticks)
+    pub fn compact_joysticks(mut self, compact: bool) -> DriverStationBuilder {
+        self.config.compact_joysticks = compact;
+        self
+    }
+
+    /// Raises events when the roboRIO reports less free disk space or memory than `limits`, see [`ResourceLimits`]
+    pub fn resource_limits(mut self, limits: ResourceLimits) -> DriverStationBuilder {
+        self.config.resource_limits = limits;
+        self
+    }
+
+    /// Changes when the CAN bus is considered saturated, see [`CanUtilizationAlert`]
+    pub fn can_utilization_alert(mut self, alert: CanUtilizationAlert) -> DriverStationBuilder {
+        self.config.can_utilization = alert;
+        self
+    }
+
+    /// Limits console messages before they reach the TCP consumer, see [`ConsoleLimits`]
+    pub fn console_limits(mut self, limits: ConsoleLimits) -> DriverStationBuilder {
+        self.config.console = limits;
+        self
+    }
+
+    /// Whether to start talking to the robot when built, or wait for [`DriverStation::connect`]. On by default
+    pub fn connect_on_build(mut self, connect: bool) -> DriverStationBuilder {
+        self.config.connect_on_build = connect;
+        self
+    }
+
+    /// What the robot is told when the driver station is shut down or dropped, and for how long shutting down may spend
+    /// telling it
+    pub fn on_drop(mut self, action: DropAction, timeout: Duration) -> DriverStationBuilder {
+        self.config.on_drop = action;
+        self.config.drop_timeout = timeout;
+        self
+    }
+
+    /// How much telemetry is summarised in each published window, see [`DsConfig::telemetry_window`]
+    pub fn telemetry_window(mut self, length: Duration) -> DriverStationBuilder {
+        self.config.telemetry_window = length;
+        self
+    }
+
+    /// Returns the settings given so far
+    #[inline(always)]
+    pub const fn config(&self) -> &DsConfig {
+        &self.config
+    }
+
+    pub async fn build(self) -> DriverStation {
+        DriverStation::from_config(&self.config).await
+    }
+
+    /// Builds a driver station for a cRIO, see [`DriverStation::new_crio`]
+    #[cfg(feature = "crio")]
+    pub async fn build_crio(self) -> DriverStation {
+        DriverStation::new_crio(&self.config).await
+    }
+}
+
+impl From<DsConfig> for DriverStationBuilder {
+    fn from(config: DsConfig) -> DriverStationBuilder {
+        DriverStationBuilder { config }
+    }
+}
@@ -0,0 +1,118 @@
+use super::config::CanUtilizationAlert;
+use super::events::Event;
+
+use crate::proto::udp::inbound::types::CANMetrics;
+
+use std::time::Instant;
+
+/// Watches the CAN bus utilization the roboRIO reports, to warn when the bus stays saturated
+pub(crate) struct CanMonitor {
+    alert: CanUtilizationAlert,
+    latest: Option<CANMetrics>,
+    /// When the utilization last rose above the threshold, while it's still above it
+    above_since: Option<Instant>,
+    saturated: bool,
+}
+
+impl CanMonitor {
+    pub(crate) fn new() -> CanMonitor {
+        CanMonitor {
+            alert: CanUtilizationAlert::default(),
+            latest: None,
+            above_since: None,
+            saturated: false,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn alert(&self) -> CanUtilizationAlert {
+        self.alert
+    }
+
+    pub(crate) fn set_alert(&mut self, alert: CanUtilizationAlert) {
+        self.alert = alert;
+    }
+
+    #[inline(always)]
+    pub(crate) const fn latest(&self) -> Option<CANMetrics> {
+        self.latest
+    }
+
+    /// Records the CAN metrics of a status packet, returning an event if the bus became or stopped being saturated
+    ///
+    /// The bus is saturated once the utilization stays at or above the threshold for the hold time, and stays
+    /// saturated until it drops below the threshold by more than the hysteresis.
+    pub(crate) fn sample(&mut self, metrics: CANMetrics, now: Instant) -> Option<Event> {
+        self.latest = Some(metrics);
+        let utilization = metrics.utilization;
+        if self.saturated {
+            if utilization < self.alert.threshold - self.alert.hysteresis {
+                self.saturated = false;
+                self.above_since = None;
+                return Some(Event::CanSaturationEnded);
+            }
+            return None;
+        }
+        if utilization < self.alert.threshold {
+            self.above_since = None;
+            return None;
+        }
+        let since = *self.above_since.get_or_insert(now);
+        if now.duration_since(since) >= self.alert.hold {
+            self.saturated = true;
+            return Some(Event::CanSaturated(utilization));
+        }
+        None
+    }
+
+    /// Forgets what the previous connection reported, keeping the alert settings
+    pub(crate) fn reset(&mut self) {
+        self.latest = None;
+        self.above_since = None;
+        self.saturated = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn metrics(utilization: f32) -> CANMetrics {
+        CANMetrics {
+            utilization,
+            bus_off: 0,
+            tx_full: 0,
+            rx_errors: 0,
+            tx_errors: 0,
+        }
+    }
+
+    #[test]
+    fn verify_saturation_needs_hold_and_clears_with_hysteresis() {
+        let mut monitor = CanMonitor::new();
+        monitor.set_alert(CanUtilizationAlert {
+            threshold: 90.0,
+            hold: Duration::from_secs(2),
+            hysteresis: 10.0,
+        });
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert_eq!(monitor.sample(metrics(95.0), at(0)), None);
+        // Dropping below the threshold before the hold time restarts it
+        assert_eq!(monitor.sample(metrics(85.0), at(1000)), None);
+        assert_eq!(monitor.sample(metrics(95.0), at(1500)), None);
+        assert_eq!(monitor.sample(metrics(95.0), at(3000)), None);
+        assert_eq!(
+            monitor.sample(metrics(96.0), at(3500)),
+            Some(Event::CanSaturated(96.0))
+        );
+
+        assert_eq!(monitor.sample(metrics(85.0), at(4000)), None);
+        assert_eq!(
+            monitor.sample(metrics(79.0), at(4500)),
+            Some(Event::CanSaturationEnded)
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use super::state::Mode;
+use crate::proto::udp::inbound::types::Trace;
+
+use std::time::Instant;
+
+/// The number of changes a subscriber can fall behind by before it starts missing them
+pub(crate) const CHANGE_CAPACITY: usize = 256;
+
+/// The smallest change in battery voltage that is reported, so the noise in every status packet isn't
+pub(crate) const VOLTAGE_STEP: f32 = 0.05;
+
+/// A change in the state reported by the robot, or in the connection to it
+///
+/// Received through [`DriverStation::subscribe`](crate::DriverStation::subscribe). Unlike [`Event`](crate::Event)s
+/// these aren't kept, and are meant for consumers that mirror the state as it changes instead of polling it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StateChange {
+    /// Status packets started or stopped arriving from the robot
+    ConnectionChanged(bool),
+    /// The TCP connection to the robot was opened or closed
+    TcpConnectionChanged(bool),
+    /// The battery voltage moved by at least 0.05V since it was last reported
+    VoltageChanged(f32),
+    /// The robot reported running in a different mode
+    ModeConfirmed(Mode),
+    /// The robot reported being enabled or disabled
+    EnabledConfirmed(bool),
+    /// The robot reported a different trace
+    TraceChanged(Trace),
+    /// The roboRIO started or stopped reporting a brownout
+    BrownoutChanged(bool),
+    /// Something in [`DriverStation::identity`](crate::DriverStation::identity) changed
+    IdentityChanged,
+}
+
+/// A control packet sent by the driver station
+///
+/// Received through [`DriverStation::subscribe_packets`](crate::DriverStation::subscribe_packets), such as to blink a
+/// communications light, or to check that the send loop hasn't stalled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PacketSent {
+    pub seqnum: u16,
+    pub sent: Instant,
+}
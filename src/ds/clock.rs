@@ -0,0 +1,139 @@
+use super::simclock::SimClock;
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// Where the driver station reads the time from, for event and console timestamps, the date sent to the roboRIO,
+/// packet timings and link statistics, and match timers
+///
+/// Set with [`DriverStation::set_clock`](crate::DriverStation::set_clock). [`SystemClock`] is used by default, and
+/// [`MockClock`] lets tests and replay tools control the time. A [`SimClock`] in use in simulation mode takes the place
+/// of this clock. The watchdog and arming time the application and its user rather than the robot, so they stay on real
+/// time, as do the socket timeouts.
+pub trait Clock: Send + Sync {
+    /// Returns the wall-clock time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Returns the monotonic time, which packets are timestamped with and timeouts are measured against
+    ///
+    /// Instants from one clock shouldn't be compared with another's, so the clock should be set before connecting.
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Returns the clock match timers should run on, if this clock doesn't follow real time
+    fn timebase(&self) -> Option<SimClock> {
+        None
+    }
+}
+
+/// The system's clock
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when it's advanced or set. Clones share the same time
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    origin: DateTime<Utc>,
+    elapsed: SimClock,
+}
+
+impl MockClock {
+    /// Creates a clock stopped at `start`
+    pub fn new(start: DateTime<Utc>) -> MockClock {
+        MockClock {
+            origin: start,
+            elapsed: SimClock::paused(),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed.step(by);
+    }
+
+    /// Sets the clock to `now`, which can be earlier than the current time but not earlier than the start
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.elapsed
+            .set((now - self.origin).to_std().unwrap_or_default());
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.origin + self.elapsed.now()
+    }
+
+    fn instant(&self) -> Instant {
+        Clock::instant(&self.elapsed)
+    }
+
+    fn timebase(&self) -> Option<SimClock> {
+        Some(self.elapsed.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn verify_mock_clock_only_moves_when_told() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 16, 9, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(clock.clone().now(), start + Duration::from_secs(15));
+        assert_eq!(clock.timebase().unwrap().now(), Duration::from_secs(15));
+
+        clock.set(start - Duration::from_secs(1));
+        assert_eq!(clock.now(), start);
+    }
+
+    #[tokio::test]
+    async fn verify_link_timings_follow_the_clock() {
+        use crate::Alliance;
+        use crate::ds::state::DsState;
+        use std::sync::Arc;
+
+        let state = DsState::new(Alliance::new_red(1), "127.0.0.1".to_string());
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 3, 16, 9, 0, 0).unwrap());
+        state.set_clock(Arc::new(clock.clone()));
+
+        state.stats().write().await.packet_sent(1, state.instant());
+        clock.advance(Duration::from_millis(40));
+        state
+            .stats()
+            .write()
+            .await
+            .packet_received(1, state.instant());
+        let stats = state.stats().read().await.snapshot(state.instant());
+        assert_eq!(stats.latency, Some(Duration::from_millis(40)));
+
+        // A simulator's clock takes over in simulation mode
+        let sim = SimClock::paused();
+        state.set_sim_clock(Some(sim.clone()));
+        assert_eq!(state.instant(), clock.instant());
+        state.set_simulating(true);
+        assert_eq!(state.instant(), sim.instant());
+        assert_eq!(state.now(), Clock::now(&sim));
+    }
+
+    #[test]
+    fn verify_mock_instants_only_move_when_told() {
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 3, 16, 9, 0, 0).unwrap());
+        let start = clock.instant();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.instant(), start);
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(clock.instant() - start, Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,398 @@
+use super::alliance::AllianceSource;
+use super::events::Event;
+use super::state::{DsState, Mode};
+use super::watchdog::Watchdog;
+
+use crate::{Alliance, RequestFlags};
+
+use anyhow::bail;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// The number of commands that can wait for the send loop before [`Commander::try_send`] fails
+pub(crate) const COMMAND_CAPACITY: usize = 64;
+
+/// A change to what is sent to the robot, applied by the send loop before it builds its next packet
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Enables the robot, with the same checks as [`DriverStation::enable`](crate::DriverStation::enable)
+    Enable,
+    Disable,
+    Estop,
+    SetMode(Mode),
+    /// Changes the alliance station chosen by the user, like
+    /// [`DriverStation::set_alliance`](crate::DriverStation::set_alliance)
+    SetAlliance(Alliance),
+    /// Sends the requests with the next control packet
+    Request(RequestFlags),
+    /// Arms the robot, see [`DriverStation::arm`](crate::DriverStation::arm)
+    Arm,
+    Disarm,
+}
+
+/// Queues [`Command`]s for the send loop without awaiting or taking any locks, so the robot can be controlled from
+/// threads outside the runtime, such as a UI event loop
+///
+/// Created with [`DriverStation::commander`](crate::DriverStation::commander). Commands share one queue with the
+/// driver station's own setters, so they're applied in the order they were made, at most 20ms later, along with the
+/// next control packet.
+#[derive(Debug, Clone)]
+pub struct Commander {
+    tx: mpsc::Sender<Command>,
+    watchdog: Arc<Watchdog>,
+}
+
+impl Commander {
+    pub(crate) const fn new(tx: mpsc::Sender<Command>, watchdog: Arc<Watchdog>) -> Commander {
+        Commander { tx, watchdog }
+    }
+
+    /// Feeds the watchdog, see [`DriverStation::feed_watchdog`](crate::DriverStation::feed_watchdog)
+    pub fn feed_watchdog(&self) {
+        self.watchdog.feed(Instant::now());
+    }
+
+    /// Queues `command`, failing if the send loop has stopped or fallen [`COMMAND_CAPACITY`] commands behind
+    pub fn try_send(&self, command: Command) -> crate::Result<()> {
+        try_queue(&self.tx, command)
+    }
+}
+
+/// Queues `command` without awaiting, failing if the send loop has fallen [`COMMAND_CAPACITY`] commands behind
+pub(crate) fn try_queue(tx: &mpsc::Sender<Command>, command: Command) -> crate::Result<()> {
+    match tx.try_send(command) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(command)) => {
+            bail!("Too many commands queued, dropped {:?}", command)
+        }
+        Err(TrySendError::Closed(command)) => {
+            bail!("The driver station stopped, dropped {:?}", command)
+        }
+    }
+}
+
+/// Queues `command` behind those already queued
+///
+/// If the queue is full, because the send loop has fallen behind or isn't running yet, the queued commands are applied
+/// here first, in order, so this never waits on the send loop.
+pub(crate) async fn queue(state: &DsState, mut command: Command) {
+    loop {
+        match state.commands_tx().try_send(command) {
+            Ok(()) => return,
+            Err(TrySendError::Full(full)) => {
+                command = full;
+                apply_queued(state).await;
+            }
+            // The state holds the receiver, so the queue can't close
+            Err(TrySendError::Closed(_)) => return,
+        }
+    }
+}
+
+/// Applies every queued command, in the order they were queued
+///
+/// Besides before each packet, this is called as the driver station connects and while a control sink is sending, so
+/// commands aren't held until the send loop next builds a packet.
+pub(crate) async fn apply_queued(state: &DsState) {
+    // Holding the receiver keeps another caller from applying a later command before this one applies an earlier one
+    let mut commands = state.commands().lock().await;
+    while let Ok(command) = commands.try_recv() {
+        match command {
+            Command::Enable => enable(state).await,
+            Command::Disable => disable(state).await,
+            Command::Estop => estop(state).await,
+            Command::SetMode(mode) => set_mode(state, mode).await,
+            Command::SetAlliance(alliance) => {
+                select_alliance(state, AllianceSource::User, Some(alliance)).await
+            }
+            Command::Request(request) => state.send().write().await.request(request),
+            Command::Arm => arm(state).await,
+            Command::Disarm => state.send().write().await.disarm(),
+        }
+    }
+}
+
+/// Applies every queued command, then disables the robot if the watchdog expired. Called by the send loop before each
+/// packet
+pub(crate) async fn apply_commands(state: &DsState) {
+    apply_queued(state).await;
+    if state.watchdog().expired(Instant::now()) && state.send().read().await.enabled() {
+        warn!(
+            "Disabling, the watchdog wasn't fed within {:?}",
+            state.watchdog().timeout()
+        );
+        disable(state).await;
+        state.emit(Event::WatchdogExpired).await;
+    }
+}
+
+/// Enables the robot, unless control was released, it wasn't armed when arming is required, another driver station
+/// seems to be controlling it, or it browned out too recently
+pub(crate) async fn enable(state: &DsState) {
+    if state.send().read().await.released() {
+        warn!("Refusing to enable, control was released");
+        return;
+    }
+    if !state.send().read().await.enable_allowed(Instant::now()) {
+        warn!("Refusing to enable, the robot isn't armed");
+        state.emit(Event::EnableRefusedNotArmed).await;
+        return;
+    }
+    if state.refuse_enable_on_conflict()
+        && let Some(conflict) = state.link().read().await.conflict()
+    {
+        warn!(
+            "Refusing to enable, another driver station may be controlling the robot: {:?}",
+            conflict
+        );
+        return;
+    }
+    // Enabling again straight away would draw the current that caused the brownout, fighting the roboRIO's protection
+    if let Some(cooldown) = state.send().read().await.brownout_cooldown()
+        && let Some(last) = state.telemetry().read().await.last_brownout()
+        && state.instant().saturating_duration_since(last) < cooldown
+    {
+        warn!(
+            "Refusing to enable, the robot browned out {:?} ago",
+            state.instant().saturating_duration_since(last)
+        );
+        state.emit(Event::EnableRefusedBrownout).await;
+        return;
+    }
+    let mut send = state.send().write().await;
+    // Each arming only allows one enable, so a frontend has to arm again after disabling
+    send.disarm();
+    // Enabling shows the application is alive, a watchdog that expired while disabled would otherwise disable it again
+    state.watchdog().feed(Instant::now());
+    if !send.enabled() {
+        send.enable();
+        drop(send);
+        state.emit(Event::Enabled).await;
+    }
+}
+
+/// Arms the robot for its arm timeout, so it can be enabled within it
+pub(crate) async fn arm(state: &DsState) {
+    if !state.send().write().await.arm(Instant::now()) {
+        info!("Arming is not required, ignoring");
+    }
+}
+
+pub(crate) async fn disable(state: &DsState) {
+    let mut send = state.send().write().await;
+    if send.enabled() {
+        send.disable();
+        drop(send);
+        state.emit(Event::Disabled).await;
+    }
+}
+
+pub(crate) async fn estop(state: &DsState) {
+    let mut send = state.send().write().await;
+    if !send.estopped() {
+        send.estop();
+        drop(send);
+        state.emit(Event::Estopped).await;
+    }
+}
+
+/// Sets the station asked for by `source`, sending the one in use and reporting it if that changed it
+pub(crate) async fn select_alliance(
+    state: &DsState,
+    source: AllianceSource,
+    alliance: Option<Alliance>,
+) {
+    if let Some((alliance, source)) = state.set_alliance_source(source, alliance) {
+        state.send().write().await.set_alliance(alliance);
+        info!("Using alliance station {} from {:?}", alliance, source);
+        state.emit(Event::AllianceChanged(alliance, source)).await;
+    }
+}
+
+pub(crate) async fn set_mode(state: &DsState, mode: Mode) {
+    let mut send = state.send().write().await;
+    if send.mode() != mode {
+        let disabled = send.set_mode(mode);
+        drop(send);
+        // The disable comes first, so the log never shows an enabled robot switching modes
+        if disabled {
+            info!("Disabling to switch to {:?}", mode);
+            state.emit(Event::Disabled).await;
+        }
+        state.emit(Event::ModeChanged(mode)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::state::send::build_control_packet;
+    use crate::{Control, DriverStation, DropAction, Ports};
+    use std::time::Duration;
+
+    fn state() -> DsState {
+        DsState::new(Alliance::new_red(1), "127.0.0.1".to_string())
+    }
+
+    #[tokio::test]
+    async fn verify_commands_apply_on_connect() {
+        let mut ds = DriverStation::builder()
+            .ip("127.0.0.1")
+            .ports(Ports {
+                robot_udp: 41110,
+                ds_udp: 41150,
+                tcp: 41740,
+                simulator: 41735,
+            })
+            .udp_only(true)
+            .connect_on_build(false)
+            .on_drop(DropAction::Nothing, Duration::ZERO)
+            .build()
+            .await;
+        ds.set_mode_sync(Mode::Test).unwrap();
+        ds.set_alliance_sync(Alliance::new_blue(2)).unwrap();
+        assert_eq!(ds.mode().await, Mode::Autonomous);
+
+        ds.connect().await;
+        assert_eq!(ds.mode().await, Mode::Test);
+        assert_eq!(ds.alliance(), Alliance::new_blue(2));
+        ds.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn verify_setters_and_commanders_apply_in_order() {
+        let mut ds = DriverStation::builder()
+            .ip("127.0.0.1")
+            .connect_on_build(false)
+            .on_drop(DropAction::Nothing, Duration::ZERO)
+            .build()
+            .await;
+        let commander = ds.commander();
+
+        // A disable made after an enable wins, whichever way each was made
+        commander.try_send(Command::Enable).unwrap();
+        ds.set_enabled_sync(false).unwrap();
+        apply_queued(&ds.state).await;
+        assert!(!ds.enabled().await);
+
+        ds.set_enabled_sync(true).unwrap();
+        ds.disable().await;
+        apply_queued(&ds.state).await;
+        assert!(!ds.enabled().await);
+
+        ds.disable().await;
+        commander.try_send(Command::Enable).unwrap();
+        apply_queued(&ds.state).await;
+        assert!(ds.enabled().await);
+
+        // Switching modes while enabled disables, and enabling afterwards enables in the new mode
+        commander
+            .try_send(Command::SetMode(Mode::Teleoperated))
+            .unwrap();
+        ds.set_enabled_sync(true).unwrap();
+        apply_queued(&ds.state).await;
+        assert_eq!(ds.mode().await, Mode::Teleoperated);
+        assert!(ds.enabled().await);
+    }
+
+    #[tokio::test]
+    async fn verify_full_queue_applies_in_order() {
+        let state = state();
+        for _ in 0..COMMAND_CAPACITY {
+            queue(&state, Command::SetMode(Mode::Test)).await;
+        }
+        // The queue is full, so this applies those before it rather than waiting for a send loop
+        queue(&state, Command::SetMode(Mode::Teleoperated)).await;
+        assert_eq!(state.send().read().await.mode(), Mode::Test);
+        apply_queued(&state).await;
+        assert_eq!(state.send().read().await.mode(), Mode::Teleoperated);
+
+        let commander = state.commander();
+        for _ in 0..COMMAND_CAPACITY {
+            commander.try_send(Command::Disarm).unwrap();
+        }
+        assert!(commander.try_send(Command::Disable).is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_expired_watchdog_disables() {
+        let state = state();
+        state
+            .watchdog()
+            .set_timeout(Some(Duration::from_millis(10)));
+        queue(&state, Command::Enable).await;
+        apply_commands(&state).await;
+        assert!(state.send().read().await.enabled());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        apply_commands(&state).await;
+        assert!(!state.send().read().await.enabled());
+        let events = state.events().read().await.recent();
+        assert_eq!(events.last().unwrap().event, Event::WatchdogExpired);
+    }
+
+    /// Returns the control bits of the next packet
+    async fn control(state: &DsState) -> Control {
+        build_control_packet(&*state.send().read().await).control
+    }
+
+    async fn events(state: &DsState) -> Vec<Event> {
+        let log = state.events().read().await;
+        log.recent().into_iter().map(|timed| timed.event).collect()
+    }
+
+    #[tokio::test]
+    async fn verify_mode_changes_disable_first() {
+        let state = state();
+        queue(&state, Command::Enable).await;
+        queue(&state, Command::SetMode(Mode::Teleoperated)).await;
+        apply_commands(&state).await;
+        assert_eq!(control(&state).await, Control::TELEOP);
+        assert_eq!(
+            events(&state).await,
+            vec![
+                Event::Enabled,
+                Event::Disabled,
+                Event::ModeChanged(Mode::Teleoperated)
+            ]
+        );
+
+        // Switching to the same mode isn't a change
+        queue(&state, Command::Enable).await;
+        queue(&state, Command::SetMode(Mode::Teleoperated)).await;
+        apply_commands(&state).await;
+        assert_eq!(control(&state).await, Control::TELEOP | Control::ENABLED);
+
+        state.send().write().await.set_disable_on_mode_change(false);
+        queue(&state, Command::SetMode(Mode::Test)).await;
+        apply_commands(&state).await;
+        assert_eq!(control(&state).await, Control::TEST | Control::ENABLED);
+    }
+
+    #[tokio::test]
+    async fn verify_enabling_waits_out_the_brownout_cooldown() {
+        let state = state();
+        let cooldown = Duration::from_millis(50);
+        state
+            .send()
+            .write()
+            .await
+            .set_brownout_cooldown(Some(cooldown));
+        {
+            let mut telemetry = state.telemetry().write().await;
+            telemetry.set_brownout(true, state.instant());
+            telemetry.set_brownout(false, state.instant());
+        }
+
+        queue(&state, Command::Enable).await;
+        apply_commands(&state).await;
+        assert!(!control(&state).await.contains(Control::ENABLED));
+        assert_eq!(events(&state).await, vec![Event::EnableRefusedBrownout]);
+
+        tokio::time::sleep(cooldown).await;
+        queue(&state, Command::Enable).await;
+        apply_commands(&state).await;
+        assert!(control(&state).await.contains(Control::ENABLED));
+    }
+}
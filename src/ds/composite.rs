@@ -0,0 +1,160 @@
+use crate::JoystickValue;
+
+/// Reads the values of one device
+type DeviceSource = dyn Fn() -> Vec<JoystickValue> + Send + Sync;
+
+/// Maps the axis, button, and POV ids of one device to those of the joystick slot it's merged into
+///
+/// Values with ids that aren't mapped are left out, so only the inputs that are wanted need to be listed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InputMap {
+    axes: Vec<(u8, u8)>,
+    buttons: Vec<(u8, u8)>,
+    povs: Vec<(u8, u8)>,
+}
+
+impl InputMap {
+    pub const fn new() -> InputMap {
+        InputMap {
+            axes: Vec::new(),
+            buttons: Vec::new(),
+            povs: Vec::new(),
+        }
+    }
+
+    /// Sends the device's axis `from` as axis `to` of the slot
+    pub fn axis(mut self, from: u8, to: u8) -> InputMap {
+        self.axes.push((from, to));
+        self
+    }
+
+    /// Sends the device's button `from` as button `to` of the slot
+    pub fn button(mut self, from: u8, to: u8) -> InputMap {
+        self.buttons.push((from, to));
+        self
+    }
+
+    /// Sends the device's POV `from` as POV `to` of the slot
+    pub fn pov(mut self, from: u8, to: u8) -> InputMap {
+        self.povs.push((from, to));
+        self
+    }
+
+    /// Returns `value` with its id changed to the one it's mapped to, if it's mapped
+    fn map(&self, value: JoystickValue) -> Option<JoystickValue> {
+        let ids = match value {
+            JoystickValue::Axis { .. } => &self.axes,
+            JoystickValue::Button { .. } => &self.buttons,
+            JoystickValue::POV { .. } => &self.povs,
+        };
+        let (_, to) = ids.iter().find(|(from, _)| *from == value.id())?;
+        Some(match value {
+            JoystickValue::Axis { value, .. } => JoystickValue::Axis { id: *to, value },
+            JoystickValue::Button { pressed, .. } => JoystickValue::Button { id: *to, pressed },
+            JoystickValue::POV { angle, .. } => JoystickValue::POV { id: *to, angle },
+        })
+    }
+}
+
+/// Merges several devices, such as a wheel, pedals, and a button box, into the values of one joystick slot
+///
+/// The protocol only has 6 slots, so devices that are used together can share one. Each device is read by its own
+/// closure, and its values are mapped with an [`InputMap`]. When devices are mapped to the same id, a button is
+/// pressed if any of them press it, and the last device added wins for axes and POVs.
+#[derive(Default)]
+pub struct CompositeJoystick {
+    devices: Vec<(Box<DeviceSource>, InputMap)>,
+}
+
+impl CompositeJoystick {
+    pub fn new() -> CompositeJoystick {
+        CompositeJoystick::default()
+    }
+
+    /// Adds a device read by `source`, which returns values with the device's own ids
+    pub fn device(
+        mut self,
+        source: impl Fn() -> Vec<JoystickValue> + Send + Sync + 'static,
+        map: InputMap,
+    ) -> CompositeJoystick {
+        self.devices.push((Box::new(source), map));
+        self
+    }
+
+    /// Reads every device, returning the values for the slot
+    ///
+    /// This is meant to be called from the joystick supplier, once for each composite slot.
+    pub fn values(&self) -> Vec<JoystickValue> {
+        let mut values: Vec<JoystickValue> = Vec::new();
+        for (source, map) in &self.devices {
+            for value in source().into_iter().filter_map(|value| map.map(value)) {
+                // Later values replace earlier ones when encoded, so a released button would hide the press before it
+                if let JoystickValue::Button { id, pressed: false } = value
+                    && values.contains(&JoystickValue::Button { id, pressed: true })
+                {
+                    continue;
+                }
+                values.push(value);
+            }
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_devices_are_merged() {
+        let composite = CompositeJoystick::new()
+            .device(
+                || {
+                    vec![
+                        JoystickValue::Axis { id: 0, value: 0.5 },
+                        JoystickValue::Axis { id: 1, value: 1.0 },
+                    ]
+                },
+                InputMap::new().axis(0, 0),
+            )
+            .device(
+                || {
+                    vec![
+                        JoystickValue::Axis {
+                            id: 0,
+                            value: -0.25,
+                        },
+                        JoystickValue::Button {
+                            id: 3,
+                            pressed: true,
+                        },
+                    ]
+                },
+                InputMap::new().axis(0, 2).button(3, 1),
+            )
+            .device(
+                || {
+                    vec![JoystickValue::Button {
+                        id: 0,
+                        pressed: false,
+                    }]
+                },
+                InputMap::new().button(0, 1),
+            );
+
+        assert_eq!(
+            composite.values(),
+            vec![
+                JoystickValue::Axis { id: 0, value: 0.5 },
+                JoystickValue::Axis {
+                    id: 2,
+                    value: -0.25
+                },
+                JoystickValue::Button {
+                    id: 1,
+                    pressed: true
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// The period of the control packet send loop, used to make reordered packets get overtaken
+const SEND_PERIOD: Duration = Duration::from_millis(20);
+
+/// Artificial impairments applied to control packets sent to a simulated robot
+///
+/// These only take effect while the driver station is in [`DsMode::Simulation`](crate::DsMode::Simulation),
+/// and can be used to exercise robot code's loss of comms handling and watchdogs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinkConditions {
+    /// A fixed delay added before every packet is sent
+    pub delay: Duration,
+    /// The maximum random delay added on top of `delay`
+    pub jitter: Duration,
+    /// The probability, from `0.0..=1.0`, that a packet is held back until after the one following it
+    pub reorder_probability: f32,
+    /// The probability, from `0.0..=1.0`, that a packet is dropped entirely
+    pub drop_probability: f32,
+}
+
+impl LinkConditions {
+    /// Creates a `LinkConditions` that leaves the link untouched
+    #[inline(always)]
+    pub const fn ideal() -> LinkConditions {
+        LinkConditions {
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            reorder_probability: 0.0,
+            drop_probability: 0.0,
+        }
+    }
+
+    /// Returns true if these conditions would not impair the link at all
+    #[inline]
+    pub fn is_ideal(&self) -> bool {
+        self.delay.is_zero()
+            && self.jitter.is_zero()
+            && self.reorder_probability <= 0.0
+            && self.drop_probability <= 0.0
+    }
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        LinkConditions::ideal()
+    }
+}
+
+/// What should happen to a single outbound packet
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Verdict {
+    Drop,
+    /// Send the packet after the given delay
+    Send(Duration),
+}
+
+/// Decides the fate of each outbound packet according to the configured `LinkConditions`
+pub(crate) struct LinkConditioner {
+    /// xorshift64 state, never zero
+    rng: u64,
+}
+
+impl LinkConditioner {
+    pub fn new() -> LinkConditioner {
+        let seed = RandomState::new().build_hasher().finish();
+        LinkConditioner { rng: seed | 1 }
+    }
+
+    pub fn judge(&mut self, conditions: &LinkConditions) -> Verdict {
+        if self.chance(conditions.drop_probability) {
+            return Verdict::Drop;
+        }
+
+        let mut delay = conditions.delay + conditions.jitter.mul_f64(self.next_f64());
+        if self.chance(conditions.reorder_probability) {
+            // Hold this packet long enough that the next one is guaranteed to overtake it
+            delay += SEND_PERIOD + conditions.jitter + Duration::from_millis(1);
+        }
+
+        Verdict::Send(delay)
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        probability > 0.0 && self.next_f64() < f64::from(probability)
+    }
+
+    /// Returns a uniformly distributed value in `0.0..1.0`
+    fn next_f64(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_drop_probability() {
+        let mut conditioner = LinkConditioner::new();
+        let always = LinkConditions {
+            drop_probability: 1.0,
+            ..LinkConditions::ideal()
+        };
+        let never = LinkConditions::ideal();
+
+        for _ in 0..100 {
+            assert_eq!(conditioner.judge(&always), Verdict::Drop);
+            assert_eq!(conditioner.judge(&never), Verdict::Send(Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn verify_delay_bounds() {
+        let mut conditioner = LinkConditioner::new();
+        let conditions = LinkConditions {
+            delay: Duration::from_millis(50),
+            jitter: Duration::from_millis(10),
+            ..LinkConditions::ideal()
+        };
+
+        for _ in 0..100 {
+            match conditioner.judge(&conditions) {
+                Verdict::Send(delay) => {
+                    assert!(delay >= Duration::from_millis(50));
+                    assert!(delay <= Duration::from_millis(60));
+                }
+                Verdict::Drop => panic!("Packet dropped with a drop probability of 0"),
+            }
+        }
+    }
+}
@@ -0,0 +1,785 @@
+//! Declarative driver station settings
+//!
+//! With the `config` feature, a [`DsConfig`] can be loaded from a TOML file:
+//!
+//! ```toml
+//! team_number = 4533
+//! # Overrides the address inferred from the team number
+//! # ip = "172.22.11.2"
+//! alliance = "red1"
+//! # Use the alliance above even when another is chosen with `DriverStation::set_alliance`
+//! lock_alliance = false
+//! # Only one driver station per host can detect simulators
+//! simulator = true
+//! refuse_enable_on_conflict = false
+//! # Only control the robot, without the console or anything else sent over TCP
+//! udp_only = false
+//! # Wait for `DriverStation::connect` before talking to the robot
+//! connect_on_build = true
+//! # "disable", "estop", or "nothing", sent as the driver station shuts down, for up to drop_timeout seconds
+//! on_drop = "disable"
+//! drop_timeout = 0.1
+//! # Seconds after a brownout that enabling is refused, unset by default
+//! brownout_cooldown = 3
+//! # Seconds an arming lasts, if enabling requires arming first. Unset by default
+//! arm_timeout = 5
+//! # Seconds the robot stays enabled without the application feeding the watchdog. Unset by default
+//! watchdog = 0.5
+//! # "standard" or "precise"
+//! pacing = "standard"
+//! # The season of the roboRIO image
+//! protocol = 2025
+//! # Leave trailing joysticks with no values out of control packets
+//! compact_joysticks = false
+//! # Seconds of telemetry summarised in each published window, 0 turns them off
+//! telemetry_window = 1
+//!
+//! [ports]
+//! robot_udp = 1110
+//! ds_udp = 1150
+//! tcp = 1740
+//! simulator = 1135
+//!
+//! # Durations are in seconds
+//! [practice]
+//! autonomous = 15
+//! delay = 1
+//! teleop = 135
+//!
+//! [reconnect]
+//! tcp_retry = 2
+//! max_backoff = 5
+//! tcp_keepalive = 2
+//! udp_timeout = 2
+//! # Unset by default, relying on keepalive probes
+//! tcp_timeout = 10
+//!
+//! # Unset by default, leaving the OS defaults
+//! [socket]
+//! dscp = 46
+//! ttl = 64
+//! recv_buffer_size = 262144
+//! send_buffer_size = 65536
+//! interface = "eth0"
+//! local_address = "10.45.33.5"
+//! reuse_address = false
+//!
+//! [source_filter]
+//! enabled = true
+//! # Also accepted besides the robot
+//! allow = ["10.45.33.20"]
+//!
+//! [console]
+//! dedup = false
+//! # Unset by default, passing every message on
+//! max_per_second = 50
+//!
+//! # Joystick values are sent as neutral during these
+//! [joystick_suppression]
+//! autonomous = false
+//! disabled = false
+//!
+//! # Bytes of free space on the roboRIO below which an event is raised, unset by default
+//! [resource_limits]
+//! min_free_disk = 52428800
+//! min_free_ram = 16777216
+//!
+//! # Percentages of CAN bus utilization, a threshold above 100 never warns
+//! [can_utilization]
+//! threshold = 90
+//! # Seconds the utilization has to stay above the threshold
+//! hold = 2
+//! hysteresis = 10
+//!
+//! [[joysticks]]
+//! slot = 0
+//! device = "Xbox Controller"
+//! required = true
+//! # Pairs of the device's axis and the slot axis it's sent as, unset by default
+//! axes = [[1, 0], [0, 1]]
+//!
+//! # Named sets of joystick mappings, switched between with `DriverStation::use_profile`
+//! [[profiles]]
+//! name = "driver-b"
+//!
+//! [[profiles.joysticks]]
+//! slot = 0
+//! device = "030000005e0400008e02000014010000"
+//! ```
+//!
+//! Every key is optional, and defaults to the values shown unless noted otherwise.
+
+#[cfg(feature = "config")]
+use crate::Result;
+use crate::ds::state::Mode;
+use crate::proto::year::ProtocolYear;
+use crate::{Alliance, JoystickValue};
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+#[cfg(feature = "config")]
+use std::path::Path;
+use std::time::Duration;
+
+/// Settings used to construct a [`DriverStation`](crate::DriverStation)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct DsConfig {
+    pub team_number: u16,
+    /// The address of the roboRIO, if it isn't at the address inferred from the team number
+    pub ip: Option<String>,
+    pub alliance: Alliance,
+    /// Whether [`DsConfig::alliance`] takes precedence over the station chosen by the user, see
+    /// [`AllianceSource`](crate::AllianceSource)
+    pub lock_alliance: bool,
+    /// The season of the roboRIO image, which decides where the robot is found from the team number, see
+    /// [`ProtocolYear`]
+    pub protocol: ProtocolYear,
+    /// Whether to switch to simulation mode when a WPILib simulator announces itself
+    ///
+    /// Simulators announce themselves on a fixed port that only one driver station per host can listen on,
+    /// so other driver stations on the same host should disable this.
+    pub simulator: bool,
+    /// Whether enabling is refused while another driver station seems to be controlling the robot
+    pub refuse_enable_on_conflict: bool,
+    /// Whether to leave the TCP connection closed, for applications that only enable and disable the robot
+    ///
+    /// The console, robot versions, game data, and other TCP tags aren't exchanged, and the connection failing isn't
+    /// reported every [`ReconnectPolicy::tcp_retry`].
+    pub udp_only: bool,
+    /// Whether the driver station starts talking to the robot as soon as it's built
+    ///
+    /// When off, nothing is sent or received until [`DriverStation::connect`](crate::DriverStation::connect), so
+    /// subscriptions and handlers can be set up without missing the first packets from the robot.
+    pub connect_on_build: bool,
+    /// What the robot is told when the [`DriverStation`](crate::DriverStation) is shut down or dropped, such as when
+    /// the application exits
+    ///
+    /// [`DriverStation::shutdown`](crate::DriverStation::shutdown) sends a few packets. Dropping without shutting down
+    /// can't await, so it sends a single packet, and nothing if the send state is locked.
+    ///
+    /// The packets go to the address the connection last sent to. Before connecting, they only go to a target given
+    /// as an IP address, as looking up a host name could block.
+    pub on_drop: DropAction,
+    /// How long shutting down the driver station may spend telling the robot, see [`DsConfig::on_drop`]
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub drop_timeout: Duration,
+    /// How long after the roboRIO last reported a brownout that enabling is refused, if at all
+    #[cfg_attr(
+        feature = "config",
+        serde(with = "opt_secs", skip_serializing_if = "Option::is_none")
+    )]
+    pub brownout_cooldown: Option<Duration>,
+    /// How long the robot stays armed after [`DriverStation::arm`](crate::DriverStation::arm), if enabling requires it
+    #[cfg_attr(
+        feature = "config",
+        serde(with = "opt_secs", skip_serializing_if = "Option::is_none")
+    )]
+    pub arm_timeout: Option<Duration>,
+    /// How long the robot stays enabled without [`DriverStation::feed_watchdog`](crate::DriverStation::feed_watchdog)
+    /// being called, if the watchdog is on
+    #[cfg_attr(
+        feature = "config",
+        serde(with = "opt_secs", skip_serializing_if = "Option::is_none")
+    )]
+    pub watchdog: Option<Duration>,
+    pub pacing: Pacing,
+    pub ports: Ports,
+    pub practice: PracticeTiming,
+    pub reconnect: ReconnectPolicy,
+    pub socket: SocketOptions,
+    pub source_filter: SourceFilter,
+    pub console: ConsoleLimits,
+    pub joystick_suppression: JoystickSuppression,
+    /// Whether trailing joysticks with no values are left out of control packets, see
+    /// [`DriverStation::set_compact_joysticks`](crate::DriverStation::set_compact_joysticks)
+    pub compact_joysticks: bool,
+    /// How much telemetry is summarised in each window, or zero to not publish them, see
+    /// [`DriverStation::subscribe_windows`](crate::DriverStation::subscribe_windows)
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub telemetry_window: Duration,
+    pub resource_limits: ResourceLimits,
+    pub can_utilization: CanUtilizationAlert,
+    /// Which physical devices the application should place in each joystick slot
+    pub joysticks: Vec<JoystickMapping>,
+    /// Other sets of joystick mappings, such as one for each driver, see
+    /// [`DriverStation::use_profile`](crate::DriverStation::use_profile)
+    pub profiles: Vec<MappingProfile>,
+}
+
+impl Default for DsConfig {
+    fn default() -> DsConfig {
+        DsConfig {
+            team_number: 0,
+            ip: None,
+            alliance: Alliance::new_red(1),
+            lock_alliance: false,
+            protocol: ProtocolYear::LATEST,
+            simulator: true,
+            refuse_enable_on_conflict: false,
+            udp_only: false,
+            connect_on_build: true,
+            on_drop: DropAction::Disable,
+            drop_timeout: Duration::from_millis(100),
+            brownout_cooldown: None,
+            arm_timeout: None,
+            watchdog: None,
+            pacing: Pacing::Standard,
+            ports: Ports::default(),
+            practice: PracticeTiming::default(),
+            reconnect: ReconnectPolicy::default(),
+            socket: SocketOptions::default(),
+            source_filter: SourceFilter::default(),
+            console: ConsoleLimits::default(),
+            joystick_suppression: JoystickSuppression::default(),
+            compact_joysticks: false,
+            telemetry_window: Duration::from_secs(1),
+            resource_limits: ResourceLimits::default(),
+            can_utilization: CanUtilizationAlert::default(),
+            joysticks: Vec::new(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl DsConfig {
+    /// Reads a config from the TOML file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<DsConfig> {
+        let contents = std::fs::read_to_string(path)?;
+        DsConfig::from_toml(&contents)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<DsConfig> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Writes this config to `path` as TOML
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+}
+
+/// The ports used to talk to the roboRIO
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct Ports {
+    /// The roboRIO port control packets are sent to
+    pub robot_udp: u16,
+    /// The local port status packets are received on
+    pub ds_udp: u16,
+    /// The roboRIO port for the TCP connection
+    pub tcp: u16,
+    /// The local port WPILib simulators announce themselves on
+    pub simulator: u16,
+}
+
+impl Default for Ports {
+    fn default() -> Ports {
+        Ports {
+            robot_udp: 1110,
+            ds_udp: 1150,
+            tcp: 1740,
+            simulator: 1135,
+        }
+    }
+}
+
+/// The length of each period of a practice match
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct PracticeTiming {
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub autonomous: Duration,
+    /// The pause between autonomous and teleop
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub delay: Duration,
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub teleop: Duration,
+}
+
+impl PracticeTiming {
+    /// The length of a whole practice match
+    pub fn match_length(&self) -> Duration {
+        self.autonomous + self.delay + self.teleop
+    }
+}
+
+impl Default for PracticeTiming {
+    fn default() -> PracticeTiming {
+        PracticeTiming {
+            autonomous: Duration::from_secs(15),
+            delay: Duration::from_secs(1),
+            teleop: Duration::from_secs(135),
+        }
+    }
+}
+
+/// How the driver station recovers from losing its connections to the roboRIO
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct ReconnectPolicy {
+    /// The time between attempts to open the TCP connection while the roboRIO is responding over UDP
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub tcp_retry: Duration,
+    /// The longest the send loop waits between retries after the target refuses control packets
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub max_backoff: Duration,
+    /// How long the TCP connection can be idle before keepalive probes are sent, and the time between probes
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub tcp_keepalive: Duration,
+    /// How long the roboRIO can go without answering control packets before it's declared disconnected
+    ///
+    /// The robot is disabled when this happens, so it doesn't enable again by itself when it comes back.
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub udp_timeout: Duration,
+    /// How long the TCP connection can go without receiving a frame before it's closed and reopened
+    ///
+    /// Robot code that doesn't print may not send anything for a long time, so this is off by default. Losing the
+    /// TCP connection doesn't disable the robot, as control packets are sent over UDP.
+    #[cfg_attr(
+        feature = "config",
+        serde(with = "opt_secs", skip_serializing_if = "Option::is_none")
+    )]
+    pub tcp_timeout: Option<Duration>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            tcp_retry: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(5),
+            tcp_keepalive: Duration::from_secs(2),
+            udp_timeout: Duration::from_secs(2),
+            tcp_timeout: None,
+        }
+    }
+}
+
+/// Tuning applied to the sockets used to talk to the roboRIO. Options left as `None` keep the OS defaults
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct SocketOptions {
+    /// The Differentiated Services code point marked on outbound packets, such as 46 (Expedited Forwarding) to
+    /// prioritise control traffic on networks with QoS
+    pub dscp: Option<u8>,
+    /// The time to live of outbound packets
+    pub ttl: Option<u32>,
+    /// The size of the receive buffers, larger buffers absorb bursts of telemetry
+    pub recv_buffer_size: Option<usize>,
+    pub send_buffer_size: Option<usize>,
+    /// The name of the network interface traffic is restricted to, such as `eth0`
+    ///
+    /// Only supported on Linux, and usually requires `CAP_NET_RAW`.
+    pub interface: Option<String>,
+    /// The local address sockets are bound to, such as the address of the NIC connected to the robot
+    ///
+    /// Unlike [`SocketOptions::interface`] this works on every platform. On a laptop that is also on venue
+    /// Wi-Fi, it keeps control traffic from leaving through the wrong NIC when both networks overlap.
+    pub local_address: Option<Ipv4Addr>,
+    /// Allows sockets to bind to ports already bound by another socket, with `SO_REUSEADDR`
+    ///
+    /// The roboRIO always replies to port 1150, so several driver stations on one host have to share it. The OS only
+    /// gives each status packet to one socket, so every driver station should also bind to the [`SocketOptions::local_address`]
+    /// of the NIC its robot is on, or use a different [`Ports::ds_udp`] with a relay or simulator.
+    pub reuse_address: bool,
+}
+
+/// Which addresses status packets are accepted from
+///
+/// By default only packets from the robot being driven are used, so that another robot on the same network can't
+/// corrupt its telemetry. Rejected packets are counted in [`Stats::packets_rejected`](crate::Stats::packets_rejected).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct SourceFilter {
+    /// Whether packets from other addresses are dropped
+    pub enabled: bool,
+    /// Addresses accepted in addition to the robot, for robots that reply from a different address than they're reached at
+    pub allow: Vec<IpAddr>,
+}
+
+impl Default for SourceFilter {
+    fn default() -> SourceFilter {
+        SourceFilter {
+            enabled: true,
+            allow: Vec::new(),
+        }
+    }
+}
+
+/// What the robot is told as the driver station is shut down or dropped
+///
+/// Without this the robot only disables once it stops receiving control packets, after its own timeout.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum DropAction {
+    /// Sends a few disabled control packets
+    #[default]
+    Disable,
+    /// Sends a few estopped control packets, so the robot stays stopped until it's rebooted
+    Estop,
+    /// Stops sending control packets, leaving the robot to time out
+    Nothing,
+}
+
+/// How accurately control packets are spaced
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Pacing {
+    /// Packets are sent when the tokio timer fires, which can be up to a millisecond late
+    #[default]
+    Standard,
+    /// The send loop spins for up to 2ms before each packet to space them exactly, for robot code with filters that
+    /// are sensitive to irregular packet spacing. This keeps a runtime thread busy for a tenth of the time
+    Precise,
+}
+
+/// Limits applied to robot console messages before they reach the TCP consumer, for robot code that floods the console
+///
+/// Both are off by default. Suppressed messages are replaced with a message saying how many there were.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct ConsoleLimits {
+    /// Whether repeats of the previous message are collapsed into a count
+    pub dedup: bool,
+    /// How many messages are passed on each second, the rest are dropped
+    pub max_per_second: Option<u32>,
+}
+
+/// When joystick values are replaced with neutral ones before being sent, so stray driver input can't reach the robot
+///
+/// Both are off by default. The supplier is still called, so faults are reported as usual.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct JoystickSuppression {
+    /// While the requested mode is autonomous
+    pub autonomous: bool,
+    /// While the robot is disabled
+    pub disabled: bool,
+}
+
+impl JoystickSuppression {
+    /// Returns whether joystick values are suppressed in `mode`
+    pub(crate) fn suppresses(self, mode: Mode, enabled: bool) -> bool {
+        (self.autonomous && mode == Mode::Autonomous) || (self.disabled && !enabled)
+    }
+}
+
+/// How little free space the roboRIO can report before [`Event::LowDisk`](crate::Event::LowDisk) or
+/// [`Event::LowRam`](crate::Event::LowRam) is raised
+///
+/// Both are unset by default. Log files filling the disk, or a leak filling memory, usually crash the robot code soon
+/// after.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct ResourceLimits {
+    /// The free disk space in bytes
+    pub min_free_disk: Option<u32>,
+    /// The free memory in bytes
+    pub min_free_ram: Option<u32>,
+}
+
+/// When [`Event::CanSaturated`](crate::Event::CanSaturated) is raised for the CAN bus utilization the roboRIO reports
+///
+/// The utilization has to stay at or above `threshold` for `hold` before the bus is considered saturated, and then
+/// drop below `threshold - hysteresis` before it isn't, so a bus hovering around the threshold doesn't flap.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct CanUtilizationAlert {
+    /// The utilization as a percentage
+    pub threshold: f32,
+    #[cfg_attr(feature = "config", serde(with = "secs"))]
+    pub hold: Duration,
+    /// Percentage points below the threshold the utilization has to drop to
+    pub hysteresis: f32,
+}
+
+impl Default for CanUtilizationAlert {
+    fn default() -> CanUtilizationAlert {
+        CanUtilizationAlert {
+            threshold: 90.0,
+            hold: Duration::from_secs(2),
+            hysteresis: 10.0,
+        }
+    }
+}
+
+/// Assigns a physical device to a joystick slot
+///
+/// The driver station doesn't read devices itself, these are for the application's joystick supplier.
+/// Slots of required joysticks are added to [`JoystickSafety::required`](crate::JoystickSafety::required).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct JoystickMapping {
+    /// The index of the joystick in the supplier's output
+    pub slot: usize,
+    /// The name or identifier of the device, such as its SDL GUID, as understood by the application
+    pub device: String,
+    #[cfg_attr(feature = "config", serde(default))]
+    pub required: bool,
+    /// Pairs of the device's axis and the slot axis it's sent as. Axes that aren't listed keep their ids
+    #[cfg_attr(
+        feature = "config",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub axes: Vec<(u8, u8)>,
+}
+
+impl JoystickMapping {
+    /// Maps `device` to `slot`, with its axes unchanged
+    pub fn new(slot: usize, device: &str, required: bool) -> JoystickMapping {
+        JoystickMapping {
+            slot,
+            device: device.to_string(),
+            required,
+            axes: Vec::new(),
+        }
+    }
+
+    /// Sends the device's axis `from` as axis `to` of the slot
+    pub fn axis(mut self, from: u8, to: u8) -> JoystickMapping {
+        self.axes.push((from, to));
+        self
+    }
+
+    /// Changes the ids of the device's axes to those of the slot. Meant to be called from the joystick supplier
+    pub fn remap(&self, values: Vec<JoystickValue>) -> Vec<JoystickValue> {
+        values
+            .into_iter()
+            .map(|value| match value {
+                JoystickValue::Axis { id, value } => {
+                    match self.axes.iter().find(|(from, _)| *from == id) {
+                        Some((_, to)) => JoystickValue::Axis { id: *to, value },
+                        None => JoystickValue::Axis { id, value },
+                    }
+                }
+                value => value,
+            })
+            .collect()
+    }
+}
+
+/// A named set of joystick mappings, so drivers who use different controllers can swap without editing code
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct MappingProfile {
+    pub name: String,
+    #[cfg_attr(feature = "config", serde(default))]
+    pub joysticks: Vec<JoystickMapping>,
+}
+
+/// (De)serializes durations as a number of seconds
+#[cfg(feature = "config")]
+mod secs {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "config")]
+mod opt_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => super::secs::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        #[derive(Deserialize)]
+        struct Secs(#[serde(with = "super::secs")] Duration);
+
+        Ok(Option::<Secs>::deserialize(deserializer)?.map(|Secs(duration)| duration))
+    }
+}
+
+/// Alliances are written as their colour followed by their position, such as `blue2`
+#[cfg(feature = "config")]
+impl Serialize for Alliance {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let colour = if self.is_red() { "red" } else { "blue" };
+        serializer.collect_str(&format_args!("{}{}", colour, self.position()))
+    }
+}
+
+#[cfg(feature = "config")]
+impl<'de> Deserialize<'de> for Alliance {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Alliance, D::Error> {
+        use serde::de::Error;
+
+        let alliance = String::deserialize(deserializer)?;
+        alliance.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Protocols are written as the year of their season
+#[cfg(feature = "config")]
+impl Serialize for ProtocolYear {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.year())
+    }
+}
+
+#[cfg(feature = "config")]
+impl<'de> Deserialize<'de> for ProtocolYear {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<ProtocolYear, D::Error> {
+        use serde::de::Error;
+
+        let year = u16::deserialize(deserializer)?;
+        ProtocolYear::from_year(year)
+            .ok_or_else(|| D::Error::custom(format!("Unsupported protocol year {}", year)))
+    }
+}
+
+#[cfg(all(test, feature = "config"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_toml_round_trip() {
+        let config = DsConfig::from_toml(
+            r#"
+            team_number = 4533
+            alliance = "blue2"
+            lock_alliance = true
+            protocol = 2019
+            brownout_cooldown = 3
+            arm_timeout = 0.5
+            udp_only = true
+            connect_on_build = false
+            on_drop = "estop"
+            compact_joysticks = true
+            telemetry_window = 5
+
+            [socket]
+            dscp = 46
+            local_address = "10.45.33.5"
+
+            [practice]
+            autonomous = 20
+            teleop = 0.5
+
+            [reconnect]
+            tcp_timeout = 10
+
+            [resource_limits]
+            min_free_disk = 1000
+
+            [can_utilization]
+            threshold = 80
+
+            [[joysticks]]
+            slot = 1
+            device = "Xbox Controller"
+            required = true
+
+            [[profiles]]
+            name = "driver-b"
+
+            [[profiles.joysticks]]
+            slot = 0
+            device = "030000005e0400008e02000014010000"
+            axes = [[1, 0], [0, 1]]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.team_number, 4533);
+        assert_eq!(config.alliance, Alliance::new_blue(2));
+        assert!(config.lock_alliance);
+        assert_eq!(config.protocol, ProtocolYear::Y2019);
+        assert_eq!(config.ports, Ports::default());
+        assert_eq!(config.brownout_cooldown, Some(Duration::from_secs(3)));
+        assert_eq!(config.arm_timeout, Some(Duration::from_millis(500)));
+        assert_eq!(config.watchdog, None);
+        assert!(config.udp_only);
+        assert!(!config.connect_on_build);
+        assert_eq!(config.on_drop, DropAction::Estop);
+        assert_eq!(config.drop_timeout, Duration::from_millis(100));
+        assert!(config.compact_joysticks);
+        assert_eq!(config.telemetry_window, Duration::from_secs(5));
+        assert_eq!(config.socket.dscp, Some(46));
+        assert_eq!(config.socket.interface, None);
+        assert_eq!(
+            config.socket.local_address,
+            Some(Ipv4Addr::new(10, 45, 33, 5))
+        );
+        assert_eq!(config.practice.autonomous, Duration::from_secs(20));
+        assert_eq!(config.practice.teleop, Duration::from_millis(500));
+        assert_eq!(config.practice.delay, Duration::from_secs(1));
+        assert_eq!(config.reconnect.udp_timeout, Duration::from_secs(2));
+        assert_eq!(config.reconnect.tcp_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.resource_limits.min_free_disk, Some(1000));
+        assert_eq!(config.resource_limits.min_free_ram, None);
+        assert_eq!(config.can_utilization.threshold, 80.0);
+        assert_eq!(config.can_utilization.hold, Duration::from_secs(2));
+        assert!(config.joysticks[0].required);
+        assert!(config.joysticks[0].axes.is_empty());
+        let mapping = &config.profiles[0].joysticks[0];
+        assert_eq!(config.profiles[0].name, "driver-b");
+        assert_eq!(
+            mapping.remap(vec![
+                JoystickValue::Axis { id: 0, value: 0.5 },
+                JoystickValue::Axis { id: 2, value: 1.0 },
+                JoystickValue::Button {
+                    id: 1,
+                    pressed: true
+                },
+            ]),
+            vec![
+                JoystickValue::Axis { id: 1, value: 0.5 },
+                JoystickValue::Axis { id: 2, value: 1.0 },
+                JoystickValue::Button {
+                    id: 1,
+                    pressed: true
+                },
+            ]
+        );
+
+        assert_eq!(
+            DsConfig::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+    }
+}
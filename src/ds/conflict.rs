@@ -0,0 +1,167 @@
+use crate::ds::state::Mode;
+use crate::proto::udp::inbound::types::Status;
+
+/// How far behind the next sequence number a reply can be while still answering a packet this driver station sent
+const SEQUENCE_SLACK: u16 = 128;
+/// The score at which a conflict is reported. Each symptom adds 2 and each clean packet subtracts 1,
+/// so a persistent symptom is reported after 5 packets, and one in every other packet after about 20
+const THRESHOLD: u32 = 10;
+
+/// A symptom of another driver station controlling the same robot
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ControlConflict {
+    /// The robot reported being enabled while this driver station had it disabled
+    UnexpectedEnable,
+    /// The enabled robot reported a different mode than the one requested
+    UnexpectedMode { requested: Mode, reported: Mode },
+    /// The robot answered control packets this driver station didn't send
+    UnknownSequence,
+}
+
+/// What this driver station asked of the robot, to compare status packets against
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Requested {
+    pub enabled: bool,
+    pub estopped: bool,
+    pub mode: Mode,
+    /// The sequence number of the next control packet to be sent
+    pub next_seqnum: u16,
+}
+
+/// Finds the symptom of a conflict in one status packet, if there is one
+pub(crate) fn symptom(
+    requested: Requested,
+    status: Status,
+    seqnum: u16,
+) -> Option<ControlConflict> {
+    // An estopped robot can't be controlled by anyone, and reports its own state
+    if requested.estopped || status.emergency_stopped() {
+        return None;
+    }
+
+    if requested.next_seqnum.wrapping_sub(seqnum) > SEQUENCE_SLACK {
+        return Some(ControlConflict::UnknownSequence);
+    }
+
+    // The robot reporting disabled while enabled is normal, for example while robot code starts
+    if !status.contains(Status::ENABLED) {
+        return None;
+    }
+    if !requested.enabled {
+        return Some(ControlConflict::UnexpectedEnable);
+    }
+    match Mode::from_status(status) {
+        Some(reported) if reported != requested.mode => Some(ControlConflict::UnexpectedMode {
+            requested: requested.mode,
+            reported,
+        }),
+        _ => None,
+    }
+}
+
+/// Debounces symptoms, so that the packets in flight while this driver station changes the robot's state aren't conflicts
+pub(crate) struct ConflictDetector {
+    score: u32,
+    conflict: Option<ControlConflict>,
+}
+
+impl ConflictDetector {
+    pub(crate) const fn new() -> ConflictDetector {
+        ConflictDetector {
+            score: 0,
+            conflict: None,
+        }
+    }
+
+    /// Records the symptom found in a status packet. Returns the new conflict if it started or ended
+    pub(crate) fn observe(
+        &mut self,
+        symptom: Option<ControlConflict>,
+    ) -> Option<Option<ControlConflict>> {
+        match symptom {
+            Some(symptom) => {
+                self.score = (self.score + 2).min(THRESHOLD);
+                if self.score >= THRESHOLD && self.conflict.is_none() {
+                    self.conflict = Some(symptom);
+                    return Some(self.conflict);
+                }
+            }
+            None => {
+                self.score = self.score.saturating_sub(1);
+                if self.score == 0 && self.conflict.is_some() {
+                    self.conflict = None;
+                    return Some(None);
+                }
+            }
+        }
+        None
+    }
+
+    pub(crate) fn reset(&mut self) -> Option<ControlConflict> {
+        self.score = 0;
+        self.conflict.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_conflict_detection() {
+        let requested = Requested {
+            enabled: false,
+            estopped: false,
+            mode: Mode::Teleoperated,
+            next_seqnum: 1000,
+        };
+        let enabled = Status::ENABLED | Status::TELEOP;
+        assert_eq!(
+            symptom(requested, enabled, 999),
+            Some(ControlConflict::UnexpectedEnable)
+        );
+        assert_eq!(symptom(requested, Status::TELEOP, 999), None);
+        assert_eq!(
+            symptom(requested, Status::TELEOP, 5000),
+            Some(ControlConflict::UnknownSequence)
+        );
+        // Answers to packets sent just before the sequence rolled over are still recent
+        let rolled = Requested {
+            next_seqnum: 2,
+            ..requested
+        };
+        assert_eq!(symptom(rolled, Status::TELEOP, u16::MAX), None);
+        let auto = Requested {
+            enabled: true,
+            mode: Mode::Autonomous,
+            ..requested
+        };
+        assert_eq!(
+            symptom(auto, enabled, 999),
+            Some(ControlConflict::UnexpectedMode {
+                requested: Mode::Autonomous,
+                reported: Mode::Teleoperated
+            })
+        );
+
+        // A packet or two in flight while enabling isn't a conflict
+        let mut detector = ConflictDetector::new();
+        assert_eq!(
+            detector.observe(Some(ControlConflict::UnexpectedEnable)),
+            None
+        );
+        assert_eq!(detector.observe(None), None);
+
+        let mut started = None;
+        for _ in 0..5 {
+            started = started.or(detector.observe(Some(ControlConflict::UnknownSequence)));
+        }
+        assert_eq!(started, Some(Some(ControlConflict::UnknownSequence)));
+
+        let mut ended = None;
+        for _ in 0..THRESHOLD {
+            ended = ended.or(detector.observe(None));
+        }
+        assert_eq!(ended, Some(None));
+    }
+}
@@ -1,30 +1,66 @@
 use super::Signal;
 
 use crate::proto::udp::inbound::UdpResponsePacket;
+use crate::proto::udp::inbound::types::Status;
 use crate::proto::udp::outbound::types::{DateTime as DTTag, *};
 
-use chrono::{Datelike, Timelike, Utc};
+use bytes::Bytes;
+use chrono::{Datelike, Timelike};
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::net::{TcpStream, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::UdpSocket;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
-use tokio::time::timeout;
+use tokio::task::JoinHandle;
+use tokio::time::timeout_at;
 use tokio_util::codec::Decoder;
 use tokio_util::udp::UdpFramed;
 
 use crate::Result;
+use crate::proto::Direction;
 use crate::proto::tcp::DsTcpCodec;
 use crate::proto::udp::DsUdpCodec;
 
-use crate::ds::state::{DsMode, DsState};
-use crate::proto::tcp::outbound::TcpTag;
+use crate::TcpPacket;
+use crate::ds::changes::StateChange;
+use crate::ds::command::{apply_commands, apply_queued, disable};
+use crate::ds::conflict::{ConflictDetector, Requested, symptom};
+use crate::ds::events::Event;
+use crate::ds::inputs::InputChange;
+use crate::ds::manual::next_manual;
+use crate::ds::outage::OutageClassifier;
+use crate::ds::safety::JoystickFault;
+use crate::ds::shape::{ShapeMismatch, find_mismatches};
+use crate::ds::sources::Polled;
+use crate::ds::state::send::{SendState, trim_unbound};
+use crate::ds::state::stats::{CONTROL_PERIOD, SLOW_SUPPLIER};
+use crate::ds::state::{Arrival, DsMode, DsState, Mode, QueuedTag};
+use crate::ds::tasks::spawn_named;
 
 mod backoff;
+#[cfg(feature = "crio")]
+mod crio;
+mod pacer;
+mod resume;
+mod shutdown;
+mod socket;
+mod source;
 
+#[cfg(feature = "crio")]
+pub(crate) use crio::crio_conn;
+pub(crate) use shutdown::FinalBurst;
+
+use crate::ds::conditioner::{LinkConditioner, Verdict};
+use crate::ds::config::{Ports, ReconnectPolicy, SocketOptions, SourceFilter};
+use crate::ds::validation::JoystickFrame;
 use backoff::ExponentialBackoff;
+use pacer::Pacer;
+use resume::ResumeDetector;
+use socket::{bind_udp, connect_tcp};
+use socket2::{SockRef, TcpKeepalive};
+use source::SourceValidator;
 use std::io::ErrorKind;
 
 /// The root task of the tokio runtime.
@@ -34,76 +70,204 @@ pub(crate) async fn udp_conn(
     state: Arc<DsState>,
     mut target_ip: String,
     mut rx: UnboundedReceiver<Signal>,
+    ports: Ports,
+    reconnect: ReconnectPolicy,
+    socket: SocketOptions,
+    filter: SourceFilter,
 ) -> Result<()> {
-    let mut tcp_connected = false;
+    let mut validator = SourceValidator::new(filter, &target_ip).await;
+    let mut conflicts = ConflictDetector::new();
+    let mut outages = OutageClassifier::new();
+    let mut tcp_task: Option<JoinHandle<Result<()>>> = None;
+    let mut last_tcp_attempt: Option<Instant> = None;
     let mut tcp_tx = None;
 
-    let udp_rx = UdpSocket::bind("0.0.0.0:1150").await?;
-    let mut udp_rx = UdpFramed::new(udp_rx, DsUdpCodec);
+    let udp_rx = bind_udp(socket.local_addr(ports.ds_udp), &socket)?;
+    let mut udp_rx = UdpFramed::new(udp_rx, DsUdpCodec::new(Some(state.raw_tap())));
 
     let (fwd_tx, mut fwd_rx) = unbounded_channel::<Signal>();
+    // Tells the receiving loop the host resumed from sleep, so the robot is reached anew
+    let (resume_tx, mut resume_rx) = unbounded_channel::<Duration>();
 
     let send_state = state.clone();
+    let send_socket = socket.clone();
     let target = target_ip.clone();
-    tokio::spawn(async move {
-        let mut udp_tx = UdpSocket::bind("0.0.0.0:0")
-            .await
-            .expect("Failed to bind tx socket");
+    let send_task = spawn_named("ds-send", async move {
+        let mut udp_tx = Arc::new(
+            bind_udp(send_socket.local_addr(0), &send_socket).expect("Failed to bind tx socket"),
+        );
         udp_tx
-            .connect(&format!("{}:1110", target))
+            .connect((target.as_str(), ports.robot_udp))
             .await
             .expect("Failed to connect to target");
+        send_state.set_robot_addr(udp_tx.peer_addr().ok());
 
-        let mut interval = tokio::time::interval(Duration::from_millis(20));
+        let mut pacer = Pacer::new(CONTROL_PERIOD, send_state.send().read().await.pacing());
 
         //let mut stream = select(interval, fwd_rx);
-        let mut backoff = ExponentialBackoff::new(Duration::new(5, 0));
+        let mut backoff = ExponentialBackoff::new(reconnect.max_backoff);
+        let mut conditioner = LinkConditioner::new();
+        let mut reporter = JoystickReporter::default();
+        let mut release = Release::new();
+        // The packets of a control sink, sent in place of the loop's own while it's alive
+        let mut manual = None;
+        let mut resume = ResumeDetector::new();
 
         loop {
+            send_state.heartbeat("ds-send");
+            if let Some(rx) = send_state.take_manual_rx() {
+                info!("Sending control packets from a control sink");
+                manual = Some(rx);
+                send_state.set_manual(true);
+                // The robot echoes the sequence numbers it's sent, which the sink's packets restart
+                send_state.link().write().await.forget_seqnum();
+            }
             tokio::select! {
-                _ = interval.tick() => {
-                    let mut state = send_state.send().write().await;
-                    let v = state.control().encode();
-                    // Massively overengineered considering the _only_ time that this actually starts
-                    // to come into play is directly after the simulator is closed before the DS switches to Normal mode again
-                    // but I don't feel like changing it, and now it's fail safe
-                    match backoff.run(udp_tx.send(&v[..])).await {
-                        Ok(_) => {}
-                        Err((e, dc)) => {
-                            if e.kind() == ErrorKind::ConnectionRefused && dc {
-                                println!("Send socket disconnected");
-                                send_state.recv().write().await.reset();
-                            }
+                deadline = pacer.tick() => {
+                    let window = send_state.stats().write().await.close_window(send_state.instant(), send_state.now());
+                    if let Some(window) = window {
+                        send_state.publish_window(window);
+                    }
+                    // An enabled state cached from before the host slept mustn't be sent as if nothing happened
+                    if let Some(away) = resume.tick(Instant::now(), SystemTime::now()) {
+                        warn!("The host seems to have slept or its clock jumped by {:?}, disabling", away);
+                        disable(&send_state).await;
+                        send_state.send().write().await.reset_seqnum();
+                        send_state.emit(Event::HostResumed(away)).await;
+                        let _ = resume_tx.send(away);
+                    }
+                    // The sink builds its own packets, but commands queued meanwhile still apply to the state
+                    if manual.is_some() {
+                        apply_queued(&send_state).await;
+                        continue;
+                    }
+                    apply_commands(&send_state).await;
+                    if !release.should_send(&send_state).await {
+                        continue;
+                    }
+                    let supplied = Instant::now();
+                    let polled = supply_joysticks(&send_state).await;
+                    reporter.supplier_ran(&send_state, supplied.elapsed()).await;
+                    reporter.source_used(&send_state, polled.as_ref().and_then(Polled::source)).await;
+                    let (v, seqnum, conditions, ds_mode, outcome, pacing, enabled, mode) = {
+                        let mut state = send_state.send().write().await;
+                        let (v, seqnum, outcome) = encode_tick(&mut state, polled);
+                        let (enabled, mode) = (state.enabled(), state.mode());
+                        (v, seqnum, state.link_conditions(), state.ds_mode(), outcome, state.pacing(), enabled, mode)
+                    };
+                    // Held before taking the stats lock, so spinning doesn't stall the recv task behind it
+                    pacer.hold(deadline);
+                    {
+                        let sent = send_state.instant();
+                        let mut stats = send_state.stats().write().await;
+                        stats.packet_sent(seqnum, sent);
+                        stats.summary_tick(sent, send_state.now(), enabled, mode);
+                        send_state.notify_sent(seqnum, sent);
+                    }
+                    reporter.report(&send_state, outcome).await;
+                    if ds_mode == DsMode::Simulation {
+                        let count = send_state.send().read().await.last_joysticks().len();
+                        send_state.tcp().write().await.describe_sim_joysticks(count);
+                    }
+                    // A new pacing takes effect from the next tick
+                    if pacing != pacer.pacing() {
+                        pacer = Pacer::new(CONTROL_PERIOD, pacing);
+                    }
+
+                    // Link impairment is only ever applied when talking to a simulator
+                    let verdict = if ds_mode == DsMode::Simulation && !conditions.is_ideal() {
+                        conditioner.judge(&conditions)
+                    } else {
+                        Verdict::Send(Duration::ZERO)
+                    };
+
+                    match verdict {
+                        Verdict::Drop => {}
+                        Verdict::Send(delay) if !delay.is_zero() => {
+                            let udp_tx = udp_tx.clone();
+                            let tap_state = send_state.clone();
+                            spawn_named("ds-delayed-send", async move {
+                                tokio::time::sleep(delay).await;
+                                tap_state.tap(Direction::UdpOutbound, &v[..]);
+                                let _ = udp_tx.send(&v[..]).await;
+                            });
                         }
+                        // Massively overengineered considering the _only_ time that this actually starts
+                        // to come into play is directly after the simulator is closed before the DS switches to Normal mode again
+                        // but I don't feel like changing it, and now it's fail safe
+                        Verdict::Send(_) => match backoff.run(async {
+                            send_state.tap(Direction::UdpOutbound, &v[..]);
+                            udp_tx.send(&v[..]).await
+                        }).await {
+                            Ok(_) => {}
+                            Err((e, dc)) => {
+                                if e.kind() == ErrorKind::ConnectionRefused && dc {
+                                    warn!("Send socket disconnected");
+                                    send_state.reset_recv().await;
+                                }
+                            }
+                        },
                     }
-                    state.increment_seqnum();
+                    // Backing off holds up the loop, which isn't the host sleeping
+                    resume.rebase(Instant::now(), SystemTime::now());
                 }
+                packet = next_manual(&mut manual) => match packet {
+                    Some(packet) => {
+                        let v = packet.encode();
+                        {
+                            let mut stats = send_state.stats().write().await;
+                            let sent = send_state.instant();
+                            stats.packet_sent(packet.seqnum, sent);
+                            send_state.notify_sent(packet.seqnum, sent);
+                        }
+                        send_state.tap(Direction::UdpOutbound, &v[..]);
+                        let _ = udp_tx.send(&v[..]).await;
+                    }
+                    None => {
+                        info!("The control sink was dropped, building control packets again");
+                        manual = None;
+                        send_state.set_manual(false);
+                        send_state.link().write().await.forget_seqnum();
+                    }
+                },
                 sig = fwd_rx.recv() => match sig {
                     Some(Signal::NewTarget(ip)) => {
                         let mut state = send_state.send().write().await;
                         state.reset_seqnum();
-                        state.disable();
-                        send_state.recv().write().await.reset();
-                        udp_tx = UdpSocket::bind("0.0.0.0:0")
-                            .await
-                            .expect("Failed to bind tx socket");
+                        if state.enabled() {
+                            state.disable();
+                            send_state.emit(Event::Disabled).await;
+                        }
+                        send_state.reset_recv().await;
+                        send_state.telemetry().write().await.clear_identity();
+                        send_state.stats().write().await.reset_link();
+                        udp_tx = Arc::new(
+                            bind_udp(send_socket.local_addr(0), &send_socket).expect("Failed to bind tx socket"),
+                        );
                         udp_tx
-                            .connect(&format!("{}:1110", &ip))
+                            .connect((ip.as_str(), ports.robot_udp))
                             .await
                             .expect("Failed to connect to new target");
+                        send_state.set_robot_addr(udp_tx.peer_addr().ok());
                         backoff.reset();
                     }
                     Some(Signal::NewMode(DsMode::Simulation)) => {
                         let mut state = send_state.send().write().await;
                         state.reset_seqnum();
-                        state.disable();
-                        send_state.recv().write().await.reset();
+                        if state.enabled() {
+                            state.disable();
+                            send_state.emit(Event::Disabled).await;
+                        }
+                        send_state.reset_recv().await;
+                        send_state.stats().write().await.reset_link();
                         udp_tx
-                            .connect("127.0.0.1:1110")
+                            .connect(("127.0.0.1", ports.robot_udp))
                             .await
                             .expect("Failed to connect to simulator socket");
+                        send_state.set_robot_addr(udp_tx.peer_addr().ok());
                         backoff.reset();
                     }
+                    None => break,
                     _ => {}
                 },
             }
@@ -113,19 +277,68 @@ pub(crate) async fn udp_conn(
     // I need the tokio extension for this, the futures extension to split codecs, and I can't import them both
     // Thanks for coordinating trait names to make using both nicely impossible
 
-    let mut connected = true;
+    let mut connected = false;
+    let mut ever_connected = false;
+    // When the last status packet came in on the simulator's clock, if one was set
+    let mut last_reply_sim = None;
+    // When the robot is declared lost unless another status packet is accepted. Only accepted packets move it, so a
+    // stray device sending status packets can't keep a silent robot from being declared lost
+    let mut deadline = Instant::now() + reconnect.udp_timeout;
     loop {
+        state.heartbeat("ds-recv");
         tokio::select! {
-            packet = timeout(Duration::from_secs(2), udp_rx.next()) => match packet {
+            packet = timeout_at(deadline.into(), udp_rx.next()) => match packet {
                 Ok(timeout_result) => match timeout_result {
                     Some(Ok(packet)) => {
+                        let (packet, source): (UdpResponsePacket, _) = packet;
+                        if !validator.accepts(source.ip()).await {
+                            state.stats().write().await.packet_rejected();
+                            continue;
+                        }
+                        deadline = Instant::now() + reconnect.udp_timeout;
                         if !connected {
                             connected = true;
+                            state.link().write().await.set_connected(true);
+                            state.notify(StateChange::ConnectionChanged(true));
+                            if ever_connected {
+                                state.stats().write().await.reconnected();
+                            }
+                            ever_connected = true;
+                            state.emit(Event::Connected).await;
+                        }
+                        state.stats().write().await.packet_received(packet.seqnum, state.instant());
+                        last_reply_sim = state.sim_clock().map(|clock| clock.now());
+                        // A delayed or repeated packet still answers its control packet, but its telemetry is out of date
+                        if !fresh_status(&state, packet.seqnum).await {
+                            continue;
+                        }
+
+                        let requested = {
+                            let send = state.send().read().await;
+                            Requested {
+                                enabled: send.enabled(),
+                                estopped: send.estopped(),
+                                mode: send.mode(),
+                                next_seqnum: send.seqnum(),
+                            }
+                        };
+                        // Manual packets ask for whatever the application chose, so the robot not matching the send
+                        // state isn't a sign of another driver station
+                        if !state.manual()
+                            && let Some(conflict) = conflicts.observe(symptom(requested, packet.status, packet.seqnum))
+                        {
+                            state.link().write().await.set_conflict(conflict);
+                            match conflict {
+                                Some(conflict) => {
+                                    warn!("Another driver station may be controlling the robot: {:?}", conflict);
+                                    state.emit(Event::ControlConflict(conflict)).await;
+                                }
+                                None => state.emit(Event::ControlConflictEnded).await,
+                            }
                         }
-                        let (packet, _): (UdpResponsePacket, _) = packet;
 
                         if packet.need_date {
-                            let local = Utc::now();
+                            let local = state.now();
                             let micros = local.naive_utc().and_utc().timestamp_subsec_micros();
                             let second = local.time().second() as u8;
                             let minute = local.time().minute() as u8;
@@ -135,51 +348,177 @@ pub(crate) async fn udp_conn(
                             let year = (local.date_naive().year() - 1900) as u8;
                             let tag = DTTag::new(micros, second, minute, hour, day, month, year);
                             state.send().write().await.queue_udp(UdpTag::DateTime(tag));
+                            state.link().write().await.clock_mut().set_date_sent(local);
                         }
 
-                        if !tcp_connected {
+                        // The TCP connection is (re)opened while the roboRIO is responding, at most once per retry interval,
+                        // unless running UDP-only
+                        let tcp_running = tcp_task.as_ref().is_some_and(|task| !task.is_finished());
+                        let retry_due = last_tcp_attempt.is_none_or(|at| at.elapsed() >= reconnect.tcp_retry);
+                        let released = state.send().read().await.released();
+                        if !tcp_running && retry_due && !released && !state.udp_only() {
                             let (tx, rx) = unbounded_channel::<Signal>();
                             tcp_tx = Some(tx);
                             let mode = state.send().read().await.ds_mode();
-                            if mode == DsMode::Normal {
-                                tokio::spawn(tcp_conn(state.clone(), target_ip.clone(), rx));
-                            } else {
-                                tokio::spawn(tcp_conn(state.clone(), "127.0.0.1".to_string(), rx));
-                            }
-                            tcp_connected = true;
+                            let ip = if mode == DsMode::Normal { target_ip.clone() } else { "127.0.0.1".to_string() };
+                            tcp_task = Some(spawn_named("ds-tcp", tcp_conn(state.clone(), ip, ports.tcp, reconnect, socket.clone(), rx)));
+                            last_tcp_attempt = Some(Instant::now());
                         }
 
                         if packet.status.emergency_stopped() {
                             let mut send = state.send().write().await;
                             if !send.estopped() {
                                 send.estop();
+                                state.emit(Event::Estopped).await;
                             }
                         }
 
-                        let mut state = state.recv().write().await;
-                        state.set_trace(packet.trace);
-                        state.set_battery_voltage(packet.battery);
+                        let brownout = packet.status.is_browning_out();
+                        let enabled = packet.status.contains(Status::ENABLED);
+                        let mode = Mode::from_status(packet.status);
+                        let mut changes = Vec::new();
+                        let mut diagnostics = Vec::new();
+                        let brownout_changed = {
+                            let mut telemetry = state.telemetry().write().await;
+                            telemetry.tags_received(&packet.tags, state.instant());
+                            telemetry.set_raw_status(packet.raw);
+                            telemetry.set_requests(packet.requests);
+                            telemetry.history_mut().record(packet.status, packet.trace, state.now());
+                            if telemetry.identity_mut().set_address(source.ip()) {
+                                changes.push(StateChange::IdentityChanged);
+                            }
+                            if telemetry.set_trace(packet.trace) {
+                                changes.push(StateChange::TraceChanged(packet.trace));
+                            }
+                            if telemetry.set_battery_voltage(packet.battery) {
+                                changes.push(StateChange::VoltageChanged(packet.battery));
+                            }
+                            if telemetry.set_reported_enabled(enabled) {
+                                changes.push(StateChange::EnabledConfirmed(enabled));
+                            }
+                            if telemetry.set_reported_mode(mode) && let Some(mode) = mode {
+                                changes.push(StateChange::ModeConfirmed(mode));
+                            }
+                            diagnostics.extend(telemetry.resources_mut().sample(
+                                packet.disk.map(|disk| disk.free),
+                                packet.ram.map(|ram| ram.free),
+                            ));
+                            diagnostics.extend(
+                                packet.can.and_then(|metrics| telemetry.can_mut().sample(metrics, state.instant())),
+                            );
+                            diagnostics.extend(outages.trace(packet.trace));
+                            telemetry.set_brownout(brownout, state.instant())
+                        };
+                        for change in changes {
+                            state.notify(change);
+                        }
+                        if brownout_changed {
+                            state.notify(StateChange::BrownoutChanged(brownout));
+                            state.emit(if brownout { Event::Brownout } else { Event::BrownoutEnded }).await;
+                        }
+                        state.stats().write().await.tags_received(&packet.tags, &packet.dropped_tags, state.instant());
+                        if let Some(ref outputs) = packet.joystick_outputs {
+                            state.robot_outputs(outputs).await;
+                        }
+                        sample_battery(&state, packet.battery, enabled).await;
+                        for event in diagnostics {
+                            info!("Robot diagnostic: {:?}", event);
+                            state.emit(event).await;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("Error decoding packet: {:?}", e);
+                        state.stats().write().await.decode_error();
+                        state.emit(Event::Error(format!("Error decoding packet: {}", e))).await;
                     }
-                    Some(Err(e)) => println!("Error decoding packet: {:?}", e),
                     None => break,
                 },
                 Err(_) => {
+                    deadline = Instant::now() + reconnect.udp_timeout;
+                    // A simulator that's paused, or stepping slower than real time, hasn't stopped responding
+                    if let Some(clock) = state.sim_clock()
+                        && let Some(last) = last_reply_sim
+                        && clock.now().saturating_sub(last) < reconnect.udp_timeout
+                    {
+                        continue;
+                    }
                     if connected {
-                        println!("RIO disconnected");
-                        state.recv().write().await.reset();
+                        info!("RIO disconnected");
+                        state.reset_recv().await;
+                        state.link().write().await.set_connected(false);
+                        state.notify(StateChange::ConnectionChanged(false));
                         connected = false;
+                        state.stats().write().await.reset_battery();
+                        // Robot code that stopped responding can't turn off the rumble it asked for
+                        state.robot_outputs(&[]).await;
+                        state.emit(Event::Disconnected).await;
+                        disable(&state).await;
+                        if conflicts.reset().is_some() {
+                            state.emit(Event::ControlConflictEnded).await;
+                        }
+                        let cause = {
+                            let tcp = state.tcp().read().await;
+                            outages.lost(tcp.connected(), tcp.robot_closed(), reconnect.udp_timeout, state.instant())
+                        };
+                        if let Some(cause) = cause {
+                            info!("Outage looks like {:?}", cause);
+                            state.emit(cause).await;
+                        }
+
+                        // A robot that stops responding has usually rebooted, or lost its radio. Either way its end of the
+                        // TCP connection is gone, and nothing is sent that would reveal that, so it's reopened along with UDP
+                        if let Some(ref tcp_tx) = tcp_tx {
+                            let _ = tcp_tx.send(Signal::Disconnect);
+                            tcp_task = None;
+                            last_tcp_attempt = None;
+                        }
                     }
                 }
             },
+            // Status packets from before the host slept are stale, and the robot has likely dropped the TCP connection
+            Some(_) = resume_rx.recv() => {
+                if connected {
+                    info!("Reconnecting to the RIO after the host resumed");
+                    state.reset_recv().await;
+                    state.link().write().await.set_connected(false);
+                    state.notify(StateChange::ConnectionChanged(false));
+                    connected = false;
+                    state.stats().write().await.reset_link();
+                    state.emit(Event::Disconnected).await;
+                    if conflicts.reset().is_some() {
+                        state.emit(Event::ControlConflictEnded).await;
+                    }
+                }
+                if let Some(ref tcp_tx) = tcp_tx {
+                    let _ = tcp_tx.send(Signal::Disconnect);
+                    tcp_task = None;
+                    last_tcp_attempt = None;
+                }
+            }
             sig = rx.recv() => match sig {
-                Some(Signal::Disconnect) => return Ok(()),
+                // The tasks are waited for, so the sockets are free to bind again once this returns
+                Some(Signal::Disconnect) => {
+                    if let Some(tcp_tx) = tcp_tx.take() {
+                        let _ = tcp_tx.send(Signal::Disconnect);
+                    }
+                    if let Some(tcp_task) = tcp_task.take() {
+                        let _ = tcp_task.await;
+                    }
+                    drop(fwd_tx);
+                    let _ = send_task.await;
+                    return Ok(());
+                }
                 Some(Signal::NewTarget(ref target)) => {
                     if let Some(ref tcp_tx) = tcp_tx {
                         let _ = tcp_tx.send(Signal::Disconnect);
-                        tcp_connected = false;
+                        tcp_task = None;
+                        last_tcp_attempt = None;
                     }
 
                     target_ip = target.clone();
+                    validator.set_target(&target_ip).await;
+                    state.set_target(&target_ip);
+                    state.emit(Event::TargetChanged(target_ip.clone())).await;
 
                     fwd_tx.send(sig.unwrap())?;
                 }
@@ -188,16 +527,34 @@ pub(crate) async fn udp_conn(
                     if mode != current_mode {
                         if let Some(ref tcp_tx) = tcp_tx {
                             let _ = tcp_tx.send(Signal::Disconnect);
-                            tcp_connected = false;
+                            tcp_task = None;
+                            last_tcp_attempt = None;
                         }
                         state.send().write().await.set_ds_mode(mode);
-                        if mode == DsMode::Normal {
-                            println!("Exiting simulation mode");
+                        state.set_simulating(mode == DsMode::Simulation);
+                        validator.set_simulation(mode == DsMode::Simulation);
+                        state.emit(Event::DsModeChanged(mode)).await;
+                        if mode == DsMode::Simulation {
+                            state.set_target("127.0.0.1");
+                        } else {
+                            info!("Exiting simulation mode");
+                            state.set_target(&target_ip);
                             fwd_tx.send(Signal::NewTarget(target_ip.clone()))?;
                         }
                         fwd_tx.send(sig.unwrap())?;
                     }
                 }
+                // The TCP connection is left to the driver station taking over
+                Some(Signal::ReleaseControl) => {
+                    if let Some(ref tcp_tx) = tcp_tx {
+                        let _ = tcp_tx.send(Signal::Disconnect);
+                        tcp_task = None;
+                        last_tcp_attempt = None;
+                    }
+                }
+                // The team number is only needed by the cRIO protocol
+                #[cfg(feature = "crio")]
+                Some(Signal::NewTeamNumber(_)) => {}
                 None => break,
             },
         }
@@ -205,55 +562,371 @@ pub(crate) async fn udp_conn(
     Ok(())
 }
 
+/// The number of disabled control packets sent after releasing control, in case some are lost
+const RELEASE_PACKETS: u8 = 5;
+
+/// Stops the send loop after control is released
+pub(crate) struct Release {
+    remaining: u8,
+}
+
+impl Release {
+    pub(crate) const fn new() -> Release {
+        Release {
+            remaining: RELEASE_PACKETS,
+        }
+    }
+
+    /// Returns whether a control packet should be sent this tick
+    ///
+    /// A few are still sent after releasing, so the robot is told it's disabled instead of waiting to time out.
+    pub(crate) async fn should_send(&mut self, state: &DsState) -> bool {
+        if !state.send().read().await.released() {
+            self.remaining = RELEASE_PACKETS;
+            return true;
+        }
+        if self.remaining == 0 {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+/// What happened while queueing one set of joystick values
+pub struct JoystickOutcome {
+    fault: Option<JoystickFault>,
+    /// Whether the fault disabled the robot
+    disabled: bool,
+    warning: Option<String>,
+    /// How the queued values differ from those queued before
+    changes: Vec<InputChange>,
+    /// The queued joysticks that don't fit their descriptors
+    mismatches: Vec<ShapeMismatch>,
+}
+
+/// Returns whether a status packet is newer than those already received, counting the ones that aren't
+pub(crate) async fn fresh_status(state: &DsState, seqnum: u16) -> bool {
+    let arrival = state.link().write().await.arrival(seqnum);
+    match arrival {
+        Arrival::New => return true,
+        Arrival::Duplicate => state.stats().write().await.packet_duplicated(),
+        Arrival::Reordered => state.stats().write().await.packet_reordered(),
+    }
+    false
+}
+
+/// Records the battery voltage of a status packet, reporting a sag on enable
+pub(crate) async fn sample_battery(state: &DsState, voltage: f32, enabled: bool) {
+    let sag = state
+        .stats()
+        .write()
+        .await
+        .battery_sample(voltage, enabled, state.instant());
+    if let Some(sag) = sag {
+        warn!(
+            "Battery sagged by {:.2}V after enabling, it may need replacing",
+            sag.drop()
+        );
+        state.emit(Event::VoltageSag(sag)).await;
+    }
+}
+
+/// Polls the joystick sources, returning `None` if there aren't any
+///
+/// The sources are user code, they're polled without holding any locks so that a slow source doesn't block unrelated
+/// reads of the send state.
+pub(crate) async fn supply_joysticks(state: &DsState) -> Option<Polled> {
+    let (sources, buffer) = {
+        let mut send = state.send().write().await;
+        (send.joystick_sources(), send.take_joystick_buffer())
+    };
+    (!sources.is_empty()).then(|| sources.poll_with(buffer))
+}
+
+/// Validates and queues the polled joysticks, disabling the robot if the safety settings require it
+///
+/// When no source is available the joysticks are sent neutral, so robot code doesn't see them unplugged.
+pub(crate) fn queue_joysticks(state: &mut SendState, polled: Option<Polled>) -> JoystickOutcome {
+    let validation = state.joystick_validation();
+    // A panicking source is a joystick fault rather than the end of the send loop
+    let frame = polled.map(|polled| match polled {
+        Polled::Values(_, joysticks) => validation.apply(joysticks),
+        Polled::Panicked(_) => JoystickFrame::Panicked,
+        Polled::Unavailable => validation.apply(state.take_neutral_joysticks()),
+    });
+    let fault = frame
+        .as_ref()
+        .and_then(|frame| state.joystick_safety().fault(frame));
+    let disabled = match fault {
+        Some(ref fault) if state.enabled() && state.joystick_safety().disables(fault) => {
+            state.disable();
+            true
+        }
+        _ => false,
+    };
+    let mut changes = Vec::new();
+    let mut mismatches = Vec::new();
+    let warning = match frame {
+        Some(JoystickFrame::Valid {
+            mut joysticks,
+            warning,
+        }) => {
+            // Trimmed before suppression, which would otherwise make every joystick look unbound
+            if state.compact_joysticks() {
+                trim_unbound(&mut joysticks);
+            }
+            // Each joystick is still sent, with none of its inputs set
+            if state.joysticks_suppressed() {
+                joysticks.iter_mut().for_each(Vec::clear);
+            }
+            changes = state.track_inputs(&joysticks);
+            mismatches = find_mismatches(&joysticks, state.declared_shapes());
+            state.queue_joysticks(joysticks);
+            warning
+        }
+        Some(JoystickFrame::Dropped(problem)) => Some(problem),
+        _ => None,
+    };
+    JoystickOutcome {
+        fault,
+        disabled,
+        warning,
+        changes,
+        mismatches,
+    }
+}
+
+/// Queues the polled joysticks and encodes the next control packet, returning it with its sequence number
+///
+/// This is the part of each send loop tick that runs under the send state's lock.
+pub fn encode_tick(state: &mut SendState, polled: Option<Polled>) -> (Bytes, u16, JoystickOutcome) {
+    let outcome = queue_joysticks(state, polled);
+    let seqnum = state.seqnum();
+    let packet = state.control();
+    let encoded = state.encode(&packet);
+    state.recycle(packet);
+    state.increment_seqnum();
+    (encoded, seqnum, outcome)
+}
+
+/// How often a supplier that stays slow is reported
+const SLOW_SUPPLIER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Reports joystick faults and warnings as they change
+#[derive(Default)]
+pub(crate) struct JoystickReporter {
+    last_fault: Option<JoystickFault>,
+    last_warning: Option<String>,
+    last_mismatches: Vec<ShapeMismatch>,
+    last_source: Option<Arc<str>>,
+    /// When a slow supplier was last reported
+    last_slow: Option<Instant>,
+}
+
+impl JoystickReporter {
+    /// Records how long the supplier took, reporting it at most every [`SLOW_SUPPLIER_INTERVAL`] while it's slow
+    pub(crate) async fn supplier_ran(&mut self, state: &DsState, elapsed: Duration) {
+        if !state.stats().write().await.supplier_ran(elapsed) {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_slow
+            .is_some_and(|last| now.duration_since(last) < SLOW_SUPPLIER_INTERVAL)
+        {
+            return;
+        }
+        self.last_slow = Some(now);
+        if elapsed >= CONTROL_PERIOD {
+            warn!(
+                "The joystick supplier took {:?}, control packets are missing their ticks",
+                elapsed
+            );
+        } else {
+            warn!(
+                "The joystick supplier took {:?}, it should return within {:?}",
+                elapsed, SLOW_SUPPLIER
+            );
+        }
+        state.emit(Event::SlowJoystickSupplier(elapsed)).await;
+    }
+
+    /// Reports the joystick source used, when it changes
+    pub(crate) async fn source_used(&mut self, state: &DsState, source: Option<&Arc<str>>) {
+        if source == self.last_source.as_ref() {
+            return;
+        }
+        match source {
+            Some(ref name) => info!("Reading joysticks from {}", name),
+            None => info!("No joystick source is available, sending neutral joysticks"),
+        }
+        state
+            .emit(Event::JoystickSourceChanged(
+                source.map(|name| name.to_string()),
+            ))
+            .await;
+        self.last_source = source.cloned();
+        state.set_output_source(self.last_source.clone()).await;
+    }
+
+    pub(crate) async fn report(&mut self, state: &DsState, outcome: JoystickOutcome) {
+        if outcome.fault != self.last_fault {
+            if let Some(ref fault) = outcome.fault {
+                warn!("Joystick fault: {:?}", fault);
+                state.emit(Event::JoystickFault(fault.clone())).await;
+            }
+            self.last_fault = outcome.fault;
+        }
+        if outcome.disabled {
+            state.emit(Event::Disabled).await;
+        }
+        // Only changes are logged, the same problem is usually repeated every packet
+        if outcome.warning != self.last_warning {
+            if let Some(ref warning) = outcome.warning {
+                warn!("Invalid joystick values: {}", warning);
+            }
+            self.last_warning = outcome.warning;
+        }
+        // A mismatch persists while the same values are sent, so only new ones are reported
+        for mismatch in &outcome.mismatches {
+            if !self.last_mismatches.contains(mismatch) {
+                warn!(
+                    "Joystick {} sets inputs its descriptor doesn't declare, needing {:?} but declared {:?}",
+                    mismatch.slot, mismatch.sent, mismatch.declared
+                );
+                state.emit(Event::JoystickShapeMismatch(*mismatch)).await;
+            }
+        }
+        self.last_mismatches = outcome.mismatches;
+        for change in outcome.changes {
+            state.notify_input(change);
+        }
+    }
+}
+
 /// tokio task for all TCP communications
 ///
 /// This task will decode incoming TCP packets, and call the tcp consumer defined in `state` if it exists.
 /// It will also accept packets to send from a channel set in `state`, for tasks such as defining game data.
+///
+/// Keepalive probes are sent after [`ReconnectPolicy::tcp_keepalive`] without traffic, so that a connection the roboRIO
+/// has forgotten about is closed by the OS instead of staying open forever. The UDP task also closes it when the robot
+/// stops responding, and [`ReconnectPolicy::tcp_timeout`] closes it when no frames arrive, checked once a second.
 pub(crate) async fn tcp_conn(
     state: Arc<DsState>,
     target_ip: String,
+    port: u16,
+    reconnect: ReconnectPolicy,
+    socket: SocketOptions,
     mut rx: UnboundedReceiver<Signal>,
 ) -> Result<()> {
-    let conn = TcpStream::connect(&format!("{}:1740", target_ip)).await?;
-    let codec = DsTcpCodec.framed(conn);
+    let conn = match connect_tcp(&target_ip, port, &socket).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            state
+                .emit(Event::Error(format!("TCP connection failed: {}", e)))
+                .await;
+            return Err(e.into());
+        }
+    };
+    let probes = TcpKeepalive::new()
+        .with_time(reconnect.tcp_keepalive)
+        .with_interval(reconnect.tcp_keepalive);
+    if let Err(e) = SockRef::from(&conn).set_tcp_keepalive(&probes) {
+        warn!("Failed to enable TCP keepalive: {}", e);
+    }
+    let codec = DsTcpCodec::new(Some(state.raw_tap())).framed(conn);
     let (mut codec_tx, mut codec_rx) = codec.split();
 
-    let (tag_tx, mut tag_rx) = unbounded_channel::<TcpTag>();
-    state.tcp().write().await.set_tcp_tx(Some(tag_tx));
+    let (tag_tx, mut tag_rx) = unbounded_channel::<QueuedTag>();
+    state.tcp().write().await.set_tcp_tx(Some(tag_tx.clone()));
+    state.emit(Event::TcpConnected).await;
+    state.notify(StateChange::TcpConnectionChanged(true));
+
+    let mut console_flush = tokio::time::interval(Duration::from_secs(1));
+    let mut last_frame = Instant::now();
+    let mut robot_closed = false;
 
-    let state = state.tcp();
     loop {
+        state.heartbeat("ds-tcp");
         tokio::select! {
             packet = codec_rx.next() => match packet {
-                Some(packet) => {
-                    if let Ok(packet) = packet {
-                        let mut state = state.write().await;
-                        if let Some(ref mut consumer) = state.tcp_consumer {
-                            consumer(packet);
-                        }
+                Some(Ok(packet)) => {
+                    last_frame = Instant::now();
+                    if let TcpPacket::Stdout(ref stdout) = packet {
+                        let received = state.now();
+                        let rtt = state.stats().read().await.latency();
+                        state.link().write().await.clock_mut().sample(stdout.timestamp, received, rtt);
                     }
+                    if let TcpPacket::VersionInfo(ref info) = packet
+                        && state.telemetry().write().await.identity_mut().record_version(info)
+                    {
+                        state.notify(StateChange::IdentityChanged);
+                    }
+                    if let TcpPacket::Malformed { .. } = packet {
+                        state.stats().write().await.tcp_decode_error();
+                    }
+                    state.tcp().write().await.consume(packet, state.instant(), state.now());
                 },
-                None => break,
+                // Frames that fail to decode arrive as malformed packets, so an error here is from the socket or lost
+                // framing. The stream ends after any error, so the connection is dropped to be reopened
+                Some(Err(e)) => {
+                    warn!("TCP connection lost: {}", e);
+                    break;
+                }
+                // Only a clean close is taken as the robot restarting, rather than a stream that broke
+                None => {
+                    robot_closed = true;
+                    break;
+                }
             },
-            _ = rx.recv() => {
-                state.write().await.set_tcp_tx(None);
+            // Reports console messages suppressed by a flood that has stopped
+            _ = console_flush.tick() => {
+                if reconnect.tcp_timeout.is_some_and(|timeout| last_frame.elapsed() > timeout) {
+                    warn!("TCP connection timed out");
+                    break;
+                }
+                state.tcp().write().await.flush_console(state.instant(), state.now());
             },
-            tag = tag_rx.recv() => match tag {
-                Some(tag) => {
-                    let _ = codec_tx.send(tag).await;
+            // Either an explicit disconnect, or the UDP task replaced this connection
+            _ = rx.recv() => break,
+            queued = tag_rx.recv() => match queued {
+                Some(queued) => {
+                    let delivers = queued.delivers;
+                    if codec_tx.send(queued.tag.clone()).await.is_err() {
+                        state.tcp().write().await.requeue(queued);
+                        break;
+                    }
+                    state.tcp().write().await.written(delivers);
                 },
                 None => break,
             }
         }
     }
+
+    {
+        let mut tcp = state.tcp().write().await;
+        tcp.clear_tcp_tx(&tag_tx);
+        if robot_closed {
+            tcp.set_robot_closed(state.instant());
+        }
+        // Tags this connection didn't get to are sent on the next one
+        tag_rx.close();
+        while let Ok(queued) = tag_rx.try_recv() {
+            tcp.requeue(queued);
+        }
+    }
+    state.emit(Event::TcpDisconnected).await;
+    state.notify(StateChange::TcpConnectionChanged(false));
     Ok(())
 }
 
-pub(crate) async fn sim_conn(tx: UnboundedSender<Signal>) -> Result<()> {
+pub(crate) async fn sim_conn(tx: UnboundedSender<Signal>, port: u16) -> Result<()> {
     use tokio::time::timeout;
     const SOCK_TIMEOUT: Duration = Duration::from_millis(250);
 
-    let sock = UdpSocket::bind("127.0.0.1:1135").await?;
+    let sock = UdpSocket::bind(("127.0.0.1", port)).await?;
     let mut buf = [0];
     let mut opmode = DsMode::Normal;
     loop {
@@ -273,3 +946,368 @@ pub(crate) async fn sim_conn(tx: UnboundedSender<Signal>) -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::config::JoystickSuppression;
+    use crate::ds::shape::JoystickShape;
+    use crate::{Alliance, JoystickValue};
+
+    /// Whether the last connection event `ds` logged was the robot connecting
+    async fn connected(ds: &crate::DriverStation) -> bool {
+        let events = ds.recent_events().await;
+        let last = events
+            .into_iter()
+            .rfind(|timed| matches!(timed.event, Event::Connected | Event::Disconnected));
+        last.is_some_and(|timed| timed.event == Event::Connected)
+    }
+
+    fn axis(id: u8) -> Vec<Vec<JoystickValue>> {
+        vec![vec![JoystickValue::Axis { id, value: 0.5 }]]
+    }
+
+    /// Queues `joysticks` from a source and reports the outcome, as the send loop does
+    async fn tick(
+        state: &DsState,
+        reporter: &mut JoystickReporter,
+        joysticks: Vec<Vec<JoystickValue>>,
+    ) {
+        let outcome = {
+            let mut send = state.send().write().await;
+            queue_joysticks(&mut send, Some(Polled::Values("gamepad".into(), joysticks)))
+        };
+        reporter.report(state, outcome).await;
+    }
+
+    /// Polls the sources of `state` and sends what they give, as the send loop does
+    async fn supply(state: &DsState, reporter: &mut JoystickReporter) {
+        let polled = supply_joysticks(state).await;
+        reporter
+            .source_used(state, polled.as_ref().and_then(Polled::source))
+            .await;
+        let outcome = queue_joysticks(&mut *state.send().write().await, polled);
+        reporter.report(state, outcome).await;
+    }
+
+    async fn events(state: &DsState) -> Vec<Event> {
+        let log = state.events().read().await;
+        log.recent().into_iter().map(|timed| timed.event).collect()
+    }
+
+    #[tokio::test]
+    async fn verify_shape_mismatches_are_reported_once() {
+        let state = DsState::new(Alliance::new_red(1), "127.0.0.1".to_string());
+        let mut reporter = JoystickReporter::default();
+        let declared = JoystickShape {
+            axes: 2,
+            buttons: 4,
+            povs: 0,
+        };
+        state
+            .send()
+            .write()
+            .await
+            .set_declared_shapes(vec![Some(declared)]);
+
+        for _ in 0..3 {
+            tick(&state, &mut reporter, axis(2)).await;
+        }
+        tick(&state, &mut reporter, axis(1)).await;
+        tick(&state, &mut reporter, axis(2)).await;
+
+        // The mismatch is reported when it starts, and again after the values fit for a packet
+        let mismatch = Event::JoystickShapeMismatch(ShapeMismatch {
+            slot: 0,
+            declared: Some(declared),
+            sent: JoystickShape {
+                axes: 3,
+                buttons: 0,
+                povs: 0,
+            },
+        });
+        assert_eq!(events(&state).await, vec![mismatch.clone(), mismatch]);
+        // The values are still sent, a tag for each packet
+        assert_eq!(state.send().write().await.control().tags.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn verify_sources_fall_back_to_neutral() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let state = DsState::new(Alliance::new_red(1), "127.0.0.1".to_string());
+        let mut reporter = JoystickReporter::default();
+        let gamepad = Arc::new(AtomicBool::new(true));
+        let keyboard = Arc::new(AtomicBool::new(true));
+        {
+            let mut send = state.send().write().await;
+            let plugged = gamepad.clone();
+            send.joystick_sources_mut().add(
+                "gamepad",
+                10,
+                Arc::new(move || plugged.load(Ordering::Relaxed).then(|| axis(1))),
+            );
+            let plugged = keyboard.clone();
+            send.joystick_sources_mut().add(
+                "keyboard",
+                0,
+                Arc::new(move || {
+                    plugged
+                        .load(Ordering::Relaxed)
+                        .then(|| vec![axis(0).remove(0), Vec::new()])
+                }),
+            );
+        }
+
+        supply(&state, &mut reporter).await;
+        supply(&state, &mut reporter).await;
+        assert_eq!(state.send().read().await.last_joysticks(), &axis(1)[..]);
+
+        gamepad.store(false, Ordering::Relaxed);
+        supply(&state, &mut reporter).await;
+        assert_eq!(state.send().read().await.last_joysticks().len(), 2);
+
+        // With nothing available, the joysticks last sent stay plugged in without any inputs
+        keyboard.store(false, Ordering::Relaxed);
+        supply(&state, &mut reporter).await;
+        supply(&state, &mut reporter).await;
+        assert_eq!(
+            state.send().read().await.last_joysticks(),
+            &[Vec::new(), Vec::new()]
+        );
+
+        gamepad.store(true, Ordering::Relaxed);
+        supply(&state, &mut reporter).await;
+
+        // A source that panics is a fault, rather than being skipped for the next
+        state.send().write().await.joystick_sources_mut().add(
+            "broken",
+            20,
+            Arc::new(|| -> Option<Vec<Vec<JoystickValue>>> { panic!("unplugged") }),
+        );
+        supply(&state, &mut reporter).await;
+        assert_eq!(state.send().read().await.last_joysticks(), &axis(1)[..]);
+
+        let source = |name: Option<&str>| Event::JoystickSourceChanged(name.map(str::to_string));
+        assert_eq!(
+            events(&state).await,
+            vec![
+                source(Some("gamepad")),
+                source(Some("keyboard")),
+                source(None),
+                source(Some("gamepad")),
+                source(Some("broken")),
+                Event::JoystickFault(JoystickFault::SupplierPanicked),
+            ]
+        );
+    }
+
+    /// Queues `joysticks` as the send loop does, returning the joystick tags of the next packet, encoded
+    fn joystick_tags(send: &mut SendState, joysticks: Vec<Vec<JoystickValue>>) -> Vec<Bytes> {
+        queue_joysticks(send, Some(Polled::Values("supplier".into(), joysticks)));
+        send.control().tags.iter().map(UdpTag::construct).collect()
+    }
+
+    #[test]
+    fn verify_suppressed_joysticks_are_sent_neutral() {
+        let mut neutral = SendState::new(Alliance::new_red(1));
+        let neutral = joystick_tags(&mut neutral, vec![Vec::new()]);
+
+        let mut send = SendState::new(Alliance::new_red(1));
+        send.set_joystick_suppression(JoystickSuppression {
+            autonomous: true,
+            disabled: false,
+        });
+        send.enable();
+        // The joystick is still sent, so robot code doesn't see it unplugged
+        assert_eq!(joystick_tags(&mut send, axis(0)), neutral);
+        send.set_mode(Mode::Teleoperated);
+        send.enable();
+        assert_ne!(joystick_tags(&mut send, axis(0)), neutral);
+
+        send.set_joystick_suppression(JoystickSuppression {
+            autonomous: false,
+            disabled: true,
+        });
+        send.disable();
+        assert_eq!(joystick_tags(&mut send, axis(0)), neutral);
+    }
+
+    /// Answers `control` the way a roboRIO would, confirming the enabled state it asked for
+    fn status(control: &[u8]) -> [u8; 8] {
+        let status = control[3] & (Control::ESTOP | Control::ENABLED).bits();
+        [control[0], control[1], 0x01, status, 0x20, 12, 128, 0]
+    }
+
+    /// Answers control packets as a roboRIO would, until `ds` is connected and enabled
+    async fn answer_until_enabled(robot: &UdpSocket, ds: &crate::DriverStation, ds_udp: u16) {
+        let mut control = [0u8; 1500];
+        loop {
+            let (len, from) = robot.recv_from(&mut control).await.unwrap();
+            let reply = status(&control[..len]);
+            robot.send_to(&reply, (from.ip(), ds_udp)).await.unwrap();
+            if ds.enabled().await && connected(ds).await {
+                return;
+            }
+        }
+    }
+
+    /// Returns the control bits of the next packet the robot receives, if one arrives soon
+    async fn next_control(robot: &UdpSocket) -> Option<Control> {
+        let mut buf = [0u8; 1500];
+        let (len, _) = tokio::time::timeout(Duration::from_millis(200), robot.recv_from(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        (len >= 6).then(|| Control::from_bits_truncate(buf[3]))
+    }
+
+    #[tokio::test]
+    async fn verify_rejected_packets_dont_delay_the_timeout() {
+        let ports = Ports {
+            robot_udp: 41310,
+            ds_udp: 41350,
+            tcp: 41940,
+            simulator: 41935,
+        };
+        let robot = UdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let stray = UdpSocket::bind(("127.0.0.2", 0)).await.unwrap();
+        let ds = crate::DriverStation::builder()
+            .ip("127.0.0.1")
+            .ports(ports)
+            .build()
+            .await;
+
+        let mut control = [0u8; 1500];
+        while !connected(&ds).await {
+            let (_, from) = robot.recv_from(&mut control).await.unwrap();
+            let reply = [control[0], control[1], 0x01, 0, 0x20, 12, 128, 0];
+            robot
+                .send_to(&reply, (from.ip(), ports.ds_udp))
+                .await
+                .unwrap();
+        }
+
+        // The robot goes silent, while another device keeps answering for it
+        let reply = [control[0], control[1], 0x01, 0, 0x20, 12, 128, 0];
+        let lost = async {
+            while connected(&ds).await {
+                stray
+                    .send_to(&reply, ("127.0.0.1", ports.ds_udp))
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(4), lost)
+            .await
+            .expect("The robot was never declared lost");
+        assert!(ds.stats().await.packets_rejected > 0);
+    }
+
+    #[tokio::test]
+    async fn verify_released_control_goes_quiet_until_taken() {
+        let ports = Ports {
+            robot_udp: 41312,
+            ds_udp: 41352,
+            tcp: 41942,
+            simulator: 41937,
+        };
+        let robot = UdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut ds = crate::DriverStation::builder()
+            .ip("127.0.0.1")
+            .ports(ports)
+            .build()
+            .await;
+        ds.enable().await;
+        answer_until_enabled(&robot, &ds, ports.ds_udp).await;
+
+        ds.release_control().await;
+        // Enabling is refused while released
+        ds.enable().await;
+        let mut sent = Vec::new();
+        while let Some(control) = next_control(&robot).await {
+            sent.push(control);
+        }
+        // Packets already sent when control was released come first, then the disabled ones telling the robot
+        let burst = &sent[sent.len() - RELEASE_PACKETS as usize..];
+        assert!(
+            burst
+                .iter()
+                .all(|control| !control.contains(Control::ENABLED))
+        );
+        assert!(sent.len() < RELEASE_PACKETS as usize + 3);
+        assert!(!ds.in_control().await);
+
+        ds.take_control().await;
+        let control = next_control(&robot).await.unwrap();
+        assert!(!control.contains(Control::ENABLED));
+        ds.enable().await;
+        let enabled = async {
+            while !next_control(&robot)
+                .await
+                .unwrap()
+                .contains(Control::ENABLED)
+            {}
+        };
+        tokio::time::timeout(Duration::from_secs(1), enabled)
+            .await
+            .expect("The robot was never enabled again");
+    }
+
+    #[tokio::test]
+    async fn verify_udp_loss_disables_the_robot() {
+        let ports = Ports {
+            robot_udp: 41311,
+            ds_udp: 41351,
+            tcp: 41941,
+            simulator: 41936,
+        };
+        let robot = UdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut ds = crate::DriverStation::builder()
+            .ip("127.0.0.1")
+            .ports(ports)
+            .reconnect(ReconnectPolicy {
+                udp_timeout: Duration::from_millis(200),
+                ..ReconnectPolicy::default()
+            })
+            .build()
+            .await;
+        ds.enable().await;
+        answer_until_enabled(&robot, &ds, ports.ds_udp).await;
+
+        // The robot goes silent, and the packets the driver station keeps sending turn disabled once it's declared lost
+        let lost = async {
+            while next_control(&robot)
+                .await
+                .unwrap()
+                .contains(Control::ENABLED)
+            {}
+        };
+        tokio::time::timeout(Duration::from_secs(2), lost)
+            .await
+            .expect("The robot was never disabled");
+        assert!(!connected(&ds).await);
+        assert!(!ds.enabled().await);
+
+        // Coming back doesn't enable it again by itself
+        let mut control = [0u8; 1500];
+        for _ in 0..10 {
+            let (len, from) = robot.recv_from(&mut control).await.unwrap();
+            assert!(!Control::from_bits_truncate(control[3]).contains(Control::ENABLED));
+            let reply = status(&control[..len]);
+            robot
+                .send_to(&reply, (from.ip(), ports.ds_udp))
+                .await
+                .unwrap();
+        }
+        assert!(connected(&ds).await);
+        assert!(!ds.enabled().await);
+    }
+}
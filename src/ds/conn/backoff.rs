@@ -25,7 +25,7 @@ impl ExponentialBackoff {
         fut: impl Future<Output = Result<O, E>>,
     ) -> Result<O, (E, bool)> {
         if let Some(timeout) = self.timeout {
-            println!("Backoff: waiting {:?}", timeout);
+            debug!("Backoff: waiting {:?}", timeout);
             time::sleep(timeout).await;
         }
         match fut.await {
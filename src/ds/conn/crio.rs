@@ -0,0 +1,187 @@
+use super::pacer::Pacer;
+use super::socket::bind_udp;
+use super::source::SourceValidator;
+use super::{
+    JoystickReporter, Release, fresh_status, queue_joysticks, sample_battery, supply_joysticks,
+};
+
+use crate::Result;
+use crate::ds::Signal;
+use crate::ds::changes::StateChange;
+use crate::ds::command::{apply_commands, disable};
+use crate::ds::config::{Ports, ReconnectPolicy, SocketOptions, SourceFilter};
+use crate::ds::events::Event;
+use crate::ds::sources::Polled;
+use crate::ds::state::DsState;
+use crate::ds::state::stats::CONTROL_PERIOD;
+use crate::proto::Direction;
+use crate::proto::crio::{CrioControl, CrioControlPacket, CrioStatusPacket};
+
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// The root task when talking to a cRIO
+///
+/// The cRIO only speaks UDP, so one task both sends control packets to the robot and receives its replies.
+/// There is no simulator support, and the TCP connection is never opened.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn crio_conn(
+    state: Arc<DsState>,
+    mut target_ip: String,
+    mut team_number: u16,
+    mut rx: UnboundedReceiver<Signal>,
+    ports: Ports,
+    reconnect: ReconnectPolicy,
+    socket: SocketOptions,
+    filter: SourceFilter,
+) -> Result<()> {
+    let mut validator = SourceValidator::new(filter, &target_ip).await;
+    let sock = bind_udp(socket.local_addr(ports.ds_udp), &socket)?;
+    let mut pacer = Pacer::new(CONTROL_PERIOD, state.send().read().await.pacing());
+    let mut reporter = JoystickReporter::default();
+    let mut release = Release::new();
+    let mut buf = vec![0u8; 2048];
+
+    let mut connected = false;
+    let mut ever_connected = false;
+    let mut last_reply = Instant::now();
+
+    loop {
+        state.heartbeat("ds-crio");
+        tokio::select! {
+            deadline = pacer.tick() => {
+                apply_commands(&state).await;
+                let sending = release.should_send(&state).await;
+                if connected && last_reply.elapsed() > reconnect.udp_timeout {
+                    info!("cRIO disconnected");
+                    state.reset_recv().await;
+                    state.link().write().await.set_connected(false);
+                    state.notify(StateChange::ConnectionChanged(false));
+                    connected = false;
+                    state.stats().write().await.reset_battery();
+                    state.emit(Event::Disconnected).await;
+                    disable(&state).await;
+                }
+                if !sending {
+                    continue;
+                }
+
+                let polled = supply_joysticks(&state).await;
+                reporter
+                    .source_used(&state, polled.as_ref().and_then(Polled::source))
+                    .await;
+                let (packet, outcome, pacing, enabled, mode) = {
+                    let mut send = state.send().write().await;
+                    let outcome = queue_joysticks(&mut send, polled);
+                    let control = send.control();
+                    let packet = CrioControlPacket::from_control(&control, team_number);
+                    send.recycle(control);
+                    send.increment_seqnum();
+                    (packet, outcome, send.pacing(), send.enabled(), send.mode())
+                };
+                let bytes = packet.encode();
+                pacer.hold(deadline);
+                {
+                    let sent = state.instant();
+                    let mut stats = state.stats().write().await;
+                    stats.packet_sent(packet.seqnum, sent);
+                    stats.summary_tick(sent, state.now(), enabled, mode);
+                    state.notify_sent(packet.seqnum, sent);
+                }
+                state.tap(Direction::UdpOutbound, &bytes[..]);
+                // The cRIO not being up yet isn't an error, it's retried on the next tick
+                let _ = sock.send_to(&bytes[..], (target_ip.as_str(), ports.robot_udp)).await;
+                reporter.report(&state, outcome).await;
+                // A new pacing takes effect from the next tick
+                if pacing != pacer.pacing() {
+                    pacer = Pacer::new(CONTROL_PERIOD, pacing);
+                }
+            }
+            received = sock.recv_from(&mut buf) => {
+                let (len, source) = match received {
+                    Ok(received) => received,
+                    Err(_) => continue,
+                };
+                if !validator.accepts(source.ip()).await {
+                    state.stats().write().await.packet_rejected();
+                    continue;
+                }
+                state.tap(Direction::UdpInbound, &buf[..len]);
+                let packet = match CrioStatusPacket::decode(&mut &buf[..len]) {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Error decoding packet: {:?}", e);
+                        state.stats().write().await.decode_error();
+                        state.emit(Event::Error(format!("Error decoding packet: {}", e))).await;
+                        continue;
+                    }
+                };
+
+                last_reply = Instant::now();
+                if !connected {
+                    connected = true;
+                    state.link().write().await.set_connected(true);
+                    state.notify(StateChange::ConnectionChanged(true));
+                    if ever_connected {
+                        state.stats().write().await.reconnected();
+                    }
+                    ever_connected = true;
+                    state.emit(Event::Connected).await;
+                }
+                state.stats().write().await.packet_received(packet.seqnum, last_reply);
+                if !fresh_status(&state, packet.seqnum).await {
+                    continue;
+                }
+                let enabled = packet.control.contains(CrioControl::ENABLED);
+                let (voltage_changed, enabled_changed) = {
+                    let mut telemetry = state.telemetry().write().await;
+                    if telemetry.identity_mut().set_address(source.ip()) {
+                        state.notify(StateChange::IdentityChanged);
+                    }
+                    (telemetry.set_battery_voltage(packet.battery), telemetry.set_reported_enabled(enabled))
+                };
+                if voltage_changed {
+                    state.notify(StateChange::VoltageChanged(packet.battery));
+                }
+                if enabled_changed {
+                    state.notify(StateChange::EnabledConfirmed(enabled));
+                }
+                sample_battery(&state, packet.battery, enabled).await;
+
+                if packet.control.emergency_stopped() {
+                    let mut send = state.send().write().await;
+                    if !send.estopped() {
+                        send.estop();
+                        drop(send);
+                        state.emit(Event::Estopped).await;
+                    }
+                }
+            }
+            sig = rx.recv() => match sig {
+                Some(Signal::Disconnect) | None => return Ok(()),
+                Some(Signal::NewTarget(target)) => {
+                    {
+                        let mut send = state.send().write().await;
+                        send.reset_seqnum();
+                        if send.enabled() {
+                            send.disable();
+                            drop(send);
+                            state.emit(Event::Disabled).await;
+                        }
+                    }
+                    state.reset_recv().await;
+                    state.telemetry().write().await.clear_identity();
+                    state.stats().write().await.reset_link();
+                    target_ip = target;
+                    validator.set_target(&target_ip).await;
+                    state.set_target(&target_ip);
+                    state.emit(Event::TargetChanged(target_ip.clone())).await;
+                }
+                Some(Signal::NewTeamNumber(team)) => team_number = team,
+                // Simulators only speak the roboRIO protocol, and there is no TCP connection to close
+                Some(Signal::NewMode(_)) | Some(Signal::ReleaseControl) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::ds::config::Pacing;
+
+use std::time::Duration;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+/// How far behind the timer packets are sent in precise pacing, spinning for whatever the timer and building the packet
+/// didn't use
+///
+/// Timers on most platforms fire up to a millisecond late, by a different amount each time. Spinning to a fixed point
+/// after the deadline turns that into a constant offset, so the spacing between packets is exact.
+const SPIN: Duration = Duration::from_millis(2);
+
+/// Wakes the send loop every `period`, with the accuracy given by its [`Pacing`]
+pub(crate) struct Pacer {
+    interval: Interval,
+    pacing: Pacing,
+}
+
+impl Pacer {
+    pub(crate) fn new(period: Duration, pacing: Pacing) -> Pacer {
+        let interval = match pacing {
+            Pacing::Standard => tokio::time::interval(period),
+            Pacing::Precise => {
+                let mut interval = tokio::time::interval_at(Instant::now(), period);
+                // A late tick pushes the ones after it back, rather than sending a burst to catch up
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval
+            }
+        };
+        Pacer { interval, pacing }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// Waits for the next tick, returning when its packet should be sent. This is cancel safe
+    pub(crate) async fn tick(&mut self) -> Instant {
+        let scheduled = self.interval.tick().await;
+        match self.pacing {
+            Pacing::Standard => scheduled,
+            Pacing::Precise => scheduled + SPIN,
+        }
+    }
+
+    /// Spins until `deadline` in precise pacing, to be called once the packet is ready to send
+    ///
+    /// A tick that ran more than [`SPIN`] late has already missed, and returns straight away.
+    pub(crate) fn hold(&self, deadline: Instant) {
+        if self.pacing == Pacing::Precise {
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// How far the two clocks can drift apart between ticks of the send loop before the host is taken to have slept
+pub(crate) const RESUME_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Notices the host sleeping or its clock jumping, from the gaps between ticks of the send loop
+///
+/// The monotonic clock stops while a laptop sleeps on most platforms, and the wall clock doesn't, so after a resume
+/// they disagree. Where the monotonic clock keeps running, the gap since the last tick is long instead.
+pub(crate) struct ResumeDetector {
+    last: Option<(Instant, SystemTime)>,
+}
+
+impl ResumeDetector {
+    pub(crate) const fn new() -> ResumeDetector {
+        ResumeDetector { last: None }
+    }
+
+    /// Restarts timing from `now`, after the loop was held up on purpose
+    pub(crate) fn rebase(&mut self, now: Instant, wall: SystemTime) {
+        self.last = Some((now, wall));
+    }
+
+    /// Records a tick, returning how long the host seems to have been away if it's more than [`RESUME_THRESHOLD`]
+    pub(crate) fn tick(&mut self, now: Instant, wall: SystemTime) -> Option<Duration> {
+        let (last, last_wall) = self.last.replace((now, wall))?;
+        let elapsed = now.saturating_duration_since(last);
+        let wall_elapsed = match wall.duration_since(last_wall) {
+            Ok(elapsed) => elapsed,
+            // The wall clock went backwards, by this much
+            Err(e) => return Some(e.duration() + elapsed).filter(|jump| *jump > RESUME_THRESHOLD),
+        };
+        let away = elapsed.max(wall_elapsed.abs_diff(elapsed));
+        (away > RESUME_THRESHOLD).then_some(away)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_sleeps_and_jumps_are_noticed() {
+        let start = Instant::now();
+        let wall = SystemTime::now();
+        let mut detector = ResumeDetector::new();
+        assert_eq!(detector.tick(start, wall), None);
+        let tick = Duration::from_millis(20);
+        assert_eq!(detector.tick(start + tick, wall + tick), None);
+
+        // The monotonic clock stood still while the laptop slept for a minute
+        let asleep = Duration::from_secs(60);
+        assert_eq!(
+            detector.tick(start + tick * 2, wall + tick * 2 + asleep),
+            Some(asleep)
+        );
+        // The process was stopped, and both clocks kept running
+        assert_eq!(
+            detector.tick(start + tick * 2 + asleep, wall + tick * 2 + asleep * 2),
+            Some(asleep)
+        );
+        // The wall clock was set back
+        let now = start + tick * 3 + asleep;
+        assert_eq!(detector.tick(now, wall), Some(asleep * 2 + tick * 3));
+        assert_eq!(detector.tick(now + tick, wall + tick), None);
+    }
+}
@@ -0,0 +1,225 @@
+use super::RELEASE_PACKETS;
+use super::socket::bind_std_udp;
+
+use crate::ds::config::{DropAction, DsConfig, SocketOptions};
+use crate::ds::state::DsState;
+use crate::ds::state::send::SendState;
+use crate::ds::state::stats::CONTROL_PERIOD;
+use crate::proto::Direction;
+
+use bytes::Bytes;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+/// The last control packets sent as the driver station is dropped, see [`DsConfig::on_drop`]
+#[derive(Debug, Clone)]
+pub(crate) struct FinalBurst {
+    pub(crate) action: DropAction,
+    timeout: Duration,
+    robot_udp: u16,
+    socket: SocketOptions,
+}
+
+impl FinalBurst {
+    pub(crate) fn new(config: &DsConfig) -> FinalBurst {
+        FinalBurst {
+            action: config.on_drop,
+            timeout: config.drop_timeout,
+            robot_udp: config.ports.robot_udp,
+            socket: config.socket.clone(),
+        }
+    }
+
+    /// Sends a few packets disabling or estopping the robot, encoded by `encode`, for up to the drop timeout
+    ///
+    /// Called by [`DriverStation::shutdown`](crate::DriverStation::shutdown) once the connection tasks have stopped, so
+    /// these are the last packets the robot is sent. Nothing is sent if control was released.
+    pub(crate) async fn send(
+        &self,
+        state: &DsState,
+        mut encode: impl FnMut(&mut SendState) -> Bytes,
+    ) {
+        let Some((socket, target)) = self.open(state) else {
+            return;
+        };
+        let burst = async {
+            for sent in 0..RELEASE_PACKETS {
+                if sent > 0 {
+                    tokio::time::sleep(CONTROL_PERIOD).await;
+                }
+                let Some(packet) = self.packet(&mut *state.send().write().await, &mut encode)
+                else {
+                    return;
+                };
+                state.tap(Direction::UdpOutbound, &packet[..]);
+                // The robot may already be gone, which is what the packets are for
+                let _ = socket.send_to(&packet[..], target);
+            }
+        };
+        let _ = tokio::time::timeout(self.timeout, burst).await;
+    }
+
+    /// Sends a single packet disabling or estopping the robot, encoded by `encode`, without blocking
+    ///
+    /// Called as the driver station is dropped without being shut down. The runtime may be shutting down along with it,
+    /// so nothing can be awaited, and nothing is sent if the send state is locked.
+    pub(crate) fn send_now(
+        &self,
+        state: &DsState,
+        mut encode: impl FnMut(&mut SendState) -> Bytes,
+    ) {
+        let Some((socket, target)) = self.open(state) else {
+            return;
+        };
+        let Ok(mut send) = state.send().try_write() else {
+            warn!("The send state is locked, so the robot can't be disabled on drop");
+            return;
+        };
+        if let Some(packet) = self.packet(&mut send, &mut encode) {
+            drop(send);
+            state.tap(Direction::UdpOutbound, &packet[..]);
+            let _ = socket.send_to(&packet[..], target);
+        }
+    }
+
+    /// Binds a socket for the packets, and finds where to send them, unless there's nothing to send
+    fn open(&self, state: &DsState) -> Option<(UdpSocket, SocketAddr)> {
+        if self.action == DropAction::Nothing {
+            return None;
+        }
+        // Host names aren't looked up, which could block for longer than the timeout
+        let Some(target) = robot_addr(state, self.robot_udp) else {
+            warn!("The robot's address isn't known, so it can't be disabled on drop");
+            return None;
+        };
+        let socket = bind_std_udp(self.socket.local_addr(0), &self.socket)
+            .and_then(|socket| socket.set_nonblocking(true).map(|()| socket));
+        match socket {
+            Ok(socket) => Some((socket, target)),
+            Err(e) => {
+                warn!(
+                    "Failed to bind a socket to disable the robot on drop: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Disables or estops `send` and encodes the packet telling the robot, unless control was released
+    fn packet(
+        &self,
+        send: &mut SendState,
+        encode: &mut impl FnMut(&mut SendState) -> Bytes,
+    ) -> Option<Bytes> {
+        if send.released() {
+            return None;
+        }
+        match self.action {
+            DropAction::Estop => send.estop(),
+            _ => send.disable(),
+        }
+        Some(encode(send))
+    }
+}
+
+/// Returns the address the send loop last connected to, or the target if it's an IP address
+fn robot_addr(state: &DsState, port: u16) -> Option<SocketAddr> {
+    state
+        .robot_addr()
+        .map(|addr| addr.ip())
+        .or_else(|| state.target().parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Control, DriverStation, Ports};
+    use tokio::net::UdpSocket as TokioUdpSocket;
+    use tokio::time::timeout;
+
+    async fn driver_station(ports: Ports) -> DriverStation {
+        DriverStation::builder()
+            .ip("127.0.0.1")
+            .ports(ports)
+            .udp_only(true)
+            .on_drop(DropAction::Disable, Duration::from_millis(500))
+            .build()
+            .await
+    }
+
+    /// Returns the control bits of the next packet the robot receives, if one arrives soon
+    async fn next_control(robot: &TokioUdpSocket) -> Option<Control> {
+        let mut buf = [0u8; 1500];
+        let (len, _) = timeout(Duration::from_millis(200), robot.recv_from(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        (len >= 6).then(|| Control::from_bits_truncate(buf[3]))
+    }
+
+    /// Waits for the robot to be sent an enabled packet
+    async fn wait_enabled(robot: &TokioUdpSocket) {
+        while let Some(control) = next_control(robot).await {
+            if control.contains(Control::ENABLED) {
+                return;
+            }
+        }
+        panic!("The robot was never enabled");
+    }
+
+    #[tokio::test]
+    async fn verify_shutdown_sends_disabled_packets() {
+        let ports = Ports {
+            robot_udp: 41410,
+            ds_udp: 41450,
+            tcp: 41640,
+            simulator: 41635,
+        };
+        let robot = TokioUdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut ds = driver_station(ports).await;
+        ds.enable().await;
+        wait_enabled(&robot).await;
+
+        ds.shutdown().await;
+        let mut sent = Vec::new();
+        while let Some(control) = next_control(&robot).await {
+            sent.push(control);
+        }
+        let burst = &sent[sent.len() - RELEASE_PACKETS as usize..];
+        assert!(
+            burst
+                .iter()
+                .all(|control| !control.contains(Control::ENABLED))
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_drop_sends_without_blocking() {
+        let ports = Ports {
+            robot_udp: 41411,
+            ds_udp: 41451,
+            tcp: 41641,
+            simulator: 41636,
+        };
+        let robot = TokioUdpSocket::bind(("127.0.0.1", ports.robot_udp))
+            .await
+            .unwrap();
+        let mut ds = driver_station(ports).await;
+        ds.enable().await;
+        wait_enabled(&robot).await;
+
+        // The test runtime has a single thread, which the send loop can't run on while dropping would wait for it
+        let started = std::time::Instant::now();
+        drop(ds);
+        assert!(started.elapsed() < CONTROL_PERIOD);
+        let mut last = None;
+        while let Some(control) = next_control(&robot).await {
+            last = Some(control);
+        }
+        assert!(!last.unwrap().contains(Control::ENABLED));
+    }
+}
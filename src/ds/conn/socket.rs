@@ -0,0 +1,174 @@
+use crate::ds::config::SocketOptions;
+
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::{TcpSocket, TcpStream, UdpSocket, lookup_host};
+
+impl SocketOptions {
+    /// Applies the options to a socket that hasn't been bound or connected yet
+    pub(crate) fn apply(&self, socket: SockRef<'_>) -> io::Result<()> {
+        if let Some(dscp) = self.dscp {
+            if dscp > 0x3F {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("DSCP {} doesn't fit in 6 bits", dscp),
+                ));
+            }
+            // The code point is the upper 6 bits of the TOS byte
+            socket.set_tos_v4((dscp as u32) << 2)?;
+        }
+        if let Some(ttl) = self.ttl {
+            socket.set_ttl_v4(ttl)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(ref interface) = self.interface {
+            bind_device(&socket, interface)?;
+        }
+        if self.reuse_address {
+            socket.set_reuse_address(true)?;
+        }
+        Ok(())
+    }
+
+    /// The address to bind to `port` on, either the local address or every interface
+    pub(crate) fn local_addr(&self, port: u16) -> SocketAddr {
+        SocketAddr::from((self.local_address.unwrap_or(Ipv4Addr::UNSPECIFIED), port))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_device(socket: &SockRef<'_>, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_device(_socket: &SockRef<'_>, _interface: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "Binding to an interface is only supported on Linux",
+    ))
+}
+
+/// Binds a UDP socket to `addr` with the given options
+pub(crate) fn bind_udp(addr: SocketAddr, options: &SocketOptions) -> io::Result<UdpSocket> {
+    let socket = bind_std_udp(addr, options)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket)
+}
+
+/// Binds a blocking UDP socket to `addr` with the given options, for sending outside the runtime
+pub(crate) fn bind_std_udp(
+    addr: SocketAddr,
+    options: &SocketOptions,
+) -> io::Result<std::net::UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    options.apply(SockRef::from(&socket))?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// Opens a TCP connection to `host:port` with the given options
+pub(crate) async fn connect_tcp(
+    host: &str,
+    port: u16,
+    options: &SocketOptions,
+) -> io::Result<TcpStream> {
+    let addr = lookup_host((host, port))
+        .await?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, format!("No IPv4 address for {}", host))
+        })?;
+
+    let socket = TcpSocket::new_v4()?;
+    options.apply(SockRef::from(&socket))?;
+    if options.local_address.is_some() {
+        socket.bind(options.local_addr(0))?;
+    }
+    socket.connect(addr).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unbound() -> Socket {
+        Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).unwrap()
+    }
+
+    #[test]
+    fn verify_options_are_applied() {
+        let socket = unbound();
+        let options = SocketOptions {
+            dscp: Some(46),
+            ttl: Some(8),
+            recv_buffer_size: Some(1 << 16),
+            ..SocketOptions::default()
+        };
+        options.apply(SockRef::from(&socket)).unwrap();
+        assert_eq!(socket.tos_v4().unwrap(), 46 << 2);
+        assert_eq!(socket.ttl_v4().unwrap(), 8);
+        // The OS can round the size up
+        assert!(socket.recv_buffer_size().unwrap() >= 1 << 16);
+    }
+
+    #[test]
+    fn verify_dscp_has_to_fit_in_six_bits() {
+        let socket = unbound();
+        let options = SocketOptions {
+            dscp: Some(0x3F),
+            ..SocketOptions::default()
+        };
+        assert!(options.apply(SockRef::from(&socket)).is_ok());
+
+        let options = SocketOptions {
+            dscp: Some(0x40),
+            ..SocketOptions::default()
+        };
+        let error = options.apply(SockRef::from(&socket)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        // Nothing is changed by options that are rejected
+        assert_eq!(socket.tos_v4().unwrap(), 0x3F << 2);
+    }
+
+    #[tokio::test]
+    async fn verify_sockets_bind_to_the_local_address() {
+        let options = SocketOptions::default();
+        assert_eq!(
+            options.local_addr(1150),
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, 1150))
+        );
+
+        let options = SocketOptions {
+            local_address: Some(Ipv4Addr::LOCALHOST),
+            ..SocketOptions::default()
+        };
+        let socket = bind_udp(options.local_addr(0), &options).unwrap();
+        assert_eq!(
+            socket.local_addr().unwrap().ip(),
+            std::net::IpAddr::from(Ipv4Addr::LOCALHOST)
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_reused_ports_can_be_shared() {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 41460));
+        let options = SocketOptions::default();
+        let first = bind_udp(addr, &options).unwrap();
+        assert!(bind_udp(addr, &options).is_err());
+        drop(first);
+
+        let options = SocketOptions {
+            reuse_address: true,
+            ..SocketOptions::default()
+        };
+        let _first = bind_udp(addr, &options).unwrap();
+        assert!(bind_udp(addr, &options).is_ok());
+    }
+}
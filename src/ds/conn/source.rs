@@ -0,0 +1,89 @@
+use crate::ds::config::SourceFilter;
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::net::lookup_host;
+
+/// The shortest time between resolving a hostname target again to check an unexpected source
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Decides whether received status packets came from the robot being driven
+pub(crate) struct SourceValidator {
+    filter: SourceFilter,
+    target: String,
+    resolved: Vec<IpAddr>,
+    simulation: bool,
+    last_resolve: Option<Instant>,
+    /// Sources that have already been reported, so each is only logged once
+    reported: HashSet<IpAddr>,
+}
+
+impl SourceValidator {
+    pub(crate) async fn new(filter: SourceFilter, target: &str) -> SourceValidator {
+        let mut validator = SourceValidator {
+            filter,
+            target: String::new(),
+            resolved: Vec::new(),
+            simulation: false,
+            last_resolve: None,
+            reported: HashSet::new(),
+        };
+        validator.set_target(target).await;
+        validator
+    }
+
+    pub(crate) async fn set_target(&mut self, target: &str) {
+        self.target = target.to_string();
+        self.reported.clear();
+        self.resolve().await;
+    }
+
+    /// Simulators are always on the loopback interface
+    pub(crate) fn set_simulation(&mut self, simulation: bool) {
+        self.simulation = simulation;
+    }
+
+    /// Returns true if packets from `source` should be used, logging sources as they are first rejected
+    pub(crate) async fn accepts(&mut self, source: IpAddr) -> bool {
+        if self.allowed(source) {
+            return true;
+        }
+
+        // The address of a hostname target, such as a roboRIO found through mDNS, may have changed or not been resolvable yet
+        let hostname = self.target.parse::<IpAddr>().is_err();
+        if hostname
+            && self
+                .last_resolve
+                .is_none_or(|at| at.elapsed() >= RESOLVE_INTERVAL)
+        {
+            self.resolve().await;
+            if self.allowed(source) {
+                return true;
+            }
+        }
+
+        if self.reported.insert(source) {
+            warn!("Ignoring status packets from unexpected address {}", source);
+        }
+        false
+    }
+
+    fn allowed(&self, source: IpAddr) -> bool {
+        !self.filter.enabled
+            || (self.simulation && source.is_loopback())
+            || self.filter.allow.contains(&source)
+            || self.resolved.contains(&source)
+    }
+
+    async fn resolve(&mut self) {
+        self.last_resolve = Some(Instant::now());
+        self.resolved = match self.target.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => match lookup_host((self.target.as_str(), 0)).await {
+                Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+                Err(_) => Vec::new(),
+            },
+        };
+    }
+}
@@ -0,0 +1,260 @@
+use crate::ds::config::ConsoleLimits;
+use crate::{Stdout, TcpPacket};
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// The number of console lines a watcher can fall behind by before it starts missing them
+pub(crate) const CONSOLE_CAPACITY: usize = 1024;
+
+/// A message printed by robot code, or an error or warning it reported
+///
+/// Received through [`DriverStation::watch_console`](crate::DriverStation::watch_console), after the
+/// [`ConsoleLimits`] are applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleLine {
+    /// When the driver station received the line
+    pub received: DateTime<Utc>,
+    /// The robot's timestamp, in seconds since robot code started
+    pub timestamp: f32,
+    pub level: ConsoleLevel,
+    pub text: String,
+}
+
+/// The target console lines are logged under with the `log` and `tracing` features
+pub(crate) const LOG_TARGET: &str = "robot";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    /// Printed to standard output
+    Output,
+    Warning,
+    Error,
+}
+
+impl ConsoleLine {
+    /// Returns the line shown for `packet`, if it's console output or an error message
+    pub(crate) fn from_packet(packet: &TcpPacket, received: DateTime<Utc>) -> Option<ConsoleLine> {
+        match packet {
+            TcpPacket::Stdout(stdout) => Some(ConsoleLine {
+                received,
+                timestamp: stdout.timestamp,
+                level: ConsoleLevel::Output,
+                text: stdout.message.clone(),
+            }),
+            TcpPacket::ErrorMessage(error) => {
+                let mut text = format!("{} ({})", error.details, error.code.code());
+                if !error.location.is_empty() {
+                    text.push_str(&format!(" at {}", error.location));
+                }
+                Some(ConsoleLine {
+                    received,
+                    timestamp: error.timestamp,
+                    level: if error.is_error {
+                        ConsoleLevel::Error
+                    } else {
+                        ConsoleLevel::Warning
+                    },
+                    text,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Passes the line to the `log` and `tracing` facades, whichever are enabled
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    pub(crate) fn log(&self) {
+        let text = self.text.trim_end();
+        #[cfg(feature = "log")]
+        {
+            let level = match self.level {
+                ConsoleLevel::Output => log::Level::Info,
+                ConsoleLevel::Warning => log::Level::Warn,
+                ConsoleLevel::Error => log::Level::Error,
+            };
+            log::log!(target: LOG_TARGET, level, "[{:.3}] {}", self.timestamp, text);
+        }
+        // tracing's macros need the level as a constant
+        #[cfg(feature = "tracing")]
+        match self.level {
+            ConsoleLevel::Output => {
+                tracing::info!(target: LOG_TARGET, robot_time = self.timestamp, "{}", text)
+            }
+            ConsoleLevel::Warning => {
+                tracing::warn!(target: LOG_TARGET, robot_time = self.timestamp, "{}", text)
+            }
+            ConsoleLevel::Error => {
+                tracing::error!(target: LOG_TARGET, robot_time = self.timestamp, "{}", text)
+            }
+        }
+    }
+}
+
+/// The window [`ConsoleLimits::max_per_second`] is counted over
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Collapses repeated console messages and enforces the rate limit, before messages reach the TCP consumer
+///
+/// Suppressed messages are summarised by a message of their own, stamped like the last one suppressed.
+pub(crate) struct ConsoleFilter {
+    limits: ConsoleLimits,
+    /// The last message passed on, when collapsing repeats
+    last: Option<String>,
+    /// The number of repeats of `last` since it, or its last summary, was passed on
+    repeats: u32,
+    /// The timestamp and sequence number of the last repeat
+    last_repeat: (f32, u16),
+    window_start: Option<Instant>,
+    window_count: u32,
+    /// The number of messages dropped in this window
+    dropped: u32,
+    last_dropped: (f32, u16),
+}
+
+impl ConsoleFilter {
+    pub(crate) const fn new() -> ConsoleFilter {
+        ConsoleFilter {
+            limits: ConsoleLimits {
+                dedup: false,
+                max_per_second: None,
+            },
+            last: None,
+            repeats: 0,
+            last_repeat: (0.0, 0),
+            window_start: None,
+            window_count: 0,
+            dropped: 0,
+            last_dropped: (0.0, 0),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn limits(&self) -> ConsoleLimits {
+        self.limits
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: ConsoleLimits) {
+        self.limits = limits;
+        if !limits.dedup {
+            self.last = None;
+        }
+    }
+
+    /// Returns the messages to pass on after `stdout` was received at `now`
+    pub(crate) fn filter(&mut self, stdout: Stdout, now: Instant) -> Vec<Stdout> {
+        let mut out = Vec::new();
+        if self.limits.dedup {
+            if self.last.as_deref() == Some(stdout.message.as_str()) {
+                self.repeats += 1;
+                self.last_repeat = (stdout.timestamp, stdout.seqnum);
+                return out;
+            }
+            out.extend(self.repeat_summary());
+            self.last = Some(stdout.message.clone());
+        }
+
+        out.extend(self.roll_window(now));
+        if let Some(max) = self.limits.max_per_second {
+            if self.window_count >= max {
+                self.dropped += 1;
+                self.last_dropped = (stdout.timestamp, stdout.seqnum);
+                // The next message that isn't dropped mustn't be collapsed into one that never arrived
+                self.last = None;
+                return out;
+            }
+            self.window_count += 1;
+        }
+        out.push(stdout);
+        out
+    }
+
+    /// Returns the summaries of messages suppressed so far, called periodically so a flood that stops is still reported
+    pub(crate) fn flush(&mut self, now: Instant) -> Vec<Stdout> {
+        let mut out = Vec::new();
+        out.extend(self.repeat_summary());
+        out.extend(self.roll_window(now));
+        out
+    }
+
+    fn repeat_summary(&mut self) -> Option<Stdout> {
+        if self.repeats == 0 {
+            return None;
+        }
+        let (timestamp, seqnum) = self.last_repeat;
+        let summary = Stdout {
+            timestamp,
+            message: format!("Last message repeated {} times", self.repeats),
+            seqnum,
+        };
+        self.repeats = 0;
+        Some(summary)
+    }
+
+    /// Starts a new rate limit window if the current one is over, summarising the messages it dropped
+    fn roll_window(&mut self, now: Instant) -> Option<Stdout> {
+        if self
+            .window_start
+            .is_some_and(|start| now.duration_since(start) < WINDOW)
+        {
+            return None;
+        }
+        self.window_start = Some(now);
+        self.window_count = 0;
+        if self.dropped == 0 {
+            return None;
+        }
+        let (timestamp, seqnum) = self.last_dropped;
+        let summary = Stdout {
+            timestamp,
+            message: format!("{} messages dropped by the rate limit", self.dropped),
+            seqnum,
+        };
+        self.dropped = 0;
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stdout(message: &str) -> Stdout {
+        Stdout {
+            timestamp: 1.0,
+            message: message.to_string(),
+            seqnum: 0,
+        }
+    }
+
+    fn messages(out: Vec<Stdout>) -> Vec<String> {
+        out.into_iter().map(|stdout| stdout.message).collect()
+    }
+
+    #[test]
+    fn verify_console_filtering() {
+        let now = Instant::now();
+        let mut filter = ConsoleFilter::new();
+        filter.set_limits(ConsoleLimits {
+            dedup: true,
+            max_per_second: Some(2),
+        });
+
+        assert_eq!(messages(filter.filter(stdout("a"), now)), vec!["a"]);
+        assert!(filter.filter(stdout("a"), now).is_empty());
+        assert!(filter.filter(stdout("a"), now).is_empty());
+        assert_eq!(
+            messages(filter.filter(stdout("b"), now)),
+            vec!["Last message repeated 2 times", "b"]
+        );
+        // The limit of 2 was reached by "a" and "b"
+        assert!(filter.filter(stdout("c"), now).is_empty());
+        assert!(filter.flush(now).is_empty());
+
+        let later = now + WINDOW;
+        assert_eq!(
+            messages(filter.filter(stdout("d"), later)),
+            vec!["1 messages dropped by the rate limit", "d"]
+        );
+    }
+}
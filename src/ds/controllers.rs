@@ -0,0 +1,229 @@
+use crate::{AxisType, JoystickDescriptor, JoystickType, JoystickValue};
+
+/// A common controller, with the descriptor and input ids that robot code written against WPILib's controller classes
+/// expects
+///
+/// Ids are those of [`JoystickValue`], so axes and POVs are numbered from 0 and buttons from 1, as in WPILib. Only the
+/// first 10 buttons of each joystick are sent in control packets, so the PlayStation controllers' stick, PS, and
+/// touchpad buttons don't reach the robot.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Controller {
+    /// An Xbox controller, matching `XboxController`
+    Xbox,
+    /// A DualShock 4, matching `PS4Controller`
+    Ps4,
+    /// A DualSense, matching `PS5Controller`
+    Ps5,
+    /// A Logitech F310 with its switch in the X position, which WPILib treats as an Xbox controller
+    F310,
+    /// A generic flight stick, matching `Joystick`
+    FlightStick,
+}
+
+/// An axis of a gamepad, named by position so it means the same on every controller
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// A button of a gamepad, with the face buttons named by position, so `South` is A on an Xbox controller and cross on a
+/// PlayStation controller
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    /// The digital side of a trigger, which PlayStation controllers also report as a button
+    LeftTrigger,
+    RightTrigger,
+    /// Back, share, or create
+    Back,
+    /// Start or options
+    Start,
+    LeftStick,
+    RightStick,
+    Home,
+    Touchpad,
+}
+
+/// An axis of a flight stick
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StickAxis {
+    X,
+    Y,
+    Twist,
+    Throttle,
+}
+
+impl Controller {
+    /// Returns the descriptor of this controller in slot `index`, to be sent with
+    /// [`DriverStation::set_joystick_descriptors`](crate::DriverStation::set_joystick_descriptors)
+    pub fn descriptor(self, index: u8) -> JoystickDescriptor {
+        use AxisType::*;
+
+        let (name, joystick_type, axis_types, buttons) = match self {
+            Controller::Xbox => (
+                "Controller (Xbox One For Windows)",
+                JoystickType::XInputGamepad,
+                vec![X, Y, Z, Z, X, Y],
+                10,
+            ),
+            Controller::F310 => (
+                "Controller (Gamepad F310)",
+                JoystickType::XInputGamepad,
+                vec![X, Y, Z, Z, X, Y],
+                10,
+            ),
+            Controller::Ps4 => (
+                "Wireless Controller",
+                JoystickType::HidGamepad,
+                vec![X, Y, Z, Twist, Throttle, Z],
+                14,
+            ),
+            Controller::Ps5 => (
+                "DualSense Wireless Controller",
+                JoystickType::HidGamepad,
+                vec![X, Y, Z, Twist, Throttle, Z],
+                14,
+            ),
+            Controller::FlightStick => (
+                "Flight Stick",
+                JoystickType::HidFlight,
+                vec![X, Y, Twist, Throttle],
+                12,
+            ),
+        };
+        JoystickDescriptor {
+            index,
+            is_xbox: joystick_type == JoystickType::XInputGamepad,
+            joystick_type,
+            name: name.to_string(),
+            axis_types,
+            buttons,
+            povs: 1,
+        }
+    }
+
+    #[inline(always)]
+    const fn is_playstation(self) -> bool {
+        matches!(self, Controller::Ps4 | Controller::Ps5)
+    }
+
+    /// Returns the id of `axis`, or `None` for a flight stick
+    pub const fn axis(self, axis: GamepadAxis) -> Option<u8> {
+        use GamepadAxis::*;
+
+        if matches!(self, Controller::FlightStick) {
+            return None;
+        }
+        let playstation = self.is_playstation();
+        Some(match axis {
+            LeftX => 0,
+            LeftY => 1,
+            RightX if playstation => 2,
+            RightX => 4,
+            RightY => 5,
+            LeftTrigger if playstation => 3,
+            LeftTrigger => 2,
+            RightTrigger if playstation => 4,
+            RightTrigger => 3,
+        })
+    }
+
+    /// Returns the id of `button`, or `None` if the controller doesn't have it
+    ///
+    /// Flight stick buttons are only numbered, with the trigger as id 0.
+    pub const fn button(self, button: GamepadButton) -> Option<u8> {
+        use GamepadButton::*;
+
+        match self {
+            Controller::Xbox | Controller::F310 => match button {
+                South => Some(1),
+                East => Some(2),
+                West => Some(3),
+                North => Some(4),
+                LeftBumper => Some(5),
+                RightBumper => Some(6),
+                Back => Some(7),
+                Start => Some(8),
+                LeftStick => Some(9),
+                RightStick => Some(10),
+                LeftTrigger | RightTrigger | Home | Touchpad => None,
+            },
+            Controller::Ps4 | Controller::Ps5 => Some(match button {
+                West => 1,
+                South => 2,
+                East => 3,
+                North => 4,
+                LeftBumper => 5,
+                RightBumper => 6,
+                LeftTrigger => 7,
+                RightTrigger => 8,
+                Back => 9,
+                Start => 10,
+                LeftStick => 11,
+                RightStick => 12,
+                Home => 13,
+                Touchpad => 14,
+            }),
+            Controller::FlightStick => None,
+        }
+    }
+
+    /// Returns the id of a flight stick's `axis`, or `None` for a gamepad
+    pub const fn stick_axis(self, axis: StickAxis) -> Option<u8> {
+        match self {
+            Controller::FlightStick => Some(match axis {
+                StickAxis::X => 0,
+                StickAxis::Y => 1,
+                StickAxis::Twist => 2,
+                StickAxis::Throttle => 3,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `axis` to send, for use in a joystick supplier
+    pub const fn axis_value(self, axis: GamepadAxis, value: f32) -> Option<JoystickValue> {
+        match self.axis(axis) {
+            Some(id) => Some(JoystickValue::Axis { id, value }),
+            None => None,
+        }
+    }
+
+    /// Returns the value of `button` to send, for use in a joystick supplier
+    pub const fn button_value(self, button: GamepadButton, pressed: bool) -> Option<JoystickValue> {
+        match self.button(button) {
+            Some(id) => Some(JoystickValue::Button { id, pressed }),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_ids_match_wpilib() {
+        // XboxController.Axis.kRightX and PS4Controller.Axis.kRightX
+        assert_eq!(Controller::Xbox.axis(GamepadAxis::RightX), Some(4));
+        assert_eq!(Controller::Ps4.axis(GamepadAxis::RightX), Some(2));
+        // XboxController.Button.kA and PS4Controller.Button.kCross
+        assert_eq!(Controller::F310.button(GamepadButton::South), Some(1));
+        assert_eq!(Controller::Ps5.button(GamepadButton::South), Some(2));
+
+        let descriptor = Controller::Ps4.descriptor(2);
+        assert_eq!(descriptor.index, 2);
+        assert!(!descriptor.is_xbox);
+        assert_eq!(descriptor.axis_types.len(), 6);
+    }
+}
@@ -0,0 +1,140 @@
+//! Finding robots that answer control packets, to offer a choice of robot before connecting
+//!
+//! Probes are disabled control packets, so they can't enable a robot. A robot that another driver station is
+//! controlling may still briefly see a second one.
+
+use crate::proto::udp::inbound::UdpResponsePacket;
+use crate::proto::udp::inbound::types::Trace;
+use crate::{Alliance, Control, Ports, UdpControlPacket};
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+use tokio::net::{UdpSocket, lookup_host};
+
+/// How long [`scan_subnet`] waits for replies after sending its probes
+pub const SCAN_WAIT: Duration = Duration::from_millis(500);
+
+/// A robot that answered a probe
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DiscoveredRobot {
+    pub address: IpAddr,
+    /// The team number, inferred from a 10.TE.AM.x address or the mDNS name that was probed
+    pub team_number: Option<u16>,
+    pub battery_voltage: f32,
+    pub trace: Trace,
+    /// The time between sending the probe and receiving the reply
+    pub latency: Duration,
+}
+
+/// Probes every host of `a.b.c.0/24`, and the roboRIO mDNS name of the team that subnet belongs to
+///
+/// Teams use `10.TE.AM.x`, so team 4533 would scan with `scan_subnet(10, 45, 33)`.
+pub async fn scan_subnet(a: u8, b: u8, c: u8) -> io::Result<Vec<DiscoveredRobot>> {
+    let mut hosts: Vec<String> = (1..=254)
+        .map(|d| Ipv4Addr::new(a, b, c, d).to_string())
+        .collect();
+    if let Some(team) = team_from_ip(IpAddr::V4(Ipv4Addr::new(a, b, c, 0))) {
+        hosts.push(format!("roboRIO-{}-FRC.local", team));
+    }
+    scan_hosts(&hosts, Ports::default(), SCAN_WAIT).await
+}
+
+/// Probes each of `hosts`, which may be addresses or host names, and returns the robots that replied within `wait`
+///
+/// Replies are received on `ports.ds_udp`, so this fails if a [`DriverStation`](crate::DriverStation) is already
+/// bound to it. Host names that don't resolve are skipped.
+pub async fn scan_hosts(
+    hosts: &[String],
+    ports: Ports,
+    wait: Duration,
+) -> io::Result<Vec<DiscoveredRobot>> {
+    let sock = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, ports.ds_udp)).await?;
+
+    // The team number each probed address was expected to belong to, and when it was probed
+    let mut probed: HashMap<IpAddr, (Option<u16>, Instant)> = HashMap::new();
+    let probe =
+        UdpControlPacket::new(0, Control::empty(), None, Alliance::new_red(1), Vec::new()).encode();
+    for host in hosts {
+        let addresses: Vec<IpAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => match lookup_host((host.as_str(), 0)).await {
+                Ok(addrs) => addrs
+                    .map(|addr| addr.ip())
+                    .filter(IpAddr::is_ipv4)
+                    .collect(),
+                Err(_) => continue,
+            },
+        };
+        for address in addresses {
+            let team = team_from_ip(address).or_else(|| team_from_hostname(host));
+            // Unreachable hosts aren't an error, they just never reply
+            let _ = sock.send_to(&probe[..], (address, ports.robot_udp)).await;
+            probed.insert(address, (team, Instant::now()));
+        }
+    }
+
+    let mut found: Vec<DiscoveredRobot> = Vec::new();
+    let mut buf = vec![0u8; 2048];
+    let deadline = tokio::time::Instant::now() + wait;
+    while let Ok(received) = tokio::time::timeout_at(deadline, sock.recv_from(&mut buf)).await {
+        let (len, source) = match received {
+            Ok(received) => received,
+            Err(_) => continue,
+        };
+        let Some(&(team_number, sent)) = probed.get(&source.ip()) else {
+            continue;
+        };
+        if found.iter().any(|robot| robot.address == source.ip()) {
+            continue;
+        }
+        if let Ok(packet) = UdpResponsePacket::decode(&mut &buf[..len]) {
+            found.push(DiscoveredRobot {
+                address: source.ip(),
+                team_number,
+                battery_voltage: packet.battery,
+                trace: packet.trace,
+                latency: sent.elapsed(),
+            });
+        }
+    }
+
+    found.sort_by_key(|robot| robot.address);
+    Ok(found)
+}
+
+/// Returns the team of a `10.TE.AM.x` address
+fn team_from_ip(ip: IpAddr) -> Option<u16> {
+    match ip {
+        IpAddr::V4(ip) => match ip.octets() {
+            [10, te, am, _] if am < 100 => Some(u16::from(te) * 100 + u16::from(am)),
+            _ => None,
+        },
+        IpAddr::V6(_) => None,
+    }
+}
+
+/// Returns the team of a `roboRIO-TEAM-FRC.local` host name
+fn team_from_hostname(host: &str) -> Option<u16> {
+    let host = host.to_ascii_lowercase();
+    host.strip_prefix("roborio-")?
+        .strip_suffix("-frc.local")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_team_is_inferred() {
+        assert_eq!(team_from_ip("10.45.33.2".parse().unwrap()), Some(4533));
+        assert_eq!(team_from_ip("10.0.5.2".parse().unwrap()), Some(5));
+        assert_eq!(team_from_ip("10.1.2.2".parse().unwrap()), Some(102));
+        assert_eq!(team_from_ip("172.22.11.2".parse().unwrap()), None);
+        assert_eq!(team_from_hostname("roboRIO-4533-FRC.local"), Some(4533));
+        assert_eq!(team_from_hostname("example.local"), None);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::Alliance;
+use crate::ds::alliance::AllianceSource;
+use crate::ds::battery::VoltageSag;
+use crate::ds::conflict::ControlConflict;
+use crate::ds::rumble::MatchPhase;
+use crate::ds::safety::JoystickFault;
+use crate::ds::shape::ShapeMismatch;
+use crate::ds::state::{DsMode, Mode};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The number of events kept by default
+pub(crate) const DEFAULT_CAPACITY: usize = 256;
+
+/// Something notable that happened to the driver station or the connected robot
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The roboRIO started responding to control packets
+    Connected,
+    /// The roboRIO stopped responding to control packets
+    Disconnected,
+    /// The TCP connection to the roboRIO was established
+    TcpConnected,
+    /// The TCP connection to the roboRIO was closed
+    TcpDisconnected,
+    /// The robot stopped responding without closing the TCP connection, as when its radio reboots or loses its link
+    RadioRebooting,
+    /// The robot closed the TCP connection shortly before it stopped responding, as the roboRIO does when it reboots
+    RioRebooting,
+    /// The robot stopped reporting code while still responding, as when its program restarts or crashes
+    CodeRestarting,
+    /// The driver station changed the IP it's targeting
+    TargetChanged(String),
+    /// The driver station switched between normal and simulation mode
+    DsModeChanged(DsMode),
+    /// The requested robot mode was changed
+    ModeChanged(Mode),
+    /// The alliance station sent to the robot changed, or now comes from a different source, as when the field
+    /// overrides the station chosen by the user
+    AllianceChanged(Alliance, AllianceSource),
+    /// The robot was enabled
+    Enabled,
+    /// The robot was disabled
+    Disabled,
+    /// The robot was emergency stopped, either by this driver station or by the roboRIO
+    Estopped,
+    /// The roboRIO started reporting a brownout
+    Brownout,
+    /// The roboRIO stopped reporting a brownout
+    BrownoutEnded,
+    /// The battery voltage dropped by more than the threshold after the robot was enabled
+    VoltageSag(VoltageSag),
+    /// Enabling was refused because the roboRIO reported a brownout within the cooldown, see
+    /// [`DriverStation::set_brownout_cooldown`](crate::DriverStation::set_brownout_cooldown)
+    EnableRefusedBrownout,
+    /// Enabling was refused because the robot wasn't armed, see [`DriverStation::arm`](crate::DriverStation::arm)
+    EnableRefusedNotArmed,
+    /// The robot was disabled because the watchdog wasn't fed in time, see
+    /// [`DriverStation::feed_watchdog`](crate::DriverStation::feed_watchdog)
+    WatchdogExpired,
+    /// The roboRIO's free disk space, in bytes, fell below
+    /// [`ResourceLimits::min_free_disk`](crate::ResourceLimits::min_free_disk)
+    LowDisk(u32),
+    /// The roboRIO's free memory, in bytes, fell below [`ResourceLimits::min_free_ram`](crate::ResourceLimits::min_free_ram)
+    LowRam(u32),
+    /// The roboRIO's CAN bus utilization, as a percentage, stayed above the threshold of the
+    /// [`CanUtilizationAlert`](crate::CanUtilizationAlert)
+    CanSaturated(f32),
+    /// The CAN bus utilization dropped back below the threshold, by more than the hysteresis
+    CanSaturationEnded,
+    /// The practice match timer reached a new phase
+    MatchPhase(MatchPhase),
+    /// The operator switched to the named mapping profile
+    MappingProfileChanged(String),
+    /// Control packets started reading joysticks from the named source, or from none, in which case they are neutral
+    JoystickSourceChanged(Option<String>),
+    /// The joystick supplier started misbehaving
+    JoystickFault(JoystickFault),
+    /// A joystick started being sent with inputs its descriptor doesn't declare, such as an axis one past the last.
+    /// Each mismatch is raised once, until the joystick fits again
+    JoystickShapeMismatch(ShapeMismatch),
+    /// The joystick supplier took this long to return, long enough to delay control packets. While it stays slow, this
+    /// is raised at most every 5 seconds
+    SlowJoystickSupplier(Duration),
+    /// The host seems to have slept, or its clock jumped, for about this long. The robot was disabled, and is
+    /// reconnected to as it may have been left enabled with a stale state
+    HostResumed(Duration),
+    /// The robot is behaving as if another driver station is controlling it
+    ControlConflict(ControlConflict),
+    /// The symptoms of another driver station stopped
+    ControlConflictEnded,
+    /// Control of the robot was handed to another driver station
+    ControlReleased,
+    /// Control of the robot was taken back after being released
+    ControlTaken,
+    /// An error occurred in one of the network tasks
+    Error(String),
+}
+
+/// An [`Event`] along with the time it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent {
+    pub timestamp: DateTime<Utc>,
+    pub event: Event,
+}
+
+/// A ring buffer of the most recent events
+pub struct EventLog {
+    events: VecDeque<TimedEvent>,
+    capacity: usize,
+}
+
+impl EventLog {
+    pub const fn new() -> EventLog {
+        EventLog {
+            events: VecDeque::new(),
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    pub fn push(&mut self, event: TimedEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Changes the number of events kept, discarding the oldest events if there are too many
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn recent(&self) -> Vec<TimedEvent> {
+        self.events.iter().cloned().collect()
+    }
+
+    /// Returns all events that happened at or after `since`, oldest first
+    pub fn since(&self, since: DateTime<Utc>) -> Vec<TimedEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.timestamp >= since)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_log_is_bounded() {
+        let mut log = EventLog::new();
+        log.set_capacity(3);
+
+        for _ in 0..5 {
+            log.push(TimedEvent {
+                timestamp: Utc::now(),
+                event: Event::Connected,
+            });
+        }
+        log.push(TimedEvent {
+            timestamp: Utc::now(),
+            event: Event::Disconnected,
+        });
+
+        let recent = log.recent();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent.last().unwrap().event, Event::Disconnected);
+
+        log.set_capacity(1);
+        assert_eq!(log.recent().len(), 1);
+    }
+}
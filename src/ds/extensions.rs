@@ -0,0 +1,100 @@
+use crate::CommandResponse;
+
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+
+/// The commands sent to robot code that haven't been answered, by ID
+pub(crate) struct PendingCommands {
+    next_id: u16,
+    waiting: HashMap<u16, oneshot::Sender<CommandResponse>>,
+}
+
+impl PendingCommands {
+    pub(crate) fn new() -> PendingCommands {
+        PendingCommands {
+            next_id: 0,
+            waiting: HashMap::new(),
+        }
+    }
+
+    /// Picks the next ID no command is waiting on, returning it with a receiver of the response
+    pub(crate) fn register(&mut self) -> (u16, oneshot::Receiver<CommandResponse>) {
+        while self.waiting.contains_key(&self.next_id) {
+            self.next_id = self.next_id.wrapping_add(1);
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let (tx, rx) = oneshot::channel();
+        self.waiting.insert(id, tx);
+        (id, rx)
+    }
+
+    /// Passes `response` to the command waiting on it, returning `false` if none is, such as after it timed out
+    pub(crate) fn resolve(&mut self, response: CommandResponse) -> bool {
+        match self.waiting.remove(&response.id) {
+            // The command gave up waiting just as the response arrived
+            Some(tx) => tx.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Stops waiting on the command `id`, once it timed out
+    pub(crate) fn cancel(&mut self, id: u16) {
+        self.waiting.remove(&id);
+    }
+
+    /// Fails every waiting command, as the connection they were sent on closed
+    pub(crate) fn clear(&mut self) {
+        self.waiting.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DsTcpCodec, RobotCommand, TcpPacket, TcpTag};
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn verify_commands_are_matched_to_responses() {
+        let mut codec = DsTcpCodec::default();
+        let mut buf = BytesMut::new();
+        let command = RobotCommand::new(0x0102, "arm", Bytes::from_static(b"up")).unwrap();
+        codec.encode(TcpTag::Command(command), &mut buf).unwrap();
+        assert_eq!(
+            &buf[..],
+            &[
+                0x00, 0x09, 0x40, 0x01, 0x02, 0x03, b'a', b'r', b'm', b'u', b'p'
+            ]
+        );
+        assert!(RobotCommand::new(0, "two words", Bytes::new()).is_err());
+        assert!(RobotCommand::new(0, "", Bytes::new()).is_err());
+
+        let mut pending = PendingCommands::new();
+        let (first, mut first_rx) = pending.register();
+        let (second, mut second_rx) = pending.register();
+        assert_ne!(first, second);
+
+        buf.clear();
+        buf.extend_from_slice(&[0x00, 0x06, 0x40, 0x00, second as u8, 0x00, b'o', b'k'][..]);
+        let Some(TcpPacket::Unknown { id: 0x40, mut data }) = codec.decode(&mut buf).unwrap()
+        else {
+            panic!("Expected a response frame");
+        };
+        let response = CommandResponse::decode(&mut data).unwrap();
+        assert!(response.succeeded());
+        assert!(pending.resolve(response));
+        assert_eq!(&second_rx.try_recv().unwrap().payload[..], b"ok");
+
+        // A response that came too late goes nowhere
+        pending.cancel(first);
+        let late = CommandResponse {
+            id: first,
+            status: 1,
+            payload: Bytes::new(),
+        };
+        assert!(!pending.resolve(late));
+        assert!(first_rx.try_recv().is_err());
+    }
+}
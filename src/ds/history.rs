@@ -0,0 +1,89 @@
+use crate::proto::udp::inbound::types::{Status, Trace};
+
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// The number of transitions kept, the oldest are dropped first
+pub(crate) const HISTORY_CAPACITY: usize = 256;
+
+/// A change in the status or trace byte reported by the robot
+///
+/// Returned by [`DriverStation::trace_history`](crate::DriverStation::trace_history). A robot program that crashes and
+/// restarts within a second shows up as its [`Trace::ROBOT_CODE`] bit clearing and setting again, even if nothing polled
+/// the trace in between.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StatusTransition {
+    /// When the status packet with the new bytes was received
+    pub timestamp: DateTime<Utc>,
+    pub status: Status,
+    pub trace: Trace,
+}
+
+/// Keeps the most recent transitions of the status and trace bytes
+pub(crate) struct TraceHistory {
+    transitions: VecDeque<StatusTransition>,
+}
+
+impl TraceHistory {
+    pub(crate) const fn new() -> TraceHistory {
+        TraceHistory {
+            transitions: VecDeque::new(),
+        }
+    }
+
+    /// Records the bytes of a status packet, returning whether they differ from the last ones
+    pub(crate) fn record(
+        &mut self,
+        status: Status,
+        trace: Trace,
+        timestamp: DateTime<Utc>,
+    ) -> bool {
+        if self
+            .transitions
+            .back()
+            .is_some_and(|last| last.status == status && last.trace == trace)
+        {
+            return false;
+        }
+        if self.transitions.len() == HISTORY_CAPACITY {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(StatusTransition {
+            timestamp,
+            status,
+            trace,
+        });
+        true
+    }
+
+    /// Returns the transitions, oldest first
+    pub(crate) fn transitions(&self) -> Vec<StatusTransition> {
+        self.transitions.iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_only_transitions_are_kept() {
+        let mut history = TraceHistory::new();
+        let now = Utc::now();
+        let running = Trace::IS_ROBORIO | Trace::ROBOT_CODE | Trace::DISABLED;
+
+        assert!(history.record(Status::empty(), running, now));
+        assert!(!history.record(Status::empty(), running, now));
+        assert!(history.record(Status::empty(), Trace::IS_ROBORIO, now));
+        assert!(history.record(Status::ESTOP, Trace::IS_ROBORIO, now));
+        assert_eq!(history.transitions().len(), 3);
+
+        for _ in 0..HISTORY_CAPACITY {
+            history.record(Status::empty(), running, now);
+            history.record(Status::empty(), Trace::IS_ROBORIO, now);
+        }
+        let transitions = history.transitions();
+        assert_eq!(transitions.len(), HISTORY_CAPACITY);
+        assert_eq!(transitions.last().unwrap().trace, Trace::IS_ROBORIO);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::VersionInfo;
+
+use std::net::IpAddr;
+
+/// What the driver station knows about the robot it's connected to
+///
+/// The address comes from status packets, and the versions from the TCP connection, so fields are `None` until the
+/// robot has reported them. Changes are announced with [`StateChange::IdentityChanged`](crate::StateChange).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RobotIdentity {
+    /// The team number the driver station is configured with
+    pub team_number: u16,
+    /// The address or host name control packets are sent to
+    pub target: String,
+    /// The address status packets are received from
+    pub address: Option<IpAddr>,
+    /// The version of the roboRIO image
+    pub image_version: Option<String>,
+    /// The version of WPILib the robot code was built with
+    pub wpilib_version: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+impl RobotIdentity {
+    pub(crate) const fn new() -> RobotIdentity {
+        RobotIdentity {
+            team_number: 0,
+            target: String::new(),
+            address: None,
+            image_version: None,
+            wpilib_version: None,
+            serial_number: None,
+        }
+    }
+
+    /// Records the address status packets came from, returning whether it changed
+    pub(crate) fn set_address(&mut self, address: IpAddr) -> bool {
+        let changed = self.address != Some(address);
+        self.address = Some(address);
+        changed
+    }
+
+    /// Records a version reported by the robot, returning whether it changed anything
+    ///
+    /// Versions of devices other than the roboRIO and robot code, such as the PDP, are ignored.
+    pub(crate) fn record_version(&mut self, info: &VersionInfo) -> bool {
+        let name = info.name.to_ascii_lowercase();
+        let field = if name.contains("serial") {
+            &mut self.serial_number
+        } else if name.contains("lib") {
+            &mut self.wpilib_version
+        } else if name.contains("image") {
+            &mut self.image_version
+        } else {
+            return false;
+        };
+        if field.as_deref() == Some(info.version.as_str()) {
+            return false;
+        }
+        *field = Some(info.version.clone());
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_versions_are_recorded() {
+        let mut buf: &[u8] = &[
+            0x00, 0x00, 0x00, 0x00, // roboRIO
+            0x0f, b'F', b'R', b'C', b'_', b'L', b'i', b'b', b'_', b'V', b'e', b'r', b's', b'i',
+            b'o', b'n', 0x08, b'2', b'0', b'2', b'4', b'.', b'3', b'.', b'2',
+        ];
+        let info = VersionInfo::decode(&mut buf).unwrap();
+        let mut identity = RobotIdentity::new();
+
+        assert!(identity.record_version(&info));
+        assert!(!identity.record_version(&info));
+        assert_eq!(identity.wpilib_version.as_deref(), Some("2024.3.2"));
+        assert_eq!(identity.image_version, None);
+    }
+}
@@ -0,0 +1,198 @@
+use super::safety::MAX_JOYSTICKS;
+use super::state::send::{NUM_AXES, NUM_BUTTONS, NUM_POVS};
+use crate::JoystickValue;
+
+/// The smallest move of an axis that is reported by default
+pub(crate) const DEFAULT_AXIS_STEP: f32 = 0.05;
+
+/// A button edge or significant axis move in the joystick values sent to the robot
+///
+/// Received through [`DriverStation::subscribe_inputs`](crate::DriverStation::subscribe_inputs), so driver inputs can
+/// be logged without comparing every 50Hz sample. `joystick` is the joystick's index in the supplied values. Values
+/// left out by the supplier are sent as neutral, so a button that disappears is released and an axis returns to 0.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputChange {
+    ButtonPressed {
+        joystick: usize,
+        id: u8,
+    },
+    ButtonReleased {
+        joystick: usize,
+        id: u8,
+    },
+    /// The axis moved by at least the threshold since it was last reported, or came to rest at 0 or either end
+    AxisMoved {
+        joystick: usize,
+        id: u8,
+        value: f32,
+    },
+    /// The POV changed angle, with `-1` meaning it was released
+    PovChanged {
+        joystick: usize,
+        id: u8,
+        angle: i16,
+    },
+}
+
+/// Finds the [`InputChange`]s between successive sets of joystick values
+///
+/// Values are kept in arrays of the protocol's limits, as this runs on every control packet. Values out of those
+/// limits are never sent, so they're ignored.
+pub(crate) struct InputTracker {
+    /// Indexed by joystick, then button id less 1
+    pressed: [[bool; NUM_BUTTONS as usize]; MAX_JOYSTICKS],
+    /// The axis values last reported
+    axes: [[f32; NUM_AXES as usize]; MAX_JOYSTICKS],
+    /// The POV angles last reported, with `-1` for released
+    povs: [[i16; NUM_POVS as usize]; MAX_JOYSTICKS],
+    axis_step: f32,
+}
+
+impl InputTracker {
+    pub(crate) const fn new() -> InputTracker {
+        InputTracker {
+            pressed: [[false; NUM_BUTTONS as usize]; MAX_JOYSTICKS],
+            axes: [[0.0; NUM_AXES as usize]; MAX_JOYSTICKS],
+            povs: [[-1; NUM_POVS as usize]; MAX_JOYSTICKS],
+            axis_step: DEFAULT_AXIS_STEP,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn axis_step(&self) -> f32 {
+        self.axis_step
+    }
+
+    pub(crate) fn set_axis_step(&mut self, step: f32) {
+        self.axis_step = step.abs();
+    }
+
+    /// Records the values about to be sent, returning how they differ from those sent before
+    pub(crate) fn observe(&mut self, joysticks: &[Vec<JoystickValue>]) -> Vec<InputChange> {
+        let mut pressed = [[false; NUM_BUTTONS as usize]; MAX_JOYSTICKS];
+        let mut axes = [[0.0; NUM_AXES as usize]; MAX_JOYSTICKS];
+        let mut povs = [[-1; NUM_POVS as usize]; MAX_JOYSTICKS];
+        for (joystick, values) in joysticks.iter().enumerate().take(MAX_JOYSTICKS) {
+            for value in values {
+                // Later values for the same id replace earlier ones, as they do when encoded
+                match *value {
+                    JoystickValue::Button { id, pressed: down } => {
+                        if let Some(button) = usize::from(id)
+                            .checked_sub(1)
+                            .and_then(|button| pressed[joystick].get_mut(button))
+                        {
+                            *button = down;
+                        }
+                    }
+                    JoystickValue::Axis { id, value } => {
+                        if let Some(axis) = axes[joystick].get_mut(usize::from(id)) {
+                            *axis = value;
+                        }
+                    }
+                    JoystickValue::POV { id, angle } => {
+                        if let Some(pov) = povs[joystick].get_mut(usize::from(id)) {
+                            *pov = angle;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut changes = Vec::new();
+        // Every press is reported before any release
+        for (was, is) in [(false, true), (true, false)] {
+            for (joystick, buttons) in pressed.iter().enumerate() {
+                for (button, &now) in buttons.iter().enumerate() {
+                    if self.pressed[joystick][button] == was && now == is {
+                        let id = button as u8 + 1;
+                        changes.push(if is {
+                            InputChange::ButtonPressed { joystick, id }
+                        } else {
+                            InputChange::ButtonReleased { joystick, id }
+                        });
+                    }
+                }
+            }
+        }
+        self.pressed = pressed;
+
+        for (joystick, values) in axes.iter().enumerate() {
+            for (id, &value) in values.iter().enumerate() {
+                let reported = &mut self.axes[joystick][id];
+                let at_rest = value == 0.0 || value.abs() >= 1.0;
+                if (value - *reported).abs() >= self.axis_step || (at_rest && value != *reported) {
+                    changes.push(InputChange::AxisMoved {
+                        joystick,
+                        id: id as u8,
+                        value,
+                    });
+                    *reported = value;
+                }
+            }
+        }
+
+        for (joystick, angles) in povs.iter().enumerate() {
+            for (id, &angle) in angles.iter().enumerate() {
+                let reported = &mut self.povs[joystick][id];
+                if angle != *reported {
+                    changes.push(InputChange::PovChanged {
+                        joystick,
+                        id: id as u8,
+                        angle,
+                    });
+                    *reported = angle;
+                }
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_only_edges_and_significant_moves_are_reported() {
+        let mut tracker = InputTracker::new();
+        let frame = |pressed, value| {
+            vec![vec![
+                JoystickValue::Button { id: 1, pressed },
+                JoystickValue::Axis { id: 0, value },
+            ]]
+        };
+
+        assert_eq!(
+            tracker.observe(&frame(true, 0.5)),
+            vec![
+                InputChange::ButtonPressed { joystick: 0, id: 1 },
+                InputChange::AxisMoved {
+                    joystick: 0,
+                    id: 0,
+                    value: 0.5
+                },
+            ]
+        );
+        assert!(tracker.observe(&frame(true, 0.52)).is_empty());
+        assert!(tracker.observe(&frame(true, 0.54)).is_empty());
+        assert_eq!(
+            tracker.observe(&frame(true, 0.56)),
+            vec![InputChange::AxisMoved {
+                joystick: 0,
+                id: 0,
+                value: 0.56
+            }]
+        );
+        assert_eq!(
+            tracker.observe(&[]),
+            vec![
+                InputChange::ButtonReleased { joystick: 0, id: 1 },
+                InputChange::AxisMoved {
+                    joystick: 0,
+                    id: 0,
+                    value: 0.0
+                },
+            ]
+        );
+    }
+}
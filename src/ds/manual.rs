@@ -0,0 +1,83 @@
+use crate::UdpControlPacket;
+
+use anyhow::anyhow;
+use futures_util::sink::Sink;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio_util::sync::PollSender;
+
+/// The number of packets that can wait for the send loop before a [`ControlSink`] stops accepting more
+pub(crate) const MANUAL_CAPACITY: usize = 16;
+
+/// Sends control packets built by the application in place of those of the send loop, for protocol research
+///
+/// Created with [`DriverStation::control_sink`](crate::DriverStation::control_sink). Each packet is sent as soon as
+/// it's pushed, and counted and decoded like those the driver station builds. Dropping the sink hands sending back to
+/// the send loop.
+pub struct ControlSink {
+    tx: PollSender<UdpControlPacket>,
+}
+
+impl ControlSink {
+    pub(crate) fn new(tx: mpsc::Sender<UdpControlPacket>) -> ControlSink {
+        ControlSink {
+            tx: PollSender::new(tx),
+        }
+    }
+}
+
+impl Sink<UdpControlPacket> for ControlSink {
+    type Error = anyhow::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        self.tx
+            .poll_reserve(cx)
+            .map_err(|_| anyhow!("The send loop stopped taking manual packets"))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: UdpControlPacket) -> crate::Result<()> {
+        self.tx
+            .send_item(packet)
+            .map_err(|_| anyhow!("The send loop stopped taking manual packets"))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        self.tx.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Waits for the next packet pushed through the sink, or forever if packets aren't sent manually
+pub(crate) async fn next_manual(
+    rx: &mut Option<mpsc::Receiver<UdpControlPacket>>,
+) -> Option<UdpControlPacket> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Alliance, Control};
+    use futures_util::SinkExt;
+
+    #[tokio::test]
+    async fn verify_sink_fails_once_the_send_loop_lets_go() {
+        let (tx, mut rx) = mpsc::channel(MANUAL_CAPACITY);
+        let mut sink = ControlSink::new(tx);
+        let packet =
+            UdpControlPacket::new(1, Control::ENABLED, None, Alliance::new_red(1), Vec::new());
+        sink.send(packet.clone()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().encode(), packet.encode());
+
+        drop(rx);
+        assert!(sink.send(packet).await.is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! An integration point for NetworkTables clients
+
+use super::events::Event;
+
+/// The port NetworkTables 4 servers listen on
+pub const NT4_PORT: u16 = 5810;
+
+/// A NetworkTables client that shares the driver station's view of where the robot is
+///
+/// The driver station doesn't implement NetworkTables itself. Instead, a client registered with
+/// `DriverStation::set_network_tables` is told which robot to connect to, and about every driver station event,
+/// so it doesn't need to follow team number, USB, and simulator changes separately. The methods are called from the
+/// network tasks, and should return quickly.
+pub trait NetworkTables: Send + Sync {
+    /// Called with the ip of the robot on registration, and whenever the driver station targets a different robot
+    ///
+    /// The client should drop its current connection and connect to `ip` on [`NT4_PORT`].
+    fn connect(&self, ip: &str);
+
+    /// Called with every driver station event, so that driver station state can be published to the tables
+    fn event(&self, _event: &Event) {}
+}
@@ -0,0 +1,86 @@
+use super::events::Event;
+
+use crate::proto::udp::inbound::types::Trace;
+
+use std::time::{Duration, Instant};
+
+/// How long before status packets stop that the robot closing the TCP connection still points to the roboRIO rebooting
+///
+/// This is on top of the UDP timeout, as the roboRIO keeps answering briefly after its programs are stopped.
+const CLOSE_SLACK: Duration = Duration::from_secs(2);
+
+/// Guesses what caused an outage from how the connections to the robot failed
+///
+/// A rebooting roboRIO closes the TCP connection as its programs are stopped, before it stops answering control
+/// packets. A rebooting radio, or an unplugged cable, drops both without closing anything. A robot program that
+/// restarts stops reporting code while the roboRIO keeps answering.
+pub(crate) struct OutageClassifier {
+    /// Whether the robot last reported running code
+    had_code: bool,
+}
+
+impl OutageClassifier {
+    pub(crate) const fn new() -> OutageClassifier {
+        OutageClassifier { had_code: false }
+    }
+
+    /// Returns the event for the robot having stopped responding after `udp_timeout`
+    ///
+    /// `robot_closed_tcp` is when the robot last closed the TCP connection. Nothing is returned when the TCP connection
+    /// wasn't open, as without it the causes can't be told apart.
+    pub(crate) fn lost(
+        &mut self,
+        tcp_open: bool,
+        robot_closed_tcp: Option<Instant>,
+        udp_timeout: Duration,
+        now: Instant,
+    ) -> Option<Event> {
+        self.had_code = false;
+        if tcp_open {
+            return Some(Event::RadioRebooting);
+        }
+        robot_closed_tcp
+            .filter(|closed| now.saturating_duration_since(*closed) <= udp_timeout + CLOSE_SLACK)
+            .map(|_| Event::RioRebooting)
+    }
+
+    /// Returns [`Event::CodeRestarting`] when the robot stops reporting code while it's still responding
+    pub(crate) fn trace(&mut self, trace: Trace) -> Option<Event> {
+        let has_code = trace.is_code_started();
+        let restarting = self.had_code && !has_code;
+        self.had_code = has_code;
+        restarting.then_some(Event::CodeRestarting)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_outages_are_classified() {
+        let mut outages = OutageClassifier::new();
+        let timeout = Duration::from_secs(2);
+        let now = Instant::now();
+
+        assert_eq!(outages.trace(Trace::IS_ROBORIO), None);
+        assert_eq!(outages.trace(Trace::IS_ROBORIO | Trace::ROBOT_CODE), None);
+        assert_eq!(
+            outages.trace(Trace::IS_ROBORIO),
+            Some(Event::CodeRestarting)
+        );
+
+        assert_eq!(
+            outages.lost(true, None, timeout, now),
+            Some(Event::RadioRebooting)
+        );
+        let closed = now - Duration::from_secs(3);
+        assert_eq!(
+            outages.lost(false, Some(closed), timeout, now),
+            Some(Event::RioRebooting)
+        );
+        let closed = now - Duration::from_secs(30);
+        assert_eq!(outages.lost(false, Some(closed), timeout, now), None);
+        assert_eq!(outages.lost(false, None, timeout, now), None);
+    }
+}
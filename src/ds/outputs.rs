@@ -0,0 +1,113 @@
+use super::rumble::Rumble;
+use super::safety::MAX_JOYSTICKS;
+use crate::JoystickOutput;
+
+use std::sync::Arc;
+
+/// What is sent back to each joystick: the outputs robot code sets, with the driver station's own rumble mixed in
+#[derive(Debug, Default)]
+pub(crate) struct OutputMixer {
+    /// What robot code last set on each joystick
+    robot: Vec<JoystickOutput>,
+    /// The driver station's own rumble of each joystick, such as at the end of a practice match
+    rumble: [Rumble; MAX_JOYSTICKS],
+    /// The source the joysticks are read from, which is given their outputs
+    source: Option<Arc<str>>,
+    /// What each joystick was last given
+    delivered: [JoystickOutput; MAX_JOYSTICKS],
+}
+
+impl OutputMixer {
+    pub fn set_robot(&mut self, outputs: &[JoystickOutput]) {
+        self.robot.clear();
+        self.robot.extend_from_slice(outputs);
+    }
+
+    pub fn set_rumble(&mut self, joystick: usize, rumble: Rumble) {
+        if let Some(own) = self.rumble.get_mut(joystick) {
+            *own = rumble;
+        }
+    }
+
+    /// Changes the source given the outputs, which is then given all of them rather than only the changes
+    pub fn set_source(&mut self, source: Option<Arc<str>>) {
+        if source != self.source {
+            self.source = source;
+            self.delivered = Default::default();
+        }
+    }
+
+    #[inline(always)]
+    pub fn source(&self) -> Option<&Arc<str>> {
+        self.source.as_ref()
+    }
+
+    /// Returns the outputs of each joystick that changed since the last call
+    pub fn take_changes(&mut self) -> Vec<(usize, JoystickOutput)> {
+        let mut changes = Vec::new();
+        for joystick in 0..MAX_JOYSTICKS {
+            let mixed = self.mixed(joystick);
+            if mixed != self.delivered[joystick] {
+                self.delivered[joystick] = mixed;
+                changes.push((joystick, mixed));
+            }
+        }
+        changes
+    }
+
+    /// Each motor rumbles as hard as either robot code or the driver station asks it to
+    fn mixed(&self, joystick: usize) -> JoystickOutput {
+        let robot = self.robot.get(joystick).copied().unwrap_or_default();
+        let own = self.rumble[joystick];
+        JoystickOutput {
+            outputs: robot.outputs,
+            rumble: Rumble::new(
+                robot.rumble.left.max(own.left),
+                robot.rumble.right.max(own.right),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_rumble_is_mixed_into_the_robot_outputs() {
+        let mut mixer = OutputMixer::default();
+        assert!(mixer.take_changes().is_empty());
+
+        mixer.set_robot(&[JoystickOutput {
+            outputs: 0b1,
+            rumble: Rumble::new(0.5, 0.0),
+        }]);
+        mixer.set_rumble(0, Rumble::new(0.25, 1.0));
+        mixer.set_rumble(2, Rumble::new(1.0, 1.0));
+        let changes = mixer.take_changes();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    0,
+                    JoystickOutput {
+                        outputs: 0b1,
+                        rumble: Rumble::new(0.5, 1.0),
+                    }
+                ),
+                (
+                    2,
+                    JoystickOutput {
+                        outputs: 0,
+                        rumble: Rumble::new(1.0, 1.0),
+                    }
+                ),
+            ]
+        );
+        // Only what changes is given again, unless the source does
+        mixer.set_rumble(2, Rumble::OFF);
+        assert_eq!(mixer.take_changes(), vec![(2, JoystickOutput::default())]);
+        mixer.set_source(Some("gamepad".into()));
+        assert_eq!(mixer.take_changes(), changes[..1]);
+    }
+}
@@ -0,0 +1,37 @@
+use super::events::TimedEvent;
+use super::state::stats::Stats;
+use super::summary::MatchSummary;
+
+use chrono::{DateTime, Utc};
+
+/// Why a driver station stopped talking to the robot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The application stopped it, with [`DriverStation::shutdown`](crate::DriverStation::shutdown)
+    Requested,
+    /// A connection task hit an error it can't recover from, such as failing to bind its socket
+    Failed(String),
+    /// A connection task panicked
+    Panicked(String),
+}
+
+impl ShutdownReason {
+    /// Whether the application asked for the driver station to stop, rather than it dying
+    #[inline(always)]
+    pub const fn requested(&self) -> bool {
+        matches!(self, ShutdownReason::Requested)
+    }
+}
+
+/// What a driver station was doing when it stopped, see [`DriverStation::shutdown`](crate::DriverStation::shutdown)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownReport {
+    pub reason: ShutdownReason,
+    pub stopped_at: DateTime<Utc>,
+    /// The health of the link as it stopped
+    pub stats: Stats,
+    /// The summary of the practice run, since the driver station started or the summary was last taken
+    pub summary: MatchSummary,
+    /// The events kept in the event log, oldest first
+    pub last_events: Vec<TimedEvent>,
+}
@@ -0,0 +1,110 @@
+use super::config::ResourceLimits;
+use super::events::Event;
+
+/// Watches the free disk space and memory the roboRIO reports, to warn before either runs out
+pub(crate) struct ResourceMonitor {
+    limits: ResourceLimits,
+    free_disk: Option<u32>,
+    free_ram: Option<u32>,
+    disk_low: bool,
+    ram_low: bool,
+}
+
+impl ResourceMonitor {
+    pub(crate) const fn new() -> ResourceMonitor {
+        ResourceMonitor {
+            limits: ResourceLimits {
+                min_free_disk: None,
+                min_free_ram: None,
+            },
+            free_disk: None,
+            free_ram: None,
+            disk_low: false,
+            ram_low: false,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) const fn limits(&self) -> ResourceLimits {
+        self.limits
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+
+    #[inline(always)]
+    pub(crate) const fn free_disk(&self) -> Option<u32> {
+        self.free_disk
+    }
+
+    #[inline(always)]
+    pub(crate) const fn free_ram(&self) -> Option<u32> {
+        self.free_ram
+    }
+
+    /// Records the free space of a status packet, returning the events for limits it fell below
+    ///
+    /// Each limit raises one event when it's crossed, and again only after the free space has recovered above it.
+    pub(crate) fn sample(&mut self, disk: Option<u32>, ram: Option<u32>) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some(free) = disk {
+            self.free_disk = Some(free);
+            #[cfg(feature = "metrics")]
+            metrics::gauge!("ds_free_disk_bytes").set(f64::from(free));
+            if crossed(free, self.limits.min_free_disk, &mut self.disk_low) {
+                events.push(Event::LowDisk(free));
+            }
+        }
+        if let Some(free) = ram {
+            self.free_ram = Some(free);
+            #[cfg(feature = "metrics")]
+            metrics::gauge!("ds_free_ram_bytes").set(f64::from(free));
+            if crossed(free, self.limits.min_free_ram, &mut self.ram_low) {
+                events.push(Event::LowRam(free));
+            }
+        }
+        events
+    }
+
+    /// Forgets what the previous connection reported, keeping the limits
+    pub(crate) fn reset(&mut self) {
+        self.free_disk = None;
+        self.free_ram = None;
+        self.disk_low = false;
+        self.ram_low = false;
+    }
+}
+
+/// Updates whether `free` is below `limit`, returning whether it just fell below
+fn crossed(free: u32, limit: Option<u32>, low: &mut bool) -> bool {
+    let is_low = limit.is_some_and(|limit| free < limit);
+    let crossed = is_low && !*low;
+    *low = is_low;
+    crossed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_low_disk_is_raised_once_per_crossing() {
+        let mut monitor = ResourceMonitor::new();
+        monitor.set_limits(ResourceLimits {
+            min_free_disk: Some(1000),
+            min_free_ram: None,
+        });
+
+        assert!(monitor.sample(Some(2000), Some(10)).is_empty());
+        assert_eq!(
+            monitor.sample(Some(900), Some(10)),
+            vec![Event::LowDisk(900)]
+        );
+        assert!(monitor.sample(Some(800), None).is_empty());
+        assert_eq!(monitor.free_disk(), Some(800));
+
+        assert!(monitor.sample(Some(1500), None).is_empty());
+        assert_eq!(monitor.sample(Some(500), None), vec![Event::LowDisk(500)]);
+    }
+}
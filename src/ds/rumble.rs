@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+/// A force feedback command for one joystick, with motor intensities from `0.0..=1.0`
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Rumble {
+    pub left: f32,
+    pub right: f32,
+}
+
+impl Rumble {
+    pub const OFF: Rumble = Rumble::new(0.0, 0.0);
+
+    #[inline(always)]
+    pub const fn new(left: f32, right: f32) -> Rumble {
+        Rumble { left, right }
+    }
+}
+
+/// A sequence of rumbles, each held for a duration
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RumblePattern {
+    steps: Vec<(Rumble, Duration)>,
+}
+
+impl RumblePattern {
+    pub const fn new() -> RumblePattern {
+        RumblePattern { steps: Vec::new() }
+    }
+
+    /// Appends `rumble`, held for `duration`
+    pub fn then(mut self, rumble: Rumble, duration: Duration) -> RumblePattern {
+        self.steps.push((rumble, duration));
+        self
+    }
+
+    /// A pattern of `count` pulses at full strength, each `on` long and separated by `off`
+    pub fn pulses(count: usize, on: Duration, off: Duration) -> RumblePattern {
+        (0..count).fold(RumblePattern::new(), |pattern, _| {
+            pattern
+                .then(Rumble::new(1.0, 1.0), on)
+                .then(Rumble::OFF, off)
+        })
+    }
+
+    #[inline(always)]
+    pub fn steps(&self) -> &[(Rumble, Duration)] {
+        &self.steps
+    }
+}
+
+/// Which joysticks rumble, and how, as a match nears its end
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndgameRumble {
+    /// The slots of the joysticks to rumble, through [`JoystickSource::output`](crate::JoystickSource::output)
+    pub joysticks: Vec<usize>,
+    /// The time remaining in the match when the endgame starts
+    pub endgame: Duration,
+    pub endgame_pattern: RumblePattern,
+    /// The time remaining in the match when the final countdown starts
+    pub countdown: Duration,
+    pub countdown_pattern: RumblePattern,
+}
+
+impl EndgameRumble {
+    /// Rumbles `joysticks` with a long pulse 20 seconds from the end of the match, and three short pulses 10 seconds from the end
+    pub fn new(joysticks: Vec<usize>) -> EndgameRumble {
+        EndgameRumble {
+            joysticks,
+            endgame: Duration::from_secs(20),
+            endgame_pattern: RumblePattern::pulses(1, Duration::from_secs(1), Duration::ZERO),
+            countdown: Duration::from_secs(10),
+            countdown_pattern: RumblePattern::pulses(
+                3,
+                Duration::from_millis(250),
+                Duration::from_millis(150),
+            ),
+        }
+    }
+}
+
+/// A point in a timed match
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MatchPhase {
+    Started,
+    Endgame,
+    FinalCountdown,
+    Ended,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_pulses() {
+        let pattern =
+            RumblePattern::pulses(2, Duration::from_millis(100), Duration::from_millis(50));
+        assert_eq!(pattern.steps().len(), 4);
+        assert_eq!(
+            pattern.steps()[0],
+            (Rumble::new(1.0, 1.0), Duration::from_millis(100))
+        );
+        assert_eq!(pattern.steps()[3], (Rumble::OFF, Duration::from_millis(50)));
+    }
+}
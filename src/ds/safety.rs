@@ -0,0 +1,109 @@
+use super::validation::JoystickFrame;
+
+/// The number of joysticks the roboRIO accepts
+pub const MAX_JOYSTICKS: usize = 6;
+
+/// A problem with the joystick supplier that makes its output unsafe to drive with
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoystickFault {
+    /// The supplier panicked, so no joystick values could be sent
+    SupplierPanicked,
+    /// The supplier returned values that can't be sent
+    Malformed(String),
+    /// The joystick at this index is marked as required, but wasn't returned by the supplier
+    Missing(usize),
+}
+
+/// Which joystick supplier faults disable the robot
+///
+/// Faults are always reported with [`Event::JoystickFault`](crate::Event::JoystickFault), this only decides
+/// whether the robot is disabled as well. The robot stays disabled while the fault persists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoystickSafety {
+    /// Disable the robot when the supplier panics
+    pub disable_on_panic: bool,
+    /// Disable the robot when the supplier returns more than [`MAX_JOYSTICKS`] joysticks, or axis values that aren't
+    /// numbers, or values rejected by [`JoystickValidation::Error`](crate::JoystickValidation::Error)
+    pub disable_on_malformed: bool,
+    /// Disable the robot when any of the joysticks at these indices are missing from the supplier's output
+    pub required: Vec<usize>,
+}
+
+impl Default for JoystickSafety {
+    fn default() -> JoystickSafety {
+        JoystickSafety {
+            disable_on_panic: true,
+            disable_on_malformed: true,
+            required: Vec::new(),
+        }
+    }
+}
+
+impl JoystickSafety {
+    /// Finds the fault in one call of the supplier, if there is one
+    pub(crate) fn fault(&self, frame: &JoystickFrame) -> Option<JoystickFault> {
+        match frame {
+            JoystickFrame::Panicked => Some(JoystickFault::SupplierPanicked),
+            JoystickFrame::Invalid(problem) => Some(JoystickFault::Malformed(problem.clone())),
+            JoystickFrame::Dropped(_) => None,
+            JoystickFrame::Valid { joysticks, .. } => self
+                .required
+                .iter()
+                .find(|&&index| index >= joysticks.len())
+                .map(|&index| JoystickFault::Missing(index)),
+        }
+    }
+
+    /// Returns whether `fault` should disable the robot
+    pub(crate) fn disables(&self, fault: &JoystickFault) -> bool {
+        match fault {
+            JoystickFault::SupplierPanicked => self.disable_on_panic,
+            JoystickFault::Malformed(_) => self.disable_on_malformed,
+            JoystickFault::Missing(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::JoystickValue;
+    use crate::ds::validation::JoystickValidation;
+
+    #[test]
+    fn verify_faults() {
+        let safety = JoystickSafety {
+            required: vec![1],
+            ..JoystickSafety::default()
+        };
+        let frame = |count| JoystickFrame::Valid {
+            joysticks: vec![vec![JoystickValue::Axis { id: 0, value: 0.5 }]; count],
+            warning: None,
+        };
+
+        assert_eq!(
+            safety.fault(&JoystickFrame::Panicked),
+            Some(JoystickFault::SupplierPanicked)
+        );
+        assert_eq!(safety.fault(&frame(1)), Some(JoystickFault::Missing(1)));
+        assert_eq!(safety.fault(&frame(2)), None);
+        assert_eq!(
+            safety.fault(&JoystickFrame::Invalid("bad".to_string())),
+            Some(JoystickFault::Malformed("bad".to_string()))
+        );
+
+        // Malformed output disables with the default validation, which clamps everything else
+        let stick = vec![JoystickValue::Axis { id: 0, value: 0.5 }];
+        let validated = |joysticks| safety.fault(&JoystickValidation::default().apply(joysticks));
+        let fault = validated(vec![stick; 7]).unwrap();
+        assert!(matches!(fault, JoystickFault::Malformed(_)));
+        assert!(safety.disables(&fault));
+        assert!(matches!(
+            validated(vec![vec![JoystickValue::Axis {
+                id: 0,
+                value: f32::NAN
+            }]]),
+            Some(JoystickFault::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,279 @@
+//! Checking which parts of the protocol a robot or simulator supports, to diagnose ones that behave unusually
+//!
+//! The self-test never enables the robot. It does change the requested mode, send a game specific message, and ask the
+//! roboRIO to request the date again, so it's best run with a driver station of its own, as [`run`] does.
+
+use super::DriverStation;
+
+use crate::{Alliance, Mode, RequestFlags};
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long the robot has to start answering control packets, and to open the TCP connection
+pub const CONNECT_WAIT: Duration = Duration::from_secs(5);
+/// How long the robot has to react to each change
+pub const REPLY_WAIT: Duration = Duration::from_secs(2);
+
+/// The game specific message sent if the driver station doesn't already have one
+const PROBE_MESSAGE: &str = "SLF";
+
+/// A part of the protocol checked by the self-test
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Feature {
+    /// Answering control packets with status packets
+    Connect,
+    /// Reporting the mode that was requested
+    ModeChanges,
+    /// Accepting the TCP connection
+    Tcp,
+    /// Keeping the TCP connection open after being sent a game specific message
+    GameData,
+    /// Sending console output over TCP
+    Console,
+    /// Acting on the request bits of control packets
+    Requests,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Feature::Connect => "connect",
+            Feature::ModeChanges => "mode changes",
+            Feature::Tcp => "tcp",
+            Feature::GameData => "game data",
+            Feature::Console => "console",
+            Feature::Requests => "requests",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The robot didn't behave as expected, for the given reason
+    Failed(String),
+    /// The check couldn't be run, because a check it depends on failed
+    Skipped(Feature),
+}
+
+/// The outcome of checking one [`Feature`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub feature: Feature,
+    pub outcome: Outcome,
+    /// How long the check took
+    pub elapsed: Duration,
+}
+
+/// Which protocol features the robot supports, returned by [`run`] and [`run_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// The address or host name that was tested
+    pub target: String,
+    pub checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// Returns whether `feature` was checked and passed
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.feature == feature && check.outcome == Outcome::Passed)
+    }
+
+    /// Returns whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.outcome == Outcome::Passed)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Self-test of {}", self.target)?;
+        for check in &self.checks {
+            match check.outcome {
+                Outcome::Passed => {
+                    writeln!(f, "  {}: passed in {:?}", check.feature, check.elapsed)?
+                }
+                Outcome::Failed(ref reason) => {
+                    writeln!(f, "  {}: failed, {}", check.feature, reason)?
+                }
+                Outcome::Skipped(after) => {
+                    writeln!(f, "  {}: skipped, {} failed", check.feature, after)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tests the robot of `team` at the address inferred from the team number, with a driver station of its own
+///
+/// That driver station binds the usual ports, so this fails to connect while another one is running on this host.
+pub async fn run(team: u16) -> SelfTestReport {
+    let mut ds = DriverStation::new_team(team, Alliance::new_red(1)).await;
+    run_with(&mut ds).await
+}
+
+/// Tests the robot `ds` is connected to, such as a simulator at a custom address
+///
+/// The requested mode is restored afterwards. The robot is disabled first if it's enabled.
+pub async fn run_with(ds: &mut DriverStation) -> SelfTestReport {
+    ds.disable().await;
+    let mut checks = Vec::new();
+    let mut record = |feature, started: Instant, outcome| {
+        checks.push(Check {
+            feature,
+            outcome,
+            elapsed: started.elapsed(),
+        })
+    };
+
+    let started = Instant::now();
+    if !wait_until(CONNECT_WAIT, async || {
+        ds.state.link().read().await.connected()
+    })
+    .await
+    {
+        record(
+            Feature::Connect,
+            started,
+            Outcome::Failed(format!("no status packets within {:?}", CONNECT_WAIT)),
+        );
+        for feature in [
+            Feature::ModeChanges,
+            Feature::Tcp,
+            Feature::GameData,
+            Feature::Console,
+            Feature::Requests,
+        ] {
+            record(feature, Instant::now(), Outcome::Skipped(Feature::Connect));
+        }
+        return SelfTestReport {
+            target: ds.state.target(),
+            checks,
+        };
+    }
+    record(Feature::Connect, started, Outcome::Passed);
+
+    let started = Instant::now();
+    let original = ds.mode().await;
+    let mut unconfirmed = Vec::new();
+    for mode in [Mode::Autonomous, Mode::Test, Mode::Teleoperated] {
+        ds.set_mode(mode).await;
+        if !wait_until(REPLY_WAIT, async || {
+            ds.state.telemetry().read().await.reported_mode() == Some(mode)
+        })
+        .await
+        {
+            unconfirmed.push(mode);
+        }
+    }
+    ds.set_mode(original).await;
+    record(
+        Feature::ModeChanges,
+        started,
+        if unconfirmed.is_empty() {
+            Outcome::Passed
+        } else {
+            Outcome::Failed(format!("{:?} weren't reported back", unconfirmed))
+        },
+    );
+
+    // The console is watched from before the TCP connection opens, as robots often print the most when it does
+    let mut console = ds.watch_console().await;
+    let started = Instant::now();
+    let tcp = wait_until(CONNECT_WAIT, async || {
+        ds.state.tcp().read().await.connected()
+    })
+    .await;
+    if tcp {
+        record(Feature::Tcp, started, Outcome::Passed);
+
+        let started = Instant::now();
+        let message = ds
+            .session
+            .game_data
+            .clone()
+            .unwrap_or_else(|| PROBE_MESSAGE.to_string());
+        let _ = ds.set_game_specific_message(&message).await;
+        tokio::time::sleep(REPLY_WAIT).await;
+        record(
+            Feature::GameData,
+            started,
+            if ds.state.tcp().read().await.connected() {
+                Outcome::Passed
+            } else {
+                Outcome::Failed("the TCP connection closed after sending it".to_string())
+            },
+        );
+
+        let started = Instant::now();
+        let printed = !console.is_empty()
+            || tokio::time::timeout(REPLY_WAIT, console.recv())
+                .await
+                .is_ok();
+        record(
+            Feature::Console,
+            started,
+            if printed {
+                Outcome::Passed
+            } else {
+                Outcome::Failed(format!("no console output within {:?}", REPLY_WAIT))
+            },
+        );
+    } else {
+        record(
+            Feature::Tcp,
+            started,
+            Outcome::Failed(format!("no TCP connection within {:?}", CONNECT_WAIT)),
+        );
+        record(
+            Feature::GameData,
+            Instant::now(),
+            Outcome::Skipped(Feature::Tcp),
+        );
+        record(
+            Feature::Console,
+            Instant::now(),
+            Outcome::Skipped(Feature::Tcp),
+        );
+    }
+
+    // Asking for the date again is the only request that doesn't restart anything
+    let started = Instant::now();
+    let sent = ds.clock_sync().await.date_sent;
+    ds.request(RequestFlags::REQUEST_TIME).await;
+    let requested = wait_until(REPLY_WAIT, async || ds.clock_sync().await.date_sent != sent).await;
+    record(
+        Feature::Requests,
+        started,
+        if requested {
+            Outcome::Passed
+        } else {
+            Outcome::Failed("the date wasn't requested again".to_string())
+        },
+    );
+
+    SelfTestReport {
+        target: ds.state.target(),
+        checks,
+    }
+}
+
+/// Polls `check` until it returns true, returning false if it didn't within `timeout`
+async fn wait_until(timeout: Duration, check: impl AsyncFn() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if check().await {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,87 @@
+//! Runtime settings that outlive a single run of the driver station
+
+use super::config::{JoystickMapping, PracticeTiming};
+use crate::Alliance;
+#[cfg(feature = "config")]
+use crate::Result;
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "config")]
+use std::path::Path;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+use std::path::PathBuf;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+use tokio::sync::watch;
+
+/// The settings a driver station operator changes while using it
+///
+/// With the `config` feature, `DriverStation::persist_session` saves these to a file whenever they change, and
+/// restores them the next time the driver station starts.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize), serde(default))]
+pub struct Session {
+    pub alliance: Alliance,
+    /// The last game specific message sent to the robot
+    pub game_data: Option<String>,
+    pub practice: PracticeTiming,
+    /// Devices the operator locked to joystick slots
+    pub joystick_locks: Vec<JoystickMapping>,
+    /// The mapping profile the locks came from, if they haven't been changed since it was chosen
+    pub profile: Option<String>,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session {
+            alliance: Alliance::new_red(1),
+            game_data: None,
+            practice: PracticeTiming::default(),
+            joystick_locks: Vec::new(),
+            profile: None,
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl Session {
+    /// Reads a session saved with [`Session::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Session> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads the session saved at `path` on the blocking pool, if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn load_saved(path: PathBuf) -> Result<Option<Session>> {
+        tokio::task::spawn_blocking(move || path.exists().then(|| Session::load(&path)).transpose())
+            .await?
+    }
+}
+
+/// Saves each session sent through `sessions` to `path`, until the sender is dropped
+///
+/// The files are written on the blocking pool, so a slow disk doesn't hold up the driver station. Sessions that change
+/// while one is being written are only saved once, as the latest of them.
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub(crate) async fn save_changes(path: PathBuf, mut sessions: watch::Receiver<Session>) {
+    loop {
+        let session = sessions.borrow_and_update().clone();
+        let target = path.clone();
+        let saved = tokio::task::spawn_blocking(move || session.save(target))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|saved| saved);
+        if let Err(e) = saved {
+            warn!("Failed to save session to {}: {}", path.display(), e);
+        }
+        if sessions.changed().await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{JoystickDescriptor, JoystickValue};
+
+/// The number of axes, buttons, and POVs of a joystick
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct JoystickShape {
+    pub axes: u8,
+    pub buttons: u8,
+    pub povs: u8,
+}
+
+impl JoystickShape {
+    /// Returns the counts declared by `descriptor`
+    pub fn declared(descriptor: &JoystickDescriptor) -> JoystickShape {
+        JoystickShape {
+            axes: descriptor.axis_types.len().min(u8::MAX as usize) as u8,
+            buttons: descriptor.buttons,
+            povs: descriptor.povs,
+        }
+    }
+
+    /// Returns the counts needed to read every value: one more than the highest axis and POV id, and the highest
+    /// button id, as buttons are numbered from 1
+    pub fn of(values: &[JoystickValue]) -> JoystickShape {
+        let mut shape = JoystickShape::default();
+        for value in values {
+            match *value {
+                JoystickValue::Axis { id, .. } => shape.axes = shape.axes.max(id.saturating_add(1)),
+                JoystickValue::Button { id, .. } => shape.buttons = shape.buttons.max(id),
+                JoystickValue::POV { id, .. } => shape.povs = shape.povs.max(id.saturating_add(1)),
+            }
+        }
+        shape
+    }
+
+    /// Returns whether a joystick of this shape only sets inputs that `declared` has
+    #[inline(always)]
+    pub const fn fits(self, declared: JoystickShape) -> bool {
+        self.axes <= declared.axes && self.buttons <= declared.buttons && self.povs <= declared.povs
+    }
+}
+
+/// A joystick sent with inputs its descriptor doesn't declare, which robot code won't read as expected
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    /// The slot of the joystick
+    pub slot: usize,
+    /// The counts declared for the slot, or `None` if it wasn't described
+    pub declared: Option<JoystickShape>,
+    /// The counts needed by the values sent
+    pub sent: JoystickShape,
+}
+
+/// Compares the joysticks being sent against the shapes declared for their slots, indexed by slot
+///
+/// Nothing is compared until shapes are declared, and slots sent without any values always match.
+pub(crate) fn find_mismatches(
+    joysticks: &[Vec<JoystickValue>],
+    declared: &[Option<JoystickShape>],
+) -> Vec<ShapeMismatch> {
+    if declared.is_empty() {
+        return Vec::new();
+    }
+    joysticks
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, values)| {
+            let sent = JoystickShape::of(values);
+            let declared = declared.get(slot).copied().flatten();
+            let fits = match declared {
+                Some(declared) => sent.fits(declared),
+                None => values.is_empty(),
+            };
+            (!fits).then_some(ShapeMismatch {
+                slot,
+                declared,
+                sent,
+            })
+        })
+        .collect()
+}
+
+/// Returns the shape declared for each slot, indexed by slot
+pub(crate) fn declared_shapes(descriptors: &[JoystickDescriptor]) -> Vec<Option<JoystickShape>> {
+    let mut shapes = Vec::new();
+    for descriptor in descriptors {
+        let slot = usize::from(descriptor.index);
+        if shapes.len() <= slot {
+            shapes.resize(slot + 1, None);
+        }
+        shapes[slot] = Some(JoystickShape::declared(descriptor));
+    }
+    shapes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Controller;
+
+    #[test]
+    fn verify_off_by_one_axis_is_caught() {
+        let declared = declared_shapes(&[Controller::Xbox.descriptor(0)]);
+        let axes = declared[0].unwrap().axes;
+        let fits = vec![JoystickValue::Axis {
+            id: axes - 1,
+            value: 0.5,
+        }];
+        assert!(find_mismatches(&[fits], &declared).is_empty());
+
+        // The undescribed slot is only a mismatch once it's sent values
+        let off = vec![JoystickValue::Axis {
+            id: axes,
+            value: 0.5,
+        }];
+        assert_eq!(
+            find_mismatches(&[off.clone(), Vec::new()], &declared).len(),
+            1
+        );
+        let mismatches = find_mismatches(
+            &[
+                off,
+                vec![JoystickValue::Button {
+                    id: 1,
+                    pressed: true,
+                }],
+            ],
+            &declared,
+        );
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].sent.axes, axes + 1);
+        assert_eq!(mismatches[1].declared, None);
+
+        // Without descriptors the protocol's own limits are all that's checked
+        assert!(
+            find_mismatches(&[vec![JoystickValue::Axis { id: 5, value: 0.0 }]], &[]).is_empty()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use super::clock::Clock;
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// A clock driven by an external simulator, so the driver station's timing can follow it in lockstep
+///
+/// Set with [`DriverStation::set_sim_clock`](crate::DriverStation::set_sim_clock), and only used in
+/// [`DsMode::Simulation`](crate::DsMode::Simulation). The match timer then runs on this clock instead of real time,
+/// and the robot isn't declared disconnected while the clock is paused. Clones share the same time.
+///
+/// As a [`Clock`], the time on the clock is counted from when it was created, so timestamps and timeouts follow the
+/// simulator too.
+#[derive(Debug, Clone)]
+pub struct SimClock {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    time: Mutex<ClockTime>,
+    /// The wall-clock and monotonic times when the clock was created, at zero
+    created: (DateTime<Utc>, Instant),
+    /// Wakes tasks sleeping on the clock when it's changed by hand
+    changed: watch::Sender<()>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ClockTime {
+    /// The time on the clock when it was last paused, stepped, or set
+    base: Duration,
+    /// When the clock was resumed, if it's running
+    running_since: Option<Instant>,
+}
+
+impl ClockTime {
+    fn now(&self) -> Duration {
+        self.base
+            + self
+                .running_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
+
+impl SimClock {
+    /// Creates a clock at zero that runs in real time until paused
+    pub fn new() -> SimClock {
+        SimClock::with_state(Some(Instant::now()))
+    }
+
+    /// Creates a clock at zero that only moves when stepped or set
+    pub fn paused() -> SimClock {
+        SimClock::with_state(None)
+    }
+
+    fn with_state(running_since: Option<Instant>) -> SimClock {
+        SimClock {
+            inner: Arc::new(Inner {
+                time: Mutex::new(ClockTime {
+                    base: Duration::ZERO,
+                    running_since,
+                }),
+                created: (Utc::now(), Instant::now()),
+                changed: watch::channel(()).0,
+            }),
+        }
+    }
+
+    /// Returns the time on the clock
+    pub fn now(&self) -> Duration {
+        self.read().now()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.read().running_since.is_none()
+    }
+
+    pub fn pause(&self) {
+        self.update(|time| {
+            time.base = time.now();
+            time.running_since = None;
+        });
+    }
+
+    /// Lets the clock run in real time from where it was paused
+    pub fn resume(&self) {
+        self.update(|time| {
+            if time.running_since.is_none() {
+                time.running_since = Some(Instant::now());
+            }
+        });
+    }
+
+    /// Moves the clock forward by `by`, such as by the length of a simulator's step
+    pub fn step(&self, by: Duration) {
+        self.update(|time| time.base += by);
+    }
+
+    /// Sets the clock to `now`, such as to the time reported by the simulator. The clock can be moved backwards
+    pub fn set(&self, now: Duration) {
+        self.update(|time| {
+            time.base = now;
+            if time.running_since.is_some() {
+                time.running_since = Some(Instant::now());
+            }
+        });
+    }
+
+    /// Waits until the clock reaches `target`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn sleep_until(&self, target: Duration) {
+        loop {
+            // Subscribing before reading the time means a change in between still wakes this task
+            let mut changed = self.inner.changed.subscribe();
+            let time = self.read();
+            let now = time.now();
+            if now >= target {
+                return;
+            }
+            if time.running_since.is_some() {
+                tokio::select! {
+                    _ = tokio::time::sleep(target - now) => {}
+                    _ = changed.changed() => {}
+                }
+            } else {
+                let _ = changed.changed().await;
+            }
+        }
+    }
+
+    fn read(&self) -> ClockTime {
+        match self.inner.time.lock() {
+            Ok(time) => *time,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ClockTime)) {
+        match self.inner.time.lock() {
+            Ok(mut time) => f(&mut time),
+            Err(poisoned) => f(&mut poisoned.into_inner()),
+        }
+        self.inner.changed.send_replace(());
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.inner.created.0 + SimClock::now(self)
+    }
+
+    fn instant(&self) -> Instant {
+        self.inner.created.1 + SimClock::now(self)
+    }
+
+    fn timebase(&self) -> Option<SimClock> {
+        Some(self.clone())
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> SimClock {
+        SimClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_paused_clock_only_moves_when_stepped() {
+        let clock = SimClock::paused();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), Duration::ZERO);
+
+        clock.step(Duration::from_millis(20));
+        clock.step(Duration::from_millis(20));
+        assert_eq!(clock.now(), Duration::from_millis(40));
+
+        clock.set(Duration::from_secs(3));
+        assert_eq!(clock.clone().now(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn verify_clock_times_follow_the_simulator() {
+        let clock = SimClock::paused();
+        let (wall, instant) = (Clock::now(&clock), clock.instant());
+        clock.step(Duration::from_millis(20));
+        assert_eq!(
+            Clock::now(&clock) - wall,
+            chrono::Duration::milliseconds(20)
+        );
+        assert_eq!(clock.instant() - instant, Duration::from_millis(20));
+        assert_eq!(clock.timebase().unwrap().now(), Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,121 @@
+use super::state::Mode;
+use crate::proto::udp::inbound::types::Trace;
+
+use std::fmt;
+use std::time::Duration;
+
+/// Everything a driver station UI usually shows, read at once so that the values are consistent with each other
+///
+/// Returned by [`DriverStation::status_snapshot`](crate::DriverStation::status_snapshot).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DsSnapshot {
+    /// The last battery voltage reported by the robot
+    pub battery_voltage: f32,
+    pub mode: Mode,
+    pub enabled: bool,
+    /// Whether the robot reported being enabled
+    pub robot_enabled: bool,
+    pub estopped: bool,
+    /// Whether the roboRIO is reporting a brownout
+    pub brownout: bool,
+    /// The last trace reported by the robot
+    pub trace: Trace,
+    /// Whether status packets are being received from the robot
+    pub connected: bool,
+    /// Whether the TCP connection to the robot is open
+    pub tcp_connected: bool,
+    /// Whether the driver station is running without the TCP connection, see [`DsConfig::udp_only`](crate::DsConfig::udp_only)
+    pub udp_only: bool,
+    /// The round trip time of the most recently answered control packet
+    pub latency: Option<Duration>,
+    /// The time left in the practice match, if the timer is running
+    pub match_time_remaining: Option<Duration>,
+}
+
+impl DsSnapshot {
+    pub const fn connection(&self) -> ConnectionState {
+        if !self.connected {
+            ConnectionState::Disconnected
+        } else if self.tcp_connected || self.udp_only {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::NoTcp
+        }
+    }
+}
+
+/// Formats the status message the official driver station shows, such as `No Robot Communication` or
+/// `Teleoperated Enabled`
+impl fmt::Display for DsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.connected {
+            f.write_str("No Robot Communication")
+        } else if self.estopped {
+            f.write_str("Emergency Stopped")
+        } else if self.brownout {
+            f.write_str("Voltage Brownout")
+        } else if !self.trace.is_code_started() {
+            f.write_str("No Robot Code")
+        } else {
+            let state = if self.robot_enabled {
+                "Enabled"
+            } else {
+                "Disabled"
+            };
+            write!(f, "{} {}", self.mode, state)
+        }
+    }
+}
+
+/// How much of the connection to the robot is up
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// No status packets are being received
+    Disconnected,
+    /// Status packets are being received, but the TCP connection isn't open yet
+    NoTcp,
+    /// Status packets are being received, and the TCP connection is open unless running UDP-only
+    Connected,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConnectionState::Disconnected => "No Robot Communication",
+            ConnectionState::NoTcp => "Communications, No TCP",
+            ConnectionState::Connected => "Communications",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_status_message() {
+        let mut snapshot = DsSnapshot {
+            battery_voltage: 12.5,
+            mode: Mode::Autonomous,
+            enabled: true,
+            robot_enabled: true,
+            estopped: false,
+            brownout: false,
+            trace: Trace::ROBOT_CODE | Trace::AUTONOMOUS,
+            connected: true,
+            tcp_connected: false,
+            udp_only: false,
+            latency: None,
+            match_time_remaining: None,
+        };
+        assert_eq!(snapshot.to_string(), "Autonomous Enabled");
+        assert_eq!(snapshot.trace.to_string(), "Autonomous");
+        assert_eq!(snapshot.connection(), ConnectionState::NoTcp);
+
+        snapshot.trace = Trace::IS_ROBORIO;
+        assert_eq!(snapshot.to_string(), "No Robot Code");
+        snapshot.connected = false;
+        assert_eq!(snapshot.to_string(), "No Robot Communication");
+        assert_eq!(snapshot.connection().to_string(), "No Robot Communication");
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{JoystickOutput, JoystickValue};
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// The name [`DriverStation::set_joystick_supplier`](crate::DriverStation::set_joystick_supplier) registers its
+/// closure under
+pub const SUPPLIER_SOURCE: &str = "supplier";
+
+/// Something the driver station can read joystick values from, such as a gamepad library or keyboard bindings
+///
+/// Sources are added with [`DriverStation::add_joystick_source`](crate::DriverStation::add_joystick_source), and
+/// every control packet uses the highest priority source that's available. Closures returning
+/// `Option<Vec<Vec<JoystickValue>>>` are sources. Sources driving devices with force feedback can also implement
+/// [`JoystickSource::output`] to rumble them.
+pub trait JoystickSource: Send + Sync {
+    /// Returns the values of each joystick slot, or `None` while the source has nothing to give, such as when its
+    /// gamepad is unplugged, so that the source below it is used instead
+    ///
+    /// This is called from the send loop without any driver station state locked, and should return quickly.
+    fn poll(&self) -> Option<Vec<Vec<JoystickValue>>>;
+
+    /// Overwrites `joysticks` with the values of each joystick slot, returning `false` while the source has nothing to
+    /// give
+    ///
+    /// `joysticks` holds the values of an earlier packet, so a source that clears and refills its vectors doesn't
+    /// allocate on every packet. The default replaces them with the result of [`JoystickSource::poll`].
+    fn poll_into(&self, joysticks: &mut Vec<Vec<JoystickValue>>) -> bool {
+        match self.poll() {
+            Some(polled) => {
+                *joysticks = polled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Receives what should be shown on the joystick in `slot`, one this source supplied: the rumble and output bits
+    /// robot code set, with the driver station's own rumble, such as [`EndgameRumble`](crate::EndgameRumble), mixed in
+    ///
+    /// Only changes are given. This is called from the network tasks, and should return quickly. The default ignores
+    /// them, for sources without force feedback.
+    fn output(&self, _slot: usize, _output: JoystickOutput) {}
+}
+
+impl<F> JoystickSource for F
+where
+    F: Fn() -> Option<Vec<Vec<JoystickValue>>> + Send + Sync,
+{
+    fn poll(&self) -> Option<Vec<Vec<JoystickValue>>> {
+        self()
+    }
+}
+
+#[derive(Clone)]
+struct Registered {
+    name: Arc<str>,
+    priority: i32,
+    source: Arc<dyn JoystickSource>,
+}
+
+/// What polling the sources gave
+#[derive(Debug, Clone, PartialEq)]
+pub enum Polled {
+    /// The values of the named source, the first available one
+    Values(Arc<str>, Vec<Vec<JoystickValue>>),
+    /// The named source panicked, which is a joystick fault rather than a reason to fall back
+    Panicked(Arc<str>),
+    /// No source was available
+    Unavailable,
+}
+
+impl Polled {
+    /// Returns the name of the source used, if there was one
+    pub const fn source(&self) -> Option<&Arc<str>> {
+        match self {
+            Polled::Values(name, _) | Polled::Panicked(name) => Some(name),
+            Polled::Unavailable => None,
+        }
+    }
+}
+
+/// The joystick sources, highest priority first
+///
+/// The list is shared, so the send loop can take it and poll without holding the lock on the send state.
+#[derive(Clone, Default)]
+pub struct JoystickSources {
+    sources: Option<Arc<Vec<Registered>>>,
+}
+
+impl JoystickSources {
+    pub const fn new() -> JoystickSources {
+        JoystickSources { sources: None }
+    }
+
+    fn list(&self) -> &[Registered] {
+        self.sources.as_deref().map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `source`, replacing any source with the same name. Sources of equal priority are polled in the order they
+    /// were added
+    pub fn add(&mut self, name: &str, priority: i32, source: Arc<dyn JoystickSource>) {
+        let mut sources: Vec<Registered> = self
+            .list()
+            .iter()
+            .filter(|registered| &*registered.name != name)
+            .cloned()
+            .collect();
+        let at = sources.partition_point(|registered| registered.priority >= priority);
+        sources.insert(
+            at,
+            Registered {
+                name: name.into(),
+                priority,
+                source,
+            },
+        );
+        self.sources = Some(Arc::new(sources));
+    }
+
+    /// Removes the named source, returning whether there was one
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.list().len();
+        let sources: Vec<Registered> = self
+            .list()
+            .iter()
+            .filter(|registered| &*registered.name != name)
+            .cloned()
+            .collect();
+        let removed = sources.len() != before;
+        self.sources = Some(Arc::new(sources));
+        removed
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.list().is_empty()
+    }
+
+    /// Returns the name and priority of each source, highest priority first
+    pub fn names(&self) -> Vec<(String, i32)> {
+        self.list()
+            .iter()
+            .map(|registered| (registered.name.to_string(), registered.priority))
+            .collect()
+    }
+
+    /// Gives `output` to the named source, if it's still registered
+    pub fn output(&self, source: &str, slot: usize, output: JoystickOutput) {
+        if let Some(registered) = self
+            .list()
+            .iter()
+            .find(|registered| &*registered.name == source)
+        {
+            // A panicking source is already reported when it's polled, and mustn't take down the caller
+            let _ =
+                panic::catch_unwind(AssertUnwindSafe(|| registered.source.output(slot, output)));
+        }
+    }
+
+    /// Polls the sources from the highest priority down, stopping at the first that's available
+    pub fn poll(&self) -> Polled {
+        self.poll_with(Vec::new())
+    }
+
+    /// Polls the sources like [`JoystickSources::poll`], giving them `joysticks` to fill in place
+    pub fn poll_with(&self, mut joysticks: Vec<Vec<JoystickValue>>) -> Polled {
+        for registered in self.list() {
+            match panic::catch_unwind(AssertUnwindSafe(|| {
+                registered.source.poll_into(&mut joysticks)
+            })) {
+                Ok(true) => return Polled::Values(registered.name.clone(), joysticks),
+                Ok(false) => {}
+                Err(_) => return Polled::Panicked(registered.name.clone()),
+            }
+        }
+        Polled::Unavailable
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn source(values: Option<Vec<Vec<JoystickValue>>>) -> Arc<dyn JoystickSource> {
+        Arc::new(move || values.clone())
+    }
+
+    #[test]
+    fn verify_sources_fall_back_by_priority() {
+        let pressed = vec![vec![JoystickValue::Button {
+            id: 1,
+            pressed: true,
+        }]];
+        let mut sources = JoystickSources::new();
+        sources.add("keyboard", 0, source(Some(pressed.clone())));
+        sources.add("gamepad", 10, source(None));
+        assert_eq!(
+            sources.poll(),
+            Polled::Values("keyboard".into(), pressed.clone())
+        );
+
+        // Adding a source again replaces it, at its new priority
+        sources.add("gamepad", 10, source(Some(Vec::new())));
+        assert_eq!(sources.poll(), Polled::Values("gamepad".into(), Vec::new()));
+        assert_eq!(
+            sources.names(),
+            vec![("gamepad".to_string(), 10), ("keyboard".to_string(), 0)]
+        );
+
+        assert!(sources.remove("gamepad"));
+        assert!(!sources.remove("gamepad"));
+        sources.add(
+            "broken",
+            5,
+            Arc::new(|| -> Option<Vec<Vec<JoystickValue>>> { panic!("unplugged") }),
+        );
+        assert_eq!(sources.poll(), Polled::Panicked("broken".into()));
+
+        sources.remove("broken");
+        sources.remove("keyboard");
+        assert_eq!(sources.poll(), Polled::Unavailable);
+    }
+
+    /// Refills the vectors it's given rather than returning new ones
+    struct Refilling;
+
+    impl JoystickSource for Refilling {
+        fn poll(&self) -> Option<Vec<Vec<JoystickValue>>> {
+            let mut joysticks = Vec::new();
+            self.poll_into(&mut joysticks);
+            Some(joysticks)
+        }
+
+        fn poll_into(&self, joysticks: &mut Vec<Vec<JoystickValue>>) -> bool {
+            joysticks.resize_with(1, Vec::new);
+            joysticks[0].clear();
+            joysticks[0].push(JoystickValue::Axis { id: 0, value: 0.5 });
+            true
+        }
+    }
+
+    #[test]
+    fn verify_sources_fill_the_buffer_given() {
+        let mut sources = JoystickSources::new();
+        sources.add("refilling", 0, Arc::new(Refilling));
+        let buffer = vec![Vec::with_capacity(8)];
+        let Polled::Values(_, joysticks) = sources.poll_with(buffer) else {
+            panic!("the source is available");
+        };
+        assert_eq!(
+            joysticks,
+            vec![vec![JoystickValue::Axis { id: 0, value: 0.5 }]]
+        );
+        assert_eq!(joysticks[0].capacity(), 8);
+
+        // Sources that only implement poll still replace the buffer
+        sources.add("closure", 1, source(Some(Vec::new())));
+        assert_eq!(
+            sources.poll_with(joysticks),
+            Polled::Values("closure".into(), Vec::new())
+        );
+    }
+}
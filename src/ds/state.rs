@@ -1,18 +1,47 @@
-use super::JoystickValue;
-
-use crate::TcpPacket;
-use crate::ds::state::recv::{RecvState, TcpState};
+use crate::ds::aggregate::{TelemetryWindow, WINDOW_CAPACITY};
+use crate::ds::alert::{Alert, AlertSink};
+use crate::ds::alliance::{AllianceSelection, AllianceSource};
+use crate::ds::changes::{CHANGE_CAPACITY, PacketSent, StateChange};
+use crate::ds::clock::{Clock, SystemClock};
+use crate::ds::command::{COMMAND_CAPACITY, Command, Commander};
+use crate::ds::events::{Event, EventLog, TimedEvent};
+use crate::ds::inputs::InputChange;
+use crate::ds::nt::NetworkTables;
+use crate::ds::outputs::OutputMixer;
+use crate::ds::report::ShutdownReason;
+use crate::ds::rumble::Rumble;
+use crate::ds::simclock::SimClock;
+use crate::ds::state::link::LinkStatus;
 use crate::ds::state::send::SendState;
+use crate::ds::state::stats::StatsState;
+use crate::ds::state::tcp::TcpState;
+use crate::ds::state::telemetry::Telemetry;
+use crate::ds::summary::SummaryRecorder;
+use crate::ds::tasks::TaskActivity;
+use crate::ds::watchdog::Watchdog;
 use crate::proto::udp::inbound::types::Status;
 use crate::proto::udp::outbound::types::{Alliance, Control};
-use std::fmt::Debug;
-use tokio::sync::RwLock;
+use crate::proto::{Direction, RawTap};
+use crate::{JoystickOutput, TcpPacket, UdpControlPacket};
+use chrono::{DateTime, Utc};
+use std::fmt::{self, Debug};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as SyncMutex, RwLock as SyncRwLock};
+use std::time::Instant;
+use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc};
 
-mod recv;
-mod send;
+mod link;
+pub(crate) use self::link::Arrival;
+pub(crate) mod send;
+pub(crate) mod stats;
+mod tcp;
+pub(crate) use self::tcp::QueuedTag;
+mod telemetry;
 
-type JoystickSupplier = dyn Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static;
 type TcpConsumer = dyn FnMut(TcpPacket) + Send + Sync + 'static;
+type PacketTap = dyn Fn(Direction, &[u8], Instant) + Send + Sync + 'static;
 
 /// The operating mode of the driver station
 ///
@@ -28,44 +57,483 @@ pub enum DsMode {
 pub struct DsState {
     /// The state associated with the sending UDP socket
     send_state: RwLock<SendState>,
-    /// The state associated with the receiving UDP socket
-    recv_state: RwLock<RecvState>,
+    /// What the robot reports about itself
+    telemetry: RwLock<Telemetry>,
+    /// How status packets from the robot are arriving. Locked separately from the telemetry, as it's updated with every
+    /// packet, even those whose telemetry is out of date
+    link: RwLock<LinkStatus>,
     /// The state associated with the TCP socket
     tcp_state: RwLock<TcpState>,
+    /// Counters describing the health of the link
+    stats_state: RwLock<StatsState>,
+    /// The most recent notable events
+    events: RwLock<EventLog>,
+    /// An optional callback that observes raw packets. This is a blocking lock as it's used from within the codecs
+    packet_tap: SyncRwLock<Option<Box<PacketTap>>>,
+    /// The ip packets are currently sent to
+    target: SyncRwLock<String>,
+    /// The address the send loop's socket was last connected to, kept so that dropping doesn't have to look it up
+    robot_addr: SyncRwLock<Option<SocketAddr>>,
+    /// An optional NetworkTables client that follows the target
+    network_tables: SyncRwLock<Option<Arc<dyn NetworkTables>>>,
+    /// What is given back to the joysticks through the source they're read from
+    outputs: SyncMutex<OutputMixer>,
+    /// An optional receiver of alerts derived from events
+    alert_sink: SyncRwLock<Option<Box<dyn AlertSink>>>,
+    /// Notifies subscribers of changes to the state
+    changes: broadcast::Sender<StateChange>,
+    /// Notifies subscribers of button edges and axis moves in the joystick values sent
+    inputs: broadcast::Sender<InputChange>,
+    /// Commands queued for the send loop, by the driver station's setters and commanders alike. The receiver is locked
+    /// while commands are applied, so they're applied in the order they were queued
+    commands_tx: mpsc::Sender<Command>,
+    commands_rx: Mutex<mpsc::Receiver<Command>>,
+    /// The packets of a new control sink, until the send loop picks them up
+    manual_rx: SyncMutex<Option<mpsc::Receiver<UdpControlPacket>>>,
+    /// Whether the send loop is sending the packets of a control sink instead of its own
+    manual: AtomicBool,
+    /// The stations asked for by each source, of which one is sent to the robot
+    alliances: SyncMutex<AllianceSelection>,
+    /// Why the connection tasks died, if they hit an error they can't recover from
+    shutdown_reason: SyncRwLock<Option<ShutdownReason>>,
+    /// Whether enabling is refused while another driver station seems to be controlling the robot
+    refuse_enable_on_conflict: AtomicBool,
+    /// Whether the TCP connection is left closed
+    udp_only: AtomicBool,
+    /// Notifies subscribers of control packets being sent
+    packets: broadcast::Sender<PacketSent>,
+    /// Only one in this many packets is sent to subscribers
+    packet_sample: AtomicU32,
+    /// Sends summaries of the telemetry to subscribers
+    windows: broadcast::Sender<TelemetryWindow>,
+    /// When each task last made progress
+    activity: SyncRwLock<Vec<TaskActivity>>,
+    /// A clock driven by an external simulator, used instead of real time in simulation mode
+    sim_clock: SyncRwLock<Option<SimClock>>,
+    /// Mirrors the DS mode of the send state, so the clock can be chosen without awaiting
+    simulating: AtomicBool,
+    /// Where timestamps and the date sent to the roboRIO come from
+    clock: SyncRwLock<Arc<dyn Clock>>,
+    /// Disables the robot when the application stops feeding it. Shared with commanders
+    watchdog: Arc<Watchdog>,
 }
 
 impl DsState {
-    #[inline(always)]
-    pub const fn new(alliance: Alliance) -> DsState {
+    pub fn new(alliance: Alliance, target: String) -> DsState {
         let send_state = RwLock::const_new(SendState::new(alliance));
-        let recv_state = RwLock::const_new(RecvState::new());
-        let tcp_state = RwLock::const_new(TcpState::new());
+        let tcp_state = RwLock::new(TcpState::new());
+        let stats_state = RwLock::const_new(StatsState::new());
+        let (commands_tx, commands_rx) = mpsc::channel(COMMAND_CAPACITY);
 
         DsState {
             send_state,
-            recv_state,
+            telemetry: RwLock::new(Telemetry::new()),
+            link: RwLock::new(LinkStatus::new()),
             tcp_state,
+            stats_state,
+            events: RwLock::const_new(EventLog::new()),
+            packet_tap: SyncRwLock::new(None),
+            target: SyncRwLock::new(target),
+            robot_addr: SyncRwLock::new(None),
+            network_tables: SyncRwLock::new(None),
+            outputs: SyncMutex::new(OutputMixer::default()),
+            alert_sink: SyncRwLock::new(None),
+            changes: broadcast::channel(CHANGE_CAPACITY).0,
+            inputs: broadcast::channel(CHANGE_CAPACITY).0,
+            commands_tx,
+            commands_rx: Mutex::new(commands_rx),
+            manual_rx: SyncMutex::new(None),
+            manual: AtomicBool::new(false),
+            alliances: SyncMutex::new(AllianceSelection::new(alliance)),
+            shutdown_reason: SyncRwLock::new(None),
+            refuse_enable_on_conflict: AtomicBool::new(false),
+            udp_only: AtomicBool::new(false),
+            packets: broadcast::channel(CHANGE_CAPACITY).0,
+            packet_sample: AtomicU32::new(1),
+            windows: broadcast::channel(WINDOW_CAPACITY).0,
+            activity: SyncRwLock::new(Vec::new()),
+            sim_clock: SyncRwLock::new(None),
+            simulating: AtomicBool::new(false),
+            clock: SyncRwLock::new(Arc::new(SystemClock)),
+            watchdog: Arc::new(Watchdog::new()),
+        }
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        match self.clock.read() {
+            Ok(clock) => clock.clone(),
+            Err(_) => Arc::new(SystemClock),
+        }
+    }
+
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        if let Ok(mut current) = self.clock.write() {
+            *current = clock;
+        }
+    }
+
+    /// Returns the wall-clock time of the driver station's clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.with_clock(|clock| clock.now())
+    }
+
+    /// Returns the monotonic time of the driver station's clock
+    pub fn instant(&self) -> Instant {
+        self.with_clock(|clock| clock.instant())
+    }
+
+    /// Reads the simulator's clock in simulation mode if one is set, otherwise the driver station's clock
+    fn with_clock<T>(&self, read: impl FnOnce(&dyn Clock) -> T) -> T {
+        match self.sim_clock() {
+            Some(clock) => read(&clock),
+            None => read(&*self.clock()),
+        }
+    }
+
+    /// Returns the simulator's clock, if one is set and the driver station is in simulation mode
+    pub fn sim_clock(&self) -> Option<SimClock> {
+        if !self.simulating.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.sim_clock.read().ok()?.clone()
+    }
+
+    pub fn set_sim_clock(&self, clock: Option<SimClock>) {
+        if let Ok(mut current) = self.sim_clock.write() {
+            *current = clock;
+        }
+    }
+
+    pub fn set_simulating(&self, simulating: bool) {
+        self.simulating.store(simulating, Ordering::Relaxed);
+    }
+
+    /// Records that the task called `name` is making progress
+    pub fn heartbeat(&self, name: &'static str) {
+        let now = Instant::now();
+        if let Ok(mut activity) = self.activity.write() {
+            match activity.iter_mut().find(|task| task.name == name) {
+                Some(task) => task.last_active = now,
+                None => activity.push(TaskActivity {
+                    name,
+                    last_active: now,
+                }),
+            }
+        }
+    }
+
+    pub fn task_activity(&self) -> Vec<TaskActivity> {
+        self.activity
+            .read()
+            .map(|activity| activity.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns a handle that queues commands for the send loop
+    pub fn commander(&self) -> Commander {
+        Commander::new(self.commands_tx.clone(), self.watchdog.clone())
+    }
+
+    #[inline(always)]
+    pub fn commands_tx(&self) -> &mpsc::Sender<Command> {
+        &self.commands_tx
+    }
+
+    #[inline(always)]
+    pub fn commands(&self) -> &Mutex<mpsc::Receiver<Command>> {
+        &self.commands_rx
+    }
+
+    /// Returns the stations asked for by each source
+    pub fn alliances(&self) -> AllianceSelection {
+        match self.alliances.lock() {
+            Ok(alliances) => alliances.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    /// Sets the station asked for by `source`, returning the station in use if that changed it, see
+    /// [`AllianceSelection::set`]
+    pub fn set_alliance_source(
+        &self,
+        source: AllianceSource,
+        alliance: Option<Alliance>,
+    ) -> Option<(Alliance, AllianceSource)> {
+        match self.alliances.lock() {
+            Ok(mut alliances) => alliances.set(source, alliance),
+            Err(e) => e.into_inner().set(source, alliance),
+        }
+    }
+
+    #[inline(always)]
+    pub fn watchdog(&self) -> &Watchdog {
+        &self.watchdog
+    }
+
+    /// Passes the packets of a new control sink to the send loop, replacing those of any sink before it
+    pub fn set_manual_rx(&self, rx: mpsc::Receiver<UdpControlPacket>) {
+        if let Ok(mut manual) = self.manual_rx.lock() {
+            *manual = Some(rx);
+        }
+    }
+
+    /// Takes the packets of a new control sink, if one was created since the send loop last checked
+    pub fn take_manual_rx(&self) -> Option<mpsc::Receiver<UdpControlPacket>> {
+        self.manual_rx.lock().ok()?.take()
+    }
+
+    pub fn manual(&self) -> bool {
+        self.manual.load(Ordering::Relaxed)
+    }
+
+    pub fn set_manual(&self, manual: bool) {
+        self.manual.store(manual, Ordering::Relaxed);
+    }
+
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason.read().ok()?.clone()
+    }
+
+    pub fn set_shutdown_reason(&self, reason: Option<ShutdownReason>) {
+        if let Ok(mut current) = self.shutdown_reason.write() {
+            *current = reason;
+        }
+    }
+
+    pub fn refuse_enable_on_conflict(&self) -> bool {
+        self.refuse_enable_on_conflict.load(Ordering::Relaxed)
+    }
+
+    pub fn set_refuse_enable_on_conflict(&self, refuse: bool) {
+        self.refuse_enable_on_conflict
+            .store(refuse, Ordering::Relaxed);
+    }
+
+    pub fn udp_only(&self) -> bool {
+        self.udp_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_udp_only(&self, udp_only: bool) {
+        self.udp_only.store(udp_only, Ordering::Relaxed);
+    }
+
+    /// Sends `change` to every subscriber
+    pub fn notify(&self, change: StateChange) {
+        // Nobody subscribing isn't an error
+        let _ = self.changes.send(change);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StateChange> {
+        self.changes.subscribe()
+    }
+
+    /// Tells subscribers that the packet numbered `seqnum` was sent, if it's one of those sampled
+    pub fn notify_sent(&self, seqnum: u16, sent: Instant) {
+        let sample = self.packet_sample.load(Ordering::Relaxed).max(1);
+        if self.packets.receiver_count() > 0 && u32::from(seqnum) % sample == 0 {
+            let _ = self.packets.send(PacketSent { seqnum, sent });
+        }
+    }
+
+    pub fn subscribe_packets(&self) -> broadcast::Receiver<PacketSent> {
+        self.packets.subscribe()
+    }
+
+    /// Passes a summary of the telemetry to subscribers. Nobody listening isn't an error
+    pub fn publish_window(&self, window: TelemetryWindow) {
+        let _ = self.windows.send(window);
+    }
+
+    pub fn subscribe_windows(&self) -> broadcast::Receiver<TelemetryWindow> {
+        self.windows.subscribe()
+    }
+
+    pub fn set_packet_sample(&self, every: u32) {
+        self.packet_sample.store(every.max(1), Ordering::Relaxed);
+    }
+
+    pub fn notify_input(&self, change: InputChange) {
+        let _ = self.inputs.send(change);
+    }
+
+    pub fn subscribe_inputs(&self) -> broadcast::Receiver<InputChange> {
+        self.inputs.subscribe()
+    }
+
+    #[inline(always)]
+    pub const fn events(&self) -> &RwLock<EventLog> {
+        &self.events
+    }
+
+    /// Records that `event` just happened
+    pub async fn emit(&self, event: Event) {
+        if let Some(nt) = self.network_tables() {
+            nt.event(&event);
+        }
+        if let Some(alert) = Alert::from_event(&event)
+            && let Ok(sink) = self.alert_sink.read()
+            && let Some(ref sink) = *sink
+        {
+            sink.alert(&alert);
+        }
+
+        if SummaryRecorder::tallies(&event) {
+            self.stats().write().await.summary_event(&event);
+        }
+        self.events.write().await.push(TimedEvent {
+            timestamp: self.now(),
+            event,
+        });
+    }
+
+    /// Returns the address or host name packets are sent to
+    pub fn target(&self) -> String {
+        self.target
+            .read()
+            .map(|target| target.clone())
+            .unwrap_or_default()
+    }
+
+    /// Records that packets are now sent to `target`, and points the NetworkTables client at it
+    pub fn set_target(&self, target: &str) {
+        if let Ok(mut current) = self.target.write() {
+            *current = target.to_string();
+        }
+        if let Some(nt) = self.network_tables() {
+            nt.connect(target);
+        }
+    }
+
+    /// Returns the address control packets were last sent to, already resolved from the target
+    pub fn robot_addr(&self) -> Option<SocketAddr> {
+        self.robot_addr.read().ok().and_then(|addr| *addr)
+    }
+
+    pub fn set_robot_addr(&self, addr: Option<SocketAddr>) {
+        if let Ok(mut current) = self.robot_addr.write() {
+            *current = addr;
+        }
+    }
+
+    pub fn set_network_tables(&self, nt: Option<Arc<dyn NetworkTables>>) {
+        if let Some(ref nt) = nt
+            && let Ok(target) = self.target.read()
+        {
+            nt.connect(&target);
+        }
+        if let Ok(mut network_tables) = self.network_tables.write() {
+            *network_tables = nt;
+        }
+    }
+
+    pub fn set_alert_sink(&self, sink: Option<Box<dyn AlertSink>>) {
+        if let Ok(mut alert_sink) = self.alert_sink.write() {
+            *alert_sink = sink;
         }
     }
 
+    /// Records what robot code set on each joystick, passing on what changed
+    pub async fn robot_outputs(&self, outputs: &[JoystickOutput]) {
+        if let Ok(mut mixer) = self.outputs.lock() {
+            mixer.set_robot(outputs);
+        }
+        self.deliver_outputs().await;
+    }
+
+    /// Rumbles the given joystick on top of what robot code asks for, until it's turned off again
+    pub async fn rumble(&self, joystick: usize, rumble: Rumble) {
+        if let Ok(mut mixer) = self.outputs.lock() {
+            mixer.set_rumble(joystick, rumble);
+        }
+        self.deliver_outputs().await;
+    }
+
+    /// Records which source the joysticks are read from, which is given their outputs from then on
+    pub async fn set_output_source(&self, source: Option<Arc<str>>) {
+        if let Ok(mut mixer) = self.outputs.lock() {
+            mixer.set_source(source);
+        }
+        self.deliver_outputs().await;
+    }
+
+    /// Gives the joystick source the outputs that changed. They're kept until there is a source to give them to
+    async fn deliver_outputs(&self) {
+        let sources = self.send().read().await.joystick_sources();
+        // Sources are user code, so they're called without the mixer locked
+        let (source, changes) = {
+            let Ok(mut mixer) = self.outputs.lock() else {
+                return;
+            };
+            let Some(source) = mixer.source().cloned() else {
+                return;
+            };
+            (source, mixer.take_changes())
+        };
+        for (joystick, output) in changes {
+            sources.output(&source, joystick, output);
+        }
+    }
+
+    fn network_tables(&self) -> Option<Arc<dyn NetworkTables>> {
+        self.network_tables.read().ok().and_then(|nt| nt.clone())
+    }
+
+    pub fn set_packet_tap(&self, tap: Option<Box<PacketTap>>) {
+        if let Ok(mut packet_tap) = self.packet_tap.write() {
+            *packet_tap = tap;
+        }
+    }
+
+    /// Passes the given raw packet to the packet tap, if one is set
+    pub fn tap(&self, direction: Direction, bytes: &[u8]) {
+        if let Ok(tap) = self.packet_tap.read()
+            && let Some(ref tap) = *tap
+        {
+            tap(direction, bytes, Instant::now());
+        }
+    }
+
+    /// Creates a hook that codecs can use to feed raw frames to the packet tap
+    pub fn raw_tap(self: &Arc<Self>) -> RawTap {
+        let state = self.clone();
+        Arc::new(move |direction, bytes| state.tap(direction, bytes))
+    }
+
     #[inline(always)]
     pub const fn send(&self) -> &RwLock<SendState> {
         &self.send_state
     }
 
     #[inline(always)]
-    pub const fn recv(&self) -> &RwLock<RecvState> {
-        &self.recv_state
+    pub const fn telemetry(&self) -> &RwLock<Telemetry> {
+        &self.telemetry
+    }
+
+    #[inline(always)]
+    pub const fn link(&self) -> &RwLock<LinkStatus> {
+        &self.link
+    }
+
+    /// Forgets what the robot reported and the sequence of its status packets, such as after it stopped responding.
+    /// Whether it's connected is left to the receiving task
+    pub async fn reset_recv(&self) {
+        self.telemetry.write().await.reset();
+        self.link.write().await.reset();
     }
 
     #[inline(always)]
     pub const fn tcp(&self) -> &RwLock<TcpState> {
         &self.tcp_state
     }
+
+    #[inline(always)]
+    pub const fn stats(&self) -> &RwLock<StatsState> {
+        &self.stats_state
+    }
 }
 
 /// Represents the current Mode that the robot is in. the `Mode` of the robot is considered separately from whether it is enabled or not
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Mode {
     Autonomous,
     Teleoperated,
@@ -76,14 +544,23 @@ impl Mode {
     /// Decodes the mode of the robot from the given status byte
     #[inline]
     pub const fn from_status(status: Status) -> Option<Mode> {
-        if status.contains(Status::TELEOP) {
-            Some(Mode::Teleoperated)
-        } else if status.contains(Status::AUTO) {
-            Some(Mode::Autonomous)
-        } else if status.contains(Status::TEST) {
-            Some(Mode::Test)
-        } else {
-            None
+        // The mode is a 2 bit value rather than flags, teleop being 0 means it can't be checked with `contains`
+        match status.intersection(Status::MODE) {
+            Status::TELEOP => Some(Mode::Teleoperated),
+            Status::TEST => Some(Mode::Test),
+            Status::AUTO => Some(Mode::Autonomous),
+            _ => None,
+        }
+    }
+
+    /// Decodes the mode requested by the given control byte, such as one seen by a packet tap
+    #[inline]
+    pub const fn from_control(control: Control) -> Option<Mode> {
+        match control.intersection(Control::MODE) {
+            Control::TELEOP => Some(Mode::Teleoperated),
+            Control::TEST => Some(Mode::Test),
+            Control::AUTO => Some(Mode::Autonomous),
+            _ => None,
         }
     }
 
@@ -97,3 +574,14 @@ impl Mode {
         }
     }
 }
+
+/// Formats the mode the way the official driver station shows it, such as `Teleoperated`
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Autonomous => "Autonomous",
+            Mode::Teleoperated => "Teleoperated",
+            Mode::Test => "Test",
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use crate::ds::conflict::ControlConflict;
+use crate::ds::timesync::ClockEstimator;
+
+/// How status packets from the robot are arriving: whether they are, their sequence, and the robot's clock
+pub struct LinkStatus {
+    /// Whether status packets are being received. This is owned by the receiving task, and isn't cleared by `reset`
+    connected: bool,
+    /// Whether another driver station seems to be controlling the robot
+    conflict: Option<ControlConflict>,
+    /// Estimates the offset of the robot's clock from the timestamps it sends
+    clock: ClockEstimator,
+    /// The sequence number of the newest status packet
+    last_seqnum: Option<u16>,
+    /// One bit per sequence number at or behind `last_seqnum`, set if it was received. `last_seqnum` is the LSB
+    seen: u64,
+}
+
+impl LinkStatus {
+    pub fn new() -> LinkStatus {
+        LinkStatus {
+            connected: false,
+            conflict: None,
+            clock: ClockEstimator::new(),
+            last_seqnum: None,
+            seen: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.conflict = None;
+        self.clock.reset();
+        self.forget_seqnum();
+    }
+
+    #[inline(always)]
+    pub const fn connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+    }
+
+    #[inline(always)]
+    pub const fn conflict(&self) -> Option<ControlConflict> {
+        self.conflict
+    }
+
+    pub fn set_conflict(&mut self, conflict: Option<ControlConflict>) {
+        self.conflict = conflict;
+    }
+
+    #[inline(always)]
+    pub const fn clock(&self) -> &ClockEstimator {
+        &self.clock
+    }
+
+    #[inline(always)]
+    pub const fn clock_mut(&mut self) -> &mut ClockEstimator {
+        &mut self.clock
+    }
+
+    /// Records the sequence number of a status packet, returning whether it's newer than those already received
+    ///
+    /// Packets delayed on a lossy link can arrive after newer ones, and would set the state back if they were used.
+    /// Some radios also deliver datagrams twice, which would report every change twice.
+    pub fn arrival(&mut self, seqnum: u16) -> Arrival {
+        let Some(last) = self.last_seqnum else {
+            self.last_seqnum = Some(seqnum);
+            self.seen = 1;
+            return Arrival::New;
+        };
+        let behind = last.wrapping_sub(seqnum);
+        // Numbers up to half the sequence behind the last are taken as older, and the rest as newer
+        if behind < 0x8000 {
+            let bit = 1u64.checked_shl(u32::from(behind)).unwrap_or(0);
+            if self.seen & bit != 0 {
+                return Arrival::Duplicate;
+            }
+            self.seen |= bit;
+            return Arrival::Reordered;
+        }
+        let ahead = seqnum.wrapping_sub(last);
+        self.seen = self.seen.checked_shl(u32::from(ahead)).unwrap_or(0) | 1;
+        self.last_seqnum = Some(seqnum);
+        Arrival::New
+    }
+
+    /// Accepts the next status packet whatever its sequence number, for when the sequence restarts
+    pub fn forget_seqnum(&mut self) {
+        self.last_seqnum = None;
+        self.seen = 0;
+    }
+}
+
+/// How a status packet's sequence number relates to those received before it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Arrival {
+    New,
+    /// The packet was already received
+    Duplicate,
+    /// The packet is older than one already received
+    Reordered,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_reordered_and_duplicate_packets_are_refused() {
+        let mut link = LinkStatus::new();
+        assert_eq!(link.arrival(65534), Arrival::New);
+        assert_eq!(link.arrival(1), Arrival::New);
+        assert_eq!(link.arrival(65535), Arrival::Reordered);
+        assert_eq!(link.arrival(65535), Arrival::Duplicate);
+        assert_eq!(link.arrival(65534), Arrival::Duplicate);
+        assert_eq!(link.arrival(1), Arrival::Duplicate);
+        assert_eq!(link.arrival(3), Arrival::New);
+        assert_eq!(link.arrival(2), Arrival::Reordered);
+
+        link.reset();
+        assert_eq!(link.arrival(0), Arrival::New);
+    }
+}
@@ -1,15 +1,36 @@
-use crate::ds::state::{DsMode, JoystickSupplier};
-use crate::proto::udp::outbound::types::{Control, Request};
+use crate::ds::conditioner::LinkConditions;
+use crate::ds::config::{JoystickSuppression, Pacing};
+use crate::ds::inputs::{InputChange, InputTracker};
+use crate::ds::safety::JoystickSafety;
+use crate::ds::shape::JoystickShape;
+use crate::ds::sources::{JoystickSources, SUPPLIER_SOURCE};
+use crate::ds::state::DsMode;
+use crate::ds::validation::JoystickValidation;
+use crate::proto::udp::outbound::types::{Control, RequestFlags};
 use crate::proto::udp::outbound::*;
-use crate::{Alliance, JoystickValue, Joysticks, Mode, Tag, UdpTag};
+use crate::{Alliance, JoystickValue, Joysticks, Mode, UdpTag};
+use bytes::{Bytes, BytesMut};
 use std::f32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The number of axes encoded for each joystick
+pub(crate) const NUM_AXES: u8 = 6;
+/// The number of buttons encoded for each joystick
+pub(crate) const NUM_BUTTONS: u8 = 10;
+/// The number of POVs encoded for each joystick
+pub(crate) const NUM_POVS: u8 = 1;
+/// The room made for each encoded control packet, more than one with six joysticks and the date and time needs
+const ENCODED_CAPACITY: usize = 512;
 
 /// State containing all the data relevant to constructing a UDP control packet to the roboRIO
 pub struct SendState {
     /// The mode the robot should be enabled in
     mode: Mode,
-    /// The current sequence number
+    /// The sequence number of the next control packet
     udp_seqnum: u16,
+    /// The sequence number restarted from when the target changes
+    initial_seqnum: u16,
     /// Whether the robot is enabled
     enabled: bool,
     /// Whether the robot is estopped
@@ -18,31 +39,114 @@ pub struct SendState {
     pub alliance: Alliance,
     /// Any UDP tags that are to be sent with the next UDP control packet
     pending_udp: Vec<UdpTag>,
-    /// An optional source for joystick values that will be encoded and sent with the packet
-    joystick_provider: Option<Box<JoystickSupplier>>,
-    /// Pending reboot or code restart requests
-    pending_request: Option<Request>,
+    /// The tag buffer of a sent packet, reused for the packet after the next so queuing tags doesn't allocate
+    spare_udp: Vec<UdpTag>,
+    /// The buffer control packets are encoded into, whose allocation is reclaimed once the last packet is sent
+    encoded: BytesMut,
+    /// Where joystick values are read from, the first available of which is encoded and sent with the packet
+    joystick_sources: JoystickSources,
+    /// Requests to send with the next control packet
+    pending_request: RequestFlags,
     dsmode: DsMode,
+    /// Artificial impairments applied to outbound packets while in simulation mode
+    link_conditions: LinkConditions,
+    /// Which joystick supplier faults disable the robot
+    joystick_safety: JoystickSafety,
+    /// What is done with joystick values that can't be sent
+    joystick_validation: JoystickValidation,
+    /// Whether control was handed to another driver station, and control packets should no longer be sent
+    released: bool,
+    /// The joystick values most recently queued
+    last_joysticks: Vec<Vec<JoystickValue>>,
+    /// The joystick values queued before the last, given back to the sources to fill in place
+    spare_joysticks: Vec<Vec<JoystickValue>>,
+    /// Bits ORed into the control byte of every packet, for robot controllers that extend the protocol
+    extra_control: u8,
+    /// How accurately control packets are spaced
+    pacing: Pacing,
+    /// Finds the button edges and axis moves in the joystick values sent
+    inputs: InputTracker,
+    /// Whether changing mode while enabled disables the robot first, as the official driver station does
+    disable_on_mode_change: bool,
+    /// How long after a brownout enabling is refused, if at all
+    brownout_cooldown: Option<Duration>,
+    /// Whether packets claim an FMS is attached, so robot code can be tested as if it were at an event
+    fms_attached: bool,
+    /// When joystick values are sent as neutral
+    joystick_suppression: JoystickSuppression,
+    /// Whether trailing joysticks with no values are left out of control packets
+    compact_joysticks: bool,
+    /// The shape of each slot's joystick declared by its descriptor, indexed by slot
+    declared_shapes: Vec<Option<JoystickShape>>,
+    /// How long arming lasts, if enabling requires the robot to be armed first
+    arm_timeout: Option<Duration>,
+    /// When the current arming runs out
+    armed_until: Option<Instant>,
+    /// The number of control packets built with the date and time
+    dates_sent: u64,
 }
 
 impl SendState {
     #[inline(always)]
-    pub const fn new(alliance: Alliance) -> SendState {
+    pub fn new(alliance: Alliance) -> SendState {
         SendState {
             mode: Mode::Autonomous,
             udp_seqnum: 0,
+            initial_seqnum: 0,
             enabled: false,
             estopped: false,
             alliance,
             pending_udp: Vec::new(),
-            joystick_provider: None,
-            pending_request: None,
+            spare_udp: Vec::new(),
+            encoded: BytesMut::new(),
+            joystick_sources: JoystickSources::new(),
+            pending_request: RequestFlags::empty(),
             dsmode: DsMode::Normal,
+            link_conditions: LinkConditions::ideal(),
+            joystick_safety: JoystickSafety {
+                disable_on_panic: true,
+                disable_on_malformed: true,
+                required: Vec::new(),
+            },
+            joystick_validation: JoystickValidation::ClampAndWarn,
+            released: false,
+            last_joysticks: Vec::new(),
+            spare_joysticks: Vec::new(),
+            extra_control: 0,
+            pacing: Pacing::Standard,
+            inputs: InputTracker::new(),
+            disable_on_mode_change: true,
+            brownout_cooldown: None,
+            fms_attached: false,
+            joystick_suppression: JoystickSuppression {
+                autonomous: false,
+                disabled: false,
+            },
+            compact_joysticks: false,
+            declared_shapes: Vec::new(),
+            arm_timeout: None,
+            armed_until: None,
+            dates_sent: 0,
         }
     }
 
-    pub fn request(&mut self, request: Request) {
-        self.pending_request = Some(request);
+    #[inline(always)]
+    pub const fn released(&self) -> bool {
+        self.released
+    }
+
+    pub fn set_released(&mut self, released: bool) {
+        self.released = released;
+    }
+
+    /// Adds `request` to the requests sent with the next control packet
+    pub fn request(&mut self, request: RequestFlags) {
+        self.pending_request |= request;
+    }
+
+    #[inline(always)]
+    pub const fn pending_requests(&self) -> RequestFlags {
+        self.pending_request
     }
 
     pub fn queue_udp(&mut self, tag: UdpTag) {
@@ -54,65 +158,131 @@ impl SendState {
         &self.pending_udp
     }
 
+    /// Adds `supplier` as the [`SUPPLIER_SOURCE`], which is always available, replacing the supplier set before
     pub fn set_joystick_supplier(
         &mut self,
         supplier: impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static,
     ) {
-        self.joystick_provider = Some(Box::new(supplier))
+        self.joystick_sources
+            .add(SUPPLIER_SOURCE, 0, Arc::new(move || Some(supplier())));
+    }
+
+    /// Returns a handle to the joystick sources, so that they can be polled without holding the lock on this state
+    #[inline]
+    pub fn joystick_sources(&self) -> JoystickSources {
+        self.joystick_sources.clone()
+    }
+
+    #[inline(always)]
+    pub fn joystick_sources_mut(&mut self) -> &mut JoystickSources {
+        &mut self.joystick_sources
+    }
+
+    #[inline(always)]
+    pub fn last_joysticks(&self) -> &[Vec<JoystickValue>] {
+        &self.last_joysticks
+    }
+
+    /// Takes the joysticks most recently queued, with their values cleared, to send in place of a source that isn't
+    /// available
+    ///
+    /// The vectors are reused so that sending neutral joysticks doesn't allocate, and are given back when queued.
+    pub fn take_neutral_joysticks(&mut self) -> Vec<Vec<JoystickValue>> {
+        let mut joysticks = std::mem::take(&mut self.last_joysticks);
+        joysticks.iter_mut().for_each(Vec::clear);
+        joysticks
+    }
+
+    /// Takes the vectors of the joysticks queued before the last, for the sources to overwrite rather than allocate
+    #[inline]
+    pub fn take_joystick_buffer(&mut self) -> Vec<Vec<JoystickValue>> {
+        std::mem::take(&mut self.spare_joysticks)
     }
 
     pub fn set_alliance(&mut self, alliance: Alliance) {
         self.alliance = alliance;
     }
 
-    /// Constructs a control packet from the current state
-    ///
-    /// if [self.joystick_provider] is Some, it will be used to construct the joysticks tag
-    /// if [self.request] is Some, its value will be consumed and sent to the roboRIO
-    pub fn control(&mut self) -> UdpControlPacket {
-        if let Some(ref supplier) = self.joystick_provider {
-            let joysticks = supplier();
-
-            // Joystick tags come one after another, iterate over the outer Vec and queue with each loop
-            for joystick in joysticks {
-                let mut axes = vec![0; 6];
-                let mut buttons = vec![false; 10];
-                let mut povs = vec![-1i16];
-
-                for value in joystick {
-                    // If statements bound check to stop it from crashing
-                    match value {
-                        JoystickValue::Button { id, pressed } => {
-                            if id >= 1 && id <= 10 {
-                                let id = id - 1;
-                                buttons.remove(id as usize);
-                                buttons.insert(id as usize, pressed)
-                            }
-                        }
-                        JoystickValue::Axis { id, value } => {
-                            if id <= 5 {
-                                let value = if (value - 1.0).abs() < f32::EPSILON {
-                                    127i8
-                                } else {
-                                    (value * 128f32) as i8
-                                };
-
-                                axes.remove(id as usize);
-                                axes.insert(id as usize, value);
-                            }
-                        }
-                        JoystickValue::POV { id, angle } => {
-                            if id == 0 {
-                                povs.remove(id as usize);
-                                povs.insert(id as usize, angle);
-                            }
+    /// Encodes the given joystick values as joystick tags, and queues them for the next control packet
+    pub fn queue_joysticks(&mut self, joysticks: Vec<Vec<JoystickValue>>) {
+        // Joystick tags come one after another, iterate over the outer Vec and queue with each loop
+        for joystick in &joysticks {
+            let mut tag = Joysticks::neutral(NUM_AXES, NUM_BUTTONS, NUM_POVS);
+
+            for &value in joystick {
+                // Out of range ids are ignored by the setters instead of crashing
+                match value {
+                    JoystickValue::Button { id, pressed } => {
+                        if id >= 1 {
+                            tag.set_button(id as usize - 1, pressed);
                         }
                     }
+                    JoystickValue::Axis { id, value } => {
+                        let value = if (value - 1.0).abs() < f32::EPSILON {
+                            127i8
+                        } else {
+                            (value * 128f32) as i8
+                        };
+
+                        tag.set_axis(id as usize, value);
+                    }
+                    JoystickValue::POV { id, angle } => tag.set_pov(id as usize, angle),
                 }
-                self.queue_udp(UdpTag::Joysticks(Joysticks::new(axes, buttons, povs)));
             }
+            self.queue_udp(UdpTag::Joysticks(tag));
+        }
+        self.spare_joysticks = std::mem::replace(&mut self.last_joysticks, joysticks);
+    }
+
+    /// Constructs a control packet from the current state
+    ///
+    /// Joystick values should be queued beforehand with [`SendState::queue_joysticks`].
+    /// Pending requests are consumed, and only sent with this packet.
+    ///
+    /// This is [`build_control_packet`], with the tags moved into the packet instead of copied. Giving the packet back
+    /// with [`SendState::recycle`] once it's encoded lets its tag buffer be reused.
+    pub fn control(&mut self) -> UdpControlPacket {
+        if self
+            .pending_udp
+            .iter()
+            .any(|tag| matches!(tag, UdpTag::DateTime(_)))
+        {
+            self.dates_sent += 1;
         }
 
+        let packet = UdpControlPacket {
+            seqnum: self.udp_seqnum,
+            control: self.control_byte(),
+            request: self.request_byte(),
+            alliance: self.alliance,
+            tags: std::mem::replace(&mut self.pending_udp, std::mem::take(&mut self.spare_udp)),
+        };
+        self.pending_request = RequestFlags::empty();
+        packet
+    }
+
+    /// Encodes a packet from [`SendState::control`], reusing the buffer of the packets before it
+    ///
+    /// The buffer is only reused once the bytes returned for the previous packet are dropped, so they shouldn't be
+    /// held after the packet is sent.
+    pub fn encode(&mut self, packet: &UdpControlPacket) -> Bytes {
+        // Packets are all around the same size, so this gets the previous packet's allocation back after the first few
+        self.encoded.reserve(ENCODED_CAPACITY);
+        packet.encode_into(&mut self.encoded);
+        self.encoded.split().freeze()
+    }
+
+    /// Keeps the tag buffer of a packet from [`SendState::control`] that has been sent, for a later packet
+    pub fn recycle(&mut self, packet: UdpControlPacket) {
+        let mut tags = packet.tags;
+        tags.clear();
+        if tags.capacity() > self.spare_udp.capacity() {
+            self.spare_udp = tags;
+        }
+    }
+
+    /// Returns the control byte of the next packet, from the mode, the enabled and estop state, and the extra bits
+    fn control_byte(&self) -> Control {
         let mut control = self.mode.to_control();
 
         if self.enabled {
@@ -123,26 +293,22 @@ impl SendState {
             control |= Control::ESTOP
         }
 
-        let mut tags: Vec<Box<dyn Tag>> = Vec::new();
-
-        for tag in self.pending_udp.clone() {
-            match tag {
-                UdpTag::Timezone(tz) => tags.push(Box::new(tz)),
-                UdpTag::DateTime(dt) => tags.push(Box::new(dt)),
-                UdpTag::Joysticks(joy) => tags.push(Box::new(joy)),
-                UdpTag::Countdown(cnt) => tags.push(Box::new(cnt)),
-            }
+        if self.fms_attached {
+            control |= Control::FMS_CONNECTED;
         }
 
-        self.pending_udp.clear();
+        control | Control::from_bits_retain(self.extra_control)
+    }
 
-        UdpControlPacket {
-            seqnum: self.udp_seqnum,
-            control,
-            request: self.pending_request.take(),
-            alliance: self.alliance,
-            tags,
-        }
+    /// Returns the request byte of the next packet, which is left out when nothing is requested
+    fn request_byte(&self) -> Option<RequestFlags> {
+        Some(self.pending_request).filter(|request| !request.is_empty())
+    }
+
+    /// Returns the number of control packets that carried the date and time, in answer to the robot asking for it
+    #[inline(always)]
+    pub const fn dates_sent(&self) -> u64 {
+        self.dates_sent
     }
 
     #[inline(always)]
@@ -150,8 +316,109 @@ impl SendState {
         self.mode
     }
 
-    pub fn set_mode(&mut self, mode: Mode) {
+    /// Changes the mode, returning whether the robot was disabled to do so
+    ///
+    /// The official driver station only switches modes while disabled, so a robot is never switched between modes while
+    /// running. Unless that's turned off with [`set_disable_on_mode_change`](Self::set_disable_on_mode_change), the
+    /// same applies here.
+    pub fn set_mode(&mut self, mode: Mode) -> bool {
+        let disable = self.disable_on_mode_change && self.enabled && mode != self.mode;
+        if disable {
+            self.enabled = false;
+        }
         self.mode = mode;
+        disable
+    }
+
+    #[inline(always)]
+    pub const fn disable_on_mode_change(&self) -> bool {
+        self.disable_on_mode_change
+    }
+
+    pub fn set_disable_on_mode_change(&mut self, disable: bool) {
+        self.disable_on_mode_change = disable;
+    }
+
+    #[inline(always)]
+    pub const fn joystick_suppression(&self) -> JoystickSuppression {
+        self.joystick_suppression
+    }
+
+    pub fn set_joystick_suppression(&mut self, suppression: JoystickSuppression) {
+        self.joystick_suppression = suppression;
+    }
+
+    /// Returns whether joystick values should be sent as neutral in the current mode
+    pub fn joysticks_suppressed(&self) -> bool {
+        self.joystick_suppression
+            .suppresses(self.mode, self.enabled)
+    }
+
+    #[inline(always)]
+    pub const fn compact_joysticks(&self) -> bool {
+        self.compact_joysticks
+    }
+
+    pub fn set_compact_joysticks(&mut self, compact: bool) {
+        self.compact_joysticks = compact;
+    }
+
+    #[inline(always)]
+    pub fn declared_shapes(&self) -> &[Option<JoystickShape>] {
+        &self.declared_shapes
+    }
+
+    pub fn set_declared_shapes(&mut self, shapes: Vec<Option<JoystickShape>>) {
+        self.declared_shapes = shapes;
+    }
+
+    #[inline(always)]
+    pub const fn fms_attached(&self) -> bool {
+        self.fms_attached
+    }
+
+    pub fn set_fms_attached(&mut self, attached: bool) {
+        self.fms_attached = attached;
+    }
+
+    #[inline(always)]
+    pub const fn brownout_cooldown(&self) -> Option<Duration> {
+        self.brownout_cooldown
+    }
+
+    pub fn set_brownout_cooldown(&mut self, cooldown: Option<Duration>) {
+        self.brownout_cooldown = cooldown;
+    }
+
+    #[inline(always)]
+    pub const fn arm_timeout(&self) -> Option<Duration> {
+        self.arm_timeout
+    }
+
+    /// Changes how long arming lasts, or stops requiring it with `None`. Any current arming is cancelled
+    pub fn set_arm_timeout(&mut self, timeout: Option<Duration>) {
+        self.arm_timeout = timeout;
+        self.armed_until = None;
+    }
+
+    /// Arms the robot until the arm timeout runs out from `now`, returning false if arming isn't required
+    pub fn arm(&mut self, now: Instant) -> bool {
+        self.armed_until = self.arm_timeout.map(|timeout| now + timeout);
+        self.armed_until.is_some()
+    }
+
+    pub fn disarm(&mut self) {
+        self.armed_until = None;
+    }
+
+    /// Returns whether the robot is armed at `now`
+    pub fn armed(&self, now: Instant) -> bool {
+        self.armed_until.is_some_and(|until| now < until)
+    }
+
+    /// Returns whether enabling at `now` is allowed by the arming interlock, which it always is when arming isn't required
+    pub fn enable_allowed(&self, now: Instant) -> bool {
+        self.arm_timeout.is_none() || self.armed(now)
     }
 
     #[inline(always)]
@@ -163,20 +430,84 @@ impl SendState {
         self.dsmode = mode;
     }
 
+    #[inline(always)]
+    pub const fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+    }
+
+    /// Returns the changes from the joystick values last sent to `joysticks`, which are about to be
+    pub fn track_inputs(&mut self, joysticks: &[Vec<JoystickValue>]) -> Vec<InputChange> {
+        self.inputs.observe(joysticks)
+    }
+
+    #[inline(always)]
+    pub const fn axis_change_threshold(&self) -> f32 {
+        self.inputs.axis_step()
+    }
+
+    pub fn set_axis_change_threshold(&mut self, threshold: f32) {
+        self.inputs.set_axis_step(threshold);
+    }
+
+    #[inline(always)]
+    pub const fn extra_control(&self) -> u8 {
+        self.extra_control
+    }
+
+    pub fn set_extra_control(&mut self, bits: u8) {
+        self.extra_control = bits;
+    }
+
+    #[inline(always)]
+    pub const fn link_conditions(&self) -> LinkConditions {
+        self.link_conditions
+    }
+
+    pub fn set_link_conditions(&mut self, conditions: LinkConditions) {
+        self.link_conditions = conditions;
+    }
+
+    #[inline(always)]
+    pub const fn joystick_safety(&self) -> &JoystickSafety {
+        &self.joystick_safety
+    }
+
+    pub fn set_joystick_safety(&mut self, safety: JoystickSafety) {
+        self.joystick_safety = safety;
+    }
+
+    #[inline(always)]
+    pub const fn joystick_validation(&self) -> JoystickValidation {
+        self.joystick_validation
+    }
+
+    pub fn set_joystick_validation(&mut self, validation: JoystickValidation) {
+        self.joystick_validation = validation;
+    }
+
     pub fn increment_seqnum(&mut self) {
         self.udp_seqnum = self.udp_seqnum.wrapping_add(1);
     }
 
     pub fn reset_seqnum(&mut self) {
-        self.udp_seqnum = 0;
+        self.udp_seqnum = self.initial_seqnum;
     }
 
-    #[allow(unused)]
     #[inline(always)]
     pub const fn seqnum(&self) -> u16 {
         self.udp_seqnum
     }
 
+    /// Restarts the sequence from `seqnum`, now and whenever the target changes
+    pub fn set_initial_seqnum(&mut self, seqnum: u16) {
+        self.initial_seqnum = seqnum;
+        self.udp_seqnum = seqnum;
+    }
+
     pub fn enable(&mut self) {
         self.enabled = true;
     }
@@ -200,3 +531,118 @@ impl SendState {
         self.estopped
     }
 }
+
+/// Builds the next control packet from `state` without changing it
+///
+/// The pending requests and tags are copied into the packet rather than consumed, so what would be sent can be checked
+/// for any state. The send loop uses [`SendState::control`], which builds the same packet.
+pub fn build_control_packet(state: &SendState) -> UdpControlPacket {
+    UdpControlPacket {
+        seqnum: state.udp_seqnum,
+        control: state.control_byte(),
+        request: state.request_byte(),
+        alliance: state.alliance,
+        tags: state.pending_udp.clone(),
+    }
+}
+
+/// Removes the joysticks after the last one with any values, which the roboRIO treats as unplugged when they aren't sent
+///
+/// Only trailing joysticks can be left out, the roboRIO numbers joysticks by the order of their tags.
+pub(crate) fn trim_unbound(joysticks: &mut Vec<Vec<JoystickValue>>) {
+    let bound = joysticks.iter().rposition(|joystick| !joystick.is_empty());
+    joysticks.truncate(bound.map_or(0, |last| last + 1));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Countdown;
+
+    #[test]
+    fn verify_arming_expires() {
+        let mut send = SendState::new(Alliance::new_red(1));
+        let now = Instant::now();
+        assert!(!send.arm(now));
+        assert!(send.enable_allowed(now));
+
+        send.set_arm_timeout(Some(Duration::from_secs(2)));
+        assert!(!send.enable_allowed(now));
+        assert!(send.arm(now));
+        assert!(send.enable_allowed(now + Duration::from_secs(1)));
+        assert!(!send.enable_allowed(now + Duration::from_secs(2)));
+
+        send.arm(now);
+        send.disarm();
+        assert!(!send.armed(now));
+    }
+
+    #[test]
+    fn verify_control_packets_for_every_state() {
+        let modes = [
+            (Mode::Teleoperated, Control::TELEOP),
+            (Mode::Autonomous, Control::AUTO),
+            (Mode::Test, Control::TEST),
+        ];
+        let requests = [
+            RequestFlags::empty(),
+            RequestFlags::REQUEST_TIME | RequestFlags::RESTART_CODE,
+        ];
+        for (mode, mode_bits) in modes {
+            for bits in 0..16u8 {
+                for request in requests {
+                    let (enabled, estopped, fms, tagged) =
+                        (bits & 1 != 0, bits & 2 != 0, bits & 4 != 0, bits & 8 != 0);
+                    let mut send = SendState::new(Alliance::new_blue(2));
+                    send.set_disable_on_mode_change(false);
+                    send.set_mode(mode);
+                    if estopped {
+                        send.estop();
+                    }
+                    if enabled {
+                        send.enable();
+                    }
+                    send.set_fms_attached(fms);
+                    send.set_initial_seqnum(u16::from(bits) * 1000);
+                    send.request(request);
+                    if tagged {
+                        send.queue_udp(UdpTag::Countdown(Countdown::new(15.0)));
+                    }
+
+                    let mut expected = mode_bits;
+                    expected.set(Control::ENABLED, enabled);
+                    expected.set(Control::ESTOP, estopped);
+                    expected.set(Control::FMS_CONNECTED, fms);
+                    let built = build_control_packet(&send);
+                    assert_eq!(built.control, expected, "{:?} {:#06b}", mode, bits);
+                    assert_eq!(built.seqnum, u16::from(bits) * 1000);
+                    assert_eq!(built.request, (!request.is_empty()).then_some(request));
+                    assert_eq!(built.alliance, Alliance::new_blue(2));
+                    assert_eq!(built.tags.len(), usize::from(tagged));
+
+                    // Building leaves the state alone, sending consumes the requests and tags
+                    assert_eq!(send.control().encode(), built.encode());
+                    let after = build_control_packet(&send);
+                    assert_eq!(after.request, None);
+                    assert!(after.tags.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify_only_trailing_unbound_joysticks_are_trimmed() {
+        let pressed = JoystickValue::Button {
+            id: 1,
+            pressed: true,
+        };
+        let mut joysticks = vec![vec![pressed], vec![], vec![pressed], vec![], vec![]];
+        trim_unbound(&mut joysticks);
+        assert_eq!(joysticks.len(), 3);
+        assert!(joysticks[1].is_empty());
+
+        let mut joysticks = vec![vec![], vec![]];
+        trim_unbound(&mut joysticks);
+        assert!(joysticks.is_empty());
+    }
+}
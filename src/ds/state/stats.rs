@@ -0,0 +1,558 @@
+use crate::ds::aggregate::{TelemetryWindow, WindowAggregator};
+use crate::ds::battery::{BatteryMonitor, VoltageSag};
+use crate::ds::events::Event;
+use crate::ds::state::Mode;
+use crate::ds::summary::{MatchSummary, SummaryRecorder};
+use crate::proto::udp::inbound::types::InboundTagKind;
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// The number of most recent control packets used to measure latency and packet loss
+const WINDOW: usize = 64;
+
+// Packets are kept in the slot of their sequence number modulo the window. That only stays contiguous across the
+// rollover from 65535 to 0 if the window divides 65536
+const _: () = assert!((u16::MAX as usize + 1).is_multiple_of(WINDOW));
+
+/// The time between control packets
+pub(crate) const CONTROL_PERIOD: Duration = Duration::from_millis(20);
+
+/// How long the joystick supplier can take each tick before it's considered slow
+pub(crate) const SLOW_SUPPLIER: Duration = Duration::from_millis(5);
+
+/// The span tag rates are measured over
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A snapshot of the health of the link to the roboRIO
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stats {
+    /// The number of control packets sent to the roboRIO
+    pub packets_sent: u64,
+    /// The number of status packets received from the roboRIO
+    pub packets_received: u64,
+    /// The round trip time of the most recently answered control packet
+    pub latency: Option<Duration>,
+    /// The fraction, from `0.0..=1.0`, of recent control packets that were never answered
+    pub packet_loss: f32,
+    /// The number of times the connection to the roboRIO was re-established after being lost
+    pub reconnects: u64,
+    /// The number of status packets dropped because they didn't come from the robot
+    pub packets_rejected: u64,
+    /// The number of status packets that couldn't be decoded
+    pub decode_errors: u64,
+    /// The number of TCP frames whose body couldn't be decoded, passed on as [`TcpPacket::Malformed`](crate::TcpPacket)
+    pub tcp_decode_errors: u64,
+    /// The number of status packets dropped because they arrived after a newer one
+    pub packets_reordered: u64,
+    /// The number of status packets dropped because they had already been received
+    pub packets_duplicated: u64,
+    /// The smoothed difference between the spacing of control packets and the 20ms they should be sent at
+    pub tick_jitter: Duration,
+    /// The battery voltage averaged over about half a second, or `None` while disconnected
+    pub smoothed_voltage: Option<f32>,
+    /// The last sag in voltage on enable that was over the threshold
+    pub last_sag: Option<VoltageSag>,
+    /// How often each kind of tag has been received, indexed like [`InboundTagKind::ALL`]
+    pub tags: [TagStats; InboundTagKind::ALL.len()],
+    /// The number of tags received with an ID that isn't known
+    pub unknown_tags: u64,
+    /// How long the joystick supplier took on the last tick
+    pub supplier_time: Duration,
+    /// The number of ticks on which the joystick supplier took over 5ms, delaying the control packet
+    pub slow_supplies: u64,
+}
+
+/// How often one kind of tag has been received in status packets
+///
+/// A kind the robot should be sending with a `received` of 0 isn't being reported, while a growing `malformed` means
+/// it is but the parser can't make sense of it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TagStats {
+    pub kind: InboundTagKind,
+    /// The number of tags of this kind decoded from status packets
+    pub received: u64,
+    /// The number of tags of this kind dropped because they failed to decode
+    pub malformed: u64,
+    /// The tags of this kind received per second, over about the last second
+    pub rate: f32,
+}
+
+/// Running counters used to build a [`TagStats`]
+#[derive(Copy, Clone)]
+struct TagCounter {
+    received: u64,
+    malformed: u64,
+    /// When the current rate window started, and how many tags were received in it
+    window_start: Option<Instant>,
+    window_count: u32,
+    /// The rate measured over the last complete window
+    rate: f32,
+}
+
+impl TagCounter {
+    const fn new() -> TagCounter {
+        TagCounter {
+            received: 0,
+            malformed: 0,
+            window_start: None,
+            window_count: 0,
+            rate: 0.0,
+        }
+    }
+
+    fn record(&mut self, at: Instant) {
+        self.received += 1;
+        let start = *self.window_start.get_or_insert(at);
+        let elapsed = at.saturating_duration_since(start);
+        if elapsed >= RATE_WINDOW {
+            self.rate = self.window_count as f32 / elapsed.as_secs_f32();
+            self.window_start = Some(at);
+            self.window_count = 0;
+        }
+        self.window_count += 1;
+    }
+
+    /// Returns the rate at `now`, falling towards 0 once tags stop arriving
+    fn rate(&self, now: Instant) -> f32 {
+        match self.window_start {
+            Some(start) if now.saturating_duration_since(start) >= RATE_WINDOW * 2 => {
+                self.window_count as f32 / now.saturating_duration_since(start).as_secs_f32()
+            }
+            _ => self.rate,
+        }
+    }
+}
+
+/// Running counters used to build [`Stats`]
+pub struct StatsState {
+    packets_sent: u64,
+    packets_received: u64,
+    reconnects: u64,
+    packets_rejected: u64,
+    decode_errors: u64,
+    tcp_decode_errors: u64,
+    packets_reordered: u64,
+    packets_duplicated: u64,
+    latency: Option<Duration>,
+    /// Control packets awaiting a response, indexed by sequence number
+    in_flight: [Option<(u16, Instant)>; WINDOW],
+    /// One bit per retired control packet, set if it was lost. The most recent packet is the LSB
+    loss_history: u64,
+    /// The number of valid bits in `loss_history`
+    retired: u32,
+    /// When the last control packet was sent
+    last_sent: Option<Instant>,
+    tick_jitter: Duration,
+    battery: BatteryMonitor,
+    tags: [TagCounter; InboundTagKind::ALL.len()],
+    unknown_tags: u64,
+    supplier_time: Duration,
+    slow_supplies: u64,
+    /// Summarises the telemetry into windows for subscribers
+    windows: WindowAggregator,
+    /// Summarises the practice run, kept across resets
+    summary: SummaryRecorder,
+}
+
+impl StatsState {
+    pub const fn new() -> StatsState {
+        StatsState {
+            packets_sent: 0,
+            packets_received: 0,
+            reconnects: 0,
+            packets_rejected: 0,
+            decode_errors: 0,
+            tcp_decode_errors: 0,
+            packets_reordered: 0,
+            packets_duplicated: 0,
+            latency: None,
+            in_flight: [None; WINDOW],
+            loss_history: 0,
+            retired: 0,
+            last_sent: None,
+            tick_jitter: Duration::ZERO,
+            battery: BatteryMonitor::new(),
+            tags: [TagCounter::new(); InboundTagKind::ALL.len()],
+            unknown_tags: 0,
+            supplier_time: Duration::ZERO,
+            slow_supplies: 0,
+            windows: WindowAggregator::new(Duration::from_secs(1)),
+            summary: SummaryRecorder::new(),
+        }
+    }
+
+    /// Records the tags of a status packet, and those that were dropped from it by ID. Every packet counts as a
+    /// [`InboundTagKind::Status`]
+    pub fn tags_received(&mut self, tags: &[InboundTagKind], dropped: &[u8], at: Instant) {
+        self.tags[InboundTagKind::Status.index()].record(at);
+        for tag in tags {
+            self.tags[tag.index()].record(at);
+        }
+        for id in dropped {
+            match InboundTagKind::from_id(*id) {
+                Some(kind) => self.tags[kind.index()].malformed += 1,
+                None => self.unknown_tags += 1,
+            }
+        }
+    }
+
+    /// Records how long the joystick supplier took, returning whether that was longer than [`SLOW_SUPPLIER`]
+    pub fn supplier_ran(&mut self, elapsed: Duration) -> bool {
+        self.supplier_time = elapsed;
+        let slow = elapsed > SLOW_SUPPLIER;
+        if slow {
+            self.slow_supplies += 1;
+
+            #[cfg(feature = "metrics")]
+            metrics::counter!("ds_slow_supplies_total").increment(1);
+        }
+        slow
+    }
+
+    /// Records that the control packet with the given sequence number was sent
+    pub fn packet_sent(&mut self, seqnum: u16, at: Instant) {
+        let slot = &mut self.in_flight[seqnum as usize % WINDOW];
+        // Anything still in the slot a full window later was never answered
+        if slot.replace((seqnum, at)).is_some() {
+            self.retire(true);
+        }
+        self.packets_sent += 1;
+        self.windows.packet_sent();
+
+        // Gaps from pauses in sending, such as after releasing control, aren't jitter
+        if let Some(last) = self.last_sent.replace(at) {
+            let spacing = at.saturating_duration_since(last);
+            if spacing < CONTROL_PERIOD * 4 {
+                self.sample_jitter(spacing.abs_diff(CONTROL_PERIOD));
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_packets_sent_total").increment(1);
+    }
+
+    /// Records a status packet from the roboRIO, which echoes the sequence number of the packet it answers
+    pub fn packet_received(&mut self, seqnum: u16, at: Instant) {
+        self.packets_received += 1;
+
+        let slot = &mut self.in_flight[seqnum as usize % WINDOW];
+        if let Some((sent, sent_at)) = *slot
+            && sent == seqnum
+        {
+            *slot = None;
+            let latency = at.saturating_duration_since(sent_at);
+            self.latency = Some(latency);
+            self.retire(false);
+            self.windows.packet_received(Some(latency));
+        } else {
+            self.windows.packet_received(None);
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("ds_packets_received_total").increment(1);
+            if let Some(latency) = self.latency {
+                metrics::gauge!("ds_latency_seconds").set(latency.as_secs_f64());
+            }
+            metrics::gauge!("ds_packet_loss_ratio").set(f64::from(self.packet_loss()));
+        }
+    }
+
+    /// Records the battery voltage of a status packet, returning a sag over the threshold once one has been measured
+    pub fn battery_sample(
+        &mut self,
+        voltage: f32,
+        enabled: bool,
+        now: Instant,
+    ) -> Option<VoltageSag> {
+        self.windows.voltage(voltage);
+        self.summary.voltage(voltage);
+        self.battery.sample(voltage, enabled, now)
+    }
+
+    pub fn reset_battery(&mut self) {
+        self.battery.reset();
+    }
+
+    #[inline(always)]
+    pub const fn sag_threshold(&self) -> f32 {
+        self.battery.threshold()
+    }
+
+    pub fn set_sag_threshold(&mut self, threshold: f32) {
+        self.battery.set_threshold(threshold);
+    }
+
+    pub fn reconnected(&mut self) {
+        self.reconnects += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_reconnects_total").increment(1);
+    }
+
+    /// Records a status packet dropped by the source filter
+    pub fn packet_rejected(&mut self) {
+        self.packets_rejected += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_packets_rejected_total").increment(1);
+    }
+
+    pub fn packet_reordered(&mut self) {
+        self.packets_reordered += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_packets_reordered_total").increment(1);
+    }
+
+    pub fn packet_duplicated(&mut self) {
+        self.packets_duplicated += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_packets_duplicated_total").increment(1);
+    }
+
+    pub fn decode_error(&mut self) {
+        self.decode_errors += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_decode_errors_total").increment(1);
+    }
+
+    pub fn tcp_decode_error(&mut self) {
+        self.tcp_decode_errors += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("ds_tcp_decode_errors_total").increment(1);
+    }
+
+    /// Forgets all in flight packets, used when sequence numbers are reset for a new target
+    pub fn reset_link(&mut self) {
+        self.in_flight = [None; WINDOW];
+        self.latency = None;
+        self.loss_history = 0;
+        self.retired = 0;
+        self.last_sent = None;
+        self.tick_jitter = Duration::ZERO;
+    }
+
+    #[inline(always)]
+    pub const fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    pub fn packet_loss(&self) -> f32 {
+        if self.retired == 0 {
+            return 0.0;
+        }
+
+        let mask = u64::MAX >> (64 - self.retired);
+        (self.loss_history & mask).count_ones() as f32 / self.retired as f32
+    }
+
+    pub fn snapshot(&self, now: Instant) -> Stats {
+        Stats {
+            packets_sent: self.packets_sent,
+            packets_received: self.packets_received,
+            latency: self.latency,
+            packet_loss: self.packet_loss(),
+            reconnects: self.reconnects,
+            packets_rejected: self.packets_rejected,
+            decode_errors: self.decode_errors,
+            tcp_decode_errors: self.tcp_decode_errors,
+            packets_reordered: self.packets_reordered,
+            packets_duplicated: self.packets_duplicated,
+            tick_jitter: self.tick_jitter,
+            smoothed_voltage: self.battery.smoothed(),
+            last_sag: self.battery.last_sag(),
+            tags: InboundTagKind::ALL.map(|kind| {
+                let counter = &self.tags[kind.index()];
+                TagStats {
+                    kind,
+                    received: counter.received,
+                    malformed: counter.malformed,
+                    rate: counter.rate(now),
+                }
+            }),
+            unknown_tags: self.unknown_tags,
+            supplier_time: self.supplier_time,
+            slow_supplies: self.slow_supplies,
+        }
+    }
+
+    /// Smooths the deviation from the control period like RTP's interarrival jitter, moving 1/16th of the way each packet
+    fn sample_jitter(&mut self, deviation: Duration) {
+        if deviation > self.tick_jitter {
+            self.tick_jitter += (deviation - self.tick_jitter) / 16;
+        } else {
+            self.tick_jitter -= (self.tick_jitter - deviation) / 16;
+        }
+
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("ds_tick_jitter_seconds").set(self.tick_jitter.as_secs_f64());
+    }
+
+    fn retire(&mut self, lost: bool) {
+        self.loss_history = (self.loss_history << 1) | lost as u64;
+        self.retired = (self.retired + 1).min(64);
+        self.windows.retired(lost);
+        self.summary.retired(lost);
+    }
+
+    #[inline(always)]
+    pub const fn window_length(&self) -> Duration {
+        self.windows.length()
+    }
+
+    /// Changes how much telemetry each window summarises, starting a new window if it's different
+    pub fn set_window_length(&mut self, length: Duration) {
+        if length != self.windows.length() {
+            self.windows.set_length(length);
+        }
+    }
+
+    /// Closes the current window if it's due, returning its summary
+    pub fn close_window(&mut self, now: Instant, wall: DateTime<Utc>) -> Option<TelemetryWindow> {
+        self.windows.close_due(now, wall)
+    }
+
+    /// Records what a control packet sent at `now` asked of the robot, for the match summary
+    pub fn summary_tick(&mut self, now: Instant, wall: DateTime<Utc>, enabled: bool, mode: Mode) {
+        self.summary.tick(now, wall, enabled, mode);
+    }
+
+    pub fn summary_event(&mut self, event: &Event) {
+        self.summary.event(event);
+    }
+
+    pub fn match_summary(&self, now: Instant) -> MatchSummary {
+        self.summary.summary(now)
+    }
+
+    /// Returns the match summary at `now`, and starts a new one
+    pub fn take_match_summary(&mut self, now: Instant) -> MatchSummary {
+        let summary = self.match_summary(now);
+        self.summary = SummaryRecorder::new();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_loss_and_latency() {
+        let mut stats = StatsState::new();
+        let start = Instant::now();
+
+        // Answer every other packet
+        for seqnum in 0..(WINDOW as u16 * 2) {
+            stats.packet_sent(seqnum, start);
+            if seqnum % 2 == 0 {
+                stats.packet_received(seqnum, start + Duration::from_millis(5));
+            }
+        }
+
+        let snapshot = stats.snapshot(Instant::now());
+        assert_eq!(snapshot.packets_sent, WINDOW as u64 * 2);
+        assert_eq!(snapshot.packets_received, WINDOW as u64);
+        assert_eq!(snapshot.latency, Some(Duration::from_millis(5)));
+        assert!((snapshot.packet_loss - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn verify_slow_supplies_are_counted() {
+        let mut stats = StatsState::new();
+        assert!(!stats.supplier_ran(Duration::from_millis(1)));
+        assert!(stats.supplier_ran(Duration::from_millis(30)));
+        let snapshot = stats.snapshot(Instant::now());
+        assert_eq!(snapshot.supplier_time, Duration::from_millis(30));
+        assert_eq!(snapshot.slow_supplies, 1);
+    }
+
+    #[test]
+    fn verify_tags_are_counted() {
+        let mut stats = StatsState::new();
+        let start = Instant::now();
+
+        for i in 0..100 {
+            let at = start + CONTROL_PERIOD * i;
+            stats.tags_received(&[InboundTagKind::CanMetrics], &[0x04, 0x42], at);
+        }
+        let tags = stats.snapshot(Instant::now()).tags;
+        let can = tags[InboundTagKind::CanMetrics.index()];
+        assert_eq!(can.received, 100);
+        assert!((can.rate - 50.0).abs() < 1.0);
+        assert_eq!(tags[InboundTagKind::DiskInfo.index()].malformed, 100);
+        assert_eq!(tags[InboundTagKind::PdpLog.index()].received, 0);
+        assert_eq!(stats.unknown_tags, 100);
+    }
+
+    #[test]
+    fn verify_sequence_rollover_isnt_loss() {
+        let mut stats = StatsState::new();
+        let start = Instant::now();
+
+        let mut seqnum = u16::MAX - 10;
+        for _ in 0..WINDOW * 2 {
+            stats.packet_sent(seqnum, start);
+            stats.packet_received(seqnum, start + Duration::from_millis(5));
+            seqnum = seqnum.wrapping_add(1);
+        }
+        assert_eq!(stats.snapshot(Instant::now()).packet_loss, 0.0);
+
+        // A late answer to a packet from before the rollover doesn't match the packet now in its slot
+        stats.packet_sent(seqnum, start);
+        stats.packet_received(seqnum.wrapping_sub(WINDOW as u16), start);
+        assert_eq!(
+            stats.snapshot(Instant::now()).latency,
+            Some(Duration::from_millis(5))
+        );
+    }
+
+    #[test]
+    fn verify_windows_follow_the_link() {
+        let mut stats = StatsState::new();
+        stats.set_window_length(Duration::from_millis(100));
+        let start = Instant::now();
+        let wall = Utc::now();
+        assert_eq!(stats.close_window(start, wall), None);
+
+        // Every packet but the third is answered, each later than the last
+        for seqnum in 0..5 {
+            let at = start + CONTROL_PERIOD * u32::from(seqnum);
+            stats.packet_sent(seqnum, at);
+            if seqnum != 2 {
+                let latency = Duration::from_millis(u64::from(seqnum) + 1);
+                stats.packet_received(seqnum, at + latency);
+            }
+            stats.battery_sample(12.5 - f32::from(seqnum) * 0.5, true, at);
+        }
+        assert_eq!(stats.close_window(start + CONTROL_PERIOD * 4, wall), None);
+
+        let window = stats
+            .close_window(start + Duration::from_millis(100), wall)
+            .unwrap();
+        assert_eq!(window.started, wall);
+        assert_eq!(window.packets_sent, 5);
+        assert_eq!(window.packets_received, 4);
+        assert_eq!(window.packet_loss, 0.0);
+        assert_eq!(window.max_latency, Some(Duration::from_millis(5)));
+        let voltage = window.voltage.unwrap();
+        assert_eq!((voltage.min, voltage.mean, voltage.max), (10.5, 11.5, 12.5));
+
+        // The next window starts from nothing, and none are closed once their length is zero
+        let next = stats
+            .close_window(start + Duration::from_millis(200), wall)
+            .unwrap();
+        assert_eq!((next.packets_sent, next.voltage), (0, None));
+        stats.set_window_length(Duration::ZERO);
+        assert_eq!(
+            stats.close_window(start + Duration::from_secs(10), wall),
+            None
+        );
+        assert_eq!(stats.window_length(), Duration::ZERO);
+    }
+}
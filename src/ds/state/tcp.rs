@@ -0,0 +1,473 @@
+use crate::TcpPacket;
+use crate::ds::console::{CONSOLE_CAPACITY, ConsoleFilter, ConsoleLine};
+#[cfg(feature = "extensions")]
+use crate::ds::extensions::PendingCommands;
+use crate::ds::state::TcpConsumer;
+use crate::ds::state::send::{NUM_AXES, NUM_BUTTONS, NUM_POVS};
+use crate::proto::tcp::outbound::{
+    AxisType, GameData, JoystickDescriptor, JoystickType, MatchInfo, TcpTag,
+};
+#[cfg(feature = "extensions")]
+use crate::{CommandResponse, ROBOT_COMMAND_ID, RobotCommand};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{broadcast, watch};
+
+/// The number of one-off TCP tags kept while the connection is closed, the oldest are dropped first
+const BACKLOG_CAPACITY: usize = 64;
+
+/// The number of joystick slots robot code has
+pub(crate) const JOYSTICK_SLOTS: usize = 6;
+
+/// A tag waiting to be written to the TCP connection
+pub struct QueuedTag {
+    pub tag: TcpTag,
+    /// What writing the tag delivers, or `None` for a one-off tag that's retried if the connection closed first
+    pub delivers: Option<Delivers>,
+}
+
+/// The part of the persistent TCP state a tag carries, which is sent again every time the connection opens
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Delivers {
+    /// The given version of the game specific message
+    GameData(u64),
+    /// The given version of the match info
+    MatchInfo(u64),
+    JoystickDescriptor,
+    /// A command, which isn't sent again on the next connection as its response would be lost with this one
+    #[cfg(feature = "extensions")]
+    Command,
+}
+
+/// The versions of the persistent TCP state written to the current connection, 0 if none has been
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Delivered {
+    pub game_data: u64,
+    pub match_info: u64,
+}
+
+/// All the state associated with TCP communication with the RIO
+pub struct TcpState {
+    /// An optional callback that should be notified upon incoming packets being decoded
+    pub tcp_consumer: Option<Box<TcpConsumer>>,
+    /// A channel of packets that should be sent to the roboRIO
+    pending_tcp: Option<UnboundedSender<QueuedTag>>,
+    /// The game specific message, sent every time the TCP connection opens
+    game_data: Option<GameData>,
+    /// Incremented every time the game specific message is set
+    game_data_version: u64,
+    /// Descriptions of the joysticks in each slot, also sent every time the TCP connection opens
+    joystick_descriptors: Vec<JoystickDescriptor>,
+    /// The number of joysticks described to a simulator on the current connection, when no descriptors are set
+    sim_described: Option<usize>,
+    /// The match info, also sent every time the TCP connection opens
+    match_info: Option<MatchInfo>,
+    match_info_version: u64,
+    /// What has been written to the current connection
+    delivered: watch::Sender<Delivered>,
+    /// One-off tags queued while the connection was closed, sent when it opens
+    backlog: VecDeque<TcpTag>,
+    /// When the robot last closed the connection, rather than the driver station
+    robot_closed: Option<Instant>,
+    /// Limits console messages before they reach the consumer
+    console: ConsoleFilter,
+    /// Sends console lines to every watcher
+    console_tx: broadcast::Sender<ConsoleLine>,
+    /// Whether console lines are passed to the `log` and `tracing` facades, with those features
+    log_console: bool,
+    /// The commands sent on the current connection that robot code hasn't answered
+    #[cfg(feature = "extensions")]
+    commands: PendingCommands,
+}
+
+impl TcpState {
+    pub fn new() -> TcpState {
+        TcpState {
+            tcp_consumer: None,
+            pending_tcp: None,
+            game_data: None,
+            game_data_version: 0,
+            joystick_descriptors: Vec::new(),
+            sim_described: None,
+            match_info: None,
+            match_info_version: 0,
+            delivered: watch::channel(Delivered::default()).0,
+            backlog: VecDeque::new(),
+            robot_closed: None,
+            console: ConsoleFilter::new(),
+            console_tx: broadcast::channel(CONSOLE_CAPACITY).0,
+            log_console: cfg!(any(feature = "log", feature = "tracing")),
+            #[cfg(feature = "extensions")]
+            commands: PendingCommands::new(),
+        }
+    }
+
+    /// Sends a one-off tag, or keeps it until the TCP connection opens if it's closed
+    pub fn queue_tcp(&mut self, tag: TcpTag) {
+        if let Err(tag) = self.send(tag, None) {
+            if self.backlog.len() == BACKLOG_CAPACITY {
+                self.backlog.pop_front();
+            }
+            self.backlog.push_back(tag);
+        }
+    }
+
+    /// Passes `tag` to the current connection, giving it back if there isn't one
+    fn send(&self, tag: TcpTag, delivers: Option<Delivers>) -> std::result::Result<(), TcpTag> {
+        // pending_tcp is set by the tcp_conn function when it connects.
+        match self.pending_tcp {
+            Some(ref tx) => tx.send(QueuedTag { tag, delivers }).map_err(|e| e.0.tag),
+            None => Err(tag),
+        }
+    }
+
+    /// Sets the channel of the current TCP connection, sending it the game data, joystick descriptors, match info, and
+    /// the tags queued while it was closed
+    pub fn set_tcp_tx(&mut self, tx: Option<UnboundedSender<QueuedTag>>) {
+        self.pending_tcp = tx;
+        self.sim_described = None;
+        #[cfg(feature = "extensions")]
+        self.commands.clear();
+        self.delivered.send_replace(Delivered::default());
+        if let Some(ref data) = self.game_data {
+            let tag = TcpTag::GameData(data.clone());
+            let _ = self.send(tag, Some(Delivers::GameData(self.game_data_version)));
+        }
+        for descriptor in &self.joystick_descriptors {
+            let tag = TcpTag::JoystickDescriptor(descriptor.clone());
+            let _ = self.send(tag, Some(Delivers::JoystickDescriptor));
+        }
+        if let Some(ref info) = self.match_info {
+            let tag = TcpTag::MatchInfo(info.clone());
+            let _ = self.send(tag, Some(Delivers::MatchInfo(self.match_info_version)));
+        }
+        while let Some(tag) = self.backlog.pop_front() {
+            if let Err(tag) = self.send(tag, None) {
+                self.backlog.push_front(tag);
+                break;
+            }
+        }
+    }
+
+    /// Forgets the channel of a closed TCP connection, unless it has already been replaced by a newer connection
+    pub fn clear_tcp_tx(&mut self, tx: &UnboundedSender<QueuedTag>) {
+        if self
+            .pending_tcp
+            .as_ref()
+            .is_some_and(|pending| pending.same_channel(tx))
+        {
+            self.pending_tcp = None;
+            self.sim_described = None;
+            #[cfg(feature = "extensions")]
+            self.commands.clear();
+            self.delivered.send_replace(Delivered::default());
+        }
+    }
+
+    #[inline(always)]
+    pub const fn robot_closed(&self) -> Option<Instant> {
+        self.robot_closed
+    }
+
+    pub fn set_robot_closed(&mut self, at: Instant) {
+        self.robot_closed = Some(at);
+    }
+
+    /// Records that a tag was written to the connection
+    pub fn written(&mut self, delivers: Option<Delivers>) {
+        self.delivered.send_modify(|delivered| match delivers {
+            Some(Delivers::GameData(version)) => delivered.game_data = version,
+            Some(Delivers::MatchInfo(version)) => delivered.match_info = version,
+            Some(Delivers::JoystickDescriptor) | None => {}
+            #[cfg(feature = "extensions")]
+            Some(Delivers::Command) => {}
+        });
+    }
+
+    /// Takes back a tag a closed connection didn't write. One-off tags are sent again, persistent ones are sent with the
+    /// rest of the persistent state when the connection reopens
+    pub fn requeue(&mut self, queued: QueuedTag) {
+        if queued.delivers.is_none() {
+            self.queue_tcp(queued.tag);
+        }
+    }
+
+    /// Returns a receiver of what has been written to the current connection
+    pub fn watch_delivered(&self) -> watch::Receiver<Delivered> {
+        self.delivered.subscribe()
+    }
+
+    #[inline(always)]
+    pub const fn game_data_version(&self) -> u64 {
+        self.game_data_version
+    }
+
+    #[inline(always)]
+    pub const fn match_info_version(&self) -> u64 {
+        self.match_info_version
+    }
+
+    /// Sets the game specific message, and sends it if the TCP connection is open
+    pub fn set_game_data(&mut self, data: GameData) {
+        self.game_data_version += 1;
+        let tag = TcpTag::GameData(data.clone());
+        self.game_data = Some(data);
+        let _ = self.send(tag, Some(Delivers::GameData(self.game_data_version)));
+    }
+
+    /// Sets the descriptions of the joysticks, and sends them if the TCP connection is open
+    pub fn set_joystick_descriptors(&mut self, descriptors: Vec<JoystickDescriptor>) {
+        for descriptor in &descriptors {
+            let tag = TcpTag::JoystickDescriptor(descriptor.clone());
+            let _ = self.send(tag, Some(Delivers::JoystickDescriptor));
+        }
+        self.joystick_descriptors = descriptors;
+    }
+
+    /// Describes the first `count` slots as the joysticks the send loop encodes, and the rest as empty, unless
+    /// descriptors were set
+    ///
+    /// WPILib's simulator otherwise keeps showing the joysticks of its own GUI, or of the last driver station, with
+    /// input counts that don't match what's sent. Nothing is sent again until `count` changes or the connection reopens.
+    pub fn describe_sim_joysticks(&mut self, count: usize) {
+        if !self.joystick_descriptors.is_empty()
+            || !self.connected()
+            || self.sim_described == Some(count)
+        {
+            return;
+        }
+        for index in 0..JOYSTICK_SLOTS as u8 {
+            let descriptor = sent_descriptor(index, usize::from(index) < count);
+            let _ = self.send(
+                TcpTag::JoystickDescriptor(descriptor),
+                Some(Delivers::JoystickDescriptor),
+            );
+        }
+        self.sim_described = Some(count);
+    }
+
+    /// Sets the match info, and sends it if the TCP connection is open
+    pub fn set_match_info(&mut self, info: MatchInfo) {
+        self.match_info_version += 1;
+        let _ = self.send(
+            TcpTag::MatchInfo(info.clone()),
+            Some(Delivers::MatchInfo(self.match_info_version)),
+        );
+        self.match_info = Some(info);
+    }
+
+    pub fn match_info(&self) -> Option<MatchInfo> {
+        self.match_info.clone()
+    }
+
+    /// Returns whether a TCP connection is open
+    #[inline(always)]
+    pub const fn connected(&self) -> bool {
+        self.pending_tcp.is_some()
+    }
+
+    pub fn game_data(&self) -> Option<GameData> {
+        self.game_data.clone()
+    }
+
+    pub fn set_tcp_consumer(&mut self, consumer: impl FnMut(TcpPacket) + Send + Sync + 'static) {
+        self.tcp_consumer = Some(Box::new(consumer));
+    }
+
+    #[inline(always)]
+    pub const fn console(&self) -> &ConsoleFilter {
+        &self.console
+    }
+
+    #[inline(always)]
+    pub const fn console_mut(&mut self) -> &mut ConsoleFilter {
+        &mut self.console
+    }
+
+    /// Passes `packet`, received at `received` by the driver station's clock, to the consumer and console watchers, after
+    /// the console limits
+    pub fn consume(&mut self, packet: TcpPacket, now: Instant, received: DateTime<Utc>) {
+        match packet {
+            TcpPacket::Stdout(stdout) => {
+                for stdout in self.console.filter(stdout, now) {
+                    self.deliver(TcpPacket::Stdout(stdout), received);
+                }
+            }
+            #[cfg(feature = "extensions")]
+            TcpPacket::Unknown {
+                id: ROBOT_COMMAND_ID,
+                mut data,
+            } => match CommandResponse::decode(&mut data) {
+                Ok(response) => {
+                    let id = response.id;
+                    if !self.commands.resolve(response) {
+                        warn!(
+                            "Dropping the response to command {}, which isn't waiting for one",
+                            id
+                        );
+                    }
+                }
+                Err(e) => warn!("Failed to decode a command response: {}", e),
+            },
+            packet => self.deliver(packet, received),
+        }
+    }
+
+    /// Sends a command to robot code, returning its ID and a receiver of the response
+    ///
+    /// Fails if the connection is closed, as the command isn't kept for the next one.
+    #[cfg(feature = "extensions")]
+    pub fn send_command(
+        &mut self,
+        name: &str,
+        payload: bytes::Bytes,
+    ) -> crate::Result<(u16, tokio::sync::oneshot::Receiver<CommandResponse>)> {
+        if self.pending_tcp.is_none() {
+            anyhow::bail!("The TCP connection is closed, can't send {:?}", name);
+        }
+        let (id, rx) = self.commands.register();
+        let command = match RobotCommand::new(id, name, payload) {
+            Ok(command) => command,
+            Err(e) => {
+                self.commands.cancel(id);
+                return Err(e);
+            }
+        };
+        if self
+            .send(TcpTag::Command(command), Some(Delivers::Command))
+            .is_err()
+        {
+            self.commands.cancel(id);
+            anyhow::bail!("The TCP connection closed, can't send {:?}", name);
+        }
+        Ok((id, rx))
+    }
+
+    /// Stops waiting on the response to the command `id`
+    #[cfg(feature = "extensions")]
+    pub fn cancel_command(&mut self, id: u16) {
+        self.commands.cancel(id);
+    }
+
+    /// Passes the summaries of suppressed console messages to the consumer and console watchers
+    pub fn flush_console(&mut self, now: Instant, received: DateTime<Utc>) {
+        for stdout in self.console.flush(now) {
+            self.deliver(TcpPacket::Stdout(stdout), received);
+        }
+    }
+
+    fn deliver(&mut self, packet: TcpPacket, received: DateTime<Utc>) {
+        let watched = self.console_tx.receiver_count() > 0;
+        if (watched || self.log_console)
+            && let Some(line) = ConsoleLine::from_packet(&packet, received)
+        {
+            #[cfg(any(feature = "log", feature = "tracing"))]
+            if self.log_console {
+                line.log();
+            }
+            // Nobody watching isn't an error
+            if watched {
+                let _ = self.console_tx.send(line);
+            }
+        }
+        if let Some(ref mut consumer) = self.tcp_consumer {
+            consumer(packet);
+        }
+    }
+
+    #[inline(always)]
+    pub const fn log_console(&self) -> bool {
+        self.log_console
+    }
+
+    pub fn set_log_console(&mut self, log: bool) {
+        self.log_console = log;
+    }
+
+    pub fn watch_console(&self) -> broadcast::Receiver<ConsoleLine> {
+        self.console_tx.subscribe()
+    }
+}
+
+/// Describes the joystick in slot `index` as laid out by the send loop, or as an empty slot
+fn sent_descriptor(index: u8, present: bool) -> JoystickDescriptor {
+    use AxisType::*;
+
+    if !present {
+        return JoystickDescriptor {
+            index,
+            is_xbox: false,
+            joystick_type: JoystickType::Unknown,
+            name: String::new(),
+            axis_types: Vec::new(),
+            buttons: 0,
+            povs: 0,
+        };
+    }
+    JoystickDescriptor {
+        index,
+        is_xbox: false,
+        joystick_type: JoystickType::Unknown,
+        name: format!("ds-rs joystick {}", index),
+        axis_types: [X, Y, Z, Twist, Throttle, Z][..NUM_AXES as usize].to_vec(),
+        buttons: NUM_BUTTONS,
+        povs: NUM_POVS,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn verify_sim_joysticks_are_described_once() {
+        let mut tcp = TcpState::new();
+        let (tx, mut rx) = unbounded_channel();
+        tcp.set_tcp_tx(Some(tx));
+
+        tcp.describe_sim_joysticks(2);
+        tcp.describe_sim_joysticks(2);
+        let mut axes = Vec::new();
+        while let Ok(queued) = rx.try_recv() {
+            if let TcpTag::JoystickDescriptor(descriptor) = queued.tag {
+                axes.push(descriptor.axis_types.len());
+            }
+        }
+        assert_eq!(axes, [6, 6, 0, 0, 0, 0]);
+
+        tcp.set_joystick_descriptors(vec![crate::Controller::Xbox.descriptor(0)]);
+        rx.try_recv().unwrap();
+        tcp.describe_sim_joysticks(1);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn verify_deliveries_start_over_with_each_connection() {
+        let mut tcp = TcpState::new();
+        let delivered = tcp.watch_delivered();
+        let (tx, mut rx) = unbounded_channel();
+        tcp.set_tcp_tx(Some(tx.clone()));
+        tcp.set_game_data(GameData::new("L").unwrap());
+        let queued = rx.try_recv().unwrap();
+        tcp.written(queued.delivers);
+        assert_eq!(delivered.borrow().game_data, 1);
+
+        // A new connection hasn't been written the game data yet, until it's sent again
+        let (next_tx, mut next_rx) = unbounded_channel();
+        tcp.set_tcp_tx(Some(next_tx.clone()));
+        assert_eq!(*delivered.borrow(), Delivered::default());
+        let queued = next_rx.try_recv().unwrap();
+        assert_eq!(queued.delivers, Some(Delivers::GameData(1)));
+        tcp.written(queued.delivers);
+        assert_eq!(delivered.borrow().game_data, 1);
+
+        // The old connection closing late doesn't forget what the new one was written
+        tcp.clear_tcp_tx(&tx);
+        assert_eq!(delivered.borrow().game_data, 1);
+        tcp.clear_tcp_tx(&next_tx);
+        assert_eq!(*delivered.borrow(), Delivered::default());
+    }
+}
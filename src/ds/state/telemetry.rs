@@ -0,0 +1,237 @@
+use crate::ds::can::CanMonitor;
+use crate::ds::changes::VOLTAGE_STEP;
+use crate::ds::history::TraceHistory;
+use crate::ds::identity::RobotIdentity;
+use crate::ds::resources::ResourceMonitor;
+use crate::ds::state::Mode;
+use crate::proto::udp::inbound::types::*;
+use std::time::Instant;
+
+/// What the robot reports about itself in status packets and over TCP: its battery, state, tags, and versions
+pub struct Telemetry {
+    /// The current battery voltage
+    battery_voltage: f32,
+    /// A bitflags struct that can be used to query the state of various aspects of the RIO
+    trace: Trace,
+    /// Whether the roboRIO is reporting a brownout
+    brownout: bool,
+    /// When the roboRIO last reported a brownout. This is kept across resets, as a browned out robot often disconnects
+    last_brownout: Option<Instant>,
+    /// The battery voltage last reported to subscribers
+    reported_voltage: f32,
+    /// The mode the robot reported running in
+    reported_mode: Option<Mode>,
+    /// Whether the robot reported being enabled
+    reported_enabled: bool,
+    /// When each kind of tag was last received, indexed like [`InboundTagKind::ALL`]. These are kept across resets
+    tags_received: [Option<Instant>; InboundTagKind::ALL.len()],
+    /// The status and trace bytes of the last status packet
+    raw_status: Option<RawStatus>,
+    /// What the robot asked for in the last status packet
+    requests: RobotRequests,
+    /// The number of status packets that asked for the date. This is kept across resets
+    date_requests: u64,
+    /// What the robot reported about itself. This is kept across resets, and only cleared when the target changes
+    identity: RobotIdentity,
+    /// The free disk space and memory last reported, and their limits
+    resources: ResourceMonitor,
+    /// The CAN metrics last reported, and when the bus is considered saturated
+    can: CanMonitor,
+    /// The recent changes of the status and trace bytes. This is kept across resets
+    history: TraceHistory,
+}
+
+impl Telemetry {
+    pub fn new() -> Telemetry {
+        Telemetry {
+            battery_voltage: 0f32,
+            trace: Trace::empty(),
+            brownout: false,
+            last_brownout: None,
+            reported_voltage: 0f32,
+            reported_mode: None,
+            reported_enabled: false,
+            tags_received: [None; InboundTagKind::ALL.len()],
+            raw_status: None,
+            requests: RobotRequests::empty(),
+            date_requests: 0,
+            identity: RobotIdentity::new(),
+            resources: ResourceMonitor::new(),
+            can: CanMonitor::new(),
+            history: TraceHistory::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.battery_voltage = 0f32;
+        self.trace = Trace::empty();
+        self.brownout = false;
+        self.reported_voltage = 0f32;
+        self.reported_mode = None;
+        self.reported_enabled = false;
+        self.raw_status = None;
+        self.requests = RobotRequests::empty();
+        self.resources.reset();
+        self.can.reset();
+    }
+
+    #[inline(always)]
+    pub const fn battery_voltage(&self) -> f32 {
+        self.battery_voltage
+    }
+
+    /// Updates the battery voltage, returning whether it moved far enough since it was last reported to report again
+    pub fn set_battery_voltage(&mut self, voltage: f32) -> bool {
+        self.battery_voltage = voltage;
+
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("ds_battery_voltage").set(f64::from(voltage));
+
+        if (voltage - self.reported_voltage).abs() >= VOLTAGE_STEP {
+            self.reported_voltage = voltage;
+            return true;
+        }
+        false
+    }
+
+    #[inline(always)]
+    pub const fn trace(&self) -> Trace {
+        self.trace
+    }
+
+    /// Updates the trace, returning whether it changed
+    pub fn set_trace(&mut self, trace: Trace) -> bool {
+        let changed = self.trace != trace;
+        self.trace = trace;
+        changed
+    }
+
+    #[inline(always)]
+    pub const fn reported_mode(&self) -> Option<Mode> {
+        self.reported_mode
+    }
+
+    /// Updates the mode the robot reported, returning whether it changed
+    pub fn set_reported_mode(&mut self, mode: Option<Mode>) -> bool {
+        let changed = self.reported_mode != mode;
+        self.reported_mode = mode;
+        changed
+    }
+
+    #[inline(always)]
+    pub const fn reported_enabled(&self) -> bool {
+        self.reported_enabled
+    }
+
+    /// Updates whether the robot reported being enabled, returning whether it changed
+    pub fn set_reported_enabled(&mut self, enabled: bool) -> bool {
+        let changed = self.reported_enabled != enabled;
+        self.reported_enabled = enabled;
+        changed
+    }
+
+    #[inline(always)]
+    pub const fn brownout(&self) -> bool {
+        self.brownout
+    }
+
+    #[inline(always)]
+    pub const fn last_brownout(&self) -> Option<Instant> {
+        self.last_brownout
+    }
+
+    /// Updates the brownout state as of `now`, returning whether it changed
+    pub fn set_brownout(&mut self, brownout: bool, now: Instant) -> bool {
+        let changed = self.brownout != brownout;
+        self.brownout = brownout;
+        if brownout {
+            self.last_brownout = Some(now);
+        }
+        changed
+    }
+
+    /// Records that a status packet with the given tags was received at `at`
+    pub fn tags_received(&mut self, tags: &[InboundTagKind], at: Instant) {
+        self.tags_received[InboundTagKind::Status.index()] = Some(at);
+        for tag in tags {
+            self.tags_received[tag.index()] = Some(at);
+        }
+    }
+
+    #[inline(always)]
+    pub const fn tag_received(&self, tag: InboundTagKind) -> Option<Instant> {
+        self.tags_received[tag.index()]
+    }
+
+    #[inline(always)]
+    pub const fn raw_status(&self) -> Option<RawStatus> {
+        self.raw_status
+    }
+
+    pub fn set_raw_status(&mut self, raw: RawStatus) {
+        self.raw_status = Some(raw);
+    }
+
+    #[inline(always)]
+    pub const fn requests(&self) -> RobotRequests {
+        self.requests
+    }
+
+    #[inline(always)]
+    pub const fn date_requests(&self) -> u64 {
+        self.date_requests
+    }
+
+    /// Records what the robot asked for in a status packet, counting requests for the date
+    pub fn set_requests(&mut self, requests: RobotRequests) {
+        self.requests = requests;
+        if requests.contains(RobotRequests::DATE) {
+            self.date_requests += 1;
+        }
+    }
+
+    #[inline(always)]
+    pub const fn identity(&self) -> &RobotIdentity {
+        &self.identity
+    }
+
+    #[inline(always)]
+    pub fn identity_mut(&mut self) -> &mut RobotIdentity {
+        &mut self.identity
+    }
+
+    /// Forgets what the previous robot reported about itself
+    pub fn clear_identity(&mut self) {
+        self.identity = RobotIdentity::new();
+    }
+
+    #[inline(always)]
+    pub const fn resources(&self) -> &ResourceMonitor {
+        &self.resources
+    }
+
+    #[inline(always)]
+    pub const fn resources_mut(&mut self) -> &mut ResourceMonitor {
+        &mut self.resources
+    }
+
+    #[inline(always)]
+    pub const fn can(&self) -> &CanMonitor {
+        &self.can
+    }
+
+    #[inline(always)]
+    pub const fn can_mut(&mut self) -> &mut CanMonitor {
+        &mut self.can
+    }
+
+    #[inline(always)]
+    pub const fn history(&self) -> &TraceHistory {
+        &self.history
+    }
+
+    #[inline(always)]
+    pub const fn history_mut(&mut self) -> &mut TraceHistory {
+        &mut self.history
+    }
+}
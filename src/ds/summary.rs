@@ -0,0 +1,291 @@
+use super::events::Event;
+use super::state::Mode;
+use super::state::stats::CONTROL_PERIOD;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long the robot was enabled in each mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct EnabledTime {
+    pub autonomous: Duration,
+    pub teleoperated: Duration,
+    pub test: Duration,
+}
+
+impl EnabledTime {
+    #[inline(always)]
+    pub const fn total(&self) -> Duration {
+        self.autonomous
+            .saturating_add(self.teleoperated)
+            .saturating_add(self.test)
+    }
+
+    const fn get_mut(&mut self, mode: Mode) -> &mut Duration {
+        match mode {
+            Mode::Autonomous => &mut self.autonomous,
+            Mode::Teleoperated => &mut self.teleoperated,
+            Mode::Test => &mut self.test,
+        }
+    }
+}
+
+/// The health of the robot over a practice run, at a glance
+///
+/// Recorded from when the driver station starts, or since the last
+/// [`DriverStation::take_match_summary`](crate::DriverStation::take_match_summary).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MatchSummary {
+    /// When the first control packet was sent, on the driver station's clock
+    pub started: Option<DateTime<Utc>>,
+    /// The time from the first control packet to when the summary was made
+    pub duration: Duration,
+    /// How long control packets asked for the robot to be enabled, in each mode
+    pub enabled: EnabledTime,
+    /// The lowest battery voltage reported, or `None` if no status packets arrived
+    pub min_voltage: Option<f32>,
+    pub brownouts: u32,
+    /// The fraction, from `0.0..=1.0`, of the control packets given up on that were never answered
+    pub packet_loss: f32,
+    pub estops: u32,
+    /// The number of times the robot stopped reporting code while still responding
+    pub code_restarts: u32,
+}
+
+impl MatchSummary {
+    /// Returns the summary as a JSON object, with durations in seconds
+    pub fn to_json(&self) -> String {
+        let started = match self.started {
+            Some(started) => format!(
+                "\"{}\"",
+                started.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ),
+            None => "null".to_string(),
+        };
+        let min_voltage = match self.min_voltage {
+            Some(voltage) if voltage.is_finite() => voltage.to_string(),
+            _ => "null".to_string(),
+        };
+        format!(
+            "{{\"started\":{},\"duration\":{},\"enabled\":{{\"autonomous\":{},\"teleoperated\":{},\"test\":{}}},\
+             \"min_voltage\":{},\"brownouts\":{},\"packet_loss\":{},\"estops\":{},\"code_restarts\":{}}}",
+            started,
+            self.duration.as_secs_f64(),
+            self.enabled.autonomous.as_secs_f64(),
+            self.enabled.teleoperated.as_secs_f64(),
+            self.enabled.test.as_secs_f64(),
+            min_voltage,
+            self.brownouts,
+            self.packet_loss,
+            self.estops,
+            self.code_restarts,
+        )
+    }
+
+    /// Writes the summary to `path` as JSON, replacing the file if it exists
+    pub fn write_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut json = self.to_json();
+        json.push('\n');
+        fs::write(path, json)
+    }
+}
+
+/// Accumulates a [`MatchSummary`] from the send loop's ticks, status packets, and events
+pub(crate) struct SummaryRecorder {
+    started: Option<(Instant, DateTime<Utc>)>,
+    /// When the last tick was recorded, and what it asked of the robot
+    last_tick: Option<(Instant, bool, Mode)>,
+    enabled: EnabledTime,
+    min_voltage: Option<f32>,
+    brownouts: u32,
+    answered: u64,
+    lost: u64,
+    estops: u32,
+    code_restarts: u32,
+}
+
+impl SummaryRecorder {
+    pub(crate) const fn new() -> SummaryRecorder {
+        SummaryRecorder {
+            started: None,
+            last_tick: None,
+            enabled: EnabledTime {
+                autonomous: Duration::ZERO,
+                teleoperated: Duration::ZERO,
+                test: Duration::ZERO,
+            },
+            min_voltage: None,
+            brownouts: 0,
+            answered: 0,
+            lost: 0,
+            estops: 0,
+            code_restarts: 0,
+        }
+    }
+
+    /// Records that a control packet asking for `enabled` in `mode` was sent at `now`
+    ///
+    /// The time since the previous packet counts towards what that packet asked for. Gaps from pauses in sending,
+    /// such as after releasing control, don't count, as the robot disables itself without packets.
+    pub(crate) fn tick(&mut self, now: Instant, wall: DateTime<Utc>, enabled: bool, mode: Mode) {
+        self.started.get_or_insert((now, wall));
+        if let Some((last, was_enabled, was_mode)) = self.last_tick.replace((now, enabled, mode)) {
+            let spacing = now.saturating_duration_since(last);
+            if was_enabled && spacing < CONTROL_PERIOD * 4 {
+                *self.enabled.get_mut(was_mode) += spacing;
+            }
+        }
+    }
+
+    pub(crate) fn voltage(&mut self, voltage: f32) {
+        self.min_voltage = Some(self.min_voltage.map_or(voltage, |min| min.min(voltage)));
+    }
+
+    /// Records that a control packet was either answered or given up on
+    pub(crate) fn retired(&mut self, lost: bool) {
+        if lost {
+            self.lost += 1;
+        } else {
+            self.answered += 1;
+        }
+    }
+
+    /// Returns whether the summary keeps a tally of `event`
+    pub(crate) const fn tallies(event: &Event) -> bool {
+        matches!(
+            event,
+            Event::Brownout | Event::Estopped | Event::CodeRestarting
+        )
+    }
+
+    pub(crate) fn event(&mut self, event: &Event) {
+        match event {
+            Event::Brownout => self.brownouts += 1,
+            Event::Estopped => self.estops += 1,
+            Event::CodeRestarting => self.code_restarts += 1,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn summary(&self, now: Instant) -> MatchSummary {
+        let retired = self.answered + self.lost;
+        MatchSummary {
+            started: self.started.map(|(_, wall)| wall),
+            duration: self
+                .started
+                .map_or(Duration::ZERO, |(at, _)| now.saturating_duration_since(at)),
+            enabled: self.enabled,
+            min_voltage: self.min_voltage,
+            brownouts: self.brownouts,
+            packet_loss: if retired == 0 {
+                0.0
+            } else {
+                self.lost as f32 / retired as f32
+            },
+            estops: self.estops,
+            code_restarts: self.code_restarts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_enabled_time_is_split_by_mode() {
+        let mut recorder = SummaryRecorder::new();
+        let start = Instant::now();
+        let wall = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        for tick in 0..10 {
+            let mode = if tick < 5 {
+                Mode::Autonomous
+            } else {
+                Mode::Teleoperated
+            };
+            recorder.tick(start + CONTROL_PERIOD * tick, wall, tick != 8, mode);
+        }
+        // Sending paused for a second, which isn't counted as enabled
+        recorder.tick(
+            start + Duration::from_secs(2),
+            wall,
+            true,
+            Mode::Teleoperated,
+        );
+        recorder.voltage(12.4);
+        recorder.voltage(11.1);
+        recorder.retired(false);
+        recorder.retired(true);
+        recorder.event(&Event::Brownout);
+        recorder.event(&Event::Disabled);
+
+        let summary = recorder.summary(start + Duration::from_secs(3));
+        assert_eq!(summary.enabled.autonomous, CONTROL_PERIOD * 5);
+        assert_eq!(summary.enabled.teleoperated, CONTROL_PERIOD * 3);
+        assert_eq!(summary.duration, Duration::from_secs(3));
+        assert_eq!(summary.min_voltage, Some(11.1));
+        assert_eq!(summary.brownouts, 1);
+        assert_eq!(summary.packet_loss, 0.5);
+        assert_eq!(
+            summary.to_json(),
+            "{\"started\":\"2023-11-14T22:13:20.000Z\",\"duration\":3,\"enabled\":{\"autonomous\":0.1,\
+             \"teleoperated\":0.06,\"test\":0},\"min_voltage\":11.1,\"brownouts\":1,\"packet_loss\":0.5,\"estops\":0,\
+             \"code_restarts\":0}"
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_summary_follows_the_driver_station() {
+        use crate::Alliance;
+        use crate::ds::state::DsState;
+
+        let state = DsState::new(Alliance::new_red(1), "127.0.0.1".to_string());
+        let start = Instant::now();
+        {
+            let mut stats = state.stats().write().await;
+            for seqnum in 0..4 {
+                let at = start + CONTROL_PERIOD * u32::from(seqnum);
+                stats.packet_sent(seqnum, at);
+                stats.summary_tick(at, state.now(), true, Mode::Teleoperated);
+                stats.packet_received(seqnum, at + Duration::from_millis(2));
+                stats.battery_sample(12.0 - f32::from(seqnum), true, at);
+            }
+        }
+        // Only some events are tallied, as they're emitted
+        for event in [
+            Event::Brownout,
+            Event::Estopped,
+            Event::Disabled,
+            Event::CodeRestarting,
+            Event::Brownout,
+        ] {
+            state.emit(event).await;
+        }
+
+        let summary = state.stats().read().await.match_summary(Instant::now());
+        assert!(summary.started.is_some());
+        assert_eq!(summary.enabled.teleoperated, CONTROL_PERIOD * 3);
+        assert_eq!(summary.min_voltage, Some(9.0));
+        assert_eq!(summary.packet_loss, 0.0);
+        assert_eq!(
+            (summary.brownouts, summary.estops, summary.code_restarts),
+            (2, 1, 1)
+        );
+
+        // Taking the summary starts the next from nothing
+        let taken = state
+            .stats()
+            .write()
+            .await
+            .take_match_summary(Instant::now());
+        assert_eq!((taken.started, taken.brownouts), (summary.started, 2));
+        let next = state.stats().read().await.match_summary(Instant::now());
+        assert_eq!(
+            (next.started, next.brownouts, next.min_voltage),
+            (None, 0, None)
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::task::JoinHandle;
+
+/// When a driver station task last made progress
+///
+/// A task whose `last_active` stops advancing is stuck, such as the send loop waiting on a lock held by a slow caller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TaskActivity {
+    /// The name the task is spawned with, such as `ds-send`
+    pub name: &'static str,
+    pub last_active: Instant,
+}
+
+/// Spawns `future` as a task called `name`
+///
+/// With the `console` feature and `--cfg tokio_unstable`, the name is shown by tokio-console.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn_named<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "console"))]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("Failed to spawn task")
+    }
+    #[cfg(not(all(tokio_unstable, feature = "console")))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
@@ -0,0 +1,276 @@
+use super::events::Event;
+use super::rumble::{EndgameRumble, MatchPhase, Rumble, RumblePattern};
+use super::simclock::SimClock;
+use super::state::DsState;
+use super::tasks::spawn_named;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How much more time the field can report left than the timer has before it's taken as a new period, such as
+/// teleop starting after autonomous, rather than the two clocks drifting apart
+const FMS_RESTART_MARGIN: Duration = Duration::from_secs(1);
+
+/// A running match timer, timing a practice match or following the time the field reports
+pub(crate) struct MatchTimer {
+    source: TimeSource,
+    length: Duration,
+    task: JoinHandle<()>,
+    /// Moves the timer to the time the field last reported, when it's following one
+    fms: Option<watch::Sender<FmsTime>>,
+}
+
+/// How far into the match the field last said it was, and when it said so
+#[derive(Debug, Copy, Clone)]
+struct FmsTime {
+    elapsed: Duration,
+    reported: tokio::time::Instant,
+}
+
+impl FmsTime {
+    fn now(&self) -> Duration {
+        self.elapsed + self.reported.elapsed()
+    }
+}
+
+/// Where the timer reads the time from, and when it started
+#[derive(Clone)]
+enum TimeSource {
+    Real(Instant),
+    /// The simulator's clock, used when one is set in simulation mode
+    Sim {
+        clock: SimClock,
+        start: Duration,
+    },
+    /// The time reported by the field, counted on from the last report
+    Fms(watch::Receiver<FmsTime>),
+}
+
+impl TimeSource {
+    fn elapsed(&self) -> Duration {
+        match self {
+            TimeSource::Real(start) => start.elapsed(),
+            TimeSource::Sim { clock, start } => clock.now().saturating_sub(*start),
+            TimeSource::Fms(reports) => reports.borrow().now(),
+        }
+    }
+
+    /// Waits until `offset` after the timer started
+    async fn sleep_until(&self, offset: Duration) {
+        match self {
+            TimeSource::Real(start) => tokio::time::sleep_until((*start + offset).into()).await,
+            TimeSource::Sim { clock, start } => clock.sleep_until(*start + offset).await,
+            TimeSource::Fms(reports) => {
+                let mut reports = reports.clone();
+                // Each report moves the deadline
+                loop {
+                    let at = *reports.borrow_and_update();
+                    let deadline = at.reported + offset.saturating_sub(at.elapsed);
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(deadline) => return,
+                        changed = reports.changed() => if changed.is_err() {
+                            return tokio::time::sleep_until(deadline).await;
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl MatchTimer {
+    /// Starts a timer for a match `length` long, rumbling joysticks at the end of the match if `rumble` is given
+    pub fn start(
+        state: Arc<DsState>,
+        length: Duration,
+        rumble: Option<EndgameRumble>,
+    ) -> MatchTimer {
+        // A simulator's clock takes precedence over a mock clock set on the driver station
+        let source = match state.sim_clock().or_else(|| state.clock().timebase()) {
+            Some(clock) => TimeSource::Sim {
+                start: clock.now(),
+                clock,
+            },
+            None => TimeSource::Real(Instant::now()),
+        };
+        let task = spawn_named("ds-match-timer", run(state, source.clone(), length, rumble));
+        MatchTimer {
+            source,
+            length,
+            task,
+            fms: None,
+        }
+    }
+
+    /// Starts a timer following the time the field reports, `remaining` being the time it reported left
+    pub fn follow_fms(
+        state: Arc<DsState>,
+        remaining: Duration,
+        rumble: Option<EndgameRumble>,
+    ) -> MatchTimer {
+        let (fms, reports) = watch::channel(FmsTime {
+            elapsed: Duration::ZERO,
+            reported: tokio::time::Instant::now(),
+        });
+        let source = TimeSource::Fms(reports);
+        let task = spawn_named(
+            "ds-match-timer",
+            run(state, source.clone(), remaining, rumble),
+        );
+        MatchTimer {
+            source,
+            length: remaining,
+            task,
+            fms: Some(fms),
+        }
+    }
+
+    /// Moves the timer to the time the field reported left, returning `false` if it isn't following the field or a
+    /// new period started, which need a new timer
+    pub fn report_fms(&self, remaining: Duration) -> bool {
+        let Some(ref fms) = self.fms else {
+            return false;
+        };
+        if remaining > self.remaining() + FMS_RESTART_MARGIN || remaining > self.length {
+            return false;
+        }
+        fms.send_replace(FmsTime {
+            elapsed: self.length - remaining,
+            reported: tokio::time::Instant::now(),
+        });
+        true
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.length.saturating_sub(self.source.elapsed())
+    }
+
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+async fn run(
+    state: Arc<DsState>,
+    source: TimeSource,
+    length: Duration,
+    rumble: Option<EndgameRumble>,
+) {
+    state.emit(Event::MatchPhase(MatchPhase::Started)).await;
+
+    // Without rumble configured the phases are still announced, at the default marks
+    let rumble = rumble.unwrap_or_else(|| EndgameRumble::new(Vec::new()));
+    let marks = [
+        (MatchPhase::Endgame, rumble.endgame, &rumble.endgame_pattern),
+        (
+            MatchPhase::FinalCountdown,
+            rumble.countdown,
+            &rumble.countdown_pattern,
+        ),
+    ];
+
+    for (phase, remaining, pattern) in marks {
+        // Marks that had already passed when the timer was started are skipped
+        if remaining >= length {
+            continue;
+        }
+        source.sleep_until(length - remaining).await;
+        state.emit(Event::MatchPhase(phase)).await;
+        play(&state, &rumble.joysticks, pattern).await;
+    }
+
+    source.sleep_until(length).await;
+    state.emit(Event::MatchPhase(MatchPhase::Ended)).await;
+}
+
+async fn play(state: &DsState, joysticks: &[usize], pattern: &RumblePattern) {
+    if joysticks.is_empty() {
+        return;
+    }
+    for &(rumble, duration) in pattern.steps() {
+        for &joystick in joysticks {
+            state.rumble(joystick, rumble).await;
+        }
+        tokio::time::sleep(duration).await;
+    }
+    for &joystick in joysticks {
+        state.rumble(joystick, Rumble::OFF).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Alliance, JoystickOutput, JoystickSource, JoystickValue};
+    use std::sync::Mutex;
+
+    fn state() -> Arc<DsState> {
+        Arc::new(DsState::new(Alliance::new_red(1), "127.0.0.1".to_string()))
+    }
+
+    #[tokio::test]
+    async fn verify_fms_reports_move_the_timer() {
+        let timer = MatchTimer::follow_fms(state(), Duration::from_secs(60), None);
+        assert!(timer.report_fms(Duration::from_secs(30)));
+        let remaining = timer.remaining();
+        assert!(remaining <= Duration::from_secs(30) && remaining > Duration::from_secs(29));
+        // More time left is a new period, not drift
+        assert!(!timer.report_fms(Duration::from_secs(50)));
+        timer.stop();
+
+        let practice = MatchTimer::start(state(), Duration::from_secs(60), None);
+        assert!(!practice.report_fms(Duration::from_secs(30)));
+        practice.stop();
+    }
+
+    /// A gamepad that records the outputs it's given
+    #[derive(Default)]
+    struct Gamepad(Mutex<Vec<(usize, JoystickOutput)>>);
+
+    impl JoystickSource for Gamepad {
+        fn poll(&self) -> Option<Vec<Vec<JoystickValue>>> {
+            None
+        }
+
+        fn output(&self, slot: usize, output: JoystickOutput) {
+            self.0.lock().unwrap().push((slot, output));
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_rumble_follows_the_field_to_the_joysticks() {
+        let state = state();
+        let gamepad = Arc::new(Gamepad::default());
+        state
+            .send()
+            .write()
+            .await
+            .joystick_sources_mut()
+            .add("gamepad", 0, gamepad.clone());
+        state.set_output_source(Some("gamepad".into())).await;
+        let pulse = RumblePattern::pulses(1, Duration::from_millis(20), Duration::ZERO);
+        let rumble = EndgameRumble {
+            endgame_pattern: pulse.clone(),
+            countdown_pattern: pulse,
+            ..EndgameRumble::new(vec![1])
+        };
+
+        let timer = MatchTimer::follow_fms(state.clone(), Duration::from_secs(60), Some(rumble));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(gamepad.0.lock().unwrap().is_empty());
+        // The field jumping to the endgame brings the rumble forward with it
+        assert!(timer.report_fms(Duration::from_millis(20_100)));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let full = JoystickOutput {
+            outputs: 0,
+            rumble: Rumble::new(1.0, 1.0),
+        };
+        assert_eq!(
+            *gamepad.0.lock().unwrap(),
+            vec![(1, full), (1, JoystickOutput::default())]
+        );
+        timer.stop();
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The number of recent timestamped messages the offset is estimated from
+const WINDOW: usize = 32;
+
+/// An estimate of how the robot's clock relates to this driver station's, for aligning logs from both
+///
+/// Returned by [`DriverStation::clock_sync`](crate::DriverStation::clock_sync).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClockSync {
+    /// The time on this driver station's clock at which the robot's timestamps were zero
+    pub robot_epoch: Option<DateTime<Utc>>,
+    /// Half the round trip time of the link when the estimate was made, which bounds its error
+    pub uncertainty: Option<Duration>,
+    /// When the robot's wall clock was last set from this driver station, in answer to its request for the date
+    pub date_sent: Option<DateTime<Utc>>,
+}
+
+impl ClockSync {
+    /// Converts a timestamp from the robot, such as that of a [`Stdout`](crate::Stdout) message, to this driver station's clock
+    pub fn to_local(&self, timestamp: f32) -> Option<DateTime<Utc>> {
+        let since_epoch = TimeDelta::try_milliseconds((f64::from(timestamp) * 1000.0) as i64)?;
+        self.robot_epoch?.checked_add_signed(since_epoch)
+    }
+}
+
+struct Sample {
+    epoch: DateTime<Utc>,
+    uncertainty: Option<Duration>,
+}
+
+/// Estimates the robot's epoch from the timestamps of the messages it sends
+///
+/// Each message arrives some time after it was stamped, so every sample places the epoch too late by however long it was
+/// delayed. Half the round trip time is subtracted, and the earliest epoch of the recent samples is the least delayed.
+pub(crate) struct ClockEstimator {
+    samples: VecDeque<Sample>,
+    date_sent: Option<DateTime<Utc>>,
+}
+
+impl ClockEstimator {
+    pub(crate) const fn new() -> ClockEstimator {
+        ClockEstimator {
+            samples: VecDeque::new(),
+            date_sent: None,
+        }
+    }
+
+    /// Records a message stamped `timestamp` by the robot that was received at `received`
+    pub(crate) fn sample(
+        &mut self,
+        timestamp: f32,
+        received: DateTime<Utc>,
+        rtt: Option<Duration>,
+    ) {
+        let one_way = rtt.map(|rtt| rtt / 2);
+        let delay = one_way
+            .and_then(|delay| TimeDelta::from_std(delay).ok())
+            .unwrap_or_default();
+        let Some(since_epoch) = TimeDelta::try_milliseconds((f64::from(timestamp) * 1000.0) as i64)
+        else {
+            return;
+        };
+        let Some(epoch) = received.checked_sub_signed(since_epoch + delay) else {
+            return;
+        };
+
+        if self.samples.len() >= WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            epoch,
+            uncertainty: one_way,
+        });
+    }
+
+    pub(crate) fn set_date_sent(&mut self, at: DateTime<Utc>) {
+        self.date_sent = Some(at);
+    }
+
+    /// Forgets the samples, used when the robot may have restarted and its timestamps with it
+    pub(crate) fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    pub(crate) fn estimate(&self) -> ClockSync {
+        let best = self.samples.iter().min_by_key(|sample| sample.epoch);
+        ClockSync {
+            robot_epoch: best.map(|sample| sample.epoch),
+            uncertainty: best.and_then(|sample| sample.uncertainty),
+            date_sent: self.date_sent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_least_delayed_sample_is_used() {
+        let epoch = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let rtt = Some(Duration::from_millis(10));
+        let mut estimator = ClockEstimator::new();
+        assert_eq!(estimator.estimate().robot_epoch, None);
+
+        // Messages stamped at 10s and 20s, arriving 25ms and 5ms after they were stamped
+        estimator.sample(10.0, epoch + TimeDelta::milliseconds(10_025), rtt);
+        estimator.sample(20.0, epoch + TimeDelta::milliseconds(20_005), rtt);
+
+        let sync = estimator.estimate();
+        assert_eq!(sync.robot_epoch, Some(epoch));
+        assert_eq!(sync.uncertainty, Some(Duration::from_millis(5)));
+        assert_eq!(
+            sync.to_local(1.5),
+            Some(epoch + TimeDelta::milliseconds(1500))
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use super::JoystickValue;
+use super::safety::MAX_JOYSTICKS;
+use super::state::send::{NUM_AXES, NUM_BUTTONS, NUM_POVS};
+
+/// What the driver station does with joystick values outside the limits of the protocol
+///
+/// Each joystick is sent with axes `0..6` ranging from `-1.0..=1.0`, buttons `1..=10`, and POV `0` ranging from `0..360`
+/// or `-1` when released.
+///
+/// More than [`MAX_JOYSTICKS`] joysticks, or an axis value that isn't a number, can't be made sense of by any policy.
+/// They are always a [`JoystickFault::Malformed`](crate::JoystickFault::Malformed), and nothing is sent until the
+/// supplier returns values that can be.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum JoystickValidation {
+    /// Axes and POVs are clamped into range, values with ids that can't be sent are discarded, and a warning is logged
+    #[default]
+    ClampAndWarn,
+    /// Packets are sent without any joysticks until the supplier returns valid values
+    DropFrame,
+    /// Invalid values are a [`JoystickFault::Malformed`](crate::JoystickFault::Malformed), and nothing is sent
+    /// until the supplier returns valid values
+    Error,
+}
+
+/// The outcome of calling the joystick supplier once
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JoystickFrame {
+    /// Values to send, with a description of what was changed to make them valid
+    Valid {
+        joysticks: Vec<Vec<JoystickValue>>,
+        warning: Option<String>,
+    },
+    /// Values that were invalid and discarded
+    Dropped(String),
+    /// Values that were invalid and should be reported as a fault
+    Invalid(String),
+    Panicked,
+}
+
+impl JoystickValidation {
+    pub(crate) fn apply(self, mut joysticks: Vec<Vec<JoystickValue>>) -> JoystickFrame {
+        if let Some(problem) = find_malformed(&joysticks) {
+            return JoystickFrame::Invalid(problem);
+        }
+        let Some(problem) = find_problem(&joysticks) else {
+            return JoystickFrame::Valid {
+                joysticks,
+                warning: None,
+            };
+        };
+
+        match self {
+            JoystickValidation::ClampAndWarn => {
+                clamp(&mut joysticks);
+                JoystickFrame::Valid {
+                    joysticks,
+                    warning: Some(problem),
+                }
+            }
+            JoystickValidation::DropFrame => JoystickFrame::Dropped(problem),
+            JoystickValidation::Error => JoystickFrame::Invalid(problem),
+        }
+    }
+}
+
+/// Describes the supplier's output if it's too broken to be clamped, whatever the policy
+fn find_malformed(joysticks: &[Vec<JoystickValue>]) -> Option<String> {
+    if joysticks.len() > MAX_JOYSTICKS {
+        return Some(format!(
+            "{} joysticks were supplied, at most {} can be sent",
+            joysticks.len(),
+            MAX_JOYSTICKS
+        ));
+    }
+
+    for (index, joystick) in joysticks.iter().enumerate() {
+        for value in joystick {
+            if let JoystickValue::Axis { id, value } = *value
+                && !value.is_finite()
+            {
+                return Some(format!("Axis {} of joystick {} is {}", id, index, value));
+            }
+        }
+    }
+    None
+}
+
+/// Describes the first value that can't be sent as is
+fn find_problem(joysticks: &[Vec<JoystickValue>]) -> Option<String> {
+    for (index, joystick) in joysticks.iter().enumerate() {
+        for value in joystick {
+            let problem = match *value {
+                JoystickValue::Axis { id, .. } if id >= NUM_AXES => {
+                    format!("axis {} doesn't exist", id)
+                }
+                JoystickValue::Axis { id, value } if !(-1.0..=1.0).contains(&value) => {
+                    format!("axis {} is {}", id, value)
+                }
+                JoystickValue::Button { id, .. } if !(1..=NUM_BUTTONS).contains(&id) => {
+                    format!("button {} doesn't exist", id)
+                }
+                JoystickValue::POV { id, .. } if id >= NUM_POVS => {
+                    format!("POV {} doesn't exist", id)
+                }
+                JoystickValue::POV { id, angle } if angle != -1 && !(0..360).contains(&angle) => {
+                    format!("POV {} is {}", id, angle)
+                }
+                _ => continue,
+            };
+            return Some(format!("Joystick {}: {}", index, problem));
+        }
+    }
+    None
+}
+
+fn clamp(joysticks: &mut [Vec<JoystickValue>]) {
+    for joystick in joysticks {
+        joystick.retain(|value| match *value {
+            JoystickValue::Axis { id, .. } => id < NUM_AXES,
+            JoystickValue::Button { id, .. } => (1..=NUM_BUTTONS).contains(&id),
+            JoystickValue::POV { id, .. } => id < NUM_POVS,
+        });
+        for value in joystick {
+            match value {
+                JoystickValue::Axis { value, .. } => *value = value.clamp(-1.0, 1.0),
+                JoystickValue::POV { angle, .. } if *angle != -1 && !(0..360).contains(angle) => {
+                    *angle = -1;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_policies() {
+        let joysticks = vec![vec![
+            JoystickValue::Axis { id: 0, value: 1.5 },
+            JoystickValue::Button {
+                id: 11,
+                pressed: true,
+            },
+            JoystickValue::POV { id: 0, angle: 400 },
+        ]];
+
+        match JoystickValidation::ClampAndWarn.apply(joysticks.clone()) {
+            JoystickFrame::Valid { joysticks, warning } => {
+                assert!(warning.is_some());
+                assert_eq!(
+                    joysticks,
+                    vec![vec![
+                        JoystickValue::Axis { id: 0, value: 1.0 },
+                        JoystickValue::POV { id: 0, angle: -1 },
+                    ]]
+                );
+            }
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+        assert!(matches!(
+            JoystickValidation::DropFrame.apply(joysticks.clone()),
+            JoystickFrame::Dropped(_)
+        ));
+        assert!(matches!(
+            JoystickValidation::Error.apply(joysticks),
+            JoystickFrame::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn verify_malformed_values_are_never_clamped() {
+        let stick = vec![JoystickValue::Axis { id: 0, value: 0.5 }];
+        let nan = vec![vec![JoystickValue::Axis {
+            id: 0,
+            value: f32::NAN,
+        }]];
+        for validation in [
+            JoystickValidation::ClampAndWarn,
+            JoystickValidation::DropFrame,
+            JoystickValidation::Error,
+        ] {
+            assert!(matches!(
+                validation.apply(vec![stick.clone(); 7]),
+                JoystickFrame::Invalid(_)
+            ));
+            assert!(matches!(
+                validation.apply(nan.clone()),
+                JoystickFrame::Invalid(_)
+            ));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Disables the robot when the application stops feeding it, such as when a frozen GUI would otherwise keep the robot
+/// enabled
+///
+/// Fed with [`DriverStation::feed_watchdog`](crate::DriverStation::feed_watchdog) or
+/// [`Commander::feed_watchdog`](crate::Commander::feed_watchdog), and checked by the send loop before every packet.
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Inner {
+    /// How long the robot stays enabled without being fed, if the watchdog is on
+    timeout: Option<Duration>,
+    last_fed: Instant,
+}
+
+impl Watchdog {
+    pub(crate) fn new() -> Watchdog {
+        Watchdog {
+            inner: Mutex::new(Inner {
+                timeout: None,
+                last_fed: Instant::now(),
+            }),
+        }
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.lock().timeout
+    }
+
+    /// Changes the timeout, or turns the watchdog off with `None`. This counts as a feed, so it doesn't expire at once
+    pub(crate) fn set_timeout(&self, timeout: Option<Duration>) {
+        let mut inner = self.lock();
+        inner.timeout = timeout;
+        inner.last_fed = Instant::now();
+    }
+
+    pub(crate) fn feed(&self, now: Instant) {
+        self.lock().last_fed = now;
+    }
+
+    /// Returns whether the watchdog is on and hasn't been fed within its timeout at `now`
+    pub(crate) fn expired(&self, now: Instant) -> bool {
+        let inner = *self.lock();
+        inner
+            .timeout
+            .is_some_and(|timeout| now.saturating_duration_since(inner.last_fed) > timeout)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_watchdog_expires_without_feeding() {
+        let watchdog = Watchdog::new();
+        let now = Instant::now();
+        assert!(!watchdog.expired(now + Duration::from_secs(60)));
+
+        watchdog.set_timeout(Some(Duration::from_millis(100)));
+        watchdog.feed(now);
+        assert!(!watchdog.expired(now + Duration::from_millis(100)));
+        assert!(watchdog.expired(now + Duration::from_millis(101)));
+
+        watchdog.feed(now + Duration::from_millis(150));
+        assert!(!watchdog.expired(now + Duration::from_millis(200)));
+    }
+}
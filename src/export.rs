@@ -0,0 +1,361 @@
+//! Exporting of driver station telemetry to CSV or JSON lines files
+//!
+//! [`export`] appends one row of the chosen [`Field`]s every interval, each starting with its timestamp. Files are
+//! named after the configured path with the time they were started, such as `practice-20240312-184501.csv`, and a new
+//! one is started when the current one grows too large or too old.
+
+use crate::{DriverStation, DsSnapshot, Stats};
+
+use chrono::{DateTime, Local, Utc};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+
+/// How rows are written
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Comma separated values, with a header row at the top of each file
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl Format {
+    const fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// A column of the exported telemetry
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// The battery voltage, in volts
+    BatteryVoltage,
+    /// The requested robot mode
+    Mode,
+    /// Whether the robot was requested to be enabled
+    Enabled,
+    /// Whether the robot reported being enabled
+    RobotEnabled,
+    Estopped,
+    Brownout,
+    /// Whether status packets are being received
+    Connected,
+    TcpConnected,
+    /// The round trip time to the robot, in milliseconds
+    Latency,
+    /// The fraction of recent control packets that were never answered
+    PacketLoss,
+    /// The bits of the last received [`Trace`](crate::Trace)
+    Trace,
+    /// The seconds left in the practice match
+    MatchTimeRemaining,
+}
+
+impl Field {
+    /// Every field, in the order they are exported by default
+    pub const ALL: [Field; 12] = [
+        Field::BatteryVoltage,
+        Field::Mode,
+        Field::Enabled,
+        Field::RobotEnabled,
+        Field::Estopped,
+        Field::Brownout,
+        Field::Connected,
+        Field::TcpConnected,
+        Field::Latency,
+        Field::PacketLoss,
+        Field::Trace,
+        Field::MatchTimeRemaining,
+    ];
+
+    /// The name of the field, used as its CSV column and JSON key
+    pub const fn name(self) -> &'static str {
+        match self {
+            Field::BatteryVoltage => "battery_voltage",
+            Field::Mode => "mode",
+            Field::Enabled => "enabled",
+            Field::RobotEnabled => "robot_enabled",
+            Field::Estopped => "estopped",
+            Field::Brownout => "brownout",
+            Field::Connected => "connected",
+            Field::TcpConnected => "tcp_connected",
+            Field::Latency => "latency_ms",
+            Field::PacketLoss => "packet_loss",
+            Field::Trace => "trace",
+            Field::MatchTimeRemaining => "match_time_remaining",
+        }
+    }
+
+    fn value(self, snapshot: &DsSnapshot, stats: &Stats) -> Value {
+        match self {
+            Field::BatteryVoltage => Value::Number(f64::from(snapshot.battery_voltage)),
+            Field::Mode => Value::Text(format!("{:?}", snapshot.mode)),
+            Field::Enabled => Value::Bool(snapshot.enabled),
+            Field::RobotEnabled => Value::Bool(snapshot.robot_enabled),
+            Field::Estopped => Value::Bool(snapshot.estopped),
+            Field::Brownout => Value::Bool(snapshot.brownout),
+            Field::Connected => Value::Bool(snapshot.connected),
+            Field::TcpConnected => Value::Bool(snapshot.tcp_connected),
+            Field::Latency => match stats.latency {
+                Some(latency) => Value::Number(latency.as_secs_f64() * 1000.0),
+                None => Value::Missing,
+            },
+            Field::PacketLoss => Value::Number(f64::from(stats.packet_loss)),
+            Field::Trace => Value::Number(f64::from(snapshot.trace.bits())),
+            Field::MatchTimeRemaining => match snapshot.match_time_remaining {
+                Some(remaining) => Value::Number(remaining.as_secs_f64()),
+                None => Value::Missing,
+            },
+        }
+    }
+}
+
+enum Value {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    Missing,
+}
+
+/// When to start a new file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Rotation {
+    /// The size a file can grow to, in bytes
+    pub max_bytes: Option<u64>,
+    /// How long rows are appended to one file
+    pub max_age: Option<Duration>,
+}
+
+/// What to export, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportConfig {
+    /// The path files are named after. The extension is replaced with that of the format
+    pub path: PathBuf,
+    pub format: Format,
+    /// The columns of each row, after the timestamp
+    pub fields: Vec<Field>,
+    /// The time between rows
+    pub interval: Duration,
+    pub rotation: Rotation,
+}
+
+impl ExportConfig {
+    /// Exports every field to files named after `path` every 100ms, without rotating
+    pub fn new(path: impl Into<PathBuf>, format: Format) -> ExportConfig {
+        ExportConfig {
+            path: path.into(),
+            format,
+            fields: Field::ALL.to_vec(),
+            interval: Duration::from_millis(100),
+            rotation: Rotation::default(),
+        }
+    }
+}
+
+/// The file rows are currently appended to
+struct Output {
+    writer: BufWriter<File>,
+    bytes: u64,
+    opened: Instant,
+}
+
+impl Output {
+    async fn open(config: &ExportConfig) -> io::Result<Output> {
+        let path = file_path(&config.path, config.format, Local::now());
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let mut output = Output {
+            writer: BufWriter::new(file),
+            bytes: 0,
+            opened: Instant::now(),
+        };
+        if config.format == Format::Csv {
+            let header = csv_header(&config.fields);
+            output.write(&header).await?;
+        }
+        Ok(output)
+    }
+
+    async fn write(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.write_all(b"\n").await?;
+        self.bytes += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn expired(&self, rotation: &Rotation) -> bool {
+        rotation.max_bytes.is_some_and(|max| self.bytes >= max)
+            || rotation
+                .max_age
+                .is_some_and(|max| self.opened.elapsed() >= max)
+    }
+}
+
+/// Appends telemetry from `ds` to the files described by `config`
+///
+/// Rows are flushed as they are written, so a spreadsheet can be opened while exporting. This future only returns if
+/// writing fails, drop it to stop exporting.
+pub async fn export(ds: Arc<Mutex<DriverStation>>, config: ExportConfig) -> io::Result<()> {
+    let mut output = Output::open(&config).await?;
+    let mut ticker = tokio::time::interval(config.interval);
+
+    loop {
+        ticker.tick().await;
+        let (snapshot, stats) = {
+            let ds = ds.lock().await;
+            (ds.status_snapshot().await, ds.stats().await)
+        };
+
+        if output.expired(&config.rotation) {
+            output = Output::open(&config).await?;
+        }
+        let row = format_row(config.format, &config.fields, Utc::now(), &snapshot, &stats);
+        output.write(&row).await?;
+        output.writer.flush().await?;
+    }
+}
+
+/// Inserts the time the file was started before the extension of `path`
+fn file_path(path: &Path, format: Format, started: DateTime<Local>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "telemetry".to_string());
+    let name = format!(
+        "{}-{}.{}",
+        stem,
+        started.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    );
+    path.with_file_name(name)
+}
+
+fn csv_header(fields: &[Field]) -> String {
+    let mut header = "timestamp".to_string();
+    for field in fields {
+        header.push(',');
+        header.push_str(field.name());
+    }
+    header
+}
+
+fn format_row(
+    format: Format,
+    fields: &[Field],
+    timestamp: DateTime<Utc>,
+    snapshot: &DsSnapshot,
+    stats: &Stats,
+) -> String {
+    let timestamp = timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    match format {
+        Format::Csv => {
+            let mut row = timestamp;
+            for field in fields {
+                row.push(',');
+                match field.value(snapshot, stats) {
+                    Value::Number(n) => row.push_str(&n.to_string()),
+                    Value::Bool(b) => row.push_str(if b { "true" } else { "false" }),
+                    // Mode names never contain commas or quotes
+                    Value::Text(s) => row.push_str(&s),
+                    Value::Missing => {}
+                }
+            }
+            row
+        }
+        Format::JsonLines => {
+            let mut row = format!("{{\"timestamp\":\"{}\"", timestamp);
+            for field in fields {
+                let value = match field.value(snapshot, stats) {
+                    Value::Number(n) if n.is_finite() => n.to_string(),
+                    Value::Number(_) | Value::Missing => "null".to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    Value::Text(s) => format!("\"{}\"", s),
+                };
+                row.push_str(&format!(",\"{}\":{}", field.name(), value));
+            }
+            row.push('}');
+            row
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{InboundTagKind, Mode, TagStats, Trace};
+
+    #[test]
+    fn verify_rows_are_formatted() {
+        let snapshot = DsSnapshot {
+            battery_voltage: 12.5,
+            mode: Mode::Teleoperated,
+            enabled: true,
+            robot_enabled: true,
+            estopped: false,
+            brownout: false,
+            trace: Trace::empty(),
+            connected: true,
+            tcp_connected: false,
+            udp_only: false,
+            latency: None,
+            match_time_remaining: None,
+        };
+        let stats = Stats {
+            packets_sent: 0,
+            packets_received: 0,
+            latency: Some(Duration::from_millis(4)),
+            packet_loss: 0.0,
+            reconnects: 0,
+            packets_rejected: 0,
+            packets_reordered: 0,
+            packets_duplicated: 0,
+            decode_errors: 0,
+            tcp_decode_errors: 0,
+            tick_jitter: Duration::ZERO,
+            smoothed_voltage: None,
+            last_sag: None,
+            tags: InboundTagKind::ALL.map(|kind| TagStats {
+                kind,
+                received: 0,
+                malformed: 0,
+                rate: 0.0,
+            }),
+            unknown_tags: 0,
+            supplier_time: Duration::ZERO,
+            slow_supplies: 0,
+        };
+        let fields = [
+            Field::BatteryVoltage,
+            Field::Mode,
+            Field::Enabled,
+            Field::MatchTimeRemaining,
+            Field::Latency,
+        ];
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        assert_eq!(
+            csv_header(&fields),
+            "timestamp,battery_voltage,mode,enabled,match_time_remaining,latency_ms"
+        );
+        assert_eq!(
+            format_row(Format::Csv, &fields, timestamp, &snapshot, &stats),
+            "2023-11-14T22:13:20.000Z,12.5,Teleoperated,true,,4"
+        );
+        assert_eq!(
+            format_row(Format::JsonLines, &fields, timestamp, &snapshot, &stats),
+            "{\"timestamp\":\"2023-11-14T22:13:20.000Z\",\"battery_voltage\":12.5,\"mode\":\"Teleoperated\",\
+             \"enabled\":true,\"match_time_remaining\":null,\"latency_ms\":4}"
+        );
+    }
+}
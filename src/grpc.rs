@@ -0,0 +1,221 @@
+//! A gRPC service for controlling a driver station
+//!
+//! The service is described by `proto/ds.proto` in the root of the crate, which can be used to generate clients
+//! in other languages. Run it with [`serve`], or add [`ControlService::into_server`] to an existing tonic server.
+
+use crate::{DriverStation, JoystickValue, Mode};
+
+use futures_util::stream::{self, Stream};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+/// Messages and service definitions generated from `proto/ds.proto`
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("ds");
+}
+
+use self::proto::driver_station_control_server::{
+    DriverStationControl, DriverStationControlServer,
+};
+
+/// The time between telemetry updates if the client doesn't ask for a specific interval
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+type RpcResult<T> = std::result::Result<Response<T>, Status>;
+
+/// Implements the `DriverStationControl` service on top of a [`DriverStation`]
+pub struct ControlService {
+    ds: Arc<Mutex<DriverStation>>,
+    joysticks: Arc<RwLock<Vec<Vec<JoystickValue>>>>,
+}
+
+impl ControlService {
+    /// Wraps `ds`, replacing its joystick supplier with one that sends the values from `SetJoysticks` calls
+    pub async fn new(mut ds: DriverStation) -> ControlService {
+        let joysticks = Arc::new(RwLock::new(Vec::new()));
+        let supplier = joysticks.clone();
+        ds.set_joystick_supplier(move || supplier.read().unwrap().clone())
+            .await;
+
+        ControlService {
+            ds: Arc::new(Mutex::new(ds)),
+            joysticks,
+        }
+    }
+
+    /// Returns the wrapped driver station, for use alongside the service
+    pub fn driver_station(&self) -> Arc<Mutex<DriverStation>> {
+        self.ds.clone()
+    }
+
+    pub fn into_server(self) -> DriverStationControlServer<ControlService> {
+        DriverStationControlServer::new(self)
+    }
+}
+
+/// Serves the `DriverStationControl` service for `ds` on `addr`
+///
+/// This future only returns if the server fails.
+pub async fn serve(ds: DriverStation, addr: SocketAddr) -> crate::Result<()> {
+    tonic::transport::Server::builder()
+        .add_service(ControlService::new(ds).await.into_server())
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl DriverStationControl for ControlService {
+    async fn enable(&self, _: Request<proto::Empty>) -> RpcResult<proto::Empty> {
+        let mut ds = self.ds.lock().await;
+        if ds.estopped().await {
+            return Err(Status::failed_precondition("The robot is estopped"));
+        }
+        ds.enable().await;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn disable(&self, _: Request<proto::Empty>) -> RpcResult<proto::Empty> {
+        self.ds.lock().await.disable().await;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn estop(&self, _: Request<proto::Empty>) -> RpcResult<proto::Empty> {
+        self.ds.lock().await.estop().await;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn set_mode(&self, request: Request<proto::SetModeRequest>) -> RpcResult<proto::Empty> {
+        let mode = proto::Mode::try_from(request.into_inner().mode)
+            .map_err(|_| Status::invalid_argument("Unknown mode"))?;
+        self.ds.lock().await.set_mode(mode.into()).await;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn set_joysticks(
+        &self,
+        request: Request<proto::SetJoysticksRequest>,
+    ) -> RpcResult<proto::Empty> {
+        let joysticks = request
+            .into_inner()
+            .joysticks
+            .into_iter()
+            .map(joystick_values)
+            .collect();
+        *self.joysticks.write().unwrap() = joysticks;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_telemetry(&self, _: Request<proto::Empty>) -> RpcResult<proto::Telemetry> {
+        let ds = self.ds.lock().await;
+        Ok(Response::new(telemetry(&ds).await))
+    }
+
+    type StreamTelemetryStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<proto::Telemetry, Status>> + Send>>;
+
+    async fn stream_telemetry(
+        &self,
+        request: Request<proto::StreamTelemetryRequest>,
+    ) -> RpcResult<Self::StreamTelemetryStream> {
+        let interval = match request.into_inner().interval_ms {
+            0 => DEFAULT_INTERVAL,
+            ms => Duration::from_millis(ms.into()),
+        };
+
+        let ticker = tokio::time::interval(interval);
+        let stream = stream::unfold((self.ds.clone(), ticker), |(ds, mut ticker)| async move {
+            ticker.tick().await;
+            let telemetry = telemetry(&*ds.lock().await).await;
+            Some((Ok(telemetry), (ds, ticker)))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+async fn telemetry(ds: &DriverStation) -> proto::Telemetry {
+    let stats = ds.stats().await;
+    proto::Telemetry {
+        enabled: ds.enabled().await,
+        estopped: ds.estopped().await,
+        mode: proto::Mode::from(ds.mode().await).into(),
+        battery_voltage: ds.battery_voltage().await,
+        robot_code: ds.trace().await.contains(crate::Trace::ROBOT_CODE),
+        packets_sent: stats.packets_sent,
+        packets_received: stats.packets_received,
+        latency_ms: stats.latency.map(|latency| latency.as_secs_f32() * 1000.0),
+        packet_loss: stats.packet_loss,
+    }
+}
+
+/// Converts a joystick from a request into the values sent to the roboRIO. Ids beyond what the protocol supports are ignored
+fn joystick_values(joystick: proto::Joystick) -> Vec<JoystickValue> {
+    let axes = (0..=u8::MAX)
+        .zip(joystick.axes)
+        .map(|(id, value)| JoystickValue::Axis { id, value });
+    let buttons = (1..=u8::MAX)
+        .zip(joystick.buttons)
+        .map(|(id, pressed)| JoystickValue::Button { id, pressed });
+    let povs = (0..=u8::MAX)
+        .zip(joystick.povs)
+        .map(|(id, angle)| JoystickValue::POV {
+            id,
+            angle: i16::try_from(angle).unwrap_or(-1),
+        });
+    axes.chain(buttons).chain(povs).collect()
+}
+
+impl From<proto::Mode> for Mode {
+    fn from(mode: proto::Mode) -> Mode {
+        match mode {
+            proto::Mode::Teleoperated => Mode::Teleoperated,
+            proto::Mode::Autonomous => Mode::Autonomous,
+            proto::Mode::Test => Mode::Test,
+        }
+    }
+}
+
+impl From<Mode> for proto::Mode {
+    fn from(mode: Mode) -> proto::Mode {
+        match mode {
+            Mode::Teleoperated => proto::Mode::Teleoperated,
+            Mode::Autonomous => proto::Mode::Autonomous,
+            Mode::Test => proto::Mode::Test,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_joystick_conversion() {
+        let values = joystick_values(proto::Joystick {
+            axes: vec![0.5],
+            buttons: vec![false, true],
+            povs: vec![90],
+        });
+
+        assert_eq!(
+            values,
+            vec![
+                JoystickValue::Axis { id: 0, value: 0.5 },
+                JoystickValue::Button {
+                    id: 1,
+                    pressed: false
+                },
+                JoystickValue::Button {
+                    id: 2,
+                    pressed: true
+                },
+                JoystickValue::POV { id: 0, angle: 90 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! An HTTP interface for controlling a driver station
+//!
+//! Meant for quick integrations and scripting with tools like curl. Every body is JSON.
+//!
+//! | Method | Path                | Body                        |
+//! |--------|---------------------|-----------------------------|
+//! | GET    | `/state`            |                             |
+//! | POST   | `/enable`           |                             |
+//! | POST   | `/disable`          |                             |
+//! | POST   | `/estop`            |                             |
+//! | PUT    | `/mode`             | `{"mode": "autonomous"}`    |
+//! | PUT    | `/game-data`        | `{"message": "LRL"}`        |
+//! | GET    | `/telemetry/stream` | Server-sent events of state |
+//!
+//! The stream sends an update every 100ms, which can be changed with the `interval_ms` query parameter.
+
+use crate::{DriverStation, Mode, Trace};
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::Mutex;
+
+/// The time between telemetry updates if the client doesn't ask for a specific interval
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+type SharedDs = Arc<Mutex<DriverStation>>;
+
+/// Serves the HTTP interface for `ds` on `listen`
+///
+/// This future only returns if the server fails.
+pub async fn serve(ds: DriverStation, listen: impl ToSocketAddrs) -> crate::Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+    axum::serve(listener, router(Arc::new(Mutex::new(ds)))).await?;
+    Ok(())
+}
+
+/// Builds the routes of the HTTP interface, so they can be merged into an existing axum application
+pub fn router(ds: Arc<Mutex<DriverStation>>) -> Router {
+    Router::new()
+        .route("/state", get(state))
+        .route("/enable", post(enable))
+        .route("/disable", post(disable))
+        .route("/estop", post(estop))
+        .route("/mode", put(set_mode))
+        .route("/game-data", put(set_game_data))
+        .route("/telemetry/stream", get(stream_telemetry))
+        .with_state(ds)
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ModeBody {
+    Autonomous,
+    Teleoperated,
+    Test,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetMode {
+    mode: ModeBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetGameData {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamParams {
+    interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct StateBody {
+    enabled: bool,
+    estopped: bool,
+    mode: ModeBody,
+    battery_voltage: f32,
+    robot_code: bool,
+    packets_sent: u64,
+    packets_received: u64,
+    latency_ms: Option<f32>,
+    packet_loss: f32,
+}
+
+async fn snapshot(ds: &DriverStation) -> StateBody {
+    let stats = ds.stats().await;
+    StateBody {
+        enabled: ds.enabled().await,
+        estopped: ds.estopped().await,
+        mode: ds.mode().await.into(),
+        battery_voltage: ds.battery_voltage().await,
+        robot_code: ds.trace().await.contains(Trace::ROBOT_CODE),
+        packets_sent: stats.packets_sent,
+        packets_received: stats.packets_received,
+        latency_ms: stats.latency.map(|latency| latency.as_secs_f32() * 1000.0),
+        packet_loss: stats.packet_loss,
+    }
+}
+
+async fn state(State(ds): State<SharedDs>) -> Json<StateBody> {
+    Json(snapshot(&*ds.lock().await).await)
+}
+
+async fn enable(State(ds): State<SharedDs>) -> Response {
+    let mut ds = ds.lock().await;
+    if ds.estopped().await {
+        return (StatusCode::CONFLICT, "The robot is estopped").into_response();
+    }
+    ds.enable().await;
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn disable(State(ds): State<SharedDs>) -> StatusCode {
+    ds.lock().await.disable().await;
+    StatusCode::NO_CONTENT
+}
+
+async fn estop(State(ds): State<SharedDs>) -> StatusCode {
+    ds.lock().await.estop().await;
+    StatusCode::NO_CONTENT
+}
+
+async fn set_mode(State(ds): State<SharedDs>, Json(body): Json<SetMode>) -> StatusCode {
+    ds.lock().await.set_mode(body.mode.into()).await;
+    StatusCode::NO_CONTENT
+}
+
+async fn set_game_data(State(ds): State<SharedDs>, Json(body): Json<SetGameData>) -> Response {
+    match ds
+        .lock()
+        .await
+        .set_game_specific_message(&body.message)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn stream_telemetry(
+    State(ds): State<SharedDs>,
+    Query(params): Query<StreamParams>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let interval = match params.interval_ms {
+        None | Some(0) => DEFAULT_INTERVAL,
+        Some(ms) => Duration::from_millis(ms),
+    };
+
+    let ticker = tokio::time::interval(interval);
+    let stream = stream::unfold((ds, ticker), |(ds, mut ticker)| async move {
+        ticker.tick().await;
+        let state = snapshot(&*ds.lock().await).await;
+        let event = Event::default()
+            .event("telemetry")
+            .json_data(state)
+            .expect("Telemetry is always serializable");
+        Some((Ok(event), (ds, ticker)))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+impl From<ModeBody> for Mode {
+    fn from(mode: ModeBody) -> Mode {
+        match mode {
+            ModeBody::Autonomous => Mode::Autonomous,
+            ModeBody::Teleoperated => Mode::Teleoperated,
+            ModeBody::Test => Mode::Test,
+        }
+    }
+}
+
+impl From<Mode> for ModeBody {
+    fn from(mode: Mode) -> ModeBody {
+        match mode {
+            Mode::Autonomous => ModeBody::Autonomous,
+            Mode::Teleoperated => ModeBody::Teleoperated,
+            Mode::Test => ModeBody::Test,
+        }
+    }
+}
@@ -11,20 +11,84 @@
 #![doc(html_root_url = "https://docs.rs/ds/1.0.1")]
 #![forbid(unsafe_code)]
 #![allow(dead_code)]
+// Much of the crate only exists to support `DriverStation`, which isn't available on wasm
+#![cfg_attr(target_arch = "wasm32", allow(unused_imports, unused_macros))]
 
 #[macro_use]
 extern crate bitflags;
 
+// Declared first, so its macros are in scope in every module after it
+#[macro_use]
+mod diag;
+
+#[cfg(feature = "capture")]
+pub mod capture;
+#[cfg(all(feature = "consolelog", not(target_arch = "wasm32")))]
+pub mod consolelog;
+#[cfg(all(feature = "dashboard", not(target_arch = "wasm32")))]
+pub mod dashboard;
 mod ds;
+#[cfg(all(feature = "export", not(target_arch = "wasm32")))]
+pub mod export;
 mod ext;
+#[cfg(all(feature = "grpc", not(target_arch = "wasm32")))]
+pub mod grpc;
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+pub mod http;
+#[cfg(all(feature = "loopback", not(target_arch = "wasm32")))]
+pub mod loopback;
+#[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+pub mod mqtt;
 mod proto;
+pub mod relay;
 pub(crate) mod util;
+#[cfg(all(feature = "wpilog", not(target_arch = "wasm32")))]
+pub mod wpilog;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::ds::discovery;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::ds::selftest;
 pub use self::ds::state::{DsMode, Mode};
-pub use self::ds::{DriverStation, JoystickValue};
+pub use self::ds::{
+    Alert, AlertSink, AllianceSource, CanUtilizationAlert, Clock, ClockSync, Command, Commander,
+    CompositeJoystick, ConnectionState, ConsoleLevel, ConsoleLimits, ConsoleLine, ControlConflict,
+    ControlSink, Controller, DropAction, DsConfig, DsSnapshot, EnabledTime, EndgameRumble, Event,
+    GamepadAxis, GamepadButton, InputChange, InputMap, JoystickFault, JoystickMapping,
+    JoystickSafety, JoystickShape, JoystickSource, JoystickSuppression, JoystickValidation,
+    JoystickValue, LinkConditions, MAX_JOYSTICKS, MappingProfile, MatchPhase, MatchSummary,
+    MockClock, NT4_PORT, NetworkTables, Pacing, PacketSent, Ports, PracticeTiming, ReconnectPolicy,
+    ResourceLimits, RobotIdentity, Rumble, RumblePattern, SUPPLIER_SOURCE, Severity, ShapeMismatch,
+    ShutdownReason, ShutdownReport, SimClock, SocketOptions, SourceFilter, StateChange, Stats,
+    StatusTransition, StickAxis, SystemClock, TagStats, TaskActivity, TelemetryWindow, TimedEvent,
+    VoltageRange, VoltageSag,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::ds::{DriverStation, DriverStationBuilder};
+pub use self::proto::Direction;
+#[cfg(feature = "crio")]
+pub use self::proto::crio;
+pub use self::proto::tcp::DsTcpCodec;
 pub use self::proto::tcp::inbound::*;
 pub use self::proto::tcp::outbound::*;
-pub use self::proto::udp::inbound::types::Trace;
+pub use self::proto::udp::DsUdpCodec;
+pub use self::proto::udp::inbound::UdpResponsePacket;
+pub use self::proto::udp::inbound::types::{
+    CANMetrics, DiskInfo, InboundTagKind, JoystickOutput, RAMInfo, RawStatus, RobotRequests,
+    Status, Trace,
+};
+pub use self::proto::udp::outbound::UdpControlPacket;
 pub use self::proto::udp::outbound::types::*;
+pub use self::proto::year::ProtocolYear;
+
+/// Internal types re-exported for the benchmarks and the allocation test. This is not part of the public API
+#[cfg(feature = "internals")]
+#[doc(hidden)]
+pub mod internals {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use crate::ds::conn::{JoystickOutcome, encode_tick};
+    pub use crate::ds::sources::{JoystickSources, Polled};
+    pub use crate::ds::state::send::{SendState, build_control_packet};
+}
 
 pub type Result<T> = std::result::Result<T, anyhow::Error>;
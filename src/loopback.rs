@@ -0,0 +1,197 @@
+//! A fake robot on the loopback interface, to run a driver station with no hardware or network setup
+//!
+//! [`LoopbackRobot`] answers control packets the way a roboRIO running robot code would, confirming the mode and
+//! enabled state it's sent, and accepts the TCP connection. It's meant for trying out examples and for tests in CI,
+//! not for simulating a robot program, use a WPILib simulator for that.
+//!
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! let (mut ds, _robot) = ds::loopback::connect().await?;
+//! ds.enable().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Control, DriverStation, DsConfig, Mode, Ports, Status, Trace};
+
+use std::io;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::task::JoinHandle;
+
+/// The battery voltage reported until [`LoopbackRobot::set_battery_voltage`] is called
+const DEFAULT_VOLTAGE: f32 = 12.5;
+
+/// A fake roboRIO on 127.0.0.1, answering the driver station on the ports it's started with
+///
+/// The robot stops when this is dropped.
+#[derive(Debug)]
+pub struct LoopbackRobot {
+    ports: Ports,
+    /// The bits of the reported battery voltage, shared with the UDP task
+    voltage: Arc<AtomicU32>,
+    tasks: [JoinHandle<()>; 2],
+}
+
+impl LoopbackRobot {
+    /// Starts a robot listening on the robot UDP and TCP ports of `ports`, and answering on its driver station port
+    ///
+    /// Fails if the ports are taken, such as by a WPILib simulator or another loopback robot. Tests running in
+    /// parallel should each use their own ports.
+    pub async fn start(ports: Ports) -> io::Result<LoopbackRobot> {
+        let udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, ports.robot_udp)).await?;
+        let tcp = TcpListener::bind((Ipv4Addr::LOCALHOST, ports.tcp)).await?;
+        let voltage = Arc::new(AtomicU32::new(DEFAULT_VOLTAGE.to_bits()));
+
+        let udp_voltage = voltage.clone();
+        let tasks = [
+            tokio::spawn(async move {
+                if let Err(e) = answer_udp(udp, ports.ds_udp, udp_voltage).await {
+                    warn!("Loopback robot stopped answering: {}", e);
+                }
+            }),
+            tokio::spawn(async move {
+                while let Ok((conn, _)) = tcp.accept().await {
+                    tokio::spawn(hold_tcp(conn));
+                }
+            }),
+        ];
+        Ok(LoopbackRobot {
+            ports,
+            voltage,
+            tasks,
+        })
+    }
+
+    /// Returns a config for a driver station that talks to this robot, with the other settings left to defaults
+    pub fn config(&self) -> DsConfig {
+        DsConfig {
+            ip: Some(Ipv4Addr::LOCALHOST.to_string()),
+            ports: self.ports,
+            // The robot is on the same address a simulator would be, and isn't one
+            simulator: false,
+            ..DsConfig::default()
+        }
+    }
+
+    #[inline(always)]
+    pub const fn ports(&self) -> Ports {
+        self.ports
+    }
+
+    /// Changes the battery voltage reported to the driver station, such as to try out brownout handling
+    pub fn set_battery_voltage(&self, voltage: f32) {
+        self.voltage.store(voltage.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn battery_voltage(&self) -> f32 {
+        f32::from_bits(self.voltage.load(Ordering::Relaxed))
+    }
+}
+
+impl Drop for LoopbackRobot {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Starts a [`LoopbackRobot`] on the default ports, and a driver station connected to it
+pub async fn connect() -> io::Result<(DriverStation, LoopbackRobot)> {
+    let robot = LoopbackRobot::start(Ports::default()).await?;
+    let ds = DriverStation::from_config(&robot.config()).await;
+    Ok((ds, robot))
+}
+
+/// Answers each control packet with a status packet confirming it
+async fn answer_udp(socket: UdpSocket, ds_port: u16, voltage: Arc<AtomicU32>) -> io::Result<()> {
+    let mut buf = [0u8; 1500];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        if len < 6 {
+            continue;
+        }
+        let voltage = f32::from_bits(voltage.load(Ordering::Relaxed));
+        let reply = status_packet(&buf[..len], voltage);
+        // The driver station not listening yet isn't an error, it sends again in 20ms
+        let _ = socket.send_to(&reply, (from.ip(), ds_port)).await;
+    }
+}
+
+/// Builds the status packet answering `control`, which is at least 6 bytes long
+fn status_packet(control: &[u8], voltage: f32) -> [u8; 8] {
+    let control_bits = Control::from_bits_truncate(control[3]);
+    let status = Status::from_bits_truncate(
+        control_bits
+            .intersection(Control::ESTOP | Control::ENABLED | Control::MODE)
+            .bits(),
+    );
+    let mut trace = Trace::ROBOT_CODE | Trace::IS_ROBORIO;
+    if !status.contains(Status::ENABLED) {
+        trace |= Trace::DISABLED;
+    } else {
+        trace |= match Mode::from_status(status) {
+            Some(Mode::Autonomous) => Trace::AUTONOMOUS,
+            Some(Mode::Test) => Trace::TEST_MODE,
+            _ => Trace::TELEOP,
+        };
+    }
+    let voltage = voltage.clamp(0.0, 255.0);
+    let whole = voltage.trunc();
+    [
+        control[0],
+        control[1],
+        0x01,
+        status.bits(),
+        trace.bits(),
+        whole as u8,
+        ((voltage - whole) * 256.0) as u8,
+        // The date isn't needed, there's no robot program to give it to
+        0,
+    ]
+}
+
+/// Keeps a TCP connection from the driver station open until it's closed, discarding what's sent
+async fn hold_tcp(conn: TcpStream) {
+    let mut buf = [0u8; 1024];
+    loop {
+        if conn.readable().await.is_err() {
+            return;
+        }
+        match conn.try_read(&mut buf) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::UdpResponsePacket;
+
+    #[test]
+    fn verify_status_confirms_control() {
+        let control = [
+            0x12,
+            0x34,
+            0x01,
+            (Control::ENABLED | Control::AUTO).bits(),
+            0,
+            0,
+        ];
+        let reply = status_packet(&control, 12.5);
+        let packet = UdpResponsePacket::decode(&mut &reply[..]).unwrap();
+
+        assert_eq!(packet.seqnum, 0x1234);
+        assert!(packet.status.contains(Status::ENABLED));
+        assert_eq!(Mode::from_status(packet.status), Some(Mode::Autonomous));
+        assert!(packet.trace.contains(Trace::ROBOT_CODE | Trace::AUTONOMOUS));
+        assert_eq!(packet.battery, 12.5);
+    }
+}
@@ -0,0 +1,107 @@
+//! Publishing of driver station telemetry to an MQTT broker
+//!
+//! Battery voltage, trace, and mode are published periodically, and every [`Event`](crate::Event) is published once
+//! to the events topic as it happens. Payloads are plain text.
+
+use crate::DriverStation;
+
+use chrono::{DateTime, Utc};
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// The topics telemetry is published to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topics {
+    /// The battery voltage, in volts
+    pub battery_voltage: String,
+    /// The bits of the last received [`Trace`](crate::Trace)
+    pub trace: String,
+    /// The requested robot mode
+    pub mode: String,
+    /// Every event, as it happens
+    pub events: String,
+}
+
+impl Topics {
+    /// Creates topics named `battery_voltage`, `trace`, `mode`, and `events` under `prefix`
+    pub fn with_prefix(prefix: &str) -> Topics {
+        Topics {
+            battery_voltage: format!("{}/battery_voltage", prefix),
+            trace: format!("{}/trace", prefix),
+            mode: format!("{}/mode", prefix),
+            events: format!("{}/events", prefix),
+        }
+    }
+}
+
+/// Where and how often to publish telemetry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// The time between publishing voltage, trace, and mode
+    pub interval: Duration,
+    pub topics: Topics,
+}
+
+impl MqttConfig {
+    /// Publishes to the broker at `host:port` every 500ms, under the `ds` topic prefix
+    pub fn new(host: &str, port: u16) -> MqttConfig {
+        MqttConfig {
+            host: host.to_string(),
+            port,
+            client_id: "ds-rs".to_string(),
+            interval: Duration::from_millis(500),
+            topics: Topics::with_prefix("ds"),
+        }
+    }
+}
+
+/// Publishes telemetry from `ds` to the broker described by `config`
+///
+/// Losing the connection to the broker isn't fatal, the client reconnects and resumes publishing.
+/// Telemetry produced while disconnected is dropped. This future never returns, drop it to stop publishing.
+pub async fn publish(ds: Arc<Mutex<DriverStation>>, config: MqttConfig) {
+    let mut options = MqttOptions::new(config.client_id, config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(5));
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+
+    let topics = config.topics;
+    let mut ticker = tokio::time::interval(config.interval);
+    let mut last_event: DateTime<Utc> = Utc::now();
+
+    loop {
+        tokio::select! {
+            notification = eventloop.poll() => {
+                if let Err(e) = notification {
+                    warn!("MQTT connection error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+            _ = ticker.tick() => {
+                let (voltage, trace, mode, events) = {
+                    let ds = ds.lock().await;
+                    let events = ds.events_since(last_event).await;
+                    (ds.battery_voltage().await, ds.trace().await, ds.mode().await, events)
+                };
+
+                // The client queue is only drained by polling the event loop, so publishing must never wait on it
+                let _ = client.try_publish(&topics.battery_voltage, QoS::AtMostOnce, false, format!("{:.2}", voltage));
+                let _ = client.try_publish(&topics.trace, QoS::AtMostOnce, false, trace.bits().to_string());
+                let _ = client.try_publish(&topics.mode, QoS::AtMostOnce, false, format!("{:?}", mode));
+
+                for event in events {
+                    // `events_since` is inclusive, so the last event published is returned again
+                    if event.timestamp <= last_event {
+                        continue;
+                    }
+                    last_event = event.timestamp;
+                    let _ = client.try_publish(&topics.events, QoS::AtLeastOnce, false, format!("{:?}", event.event));
+                }
+            }
+        }
+    }
+}
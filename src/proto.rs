@@ -1,2 +1,37 @@
+use std::sync::Arc;
+
+#[cfg(feature = "crio")]
+pub mod crio;
 pub mod tcp;
 pub mod udp;
+pub mod year;
+
+/// The direction and transport of a raw packet observed by a packet tap
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// A UDP control packet sent to the roboRIO
+    UdpOutbound,
+    /// A UDP status packet received from the roboRIO
+    UdpInbound,
+    /// A TCP frame sent to the roboRIO
+    TcpOutbound,
+    /// A TCP frame received from the roboRIO
+    TcpInbound,
+}
+
+impl Direction {
+    /// Returns true if the packet was received from the roboRIO
+    #[inline(always)]
+    pub const fn is_inbound(self) -> bool {
+        matches!(self, Direction::UdpInbound | Direction::TcpInbound)
+    }
+
+    /// Returns true if the packet was carried over UDP
+    #[inline(always)]
+    pub const fn is_udp(self) -> bool {
+        matches!(self, Direction::UdpInbound | Direction::UdpOutbound)
+    }
+}
+
+/// Hook called by the codecs with the raw bytes of every frame they encode or decode
+pub(crate) type RawTap = Arc<dyn Fn(Direction, &[u8]) + Send + Sync>;
@@ -0,0 +1,266 @@
+//! The protocol spoken by the cRIO, used from 2009 to 2014
+//!
+//! Every 20ms the driver station sends a fixed length 1024 byte control packet to the robot on UDP port 1110,
+//! and the cRIO replies to UDP port 1150. Unlike the roboRIO protocol there are no tags or TCP connection,
+//! so only the control flags, alliance station, and up to 4 joysticks can be sent.
+//!
+//! Control packet layout, all values big endian:
+//!
+//! | Offset | Length | Contents                                     |
+//! |--------|--------|----------------------------------------------|
+//! | 0      | 2      | Packet index                                 |
+//! | 2      | 1      | [`CrioControl`]                              |
+//! | 3      | 1      | Driver station digital inputs                |
+//! | 4      | 2      | Team number                                  |
+//! | 6      | 1      | Alliance, `R` or `B`                         |
+//! | 7      | 1      | Position, `1` to `3`                         |
+//! | 8      | 32     | 4 joysticks, 6 `i8` axes and 16 button bits  |
+//! | 40     | 8      | Driver station analog inputs                 |
+//! | 48     | 24     | cRIO and FPGA checksums, zeroed              |
+//! | 72     | 8      | Driver station version, as ASCII             |
+//! | 1020   | 4      | CRC32 of the packet, with this field zeroed  |
+
+use crate::Result;
+use crate::proto::udp::outbound::UdpControlPacket;
+use crate::proto::udp::outbound::types::{Alliance, Control, RequestFlags, UdpTag};
+
+use anyhow::bail;
+use bitflags::bitflags;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// The length of every control packet
+pub const CONTROL_LEN: usize = 1024;
+/// The number of joysticks the cRIO accepts
+pub const NUM_JOYSTICKS: usize = 4;
+/// The number of axes the cRIO accepts for a single joystick
+pub const NUM_AXES: usize = 6;
+/// The number of buttons the cRIO accepts for a single joystick
+pub const NUM_BUTTONS: usize = 16;
+
+/// The version string of the last official driver station for the cRIO, checked by robot code
+const DS_VERSION: &[u8; 8] = b"12191200";
+const VERSION_OFFSET: usize = 72;
+/// The replies are only needed up to the echoed packet index
+const MIN_STATUS_LEN: usize = 32;
+const STATUS_SEQNUM_OFFSET: usize = 30;
+
+bitflags! {
+    /// bitflag struct for the control byte of cRIO packets, in both directions
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CrioControl: u8 {
+        /// Reboots the cRIO
+        const RESET = 0b1000_0000;
+        /// Cleared to estop the robot
+        const NOT_ESTOP = 0b0100_0000;
+        const ENABLED = 0b0010_0000;
+        const AUTONOMOUS = 0b0001_0000;
+        const FMS_ATTACHED = 0b0000_1000;
+        const RESYNC = 0b0000_0100;
+        const TEST = 0b0000_0010;
+        const CHECK_VERSIONS = 0b0000_0001;
+    }
+}
+
+impl CrioControl {
+    /// Translates the flags of a roboRIO control packet, along with its reboot request
+    pub fn from_control(control: Control, request: Option<RequestFlags>) -> CrioControl {
+        let mut crio = CrioControl::empty();
+        if !control.contains(Control::ESTOP) {
+            crio |= CrioControl::NOT_ESTOP;
+        }
+        if control.contains(Control::ENABLED) {
+            crio |= CrioControl::ENABLED;
+        }
+        if control.contains(Control::FMS_CONNECTED) {
+            crio |= CrioControl::FMS_ATTACHED;
+        }
+        if control.contains(Control::AUTO) {
+            crio |= CrioControl::AUTONOMOUS;
+        } else if control.contains(Control::TEST) {
+            crio |= CrioControl::TEST;
+        }
+        if request.is_some_and(|request| request.contains(RequestFlags::REBOOT_ROBORIO)) {
+            crio |= CrioControl::RESET;
+        }
+        crio
+    }
+
+    #[inline(always)]
+    pub const fn emergency_stopped(self) -> bool {
+        !self.contains(CrioControl::NOT_ESTOP)
+    }
+}
+
+/// The values of one joystick, as understood by the cRIO
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct CrioJoystick {
+    pub axes: [i8; NUM_AXES],
+    /// Button states, LSB first (button 1 is bit 0)
+    pub buttons: u16,
+}
+
+/// Control packet to send to the cRIO
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrioControlPacket {
+    pub seqnum: u16,
+    pub control: CrioControl,
+    pub team_number: u16,
+    pub alliance: Alliance,
+    pub joysticks: [CrioJoystick; NUM_JOYSTICKS],
+}
+
+impl CrioControlPacket {
+    /// Converts a roboRIO control packet for the cRIO
+    ///
+    /// Joystick tags past the fourth, and axes and buttons the cRIO doesn't support, are dropped. POVs and every other tag
+    /// have no equivalent and are ignored. Restarting robot code isn't supported, only rebooting the cRIO.
+    pub fn from_control(packet: &UdpControlPacket, team_number: u16) -> CrioControlPacket {
+        let mut joysticks = [CrioJoystick::default(); NUM_JOYSTICKS];
+        let tags = packet.tags.iter().filter_map(|tag| match tag {
+            UdpTag::Joysticks(joystick) => Some(joystick),
+            _ => None,
+        });
+        for (crio, joystick) in joysticks.iter_mut().zip(tags) {
+            for (i, axis) in crio.axes.iter_mut().enumerate() {
+                *axis = joystick.axis(i);
+            }
+            for i in 0..NUM_BUTTONS {
+                if joystick.button(i) {
+                    crio.buttons |= 1 << i;
+                }
+            }
+        }
+
+        CrioControlPacket {
+            seqnum: packet.seqnum,
+            control: CrioControl::from_control(packet.control, packet.request),
+            team_number,
+            alliance: packet.alliance,
+            joysticks,
+        }
+    }
+
+    /// Encodes the packet, padded to [`CONTROL_LEN`] with its checksum at the end
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(CONTROL_LEN);
+        buf.put_u16(self.seqnum);
+        buf.put_u8(self.control.bits());
+        buf.put_u8(0); // Digital inputs
+        buf.put_u16(self.team_number);
+        buf.put_u8(if self.alliance.is_red() { b'R' } else { b'B' });
+        buf.put_u8(b'0' + self.alliance.position());
+
+        for joystick in &self.joysticks {
+            for axis in joystick.axes {
+                buf.put_i8(axis);
+            }
+            buf.put_u16(joystick.buttons);
+        }
+
+        buf.resize(VERSION_OFFSET, 0); // Analog inputs and checksums
+        buf.put_slice(DS_VERSION);
+        buf.resize(CONTROL_LEN, 0);
+
+        let crc = crc32(&buf);
+        buf[CONTROL_LEN - 4..].copy_from_slice(&crc.to_be_bytes());
+        buf.freeze()
+    }
+}
+
+/// Status packet received from the cRIO
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrioStatusPacket {
+    pub control: CrioControl,
+    pub battery: f32,
+    /// The index of the control packet being replied to
+    pub seqnum: u16,
+}
+
+impl CrioStatusPacket {
+    pub fn decode(buf: &mut impl Buf) -> Result<CrioStatusPacket> {
+        if buf.remaining() < MIN_STATUS_LEN {
+            bail!("cRIO status packet too short");
+        }
+
+        let control = CrioControl::from_bits_retain(buf.get_u8());
+        // The voltage is sent as binary coded decimal, 0x12 0x34 is 12.34V
+        let volts = from_bcd(buf.get_u8());
+        let hundredths = from_bcd(buf.get_u8());
+        buf.advance(STATUS_SEQNUM_OFFSET - 3);
+        let seqnum = buf.get_u16();
+        buf.advance(buf.remaining());
+
+        Ok(CrioStatusPacket {
+            control,
+            battery: volts as f32 + hundredths as f32 / 100.0,
+            seqnum,
+        })
+    }
+}
+
+fn from_bcd(byte: u8) -> u8 {
+    (byte >> 4) * 10 + (byte & 0x0F)
+}
+
+/// The CRC-32 used by zlib, which the cRIO checks every control packet against
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::udp::outbound::types::Joysticks;
+
+    #[test]
+    fn verify_control_encoding() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let packet = UdpControlPacket::new(
+            0x0102,
+            Control::ENABLED | Control::AUTO,
+            None,
+            Alliance::new_blue(2),
+            vec![UdpTag::Joysticks(Joysticks::new(
+                [-128, 127],
+                [true, false, true],
+                [90],
+            ))],
+        );
+        let bytes = CrioControlPacket::from_control(&packet, 4533).encode();
+
+        assert_eq!(bytes.len(), CONTROL_LEN);
+        assert_eq!(
+            &bytes[..8],
+            &[0x01, 0x02, 0x70, 0x00, 0x11, 0xB5, b'B', b'2']
+        );
+        assert_eq!(&bytes[8..16], &[0x80, 0x7F, 0, 0, 0, 0, 0x00, 0b101]);
+        assert_eq!(&bytes[72..80], DS_VERSION);
+
+        let mut unsigned = bytes.to_vec();
+        unsigned[CONTROL_LEN - 4..].fill(0);
+        assert_eq!(&bytes[CONTROL_LEN - 4..], &crc32(&unsigned).to_be_bytes());
+    }
+
+    #[test]
+    fn verify_status_decoding() {
+        let mut status = vec![0u8; 1152];
+        status[..3].copy_from_slice(&[0x20, 0x12, 0x34]);
+        status[30..32].copy_from_slice(&[0x01, 0x02]);
+
+        let packet = CrioStatusPacket::decode(&mut &status[..]).unwrap();
+        assert!(packet.control.emergency_stopped());
+        assert!((packet.battery - 12.34).abs() < 0.001);
+        assert_eq!(packet.seqnum, 0x0102);
+
+        assert!(CrioStatusPacket::decode(&mut &status[..8]).is_err());
+    }
+}
@@ -1,25 +1,50 @@
-use crate::ext::BufExt;
 use crate::proto::tcp::outbound::{OutgoingTcpTag, TcpTag};
-use crate::{Stdout, TcpPacket};
-use bytes::{Buf, BytesMut};
+use crate::proto::{Direction, RawTap};
+use crate::{DisableFaults, ErrorMessage, RadioEvent, RailFaults, Stdout, TcpPacket, VersionInfo};
+use bytes::BytesMut;
 use std::io;
 use tokio_util::codec::{Decoder, Encoder};
 
 pub mod inbound;
 pub mod outbound;
 
+/// The largest TCP frame accepted from the roboRIO, larger than any console message or error report it sends
+pub(crate) const MAX_FRAME_LEN: usize = 32 * 1024;
+
 /// The tokio codec for TCP traffic to and from the roboRIO
-pub struct DsTcpCodec;
+pub struct DsTcpCodec {
+    tap: Option<RawTap>,
+}
+
+impl DsTcpCodec {
+    /// Creates a codec that passes the raw bytes of every frame to `tap`, if present
+    pub(crate) fn new(tap: Option<RawTap>) -> DsTcpCodec {
+        DsTcpCodec { tap }
+    }
+}
+
+impl Default for DsTcpCodec {
+    fn default() -> Self {
+        DsTcpCodec::new(None)
+    }
+}
 
 impl Encoder<TcpTag> for DsTcpCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: TcpTag, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let start = dst.len();
         match item {
             TcpTag::GameData(gd) => {
                 dst.extend(gd.construct().iter());
             }
             TcpTag::MatchInfo(mi) => dst.extend(mi.construct().iter()),
+            TcpTag::JoystickDescriptor(jd) => dst.extend(jd.construct().iter()),
+            #[cfg(feature = "extensions")]
+            TcpTag::Command(command) => dst.extend(command.construct().iter()),
+        }
+        if let Some(ref tap) = self.tap {
+            tap(Direction::TcpOutbound, &dst[start..]);
         }
         Ok(())
     }
@@ -30,42 +55,122 @@ impl Decoder for DsTcpCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut buf = src.clone().freeze();
-
-        fn inner(buf: &mut impl Buf) -> crate::Result<(TcpPacket, usize)> {
-            let len = buf.read_u16_be()?;
-
-            let id = buf.read_u8()?;
-            match id {
-                0x0c => Ok((
-                    TcpPacket::Stdout(Stdout::decode(buf, len as usize - 1)?),
-                    len as usize + 2,
-                )),
-                _ => {
-                    for _ in 0..(len - 1) {
-                        let _ = buf.read_u8()?;
-                    }
-                    Ok((TcpPacket::Dummy, len as usize + 2))
-                }
+        loop {
+            if src.len() < 2 {
+                return Ok(None);
+            }
+            let len = u16::from_be_bytes([src[0], src[1]]) as usize;
+            // A length that large means the stream has lost its framing, and waiting for it would only buffer garbage
+            if len > MAX_FRAME_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "TCP frame of {} bytes is over the limit of {}",
+                        len, MAX_FRAME_LEN
+                    ),
+                )
+                .into());
+            }
+            if src.len() < len + 2 {
+                src.reserve(len + 2 - src.len());
+                return Ok(None);
             }
-        }
 
-        use anyhow::bail;
-        match inner(&mut buf) {
-            Ok((packet, n)) => {
-                src.advance(n);
-                Ok(Some(packet))
+            // Frames are split off whole, so one with a bad body can't desync the ones after it
+            let frame = src.split_to(len + 2).freeze();
+            // Empty frames are keepalives
+            if len == 0 {
+                continue;
+            }
+            if let Some(ref tap) = self.tap {
+                tap(Direction::TcpInbound, &frame);
             }
-            Err(e) => match e.downcast::<io::Error>() {
-                Ok(e) => {
-                    if e.kind() == io::ErrorKind::UnexpectedEof {
-                        Ok(None)
-                    } else {
-                        Err(e.into())
-                    }
-                }
-                _ => bail!("Failed to decode TCP packet"),
-            },
+
+            let data = frame.slice(3..);
+            let mut body = data.clone();
+            let id = frame[2];
+            let packet = match id {
+                0x0c => Stdout::decode(&mut body, len - 1).map(TcpPacket::Stdout),
+                0x0b => ErrorMessage::decode(&mut body).map(TcpPacket::ErrorMessage),
+                0x0a => VersionInfo::decode(&mut body).map(TcpPacket::VersionInfo),
+                0x04 => DisableFaults::decode(&mut body).map(TcpPacket::DisableFaults),
+                0x05 => RailFaults::decode(&mut body).map(TcpPacket::RailFaults),
+                0x00 => RadioEvent::decode(&mut body).map(TcpPacket::RadioEvent),
+                _ => Ok(TcpPacket::Unknown { id, data: body }),
+            };
+            // A bad body only loses its own frame, so it's kept whole rather than erroring the stream, which would end
+            // the connection
+            return Ok(Some(packet.unwrap_or_else(|e| TcpPacket::Malformed {
+                id,
+                data,
+                error: e.to_string(),
+            })));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_partial_and_oversized_frames() {
+        let mut codec = DsTcpCodec::default();
+        let frame = [
+            0x00, 0x0b, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, b'h', b'i', b'!', b'\n',
+            b'x', // Stdout
+        ];
+        let mut src = BytesMut::new();
+
+        // A keepalive, then a frame split across reads
+        src.extend_from_slice(&[0x00, 0x00]);
+        src.extend_from_slice(&frame[..5]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&frame[5..]);
+        match codec.decode(&mut src).unwrap() {
+            Some(TcpPacket::Stdout(stdout)) => assert_eq!(stdout.message, "hi!\n"),
+            packet => panic!("Expected stdout, got {:?}", packet),
+        }
+        assert_eq!(&src[..], b"x");
+
+        src.clear();
+        src.extend_from_slice(&[0x00, 0x03, 0x01, 0xab, 0xcd]);
+        match codec.decode(&mut src).unwrap() {
+            Some(TcpPacket::Unknown { id: 0x01, data }) => assert_eq!(&data[..], &[0xab, 0xcd]),
+            packet => panic!("Expected an unknown frame, got {:?}", packet),
+        }
+
+        src.clear();
+        src.extend_from_slice(&[0xff, 0xff, 0x0c]);
+        let e = codec.decode(&mut src).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_bad_frame_does_not_end_stream() {
+        use futures_util::StreamExt;
+        use tokio_util::codec::FramedRead;
+
+        // Stdout that isn't UTF-8, then stdout that is
+        let bad = [
+            0x00, 0x09, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xff, 0xfe,
+        ];
+        let good = [
+            0x00, 0x0b, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, b'h', b'i', b'!', b'\n',
+        ];
+        let stream = [&bad[..], &good[..]].concat();
+        let mut frames = FramedRead::new(&stream[..], DsTcpCodec::default());
+        match frames.next().await {
+            Some(Ok(TcpPacket::Malformed { id: 0x0c, data, .. })) => assert_eq!(data.len(), 8),
+            packet => panic!("Expected the bad frame whole, got {:?}", packet),
+        }
+        match frames.next().await {
+            Some(Ok(TcpPacket::Stdout(stdout))) => assert_eq!(stdout.message, "hi!\n"),
+            packet => panic!("Expected stdout, got {:?}", packet),
         }
+        assert!(frames.next().await.is_none());
     }
 }
@@ -1,15 +1,64 @@
 use crate::Result as CResult;
 use crate::ext::BufExt;
-use bytes::Buf;
+use bytes::{Buf, Bytes};
 use std::io::{Error, ErrorKind};
 use std::str;
 
+#[cfg(feature = "extensions")]
+mod command;
+mod error_codes;
+
+#[cfg(feature = "extensions")]
+pub use self::command::CommandResponse;
+pub use self::error_codes::ErrorCode;
+
 /// Enum containing possible incoming TCP packets from the roboRIO
+///
+/// Every frame is decoded into one of these, frames with an ID this crate doesn't know being kept whole in
+/// [`TcpPacket::Unknown`], and those with a body that fails to decode in [`TcpPacket::Malformed`].
 #[derive(Debug)]
 pub enum TcpPacket {
     /// Contains a message from the robot code's standard output
     Stdout(Stdout),
-    Dummy,
+    /// Contains an error or warning reported by robot code or WPILib
+    ErrorMessage(ErrorMessage),
+    /// Contains the version of a part of the robot, such as the roboRIO image or WPILib
+    VersionInfo(VersionInfo),
+    /// Contains the number of times the robot was disabled by each cause
+    DisableFaults(DisableFaults),
+    /// Contains the number of faults on each of the roboRIO's power rails
+    RailFaults(RailFaults),
+    /// Contains a message logged by the radio
+    RadioEvent(RadioEvent),
+    /// A frame with an ID that isn't decoded, such as the usage report
+    Unknown {
+        id: u8,
+        /// The frame after its length and ID
+        data: Bytes,
+    },
+    /// A frame with a known ID whose body failed to decode
+    Malformed {
+        id: u8,
+        /// The frame after its length and ID
+        data: Bytes,
+        /// Why the body failed to decode
+        error: String,
+    },
+}
+
+impl TcpPacket {
+    /// Returns the ID of the frame this was decoded from
+    pub const fn id(&self) -> u8 {
+        match self {
+            TcpPacket::Stdout(_) => 0x0c,
+            TcpPacket::ErrorMessage(_) => 0x0b,
+            TcpPacket::VersionInfo(_) => 0x0a,
+            TcpPacket::DisableFaults(_) => 0x04,
+            TcpPacket::RailFaults(_) => 0x05,
+            TcpPacket::RadioEvent(_) => 0x00,
+            TcpPacket::Unknown { id, .. } | TcpPacket::Malformed { id, .. } => *id,
+        }
+    }
 }
 
 pub(crate) trait IncomingTcpPacket: Sized {
@@ -29,7 +78,14 @@ impl Stdout {
     pub fn decode(buf: &mut impl Buf, len: usize) -> CResult<Self> {
         let timestamp = buf.read_f32_be()?;
         let seqnum = buf.read_u16_be()?;
-        let mut v = vec![0; len - 6];
+        let Some(message_len) = len.checked_sub(6) else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Console message shorter than its header",
+            )
+            .into());
+        };
+        let mut v = vec![0; message_len];
         if buf.remaining() < v.len() {
             return Err(Error::new(ErrorKind::UnexpectedEof, "Not enough data").into());
         }
@@ -42,3 +98,183 @@ impl Stdout {
         })
     }
 }
+
+/// An error or warning reported by the robot, such as a loop overrun or a CAN timeout
+#[derive(Debug)]
+pub struct ErrorMessage {
+    pub timestamp: f32,
+    pub seqnum: u16,
+    pub code: ErrorCode,
+    /// Whether this is an error, rather than a warning
+    pub is_error: bool,
+    pub details: String,
+    /// Where the error was reported from
+    pub location: String,
+    pub call_stack: String,
+}
+
+impl ErrorMessage {
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        let timestamp = buf.read_f32_be()?;
+        let seqnum = buf.read_u16_be()?;
+        // The number of occurrences, always 1
+        let _count = buf.read_u16_be()?;
+        let code = ErrorCode::from_code(buf.read_i32_be()?);
+        let flags = buf.read_u8()?;
+        let details = read_string(buf)?;
+        let location = read_string(buf)?;
+        let call_stack = read_string(buf)?;
+        Ok(ErrorMessage {
+            timestamp,
+            seqnum,
+            code,
+            is_error: flags & 0x01 != 0,
+            details,
+            location,
+            call_stack,
+        })
+    }
+}
+
+/// The version of a part of the robot, sent when the TCP connection opens
+#[derive(Debug)]
+pub struct VersionInfo {
+    /// What the version is of, such as `roboRIO Image` or `FRC_Lib_Version`
+    pub name: String,
+    pub version: String,
+}
+
+impl VersionInfo {
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        // The type and ID of the device, which are 0 for the roboRIO
+        for _ in 0..4 {
+            let _ = buf.read_u8()?;
+        }
+        let name = read_short_string(buf)?;
+        let version = read_short_string(buf)?;
+        Ok(VersionInfo { name, version })
+    }
+}
+
+/// The number of times the robot has been disabled by each cause, since the roboRIO booted
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisableFaults {
+    /// Disables from losing communication with the driver station
+    pub comms: u16,
+    /// Disables from the battery voltage dropping too low
+    pub rail_12v: u16,
+}
+
+impl DisableFaults {
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        let comms = buf.read_u16_be()?;
+        let rail_12v = buf.read_u16_be()?;
+        Ok(DisableFaults { comms, rail_12v })
+    }
+}
+
+/// The number of faults on each of the roboRIO's user power rails, such as from a short, since it booted
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RailFaults {
+    pub rail_6v: u16,
+    pub rail_5v: u16,
+    pub rail_3v3: u16,
+}
+
+impl RailFaults {
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        let rail_6v = buf.read_u16_be()?;
+        let rail_5v = buf.read_u16_be()?;
+        let rail_3v3 = buf.read_u16_be()?;
+        Ok(RailFaults {
+            rail_6v,
+            rail_5v,
+            rail_3v3,
+        })
+    }
+}
+
+/// A message logged by the radio, forwarded by the roboRIO
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RadioEvent {
+    pub message: String,
+}
+
+impl RadioEvent {
+    /// Decodes the message from the rest of the frame, replacing invalid UTF-8
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        let bytes = buf.copy_to_bytes(buf.remaining());
+        Ok(RadioEvent {
+            message: String::from_utf8_lossy(&bytes).into_owned(),
+        })
+    }
+}
+
+/// Reads a string prefixed with its u8 length, replacing invalid UTF-8
+fn read_short_string(buf: &mut impl Buf) -> CResult<String> {
+    let len = buf.read_u8()? as usize;
+    if buf.remaining() < len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Not enough data").into());
+    }
+    let bytes = buf.copy_to_bytes(len);
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads a string prefixed with its u16 length. Invalid UTF-8 is replaced, so a garbled call stack doesn't lose the message
+fn read_string(buf: &mut impl Buf) -> CResult<String> {
+    let len = buf.read_u16_be()? as usize;
+    if buf.remaining() < len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Not enough data").into());
+    }
+    let bytes = buf.copy_to_bytes(len);
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_error_message_decode() {
+        let mut buf: &[u8] = &[
+            0x41, 0x20, 0x00, 0x00, // 10.0s
+            0x00, 0x07, 0x00, 0x01, // Sequence number, count
+            0xFF, 0xFF, 0xFB, 0x7A, // -1158, unknown
+            0x01, // Error
+            0x00, 0x04, b'o', b'o', b'p', b's', // Details
+            0x00, 0x03, b'a', b'.', b'c', // Location
+            0x00, 0x00, // Call stack
+        ];
+        let message = ErrorMessage::decode(&mut buf).unwrap();
+        assert_eq!(message.timestamp, 10.0);
+        assert_eq!(message.seqnum, 7);
+        assert_eq!(message.code, ErrorCode::Unknown(-1158));
+        assert!(message.is_error);
+        assert_eq!(message.details, "oops");
+        assert_eq!(message.location, "a.c");
+        assert!(message.call_stack.is_empty());
+
+        assert_eq!(ErrorCode::from_code(-1154), ErrorCode::CanTimeout);
+        assert_eq!(ErrorCode::CanTimeout.code(), -1154);
+        assert_eq!(
+            ErrorCode::CanTimeout.description(),
+            Some("CAN receive timed out")
+        );
+    }
+
+    #[test]
+    fn verify_fault_decode() {
+        let mut buf: &[u8] = &[0x00, 0x02, 0x00, 0x01];
+        let faults = DisableFaults::decode(&mut buf).unwrap();
+        assert_eq!(
+            faults,
+            DisableFaults {
+                comms: 2,
+                rail_12v: 1
+            }
+        );
+
+        let mut buf: &[u8] = &[0x00, 0x00, 0x00, 0x03, 0x00];
+        assert!(RailFaults::decode(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use crate::Result as CResult;
+use crate::ext::BufExt;
+use bytes::{Buf, Bytes};
+
+/// Robot code's answer to a [`RobotCommand`](crate::RobotCommand)
+///
+/// The frame is the command's ID, a status byte, and a payload whose meaning is up to the robot program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandResponse {
+    /// The ID of the command answered
+    pub id: u16,
+    /// 0 if the command succeeded, otherwise a failure code chosen by the robot program
+    pub status: u8,
+    pub payload: Bytes,
+}
+
+impl CommandResponse {
+    pub fn decode(buf: &mut impl Buf) -> CResult<Self> {
+        let id = buf.read_u16_be()?;
+        let status = buf.read_u8()?;
+        let payload = buf.copy_to_bytes(buf.remaining());
+        Ok(CommandResponse {
+            id,
+            status,
+            payload,
+        })
+    }
+
+    #[inline(always)]
+    pub const fn succeeded(&self) -> bool {
+        self.status == 0
+    }
+}
@@ -0,0 +1,82 @@
+/// Generates [`ErrorCode`] from the code, name, and description of each known error
+macro_rules! error_codes {
+    ($($name:ident = $code:literal => $description:literal,)*) => {
+        /// A known WPILib or HAL error code, as reported in an [`ErrorMessage`](crate::ErrorMessage)
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $(
+                #[doc = $description]
+                $name,
+            )*
+            /// A code this crate doesn't know about
+            Unknown(i32),
+        }
+
+        impl ErrorCode {
+            pub const fn from_code(code: i32) -> ErrorCode {
+                match code {
+                    $($code => ErrorCode::$name,)*
+                    code => ErrorCode::Unknown(code),
+                }
+            }
+
+            /// The numeric code sent by the robot
+            pub const fn code(self) -> i32 {
+                match self {
+                    $(ErrorCode::$name => $code,)*
+                    ErrorCode::Unknown(code) => code,
+                }
+            }
+
+            /// A human readable description of the error, if the code is known
+            pub const fn description(self) -> Option<&'static str> {
+                match self {
+                    $(ErrorCode::$name => Some($description),)*
+                    ErrorCode::Unknown(_) => None,
+                }
+            }
+        }
+    };
+}
+
+// Codes from WPILib's hal/Errors.h. Robot code reporting through DriverStation.reportError or reportWarning always uses 1
+error_codes! {
+    Reported = 1 => "Reported by robot code",
+    SampleRateTooHigh = 1001 => "Analog module sample rate is too high",
+    VoltageOutOfRange = 1002 => "Voltage to convert to raw value is out of range",
+    LoopTimingError = 1004 => "Digital module loop timing is not the expected value",
+    SpiWriteNoMosi = 1012 => "Cannot write to SPI port with no MOSI output",
+    SpiReadNoMiso = 1013 => "Cannot read from SPI port with no MISO input",
+    SpiReadNoData = 1014 => "No data available to read from SPI",
+    IncompatibleState = 1015 => "The operation cannot be completed in the current state",
+    NoAvailableResources = -1004 => "No available resources to allocate",
+    NullParameter = -1005 => "A pointer parameter to a method is null",
+    AnalogTriggerLimitOrder = -1010 => "The lower limit of an analog trigger is above its upper limit",
+    AnalogTriggerPulseOutput = -1011 => "Attempted to read the pulse output of an analog trigger",
+    ParameterOutOfRange = -1028 => "A parameter is out of range",
+    ResourceIsAllocated = -1029 => "The resource is already allocated",
+    ResourceOutOfRange = -1030 => "The requested resource is out of range",
+    InvalidAccumulatorChannel = -1035 => "The requested input is not an accumulator channel",
+    CounterNotSupported = -1058 => "The counter mode isn't supported for this encoder method",
+    PwmScaleError = -1072 => "The PWM scale factors are out of range",
+    HandleError = -1098 => "A handle parameter was passed incorrectly",
+    LedChannelError = -1099 => "Invalid LED channel",
+    InvalidDmaAddition = -1102 => "The addition to the DMA object is invalid",
+    InvalidDmaState = -1103 => "DMA can't be modified while running",
+    SerialPortNotFound = -1123 => "The serial port could not be found",
+    SerialPortOpenError = -1124 => "The serial port could not be opened",
+    SerialPortError = -1125 => "Serial port error",
+    ThreadPriorityError = -1152 => "Getting or setting the priority of a thread failed",
+    ThreadPriorityRangeError = -1153 => "The requested thread priority is invalid",
+    CanTimeout = -1154 => "CAN receive timed out",
+    SimNotSupported = -1155 => "The method isn't supported in simulation",
+    CanBufferOverrun = -35007 => "The CAN output buffer is full, make sure a device is attached",
+    CanInvalidBuffer = -44086 => "CAN: invalid buffer",
+    CanMessageNotFound = -44087 => "CAN: message not found",
+    CanNotAllowed = -44088 => "CAN: not allowed",
+    CanNotInitialized = -44089 => "CAN: not initialized",
+    CanTxQueueFull = 44086 => "CAN: transmit queue full",
+    CanNoToken = 44087 => "CAN: no token",
+    NetCommNotResponding = -44049 => "NetComm isn't responding",
+    NoDsConnection = -44018 => "No driver station is connected",
+}
@@ -1,9 +1,21 @@
 use bytes::{BufMut, Bytes, BytesMut};
 
+#[cfg(feature = "extensions")]
+mod command;
+mod game_data;
+
+#[cfg(feature = "extensions")]
+pub use self::command::*;
+pub use self::game_data::*;
+
 #[derive(Debug, Clone)]
 pub enum TcpTag {
     MatchInfo(MatchInfo),
     GameData(GameData),
+    JoystickDescriptor(JoystickDescriptor),
+    /// A command for a cooperating robot program, with the `extensions` feature
+    #[cfg(feature = "extensions")]
+    Command(RobotCommand),
 }
 
 pub(crate) trait OutgoingTcpTag {
@@ -34,12 +46,36 @@ pub(crate) trait OutgoingTcpTag {
     }
 }
 
-#[derive(Debug, Clone)]
+/// The event and kind of match the robot is playing, normally sent by the FMS
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MatchInfo {
     competition: String,
     match_type: MatchType,
 }
 
+impl MatchInfo {
+    /// Creates match info for `competition`, of which at most 255 bytes are sent
+    pub fn new(competition: &str, match_type: MatchType) -> MatchInfo {
+        let mut end = competition.len().min(u8::MAX as usize);
+        while !competition.is_char_boundary(end) {
+            end -= 1;
+        }
+        MatchInfo {
+            competition: competition[..end].to_string(),
+            match_type,
+        }
+    }
+
+    pub fn competition(&self) -> &str {
+        &self.competition
+    }
+
+    #[inline(always)]
+    pub const fn match_type(&self) -> MatchType {
+        self.match_type
+    }
+}
+
 impl OutgoingTcpTag for MatchInfo {
     #[inline(always)]
     fn id(&self) -> u8 {
@@ -55,26 +91,75 @@ impl OutgoingTcpTag for MatchInfo {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct GameData {
-    pub gsm: String,
+/// Describes the joystick in one slot, so robot code can tell what kind of controller it is and how many inputs it has
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoystickDescriptor {
+    /// The slot the joystick is in
+    pub index: u8,
+    pub is_xbox: bool,
+    pub joystick_type: JoystickType,
+    /// The name shown to robot code, at most 255 bytes are sent
+    pub name: String,
+    /// The type of each axis, which also gives the number of axes
+    pub axis_types: Vec<AxisType>,
+    pub buttons: u8,
+    pub povs: u8,
 }
 
-impl OutgoingTcpTag for GameData {
+impl OutgoingTcpTag for JoystickDescriptor {
+    #[inline(always)]
     fn id(&self) -> u8 {
-        0x0e
+        0x02
     }
 
     fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(self.gsm.len());
-        buf.put_slice(self.gsm.as_bytes());
+        let name = &self.name.as_bytes()[..self.name.len().min(u8::MAX as usize)];
+        let axes = &self.axis_types[..self.axis_types.len().min(u8::MAX as usize)];
+        let mut buf = BytesMut::with_capacity(7 + name.len() + axes.len());
+        buf.put_u8(self.index);
+        buf.put_u8(self.is_xbox as u8);
+        buf.put_u8(self.joystick_type as u8);
+        buf.put_u8(name.len() as u8);
+        buf.put_slice(name);
+        buf.put_u8(axes.len() as u8);
+        for axis in axes {
+            buf.put_u8(*axis as u8);
+        }
+        buf.put_u8(self.buttons);
+        buf.put_u8(self.povs);
         buf.freeze()
     }
 }
 
+/// The kind of controller in a [`JoystickDescriptor`], as reported by WPILib's `GenericHID.getType()`
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoystickType {
+    Unknown = 0xff,
+    XInputGamepad = 1,
+    XInputWheel = 2,
+    XInputArcadeStick = 3,
+    XInputFlightStick = 4,
+    HidJoystick = 20,
+    HidGamepad = 21,
+    HidDriving = 22,
+    HidFlight = 23,
+}
+
+/// The kind of input an axis is, in a [`JoystickDescriptor`]
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AxisType {
+    X = 0,
+    Y = 1,
+    Z = 2,
+    Twist = 3,
+    Throttle = 4,
+}
+
+/// The kind of match in [`MatchInfo`], as reported by WPILib's `DriverStation.getMatchType()`
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
-#[allow(unused)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MatchType {
     MatchTest = 0,
     Practice = 1,
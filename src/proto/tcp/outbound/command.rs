@@ -0,0 +1,77 @@
+use super::OutgoingTcpTag;
+
+use crate::Result;
+
+use anyhow::bail;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// The ID of the frames carrying commands to robot code and its responses. Stock robot code ignores frames with IDs it
+/// doesn't know, so this is only answered by robot programs built to handle it
+pub const ROBOT_COMMAND_ID: u8 = 0x40;
+
+/// A structured command for a cooperating robot program, such as a line typed into a custom debugging console
+///
+/// The frame is the command's ID, the length of its name as a byte, the name, and the payload. Robot code answers with
+/// a [`CommandResponse`](crate::CommandResponse) carrying the same ID, see
+/// [`DriverStation::send_command`](crate::DriverStation::send_command).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RobotCommand {
+    id: u16,
+    name: String,
+    payload: Bytes,
+}
+
+impl RobotCommand {
+    /// Creates the command `name`, failing if it's empty, longer than 255 bytes, or has characters that aren't
+    /// printable ASCII
+    pub fn new(id: u16, name: &str, payload: Bytes) -> Result<RobotCommand> {
+        if name.is_empty() || name.len() > u8::MAX as usize {
+            bail!(
+                "Command names are 1 to {} bytes long, {:?} is {}",
+                u8::MAX,
+                name,
+                name.len()
+            );
+        }
+        if let Some(c) = name.chars().find(|c| !c.is_ascii_graphic()) {
+            bail!(
+                "Command names can only be printable ASCII without spaces, found {:?}",
+                c
+            );
+        }
+        Ok(RobotCommand {
+            id,
+            name: name.to_string(),
+            payload,
+        })
+    }
+
+    /// Returns the ID matching the command to its response
+    #[inline(always)]
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+}
+
+impl OutgoingTcpTag for RobotCommand {
+    fn id(&self) -> u8 {
+        ROBOT_COMMAND_ID
+    }
+
+    fn data(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(3 + self.name.len() + self.payload.len());
+        buf.put_u16(self.id);
+        buf.put_u8(self.name.len() as u8);
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.extend_from_slice(&self.payload);
+        buf.freeze()
+    }
+}
@@ -0,0 +1,202 @@
+use super::OutgoingTcpTag;
+
+use crate::Result;
+use crate::proto::year::ProtocolYear;
+
+use anyhow::bail;
+use bytes::Bytes;
+use std::fmt;
+
+/// The longest game specific message WPILib passes to robot code, longer messages are cut off by the HAL
+pub const MAX_GAME_DATA_LEN: usize = 64;
+
+/// The game specific message, checked to be something robot code can read back
+///
+/// Messages are printable ASCII, at most [`MAX_GAME_DATA_LEN`] bytes long. Seasons that used the message give it their
+/// own format, which can be built with the [`SeasonMessage`] implementations instead of writing it by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameData {
+    message: String,
+    /// The season the message was built for, if it was built with a [`SeasonMessage`]
+    season: Option<ProtocolYear>,
+}
+
+impl GameData {
+    /// Checks `message`, failing if it's too long or has characters that aren't printable ASCII
+    ///
+    /// An empty message is allowed, it's what the FMS sends before the message is revealed.
+    pub fn new(message: &str) -> Result<GameData> {
+        if message.len() > MAX_GAME_DATA_LEN {
+            bail!(
+                "Game data is {} bytes long, at most {} are allowed",
+                message.len(),
+                MAX_GAME_DATA_LEN
+            );
+        }
+        if let Some(c) = message.chars().find(|c| !c.is_ascii_graphic() && *c != ' ') {
+            bail!("Game data can only be printable ASCII, found {:?}", c);
+        }
+        Ok(GameData {
+            message: message.to_string(),
+            season: None,
+        })
+    }
+
+    /// Builds the message for a season's format
+    pub fn from_season<M: SeasonMessage>(message: &M) -> Result<GameData> {
+        let mut data = GameData::new(&message.encode())?;
+        data.season = Some(M::SEASON);
+        Ok(data)
+    }
+
+    /// The 2018 message, giving the side of the near switch, the scale, and the far switch owned by the alliance
+    pub fn power_up(near_switch: Side, scale: Side, far_switch: Side) -> GameData {
+        let message = PowerUp {
+            near_switch,
+            scale,
+            far_switch,
+        };
+        GameData {
+            message: message.encode(),
+            season: Some(ProtocolYear::Y2018),
+        }
+    }
+
+    /// The 2020 message, giving the colour the control panel is to be turned to in stage 3
+    pub fn infinite_recharge(colour: Option<PanelColour>) -> GameData {
+        let message = InfiniteRecharge { colour };
+        GameData {
+            message: message.encode(),
+            season: Some(ProtocolYear::Y2020),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    #[inline(always)]
+    pub const fn season(&self) -> Option<ProtocolYear> {
+        self.season
+    }
+}
+
+impl fmt::Display for GameData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl OutgoingTcpTag for GameData {
+    fn id(&self) -> u8 {
+        0x0e
+    }
+
+    fn data(&self) -> Bytes {
+        Bytes::copy_from_slice(self.message.as_bytes())
+    }
+}
+
+/// A season's format of game specific message
+///
+/// Implement this to build messages for a season this crate doesn't know about yet.
+pub trait SeasonMessage {
+    /// The season whose robot code reads this format
+    const SEASON: ProtocolYear;
+
+    /// Returns the message, which should pass the checks of [`GameData::new`]
+    fn encode(&self) -> String;
+}
+
+/// Which side of a field element belongs to an alliance
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    const fn letter(self) -> char {
+        match self {
+            Side::Left => 'L',
+            Side::Right => 'R',
+        }
+    }
+}
+
+/// The 2018 message, such as `LRL`, seen from the alliance's driver stations
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PowerUp {
+    pub near_switch: Side,
+    pub scale: Side,
+    pub far_switch: Side,
+}
+
+impl SeasonMessage for PowerUp {
+    const SEASON: ProtocolYear = ProtocolYear::Y2018;
+
+    fn encode(&self) -> String {
+        [self.near_switch, self.scale, self.far_switch]
+            .map(Side::letter)
+            .iter()
+            .collect()
+    }
+}
+
+/// A colour of the 2020 control panel
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PanelColour {
+    Blue,
+    Green,
+    Red,
+    Yellow,
+}
+
+/// The 2020 message, a single letter once stage 3 is reached and empty before
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InfiniteRecharge {
+    pub colour: Option<PanelColour>,
+}
+
+impl SeasonMessage for InfiniteRecharge {
+    const SEASON: ProtocolYear = ProtocolYear::Y2020;
+
+    fn encode(&self) -> String {
+        let letter = match self.colour {
+            Some(PanelColour::Blue) => "B",
+            Some(PanelColour::Green) => "G",
+            Some(PanelColour::Red) => "R",
+            Some(PanelColour::Yellow) => "Y",
+            None => "",
+        };
+        letter.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_game_data_is_checked() {
+        assert!(GameData::new("").is_ok());
+        assert!(GameData::new("LRL").is_ok());
+        assert!(GameData::new(&"A".repeat(MAX_GAME_DATA_LEN + 1)).is_err());
+        assert!(GameData::new("L\nR").is_err());
+        assert!(GameData::new("é").is_err());
+
+        let data = GameData::power_up(Side::Left, Side::Right, Side::Left);
+        assert_eq!(data.message(), "LRL");
+        assert_eq!(data.season(), Some(ProtocolYear::Y2018));
+        assert_eq!(
+            GameData::infinite_recharge(Some(PanelColour::Yellow)).message(),
+            "Y"
+        );
+        assert_eq!(
+            GameData::from_season(&InfiniteRecharge { colour: None })
+                .unwrap()
+                .message(),
+            ""
+        );
+    }
+}
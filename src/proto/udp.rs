@@ -1,5 +1,6 @@
 use crate::proto::udp::inbound::UdpResponsePacket;
 use crate::proto::udp::outbound::UdpControlPacket;
+use crate::proto::{Direction, RawTap};
 use bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder};
 
@@ -7,17 +8,40 @@ pub mod inbound;
 pub mod outbound;
 
 /// The tokio codec for UDP traffic to and from the roboRIO
-pub struct DsUdpCodec;
+pub struct DsUdpCodec {
+    tap: Option<RawTap>,
+}
+
+impl DsUdpCodec {
+    /// Creates a codec that passes the raw bytes of every datagram to `tap`, if present
+    pub(crate) fn new(tap: Option<RawTap>) -> DsUdpCodec {
+        DsUdpCodec { tap }
+    }
+}
+
+impl Default for DsUdpCodec {
+    fn default() -> Self {
+        DsUdpCodec::new(None)
+    }
+}
 
 impl Decoder for DsUdpCodec {
     type Item = UdpResponsePacket;
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // UdpFramed decodes again after every packet until it gets None, an empty buffer means the datagram was used up.
+        // Erroring here instead would be returned forever without another datagram being read.
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(ref tap) = self.tap {
+            tap(Direction::UdpInbound, &src[..]);
+        }
+
         match UdpResponsePacket::decode(src) {
-            Ok(packet) => {
-                Ok(Some(packet))
-            }
+            Ok(packet) => Ok(Some(packet)),
             // In other Decoder implementations, the error is checked and if it was due
             // to a lack of data, Ok(None) is returned.
             // This implementation does it differently due to a bug in UDPFramed assuming there's nothing left
@@ -31,8 +55,11 @@ impl Encoder<UdpControlPacket> for DsUdpCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: UdpControlPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        println!("a");
+        let start = dst.len();
         dst.extend(item.encode());
+        if let Some(ref tap) = self.tap {
+            tap(Direction::UdpOutbound, &dst[start..]);
+        }
 
         Ok(())
     }
@@ -2,11 +2,12 @@ pub mod types;
 
 use types::*;
 
-use crate::util::InboundTag;
 use crate::Result;
 use crate::ext::BufExt;
+use crate::util::InboundTag;
 
 use bytes::Buf;
+use std::io::{Error, ErrorKind};
 
 /// Response packet sent by the RIO over UDP every ~20ms.
 #[derive(Debug)]
@@ -14,8 +15,25 @@ pub struct UdpResponsePacket {
     pub seqnum: u16,
     pub status: Status,
     pub trace: Trace,
+    /// The status and trace bytes before unknown bits were dropped
+    pub raw: RawStatus,
     pub battery: f32,
+    /// What the robot asked for, including bits without a known meaning
+    pub requests: RobotRequests,
+    /// Whether the robot asked for the date and time, the same as [`RobotRequests::DATE`] being set
     pub need_date: bool,
+    /// The free disk space, if the packet reported it
+    pub disk: Option<DiskInfo>,
+    /// The memory usage, if the packet reported it
+    pub ram: Option<RAMInfo>,
+    /// The CAN bus metrics, if the packet reported them
+    pub can: Option<CANMetrics>,
+    /// What robot code set on each joystick, if the packet reported it
+    pub joystick_outputs: Option<Vec<JoystickOutput>>,
+    /// The kinds of the tags that were decoded from the packet
+    pub tags: Vec<InboundTagKind>,
+    /// The IDs of the tags that were skipped, because their ID isn't known or they failed to decode
+    pub dropped_tags: Vec<u8>,
 }
 
 impl UdpResponsePacket {
@@ -24,48 +42,140 @@ impl UdpResponsePacket {
     pub fn decode(buf: &mut impl Buf) -> Result<UdpResponsePacket> {
         let seqnum = buf.read_u16_be()?;
         let _comm_version = buf.read_u8()?;
-        let status = Status::from_bits(buf.read_u8()?).unwrap();
-        let trace = Trace::from_bits(buf.read_u8()?).unwrap();
+        // Unknown bits are dropped instead of panicking, the packet may not have come from a roboRIO
+        let raw = RawStatus {
+            status: buf.read_u8()?,
+            trace: buf.read_u8()?,
+        };
+        let status = Status::from_bits_truncate(raw.status);
+        let trace = Trace::from_bits_truncate(raw.trace);
         let battery = {
             let high = buf.read_u8()?;
             let low = buf.read_u8()?;
             f32::from(high) + f32::from(low) / 256f32
         };
-        let need_date = buf.read_u8()? == 1;
-        while let Ok(tag_id) = buf.read_u8() {
-            match tag_id {
-                0x01 => {
-                    types::JoystickOutput::chomp(buf)?;
-                }
-                0x04 => {
-                    types::DiskInfo::chomp(buf)?;
-                }
-                0x05 => {
-                    types::CPUInfo::chomp(buf)?;
-                }
-                0x06 => {
-                    types::RAMInfo::chomp(buf)?;
-                }
-                0x08 => {
-                    types::PDPLog::chomp(buf)?;
-                }
-                0x09 => {
-                    types::Unknown::chomp(buf)?;
-                }
-                0x0e => {
-                    types::CANMetrics::chomp(buf)?;
-                }
-                _ => {}
+        let requests = RobotRequests::from_bits_retain(buf.read_u8()?);
+        let need_date = requests.contains(RobotRequests::DATE);
+        let mut tags = Vec::new();
+        let mut dropped_tags = Vec::new();
+        let mut disk = None;
+        let mut ram = None;
+        let mut can = None;
+        let mut joystick_outputs = None;
+
+        // Each tag is prefixed with its size, which includes the ID byte
+        while let Ok(size) = buf.read_u8() {
+            if size == 0 {
+                continue;
+            }
+            if buf.remaining() < size as usize {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated tag").into());
+            }
+            let mut tag = buf.copy_to_bytes(size as usize);
+            let tag_id = tag.read_u8()?;
+            // Tags are chomped from their own slice so a malformed tag can't desync the ones after it
+            let chomped = match tag_id {
+                0x01 => types::JoystickOutput::chomp_all(&mut tag)
+                    .map(|outputs| joystick_outputs = Some(outputs)),
+                0x04 => types::DiskInfo::chomp(&mut tag).map(|info| disk = Some(info)),
+                0x05 => types::CPUInfo::chomp(&mut tag).map(|_| ()),
+                0x06 => types::RAMInfo::chomp(&mut tag).map(|info| ram = Some(info)),
+                0x08 => types::PDPLog::chomp(&mut tag).map(|_| ()),
+                0x09 => types::Unknown::chomp(&mut tag).map(|_| ()),
+                0x0e => types::CANMetrics::chomp(&mut tag).map(|metrics| can = Some(metrics)),
+                _ => Ok(()),
+            };
+            match (chomped, InboundTagKind::from_id(tag_id)) {
+                (Ok(()), Some(kind)) => tags.push(kind),
+                _ => dropped_tags.push(tag_id),
             }
         }
 
-        Ok(
-            UdpResponsePacket {
-                seqnum,
-                status,
-                trace,
-                battery,
-                need_date,
+        Ok(UdpResponsePacket {
+            seqnum,
+            status,
+            trace,
+            raw,
+            battery,
+            requests,
+            need_date,
+            disk,
+            ram,
+            can,
+            joystick_outputs,
+            tags,
+            dropped_tags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_tagged_response_decode() {
+        let mut buf: &[u8] = &[
+            0x00, 0x2A, 0x01, 0x04, 0x30, 0x0C, 0x80, 0x00, // Header, 12.5V
+            0x05, 0x04, 0x00, 0x00, 0x10, 0x00, // Disk info
+            0x02, 0x7F, 0x00, // Unknown tag ID
+            0x09, 0x06, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x10, 0x00, // RAM info
+            0x0f, 0x0e, 0x42, 0x48, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x02, // CAN, 50%
+        ];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert_eq!(packet.seqnum, 42);
+        assert!(packet.status.contains(Status::ENABLED));
+        assert!(packet.trace.is_code_started());
+        assert_eq!(packet.battery, 12.5);
+        assert!(!packet.need_date);
+        assert_eq!(packet.requests, RobotRequests::empty());
+        assert_eq!(packet.disk, Some(DiskInfo { free: 4096 }));
+        assert_eq!(
+            packet.ram,
+            Some(RAMInfo {
+                block: 8192,
+                free: 4096
             })
+        );
+        let can = packet.can.unwrap();
+        assert_eq!(can.utilization, 50.0);
+        assert_eq!(can.bus_off, 1);
+        assert_eq!(can.tx_errors, 2);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn verify_unknown_request_bits_are_kept() {
+        let mut buf: &[u8] = &[0x00, 0x01, 0x01, 0x00, 0x30, 0x0C, 0x00, 0x81];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert!(packet.need_date);
+        assert_eq!(packet.requests.bits(), 0x81);
+    }
+
+    #[test]
+    fn verify_joystick_outputs_decode() {
+        let mut buf: &[u8] = &[
+            0x00, 0x01, 0x01, 0x00, 0x30, 0x0C, 0x00, 0x00, // Header
+            0x11, 0x01, // Joystick output, for two joysticks
+            0x00, 0x00, 0x00, 0x05, 0xFF, 0xFF, 0x00,
+            0x00, // Lights 0 and 2, full left rumble
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, // Full right rumble
+        ];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert_eq!(
+            packet.joystick_outputs,
+            Some(vec![
+                JoystickOutput {
+                    outputs: 0b101,
+                    rumble: crate::Rumble::new(1.0, 0.0),
+                },
+                JoystickOutput {
+                    outputs: 0,
+                    rumble: crate::Rumble::new(0.0, 1.0),
+                },
+            ])
+        );
+        assert!(packet.tags.contains(&InboundTagKind::JoystickOutput));
     }
 }
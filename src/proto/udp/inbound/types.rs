@@ -1,7 +1,9 @@
 use crate::Result;
+use crate::Rumble;
 use crate::ext::BufExt;
 use crate::util::InboundTag;
 use bytes::Buf;
+use std::fmt;
 
 macro_rules! gen_stub_tags {
     ($($struct_name:ident : $num_bytes:expr),*) => {
@@ -26,18 +28,160 @@ macro_rules! gen_stub_tags {
 }
 
 // UDP tags should be eaten to ensure the pipe doesn't get clogged, but for now proper structs aren't implemented.
-gen_stub_tags!(PDPLog : 25, JoystickOutput : 8, DiskInfo : 4, CPUInfo : 20, RAMInfo : 8, Unknown : 9, CANMetrics : 14);
+gen_stub_tags!(PDPLog : 25, CPUInfo : 20, Unknown : 9);
 
+/// What robot code set on one joystick, such as with `GenericHID.setRumble`
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct JoystickOutput {
+    /// The output bits, such as for the lights on a controller
+    pub outputs: u32,
+    pub rumble: Rumble,
+}
+
+impl JoystickOutput {
+    /// Reads the outputs of each joystick in turn, until the tag is used up
+    pub(crate) fn chomp_all(buf: &mut impl Buf) -> Result<Vec<JoystickOutput>> {
+        let mut outputs = Vec::new();
+        while buf.has_remaining() {
+            outputs.push(JoystickOutput::chomp(buf)?);
+        }
+        Ok(outputs)
+    }
+}
+
+impl InboundTag for JoystickOutput {
+    fn chomp(buf: &mut impl Buf) -> Result<Self> {
+        let outputs = buf.read_u32_be()?;
+        let mut motor = || {
+            buf.read_u16_be()
+                .map(|value| f32::from(value) / f32::from(u16::MAX))
+        };
+        let left = motor()?;
+        let right = motor()?;
+        Ok(JoystickOutput {
+            outputs,
+            rumble: Rumble::new(left, right),
+        })
+    }
+}
+
+/// The free space on the roboRIO's disk
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiskInfo {
+    /// The free space in bytes
+    pub free: u32,
+}
+
+impl InboundTag for DiskInfo {
+    fn chomp(buf: &mut impl Buf) -> Result<Self> {
+        Ok(DiskInfo {
+            free: buf.read_u32_be()?,
+        })
+    }
+}
+
+/// The health of the roboRIO's CAN bus
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CANMetrics {
+    /// The share of the bus's bandwidth in use, as a percentage
+    pub utilization: f32,
+    /// How many times the bus has gone off
+    pub bus_off: u32,
+    /// How many times the transmit buffer has been full
+    pub tx_full: u32,
+    pub rx_errors: u8,
+    pub tx_errors: u8,
+}
+
+impl InboundTag for CANMetrics {
+    fn chomp(buf: &mut impl Buf) -> Result<Self> {
+        Ok(CANMetrics {
+            utilization: buf.read_f32_be()?,
+            bus_off: buf.read_u32_be()?,
+            tx_full: buf.read_u32_be()?,
+            rx_errors: buf.read_u8()?,
+            tx_errors: buf.read_u8()?,
+        })
+    }
+}
+
+/// The memory usage of the roboRIO
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RAMInfo {
+    pub block: u32,
+    /// The free memory in bytes
+    pub free: u32,
+}
+
+impl InboundTag for RAMInfo {
+    fn chomp(buf: &mut impl Buf) -> Result<Self> {
+        Ok(RAMInfo {
+            block: buf.read_u32_be()?,
+            free: buf.read_u32_be()?,
+        })
+    }
+}
+
+/// The kinds of data the roboRIO reports in status packets, to tell when it stopped reporting one
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InboundTagKind {
+    /// The header of every status packet, with the battery voltage, status, and trace
+    Status,
+    JoystickOutput,
+    DiskInfo,
+    CpuInfo,
+    RamInfo,
+    PdpLog,
+    CanMetrics,
+}
+
+impl InboundTagKind {
+    pub const ALL: [InboundTagKind; 7] = [
+        InboundTagKind::Status,
+        InboundTagKind::JoystickOutput,
+        InboundTagKind::DiskInfo,
+        InboundTagKind::CpuInfo,
+        InboundTagKind::RamInfo,
+        InboundTagKind::PdpLog,
+        InboundTagKind::CanMetrics,
+    ];
+
+    /// Returns the kind of the tag with the given ID, if it's known
+    pub const fn from_id(id: u8) -> Option<InboundTagKind> {
+        match id {
+            0x01 => Some(InboundTagKind::JoystickOutput),
+            0x04 => Some(InboundTagKind::DiskInfo),
+            0x05 => Some(InboundTagKind::CpuInfo),
+            0x06 => Some(InboundTagKind::RamInfo),
+            0x08 => Some(InboundTagKind::PdpLog),
+            0x0e => Some(InboundTagKind::CanMetrics),
+            _ => None,
+        }
+    }
+
+    /// The position of this kind in [`InboundTagKind::ALL`]
+    pub(crate) const fn index(self) -> usize {
+        self as usize
+    }
+}
 
 bitflags! {
+    /// The bits of the status byte of a status packet, as reported by the roboRIO
+    ///
+    /// Like [`Control`](crate::Control), the lowest two bits are the mode rather than flags, so [`Status::TELEOP`]
+    /// can't be checked with `contains`. Bits 5 and 6 have no known meaning, and are kept in [`RawStatus`].
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Status: u8 {
+        /// Set while the robot is emergency stopped
         const ESTOP = 0b1000_0000;
+        /// Set while the roboRIO is disabling outputs to protect the battery voltage
         const BROWNOUT = 0b0001_0000;
+        /// Set while the robot program is starting, before it reports robot code in the [`Trace`]
         const CODE_START = 0b0000_1000;
         const ENABLED = 0b0000_0100;
 
-        // Mode flags
+        /// The mask of the mode bits
+        const MODE = 0b11;
         const TELEOP = 0b00;
         const TEST = 0b01;
         const AUTO = 0b10;
@@ -57,9 +201,15 @@ impl Status {
 }
 
 bitflags! {
+    /// The bits of the trace byte of a status packet, which report what the robot program is doing
+    ///
+    /// Unlike the status byte, each mode is its own bit, set by the robot program while it runs that mode's loop.
+    /// Bits 6 and 7 have no known meaning.
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Trace: u8 {
+        /// Set while a robot program is running
         const ROBOT_CODE = 0b0010_0000;
+        /// Set when the robot is a roboRIO
         const IS_ROBORIO = 0b0001_0000;
         const TEST_MODE = 0b0000_1000;
         const AUTONOMOUS = 0b0000_0100;
@@ -68,6 +218,24 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The request byte of a status packet, with which the robot asks the driver station for something
+    ///
+    /// Bits without a known meaning are kept, so they can be inspected.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RobotRequests: u8 {
+        /// Set until the driver station sends the date and time, usually after the roboRIO boots
+        const DATE = 0b0000_0001;
+    }
+}
+
+/// The status and trace bytes of a status packet as they were received, including bits without a known meaning
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawStatus {
+    pub status: u8,
+    pub trace: u8,
+}
+
 macro_rules! gen_trace_methods {
     ($($func_name:ident => $flag_name:expr),+) => {
         impl Trace {
@@ -83,3 +251,23 @@ macro_rules! gen_trace_methods {
 
 gen_trace_methods!(is_autonomous => Trace::AUTONOMOUS, is_teleop => Trace::TELEOP, is_disabled => Trace::DISABLED,
                    is_test => Trace::TEST_MODE, is_code_started => Trace::ROBOT_CODE, is_connected => Trace::IS_ROBORIO);
+
+/// Formats what the robot program is doing the way the official driver station shows it, such as `No Robot Code` or
+/// `Autonomous`
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if !self.is_code_started() {
+            "No Robot Code"
+        } else if self.is_disabled() {
+            "Disabled"
+        } else if self.is_autonomous() {
+            "Autonomous"
+        } else if self.is_teleop() {
+            "Teleoperated"
+        } else if self.is_test() {
+            "Test"
+        } else {
+            "Robot Code"
+        })
+    }
+}
@@ -4,18 +4,44 @@ use self::types::*;
 use bytes::{BufMut, Bytes, BytesMut};
 
 /// UDP control packet to send to the roboRIO
+#[derive(Clone, Debug)]
 pub struct UdpControlPacket {
     pub(crate) seqnum: u16,
     pub(crate) control: Control,
-    pub(crate) request: Option<Request>,
+    pub(crate) request: Option<RequestFlags>,
     pub(crate) alliance: Alliance,
-    pub(crate) tags: Vec<Box<dyn Tag>>,
+    pub(crate) tags: Vec<UdpTag>,
 }
 
 impl UdpControlPacket {
+    /// Creates a control packet from its raw parts, for use when packets aren't built by a `DriverStation`
+    pub fn new(
+        seqnum: u16,
+        control: Control,
+        request: Option<RequestFlags>,
+        alliance: Alliance,
+        tags: Vec<UdpTag>,
+    ) -> UdpControlPacket {
+        UdpControlPacket {
+            seqnum,
+            control,
+            request,
+            alliance,
+            tags,
+        }
+    }
+
     /// Encodes the current state of the packet into a vec to send to the roboRIO
     pub fn encode(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(2 + 1 + 1 + 1 + 1);
+        let mut buf = BytesMut::new();
+        self.encode_into(&mut buf);
+        buf.freeze()
+    }
+
+    /// Appends the encoded packet to `buf`, so a buffer kept between packets can be reused instead of allocating a new
+    /// one for each
+    pub fn encode_into(&self, buf: &mut BytesMut) {
+        buf.reserve(2 + 1 + 1 + 1 + 1);
         buf.put_u16(self.seqnum);
         buf.put_u8(0x01);
         buf.put_u8(self.control.bits());
@@ -27,11 +53,9 @@ impl UdpControlPacket {
         buf.put_u8(self.alliance.0);
 
         for tag in self.tags.iter() {
-            buf.extend(tag.construct());
+            tag.write(buf);
         }
 
-        buf.freeze()
-
         // let mut buf = vec![];
         // buf.write_u16::<BigEndian>(self.seqnum).unwrap();
         // buf.push(0x01); // comm version
@@ -1,7 +1,7 @@
+use anyhow::bail;
 use bytes::{BufMut, Bytes, BytesMut};
-
-// Assuming this helper still exists and returns Vec<u8> or preferably Bytes
-use crate::util::to_u8_vec;
+use std::fmt;
+use std::str::FromStr;
 
 /// Enum wrapping possible outgoing UDP tags
 #[derive(Clone, Debug)]
@@ -16,35 +16,57 @@ pub enum UdpTag {
     Timezone(Timezone),
 }
 
+impl UdpTag {
+    /// Encodes this tag, including its length prefix and ID
+    pub(crate) fn construct(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        self.write(&mut buf);
+        buf.freeze()
+    }
+
+    /// Appends this tag, including its length prefix and ID, to `buf`
+    pub(crate) fn write(&self, buf: &mut BytesMut) {
+        match self {
+            UdpTag::Countdown(cnt) => cnt.write(buf),
+            UdpTag::Joysticks(joy) => joy.write(buf),
+            UdpTag::DateTime(dt) => dt.write(buf),
+            UdpTag::Timezone(tz) => tz.write(buf),
+        }
+    }
+}
+
 /// Represents an outgoing UDP tag
 pub(crate) trait Tag: Send {
     /// Returns the unique ID byte for this tag type.
     fn id(&self) -> u8;
 
-    /// Returns the serialized data payload for this tag as immutable Bytes.
-    fn data(&self) -> Bytes;
+    /// Appends the serialized data payload for this tag to `buf`.
+    fn write_data(&self, buf: &mut BytesMut);
 
-    /// Constructs the final tag bytes including the length prefix and ID.
+    /// Appends the final tag bytes including the length prefix and ID to `buf`, without allocating once `buf` has
+    /// room for them.
     /// Format: Length (u8) | ID (u8) | Data (...)
-    fn construct(&self) -> Bytes {
-        let id_byte = self.id();
-        let data_bytes = self.data();
-        let data_len = data_bytes.len();
+    fn write(&self, buf: &mut BytesMut) {
+        let start = buf.len();
+        // The length is filled in once the data is written
+        buf.put_u8(0);
+        buf.put_u8(self.id());
+        self.write_data(buf);
 
-        let payload_len = 1 + data_len;
+        let payload_len = buf.len() - start - 1;
 
         assert!(
             payload_len <= u8::MAX as usize,
             "Tag payload too large for u8 length field"
         );
 
-        let total_len = 1 + payload_len;
-        let mut buf = BytesMut::with_capacity(total_len);
-
-        buf.put_u8(payload_len as u8);
-        buf.put_u8(id_byte);
-        buf.put(data_bytes);
+        buf[start] = payload_len as u8;
+    }
 
+    /// Constructs the final tag bytes including the length prefix and ID.
+    fn construct(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        self.write(&mut buf);
         buf.freeze()
     }
 }
@@ -70,28 +92,119 @@ impl Tag for Countdown {
         0x07
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(4);
+    fn write_data(&self, buf: &mut BytesMut) {
         buf.put_f32(self.seconds_remaining);
-        buf.freeze()
     }
 }
 
+/// The maximum number of axes the roboRIO accepts for a single joystick
+pub const MAX_AXES: usize = 12;
+/// The maximum number of buttons the roboRIO accepts for a single joystick
+pub const MAX_BUTTONS: usize = 32;
+/// The maximum number of POVs the roboRIO accepts for a single joystick
+pub const MAX_POVS: usize = 12;
+
 /// Tag containing values from joysticks
-#[derive(Clone, Debug)]
+///
+/// Values are stored inline with protocol-bounded capacity, so building this tag never allocates
+#[derive(Copy, Clone, Debug)]
 pub struct Joysticks {
-    axes: Vec<i8>,
-    buttons: Vec<bool>,
-    povs: Vec<i16>,
+    axes: [i8; MAX_AXES],
+    num_axes: u8,
+    /// Button states, LSB first (button 1 is bit 0)
+    buttons: u32,
+    num_buttons: u8,
+    povs: [i16; MAX_POVS],
+    num_povs: u8,
 }
 
 impl Joysticks {
-    #[inline(always)]
-    pub fn new(axes: Vec<i8>, buttons: Vec<bool>, povs: Vec<i16>) -> Joysticks {
+    /// Creates a new joystick tag from the given axis, button, and POV values
+    ///
+    /// # Panics
+    /// Panics if more than [`MAX_AXES`] axes, [`MAX_BUTTONS`] buttons, or [`MAX_POVS`] POVs are given
+    pub fn new(
+        axes: impl AsRef<[i8]>,
+        buttons: impl AsRef<[bool]>,
+        povs: impl AsRef<[i16]>,
+    ) -> Joysticks {
+        let (axes, buttons, povs) = (axes.as_ref(), buttons.as_ref(), povs.as_ref());
+        assert!(axes.len() <= MAX_AXES, "Too many axes for one joystick");
+        assert!(
+            buttons.len() <= MAX_BUTTONS,
+            "Too many buttons for one joystick"
+        );
+        assert!(povs.len() <= MAX_POVS, "Too many POVs for one joystick");
+
+        let mut joysticks =
+            Joysticks::neutral(axes.len() as u8, buttons.len() as u8, povs.len() as u8);
+        joysticks.axes[..axes.len()].copy_from_slice(axes);
+        joysticks.povs[..povs.len()].copy_from_slice(povs);
+        for (i, pressed) in buttons.iter().enumerate() {
+            joysticks.set_button(i, *pressed);
+        }
+        joysticks
+    }
+
+    /// Creates a joystick with centered axes, released buttons, and unpressed POVs
+    ///
+    /// The counts are clamped to the protocol maximums
+    pub(crate) const fn neutral(num_axes: u8, num_buttons: u8, num_povs: u8) -> Joysticks {
+        const fn clamp(n: u8, max: usize) -> u8 {
+            if n as usize > max { max as u8 } else { n }
+        }
+
         Joysticks {
-            axes,
-            buttons,
-            povs,
+            axes: [0; MAX_AXES],
+            num_axes: clamp(num_axes, MAX_AXES),
+            buttons: 0,
+            num_buttons: clamp(num_buttons, MAX_BUTTONS),
+            povs: [-1; MAX_POVS],
+            num_povs: clamp(num_povs, MAX_POVS),
+        }
+    }
+
+    /// Sets the axis at `index`, ignoring indices past the axis count
+    #[inline]
+    pub(crate) fn set_axis(&mut self, index: usize, value: i8) {
+        if index < self.num_axes as usize {
+            self.axes[index] = value;
+        }
+    }
+
+    /// Sets the button at `index` (zero based), ignoring indices past the button count
+    #[inline]
+    pub(crate) fn set_button(&mut self, index: usize, pressed: bool) {
+        if index < self.num_buttons as usize {
+            if pressed {
+                self.buttons |= 1 << index;
+            } else {
+                self.buttons &= !(1 << index);
+            }
+        }
+    }
+
+    /// Returns the axis at `index`, or 0 past the axis count
+    #[inline]
+    pub(crate) fn axis(&self, index: usize) -> i8 {
+        if index < self.num_axes as usize {
+            self.axes[index]
+        } else {
+            0
+        }
+    }
+
+    /// Returns whether the button at `index` (zero based) is pressed, or false past the button count
+    #[inline]
+    pub(crate) fn button(&self, index: usize) -> bool {
+        index < self.num_buttons as usize && self.buttons & (1 << index) != 0
+    }
+
+    /// Sets the POV at `index`, ignoring indices past the POV count
+    #[inline]
+    pub(crate) fn set_pov(&mut self, index: usize, angle: i16) {
+        if index < self.num_povs as usize {
+            self.povs[index] = angle;
         }
     }
 }
@@ -102,43 +215,33 @@ impl Tag for Joysticks {
         0x0c
     }
 
-    fn data(&self) -> Bytes {
-        let buttons_packed = to_u8_vec(&self.buttons);
+    fn write_data(&self, buf: &mut BytesMut) {
+        let num_axes = self.num_axes as usize;
+        let num_povs = self.num_povs as usize;
+        let button_bytes = (self.num_buttons as usize).div_ceil(8);
         let capacity = 1 // axes count
-                     + self.axes.len() // Each i8 is 1 byte
+                     + num_axes // Each i8 is 1 byte
                      + 1 // button count
-                     + buttons_packed.len()
+                     + button_bytes
                      + 1 // pov count
-                     + (self.povs.len() * 2); // Each i16 is 2 bytes
-        let mut buf = BytesMut::with_capacity(capacity);
+                     + (num_povs * 2); // Each i16 is 2 bytes
+        buf.reserve(capacity);
 
-        assert!(
-            self.axes.len() <= u8::MAX as usize,
-            "Too many axes for u8 count"
-        );
-        buf.put_u8(self.axes.len() as u8);
-
-        for axis in &self.axes {
+        buf.put_u8(self.num_axes);
+        for axis in &self.axes[..num_axes] {
             buf.put_i8(*axis);
         }
 
-        assert!(
-            self.buttons.len() <= u8::MAX as usize,
-            "Too many buttons for u8 count"
-        );
-        buf.put_u8(self.buttons.len() as u8);
-        buf.extend_from_slice(&buttons_packed);
+        // Buttons are sent big endian, the byte containing button 1 comes last
+        buf.put_u8(self.num_buttons);
+        for i in (0..button_bytes).rev() {
+            buf.put_u8((self.buttons >> (i * 8)) as u8);
+        }
 
-        assert!(
-            self.povs.len() <= u8::MAX as usize,
-            "Too many POVs for u8 count"
-        );
-        buf.put_u8(self.povs.len() as u8);
-        for pov in &self.povs {
+        buf.put_u8(self.num_povs);
+        for pov in &self.povs[..num_povs] {
             buf.put_i16(*pov);
         }
-
-        buf.freeze()
     }
 }
 
@@ -183,8 +286,8 @@ impl Tag for DateTime {
         0x0f
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(4 + 1 + 1 + 1 + 1 + 1 + 1);
+    fn write_data(&self, buf: &mut BytesMut) {
+        buf.reserve(4 + 1 + 1 + 1 + 1 + 1 + 1);
         buf.put_u32(self.micros);
         buf.put_u8(self.second);
         buf.put_u8(self.minute);
@@ -192,7 +295,6 @@ impl Tag for DateTime {
         buf.put_u8(self.day);
         buf.put_u8(self.month);
         buf.put_u8(self.year);
-        buf.freeze()
     }
 }
 
@@ -215,20 +317,26 @@ impl Tag for Timezone {
         0x10
     }
 
-    fn data(&self) -> Bytes {
-        Bytes::from(self.tz.clone())
+    fn write_data(&self, buf: &mut BytesMut) {
+        buf.put_slice(self.tz.as_bytes());
     }
 }
 
-
 bitflags! {
-    /// bitflag struct for the Control value of the packet
+    /// The bits of the control byte of a control packet, the third byte after the sequence number and comm version
+    ///
+    /// The lowest two bits are the mode, a 2 bit value rather than flags. [`Control::TELEOP`] is 0, so checking for it
+    /// with `contains` is always true; compare `self & Control::MODE` instead. Bits 4 to 6 have no known meaning.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Control: u8 {
+        /// Emergency stops the robot until the roboRIO is rebooted
         const ESTOP = 0b1000_0000;
+        /// Set when the driver station is connected to the field management system
         const FMS_CONNECTED = 0b0000_1000;
         const ENABLED = 0b0000_0100;
 
-        // Mode flags
+        /// The mask of the mode bits
+        const MODE = 0b11;
         const TELEOP = 0b00;
         const TEST = 0b01;
         const AUTO = 0b10;
@@ -236,15 +344,27 @@ bitflags! {
 }
 
 bitflags! {
-    /// bitflags for reboot and code restart requests
-    pub struct Request: u8 {
+    /// The bits of the request byte of a control packet
+    ///
+    /// Requests are one-shot, they are sent with the next control packet and then cleared.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RequestFlags: u8 {
+        /// Reboots the roboRIO, closing both connections until it's back
         const REBOOT_ROBORIO = 0b0000_1000;
+        /// Restarts the robot program without rebooting
         const RESTART_CODE = 0b0000_0100;
+        /// Asks the roboRIO to request the date and timezone again
+        const REQUEST_TIME = 0b0000_0001;
+        /// Bits with no known meaning, which the roboRIO ignores
+        const RESERVED = 0b1111_0010;
     }
 }
 
+/// The previous name of [`RequestFlags`]
+pub type Request = RequestFlags;
+
 /// Struct abstracting the byte value for alliance colour and position
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Alliance(pub u8);
 
 impl Alliance {
@@ -283,12 +403,94 @@ impl Alliance {
     pub const fn position(self) -> u8 {
         (self.0 % 3) + 1
     }
+
+    /// Returns every station, from red 1 to blue 3, such as to fill a station picker
+    pub const fn all_stations() -> [Alliance; 6] {
+        [
+            Alliance::new_red(1),
+            Alliance::new_red(2),
+            Alliance::new_red(3),
+            Alliance::new_blue(1),
+            Alliance::new_blue(2),
+            Alliance::new_blue(3),
+        ]
+    }
+
+    /// Returns true if `self` is one of the 6 stations. The byte is public, so it can hold values the roboRIO doesn't know
+    #[inline(always)]
+    pub const fn is_valid(self) -> bool {
+        self.0 < 6
+    }
+
+    /// Returns the station after `self` in [`Alliance::all_stations`], wrapping from blue 3 to red 1
+    ///
+    /// An invalid station is followed by red 1.
+    #[inline(always)]
+    pub const fn next_station(self) -> Alliance {
+        if self.is_valid() {
+            Alliance((self.0 + 1) % 6)
+        } else {
+            Alliance::new_red(1)
+        }
+    }
+}
+
+/// Formats the alliance the way the official driver station shows it, such as `Red 1`
+impl fmt::Display for Alliance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colour = if self.is_red() { "Red" } else { "Blue" };
+        write!(f, "{} {}", colour, self.position())
+    }
+}
+
+impl FromStr for Alliance {
+    type Err = anyhow::Error;
+
+    /// Parses an alliance formatted like `red1` or `Blue 3`
+    fn from_str(s: &str) -> Result<Alliance, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let (colour, position) =
+            s.split_at(s.find(|c: char| c.is_ascii_digit()).unwrap_or(s.len()));
+        let position = match position.parse::<u8>() {
+            Ok(position @ 1..=3) => position,
+            _ => bail!(
+                "Invalid alliance station {:?}, expected 1, 2, or 3",
+                position
+            ),
+        };
+
+        match colour.trim() {
+            "red" => Ok(Alliance::new_red(position)),
+            "blue" => Ok(Alliance::new_blue(position)),
+            _ => bail!("Invalid alliance colour {:?}, expected red or blue", colour),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn verify_alliance_parse() {
+        assert_eq!("red1".parse::<Alliance>().unwrap(), Alliance::new_red(1));
+        assert_eq!("Blue 3".parse::<Alliance>().unwrap(), Alliance::new_blue(3));
+        assert!("green1".parse::<Alliance>().is_err());
+        assert!("red4".parse::<Alliance>().is_err());
+        assert_eq!(Alliance::new_blue(2).to_string(), "Blue 2");
+        assert_eq!("Blue 2".parse::<Alliance>().unwrap(), Alliance::new_blue(2));
+    }
+
+    #[test]
+    fn verify_stations_cycle() {
+        let stations = Alliance::all_stations();
+        assert!(stations.iter().all(|station| station.is_valid()));
+        for (i, station) in stations.iter().enumerate() {
+            assert_eq!(station.next_station(), stations[(i + 1) % stations.len()]);
+        }
+        assert_eq!(Alliance(7).next_station(), Alliance::new_red(1));
+    }
+
     #[test]
     fn verify_countdown_format() {
         let countdown = Countdown::new(2.0f32);
@@ -307,7 +509,7 @@ mod test {
         assert_eq!(
             buf.as_ref(),
             &[
-                0x0D, 0x0c, 0x03, 0x80, 0x00, 0x7F, 0x09, 0x05, 0x01, 0x02, 0x00, 0x00, 0x46, 0x50
+                0x0D, 0x0c, 0x03, 0x80, 0x00, 0x7F, 0x09, 0x01, 0x05, 0x02, 0x00, 0x00, 0x46, 0x50
             ]
         );
     }
@@ -324,6 +526,13 @@ mod test {
         );
     }
 
+    #[test]
+    fn verify_control_mode_mask() {
+        let control = Control::ESTOP | Control::ENABLED | Control::AUTO;
+        assert_eq!(control & Control::MODE, Control::AUTO);
+        assert_eq!(Control::from_bits_truncate(0b0111_0000), Control::empty());
+    }
+
     #[test]
     fn verify_timezone_format() {
         let tz = Timezone::new("UTC");
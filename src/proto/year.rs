@@ -0,0 +1,81 @@
+use crate::util::ip_from_team_number;
+
+/// The season whose roboRIO image the driver station is talking to
+///
+/// Every roboRIO season since 2016 uses the same UDP and TCP framing and comm version, so the season doesn't change
+/// the packets sent. It only decides where the robot is found from a team number, see
+/// [`ProtocolYear::robot_address`], and which season [`GameData`](crate::GameData) built for a particular game is
+/// expected to match.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ProtocolYear {
+    Y2016,
+    Y2017,
+    Y2018,
+    Y2019,
+    Y2020,
+    Y2021,
+    Y2022,
+    Y2023,
+    Y2024,
+    #[default]
+    Y2025,
+}
+
+impl ProtocolYear {
+    pub const LATEST: ProtocolYear = ProtocolYear::Y2025;
+
+    /// All supported years, oldest first
+    pub const ALL: [ProtocolYear; 10] = [
+        ProtocolYear::Y2016,
+        ProtocolYear::Y2017,
+        ProtocolYear::Y2018,
+        ProtocolYear::Y2019,
+        ProtocolYear::Y2020,
+        ProtocolYear::Y2021,
+        ProtocolYear::Y2022,
+        ProtocolYear::Y2023,
+        ProtocolYear::Y2024,
+        ProtocolYear::Y2025,
+    ];
+
+    /// Returns the protocol for the given season, if it's supported
+    pub fn from_year(year: u16) -> Option<ProtocolYear> {
+        ProtocolYear::ALL
+            .into_iter()
+            .find(|protocol| protocol.year() == year)
+    }
+
+    #[inline(always)]
+    pub const fn year(self) -> u16 {
+        2016 + self as u16
+    }
+
+    /// The address a roboRIO running this season's image can be found at, given a team number
+    ///
+    /// Before 2020 the roboRIO wasn't expected at a static address, and was found through mDNS instead.
+    /// Resolving the hostname requires mDNS support on the host.
+    pub fn robot_address(self, team_number: u16) -> String {
+        if self < ProtocolYear::Y2020 {
+            format!("roboRIO-{}-FRC.local", team_number)
+        } else {
+            ip_from_team_number(team_number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_years() {
+        assert_eq!(ProtocolYear::from_year(2018), Some(ProtocolYear::Y2018));
+        assert_eq!(ProtocolYear::from_year(2015), None);
+        assert_eq!(ProtocolYear::LATEST.year(), 2025);
+        assert_eq!(
+            ProtocolYear::Y2019.robot_address(4533),
+            "roboRIO-4533-FRC.local"
+        );
+        assert_eq!(ProtocolYear::Y2020.robot_address(4533), "10.45.33.2");
+    }
+}
@@ -0,0 +1,90 @@
+//! Tunnelling of driver station traffic over WebSocket
+//!
+//! Browsers can't open raw UDP or TCP sockets, so a browser hosted driver station instead talks to a
+//! small native relay over a WebSocket. Every binary WebSocket message carries one [`RelayFrame`], a channel
+//! byte followed by either a UDP datagram or a complete TCP frame, unmodified.
+//!
+//! The relay itself is available on native targets with the `relay` feature, see `serve`.
+//! Browser code can build the payloads with [`UdpControlPacket`](crate::UdpControlPacket), and decode replies
+//! with [`UdpResponsePacket`](crate::UdpResponsePacket) and [`DsTcpCodec`](crate::DsTcpCodec).
+
+use crate::Result;
+use anyhow::bail;
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[cfg(all(feature = "relay", not(target_arch = "wasm32")))]
+mod server;
+#[cfg(all(feature = "relay", not(target_arch = "wasm32")))]
+pub use self::server::serve;
+
+/// The roboRIO connection a relayed payload belongs to
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Channel {
+    /// Control packets to the roboRIO, and status packets from it
+    Udp = 0,
+    /// TCP frames to and from the roboRIO
+    Tcp = 1,
+}
+
+/// A single payload tunnelled between a browser and the relay
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayFrame {
+    pub channel: Channel,
+    pub payload: Bytes,
+}
+
+impl RelayFrame {
+    #[inline(always)]
+    pub fn udp(payload: impl Into<Bytes>) -> RelayFrame {
+        RelayFrame {
+            channel: Channel::Udp,
+            payload: payload.into(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn tcp(payload: impl Into<Bytes>) -> RelayFrame {
+        RelayFrame {
+            channel: Channel::Tcp,
+            payload: payload.into(),
+        }
+    }
+
+    /// Encodes this frame into the body of a binary WebSocket message
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(1 + self.payload.len());
+        buf.put_u8(self.channel as u8);
+        buf.put_slice(&self.payload);
+        buf.freeze()
+    }
+
+    /// Decodes a frame from the body of a binary WebSocket message
+    pub fn decode(mut message: Bytes) -> Result<RelayFrame> {
+        if message.is_empty() {
+            bail!("Empty relay frame");
+        }
+
+        let channel = match message[0] {
+            0 => Channel::Udp,
+            1 => Channel::Tcp,
+            id => bail!("Unknown relay channel {}", id),
+        };
+        let payload = message.split_off(1);
+        Ok(RelayFrame { channel, payload })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_frame_format() {
+        let frame = RelayFrame::tcp(&[0x00, 0x01, 0x1d][..]);
+        let buf = frame.encode();
+        assert_eq!(buf.as_ref(), &[0x01, 0x00, 0x01, 0x1d]);
+        assert_eq!(RelayFrame::decode(buf).unwrap(), frame);
+        assert!(RelayFrame::decode(Bytes::from_static(&[0x05])).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Channel, RelayFrame};
+use crate::Result;
+
+use bytes::{Buf, BytesMut};
+use futures_util::sink::SinkExt;
+use futures_util::stream::StreamExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use tokio::time::timeout;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+
+/// How long to wait between attempts to open the TCP connection to the roboRIO
+const TCP_RETRY: Duration = Duration::from_secs(2);
+
+/// Runs a relay that accepts WebSocket connections on `listen`, and forwards their traffic to the roboRIO at `robot_ip`
+///
+/// Only one client is served at a time, as the port the roboRIO sends status packets to can only be bound once.
+/// This future only returns if the listening socket fails.
+pub async fn serve(listen: impl ToSocketAddrs, robot_ip: &str) -> Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                warn!("Relay handshake with {} failed: {}", addr, e);
+                continue;
+            }
+        };
+
+        info!("Relaying for {}", addr);
+        if let Err(e) = session(ws, robot_ip).await {
+            warn!("Relay session with {} ended: {}", addr, e);
+        }
+    }
+}
+
+/// Forwards traffic for one client until its WebSocket closes
+async fn session(ws: WebSocketStream<TcpStream>, robot_ip: &str) -> Result<()> {
+    let udp_rx = UdpSocket::bind("0.0.0.0:1150").await?;
+    let udp_tx = UdpSocket::bind("0.0.0.0:0").await?;
+    udp_tx.connect(&format!("{}:1110", robot_ip)).await?;
+
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    let mut tcp: Option<TcpStream> = None;
+    let mut last_tcp_attempt: Option<Instant> = None;
+    let mut tcp_buf = BytesMut::new();
+    let mut udp_buf = [0u8; 1500];
+
+    loop {
+        tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    let frame = RelayFrame::decode(data)?;
+                    match frame.channel {
+                        Channel::Udp => {
+                            let _ = udp_tx.send(&frame.payload).await;
+                        }
+                        Channel::Tcp => {
+                            if let Some(ref mut conn) = tcp
+                                && conn.write_all(&frame.payload).await.is_err()
+                            {
+                                tcp = None;
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+            n = udp_rx.recv(&mut udp_buf) => {
+                let n = n?;
+                ws_tx.send(Message::Binary(RelayFrame::udp(udp_buf[..n].to_vec()).encode())).await?;
+
+                // Like the driver station, only connect over TCP once the roboRIO is known to be up
+                let retry = last_tcp_attempt.is_none_or(|at| at.elapsed() >= TCP_RETRY);
+                if tcp.is_none() && retry {
+                    last_tcp_attempt = Some(Instant::now());
+                    let addr = format!("{}:1740", robot_ip);
+                    if let Ok(Ok(conn)) = timeout(Duration::from_millis(500), TcpStream::connect(&addr)).await {
+                        tcp_buf.clear();
+                        tcp = Some(conn);
+                    }
+                }
+            },
+            n = read_tcp(&mut tcp, &mut tcp_buf) => {
+                match n {
+                    Ok(0) | Err(_) => tcp = None,
+                    Ok(_) => {
+                        // Only complete frames are relayed, so that the browser can decode each message on its own
+                        while tcp_buf.len() >= 2 {
+                            let len = 2 + (&tcp_buf[..2]).get_u16() as usize;
+                            if tcp_buf.len() < len {
+                                break;
+                            }
+                            let frame = tcp_buf.split_to(len).freeze();
+                            ws_tx.send(Message::Binary(RelayFrame::tcp(frame).encode())).await?;
+                        }
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Reads from the TCP connection if it exists, otherwise never completes
+async fn read_tcp(tcp: &mut Option<TcpStream>, buf: &mut BytesMut) -> std::io::Result<usize> {
+    match tcp {
+        Some(conn) => conn.read_buf(buf).await,
+        None => std::future::pending().await,
+    }
+}
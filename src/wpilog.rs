@@ -0,0 +1,362 @@
+//! Recording of driver station telemetry in WPILib's `.wpilog` format
+//!
+//! [`record`] logs battery voltage, the requested and reported robot state, joystick inputs, and every
+//! [`Event`](crate::Event), so that they can be viewed alongside the robot's own logs in tools such as AdvantageScope.
+//! Entries are named like those WPILib logs on the robot, prefixed with `DS:`. Timestamps are microseconds since
+//! recording started, and the `systemTime` entry maps them to the wall clock.
+//!
+//! [`WpilogWriter`] can also be used directly to log other data.
+
+use crate::{DriverStation, DsSnapshot, JoystickValue, Mode};
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// The only version of the format, 1.0
+const VERSION: u16 = 0x0100;
+
+/// Writes records in the `.wpilog` format to `W`
+pub struct WpilogWriter<W: Write> {
+    writer: W,
+    next_entry: u32,
+}
+
+impl<W: Write> WpilogWriter<W> {
+    /// Writes the file header, with `extra_header` as the free form header text
+    pub fn new(mut writer: W, extra_header: &str) -> io::Result<WpilogWriter<W>> {
+        writer.write_all(b"WPILOG")?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(extra_header.len() as u32).to_le_bytes())?;
+        writer.write_all(extra_header.as_bytes())?;
+        Ok(WpilogWriter {
+            writer,
+            next_entry: 1,
+        })
+    }
+
+    /// Starts an entry named `name` holding values of `ty`, such as `double` or `string[]`, returning its id
+    pub fn start(
+        &mut self,
+        name: &str,
+        ty: &str,
+        metadata: &str,
+        timestamp: u64,
+    ) -> io::Result<u32> {
+        let entry = self.next_entry;
+        self.next_entry += 1;
+
+        let mut payload = vec![0u8];
+        payload.extend_from_slice(&entry.to_le_bytes());
+        for s in [name, ty, metadata] {
+            payload.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            payload.extend_from_slice(s.as_bytes());
+        }
+        self.append_raw(0, timestamp, &payload)?;
+        Ok(entry)
+    }
+
+    /// Appends a record with an already encoded payload to `entry`
+    pub fn append_raw(&mut self, entry: u32, timestamp: u64, payload: &[u8]) -> io::Result<()> {
+        let entry_len = byte_len(u64::from(entry));
+        let size_len = byte_len(payload.len() as u64);
+        let timestamp_len = byte_len(timestamp);
+
+        let header = (entry_len - 1) | (size_len - 1) << 2 | (timestamp_len - 1) << 4;
+        self.writer.write_all(&[header as u8])?;
+        self.writer
+            .write_all(&u64::from(entry).to_le_bytes()[..entry_len])?;
+        self.writer
+            .write_all(&(payload.len() as u64).to_le_bytes()[..size_len])?;
+        self.writer
+            .write_all(&timestamp.to_le_bytes()[..timestamp_len])?;
+        self.writer.write_all(payload)
+    }
+
+    pub fn append_boolean(&mut self, entry: u32, timestamp: u64, value: bool) -> io::Result<()> {
+        self.append_raw(entry, timestamp, &[value as u8])
+    }
+
+    pub fn append_int64(&mut self, entry: u32, timestamp: u64, value: i64) -> io::Result<()> {
+        self.append_raw(entry, timestamp, &value.to_le_bytes())
+    }
+
+    pub fn append_double(&mut self, entry: u32, timestamp: u64, value: f64) -> io::Result<()> {
+        self.append_raw(entry, timestamp, &value.to_le_bytes())
+    }
+
+    pub fn append_string(&mut self, entry: u32, timestamp: u64, value: &str) -> io::Result<()> {
+        self.append_raw(entry, timestamp, value.as_bytes())
+    }
+
+    pub fn append_boolean_array(
+        &mut self,
+        entry: u32,
+        timestamp: u64,
+        values: &[bool],
+    ) -> io::Result<()> {
+        let payload = values.iter().map(|&v| v as u8).collect::<Vec<u8>>();
+        self.append_raw(entry, timestamp, &payload)
+    }
+
+    pub fn append_int64_array(
+        &mut self,
+        entry: u32,
+        timestamp: u64,
+        values: &[i64],
+    ) -> io::Result<()> {
+        let payload = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>();
+        self.append_raw(entry, timestamp, &payload)
+    }
+
+    pub fn append_double_array(
+        &mut self,
+        entry: u32,
+        timestamp: u64,
+        values: &[f64],
+    ) -> io::Result<()> {
+        let payload = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>();
+        self.append_raw(entry, timestamp, &payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// The number of bytes needed to store `value`, at least 1
+fn byte_len(value: u64) -> usize {
+    (8 - value.leading_zeros() as usize / 8).max(1)
+}
+
+/// The ids of the entries written by [`record`]
+struct Entries {
+    system_time: u32,
+    voltage: u32,
+    enabled: u32,
+    autonomous: u32,
+    test: u32,
+    estop: u32,
+    mode: u32,
+    connected: u32,
+    brownout: u32,
+    robot_enabled: u32,
+    events: u32,
+}
+
+impl Entries {
+    fn start<W: Write>(log: &mut WpilogWriter<W>) -> io::Result<Entries> {
+        Ok(Entries {
+            system_time: log.start("systemTime", "int64", "", 0)?,
+            voltage: log.start("DS:voltage", "double", "", 0)?,
+            enabled: log.start("DS:enabled", "boolean", "", 0)?,
+            autonomous: log.start("DS:autonomous", "boolean", "", 0)?,
+            test: log.start("DS:test", "boolean", "", 0)?,
+            estop: log.start("DS:estop", "boolean", "", 0)?,
+            mode: log.start("DS:mode", "string", "", 0)?,
+            connected: log.start("DS:connected", "boolean", "", 0)?,
+            brownout: log.start("DS:brownout", "boolean", "", 0)?,
+            robot_enabled: log.start("DS:robotEnabled", "boolean", "", 0)?,
+            events: log.start("DS:events", "string", "", 0)?,
+        })
+    }
+}
+
+/// The ids of the entries for one joystick
+struct JoystickEntries {
+    axes: u32,
+    buttons: u32,
+    povs: u32,
+}
+
+/// Joystick values laid out as the arrays WPILib logs
+#[derive(Default, PartialEq)]
+struct JoystickArrays {
+    axes: Vec<f64>,
+    buttons: Vec<bool>,
+    povs: Vec<i64>,
+}
+
+impl JoystickArrays {
+    fn from_values(values: &[JoystickValue]) -> JoystickArrays {
+        let mut arrays = JoystickArrays::default();
+        for value in values {
+            match *value {
+                JoystickValue::Axis { id, value } => {
+                    let id = id as usize;
+                    if arrays.axes.len() <= id {
+                        arrays.axes.resize(id + 1, 0.0);
+                    }
+                    arrays.axes[id] = f64::from(value);
+                }
+                // Buttons are numbered from 1
+                JoystickValue::Button { id, pressed } if id >= 1 => {
+                    let id = id as usize - 1;
+                    if arrays.buttons.len() <= id {
+                        arrays.buttons.resize(id + 1, false);
+                    }
+                    arrays.buttons[id] = pressed;
+                }
+                JoystickValue::Button { .. } => {}
+                JoystickValue::POV { id, angle } => {
+                    let id = id as usize;
+                    if arrays.povs.len() <= id {
+                        arrays.povs.resize(id + 1, -1);
+                    }
+                    arrays.povs[id] = i64::from(angle);
+                }
+            }
+        }
+        arrays
+    }
+}
+
+/// Records telemetry from `ds` to `log` every `interval`
+///
+/// Values are only written when they change. The log is flushed after every interval, so little is lost if the
+/// process stops. This future only returns if writing fails, drop it to stop recording.
+pub async fn record<W: Write>(
+    ds: Arc<Mutex<DriverStation>>,
+    mut log: WpilogWriter<W>,
+    interval: Duration,
+) -> io::Result<()> {
+    let started = Instant::now();
+    let entries = Entries::start(&mut log)?;
+    let mut joystick_entries = Vec::<JoystickEntries>::new();
+    let mut last_joysticks = HashMap::<usize, JoystickArrays>::new();
+    let mut last: Option<DsSnapshot> = None;
+    let mut last_event: DateTime<Utc> = Utc::now();
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        let (snapshot, joysticks, events) = {
+            let ds = ds.lock().await;
+            let events = ds.events_since(last_event).await;
+            (ds.status_snapshot().await, ds.joysticks().await, events)
+        };
+        let timestamp = started.elapsed().as_micros() as u64;
+        log.append_int64(
+            entries.system_time,
+            timestamp,
+            Utc::now().timestamp_micros(),
+        )?;
+
+        let changed =
+            |f: fn(&DsSnapshot) -> bool| last.as_ref().is_none_or(|last| f(last) != f(&snapshot));
+        if last
+            .as_ref()
+            .is_none_or(|last| last.battery_voltage != snapshot.battery_voltage)
+        {
+            log.append_double(
+                entries.voltage,
+                timestamp,
+                f64::from(snapshot.battery_voltage),
+            )?;
+        }
+        if changed(|s| s.enabled) {
+            log.append_boolean(entries.enabled, timestamp, snapshot.enabled)?;
+        }
+        if last.as_ref().is_none_or(|last| last.mode != snapshot.mode) {
+            log.append_boolean(
+                entries.autonomous,
+                timestamp,
+                snapshot.mode == Mode::Autonomous,
+            )?;
+            log.append_boolean(entries.test, timestamp, snapshot.mode == Mode::Test)?;
+            log.append_string(entries.mode, timestamp, &format!("{:?}", snapshot.mode))?;
+        }
+        if changed(|s| s.estopped) {
+            log.append_boolean(entries.estop, timestamp, snapshot.estopped)?;
+        }
+        if changed(|s| s.connected) {
+            log.append_boolean(entries.connected, timestamp, snapshot.connected)?;
+        }
+        if changed(|s| s.brownout) {
+            log.append_boolean(entries.brownout, timestamp, snapshot.brownout)?;
+        }
+        if changed(|s| s.robot_enabled) {
+            log.append_boolean(entries.robot_enabled, timestamp, snapshot.robot_enabled)?;
+        }
+        last = Some(snapshot);
+
+        for (i, values) in joysticks.iter().enumerate() {
+            while joystick_entries.len() <= i {
+                let n = joystick_entries.len();
+                joystick_entries.push(JoystickEntries {
+                    axes: log.start(
+                        &format!("DS:joystick{}/axes", n),
+                        "double[]",
+                        "",
+                        timestamp,
+                    )?,
+                    buttons: log.start(
+                        &format!("DS:joystick{}/buttons", n),
+                        "boolean[]",
+                        "",
+                        timestamp,
+                    )?,
+                    povs: log.start(&format!("DS:joystick{}/povs", n), "int64[]", "", timestamp)?,
+                });
+            }
+            let arrays = JoystickArrays::from_values(values);
+            if last_joysticks.get(&i) == Some(&arrays) {
+                continue;
+            }
+            let ids = &joystick_entries[i];
+            log.append_double_array(ids.axes, timestamp, &arrays.axes)?;
+            log.append_boolean_array(ids.buttons, timestamp, &arrays.buttons)?;
+            log.append_int64_array(ids.povs, timestamp, &arrays.povs)?;
+            last_joysticks.insert(i, arrays);
+        }
+
+        for event in events {
+            // `events_since` is inclusive, so the last event recorded is returned again
+            if event.timestamp <= last_event {
+                continue;
+            }
+            last_event = event.timestamp;
+            log.append_string(entries.events, timestamp, &format!("{:?}", event.event))?;
+        }
+
+        log.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_records_are_encoded() {
+        let mut log = WpilogWriter::new(Vec::new(), "").unwrap();
+        let entry = log.start("DS:voltage", "double", "", 0).unwrap();
+        log.append_double(entry, 0x1234, 12.5).unwrap();
+        let bytes = log.into_inner();
+
+        assert_eq!(&bytes[..12], b"WPILOG\x00\x01\x00\x00\x00\x00");
+        // A start record for entry 1, in the control entry 0
+        let start_len = 1 + 4 + (4 + 10) + (4 + 6) + 4;
+        assert_eq!(&bytes[12..16], &[0x00, 0x00, start_len as u8, 0x00]);
+        assert_eq!(bytes[16], 0);
+        assert_eq!(&bytes[17..21], &1u32.to_le_bytes());
+
+        let record = &bytes[16 + start_len..];
+        // 1 byte entry id, 1 byte size, and 2 byte timestamp
+        assert_eq!(&record[..5], &[0x10, 0x01, 0x08, 0x34, 0x12]);
+        assert_eq!(&record[5..], &12.5f64.to_le_bytes());
+    }
+}
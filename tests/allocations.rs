@@ -0,0 +1,122 @@
+//! Checks that the send loop's handling of joystick values, up to the encoded packet, doesn't allocate once warmed up
+//!
+//! The library forbids unsafe code, so the counting allocator lives in this test instead.
+
+use ds::internals::{JoystickSources, Polled, SendState, encode_tick};
+use ds::{Alliance, JoystickSource, JoystickValue};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the allocations made on each thread, as tests run in parallel
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+fn joysticks() -> Vec<Vec<JoystickValue>> {
+    let joystick = vec![
+        JoystickValue::Axis { id: 0, value: 0.5 },
+        JoystickValue::Button {
+            id: 1,
+            pressed: true,
+        },
+        JoystickValue::POV { id: 0, angle: 90 },
+    ];
+    vec![joystick; 2]
+}
+
+/// Runs what the send loop does with joysticks from a source, dropping the encoded packet as it's sent
+fn tick(state: &mut SendState, polled: Polled) {
+    let (encoded, _, _) = encode_tick(state, Some(polled));
+    assert!(!encoded.is_empty());
+}
+
+fn values(joysticks: Vec<Vec<JoystickValue>>) -> Polled {
+    Polled::Values("gamepad".into(), joysticks)
+}
+
+#[test]
+fn verify_send_path_does_not_allocate() {
+    let mut state = SendState::new(Alliance::new_red(1));
+    // Both tag buffers and the encode buffer grow on the first packets, and the first values are reported as changes
+    for _ in 0..2 {
+        tick(&mut state, values(joysticks()));
+    }
+    for _ in 0..3 {
+        let supplied = values(joysticks());
+        assert_eq!(allocations(|| tick(&mut state, supplied)), 0);
+    }
+
+    // Neutral joysticks reuse the ones last sent, after the releases are reported once
+    tick(&mut state, Polled::Unavailable);
+    for _ in 0..3 {
+        assert_eq!(allocations(|| tick(&mut state, Polled::Unavailable)), 0);
+    }
+    assert_eq!(state.last_joysticks().len(), 2);
+}
+
+/// A gamepad that refills the vectors it's given
+struct Gamepad;
+
+impl JoystickSource for Gamepad {
+    fn poll(&self) -> Option<Vec<Vec<JoystickValue>>> {
+        Some(joysticks())
+    }
+
+    fn poll_into(&self, joysticks: &mut Vec<Vec<JoystickValue>>) -> bool {
+        joysticks.resize_with(2, Vec::new);
+        for joystick in joysticks.iter_mut() {
+            joystick.clear();
+            joystick.push(JoystickValue::Axis { id: 0, value: 0.5 });
+            joystick.push(JoystickValue::Button {
+                id: 1,
+                pressed: true,
+            });
+        }
+        true
+    }
+}
+
+#[test]
+fn verify_sources_that_refill_do_not_allocate() {
+    let mut state = SendState::new(Alliance::new_red(1));
+    let mut sources = JoystickSources::new();
+    sources.add("gamepad", 0, Arc::new(Gamepad));
+    let poll = |state: &mut SendState| {
+        let polled = sources.poll_with(state.take_joystick_buffer());
+        assert!(
+            matches!(polled, Polled::Values(..)),
+            "the gamepad is available"
+        );
+        tick(state, polled);
+    };
+    // Two buffers are filled in turn, the one sent and the one before it
+    for _ in 0..3 {
+        poll(&mut state);
+    }
+    for _ in 0..3 {
+        assert_eq!(allocations(|| poll(&mut state)), 0);
+    }
+}